@@ -0,0 +1,428 @@
+// Quick actions - saved prompt templates ("explain this error", "summarize") that
+// capture, OCR and ask the model in one go, optionally from their own global shortcut
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const ACTIONS_FORMAT_VERSION: u32 = 1;
+
+// The main FrameSense shortcut can't be taken by an action
+pub const RESERVED_HOTKEY: &str = "CmdOrCtrl+Shift+F";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Markdown,
+    Bullets,
+    Json,
+}
+
+impl OutputFormat {
+    // Appended to the rendered prompt so every provider gets the same instruction
+    fn instruction(&self) -> Option<&'static str> {
+        match self {
+            Self::Text => None,
+            Self::Markdown => Some("Format the answer as Markdown."),
+            Self::Bullets => Some("Answer with a short bulleted list."),
+            Self::Json => Some("Answer with a single JSON object and nothing else."),
+        }
+    }
+
+    // The prompt sent to the model: the rendered template plus the format instruction
+    pub fn apply(&self, prompt: String) -> String {
+        match self.instruction() {
+            Some(instruction) => format!("{}\n\n{}", prompt.trim_end(), instruction),
+            None => prompt,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureTarget {
+    #[default]
+    LastSelection, // Falls back to the full screen before the first selection
+    FullScreen,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickAction {
+    #[serde(default)]
+    pub id: String, // Generated from the name when empty
+    pub name: String,
+    pub prompt: String, // Template, e.g. "Explain this error from {app_name}:\n{ocr_text}"
+    #[serde(default)]
+    pub model: Option<String>, // None uses the model picked in the chat window
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    #[serde(default)]
+    pub capture: CaptureTarget,
+    #[serde(default)]
+    pub hotkey: Option<String>, // e.g. "CmdOrCtrl+Alt+E"
+    #[serde(default)]
+    pub pipeline: Option<String>, // Processing pipeline id, None for the standard quick action pipeline
+}
+
+// Values available to prompt templates
+pub struct TemplateVars {
+    pub ocr_text: String,
+    pub app_name: String,
+    pub selection_size: String, // "1280x720"
+}
+
+// What actions are saved and shared as, so a shared file can be imported as-is
+#[derive(Serialize, Deserialize)]
+struct ActionBundle {
+    version: u32,
+    actions: Vec<QuickAction>,
+}
+
+pub struct ActionStore {
+    path: PathBuf,
+    actions: Vec<QuickAction>,
+}
+
+// Replaces known {placeholders} in one pass, so OCR text that happens to contain
+// "{app_name}" isn't substituted again. Unknown placeholders are left as typed.
+pub fn render_template(template: &str, vars: &TemplateVars) -> String {
+    let mut output = String::with_capacity(template.len() + vars.ocr_text.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let value = match &after[..end] {
+                "ocr_text" => vars.ocr_text.as_str(),
+                "app_name" => vars.app_name.as_str(),
+                "selection_size" => vars.selection_size.as_str(),
+                _ => return None,
+            };
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                output.push_str(value);
+                rest = &after[end + 1..];
+            },
+            None => {
+                output.push('{');
+                rest = after;
+            },
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn slugify(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-");
+    if slug.is_empty() {
+        "action".to_string()
+    } else {
+        slug
+    }
+}
+
+// Hotkeys compare case-insensitively and ignore spacing ("ctrl+alt+e" == "Ctrl + Alt + E")
+fn hotkey_key(hotkey: &str) -> String {
+    hotkey
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn default_actions() -> Vec<QuickAction> {
+    let action = |id: &str, name: &str, prompt: &str, output_format| QuickAction {
+        id: id.to_string(),
+        name: name.to_string(),
+        prompt: prompt.to_string(),
+        model: None,
+        output_format,
+        capture: CaptureTarget::LastSelection,
+        hotkey: None,
+        pipeline: None,
+    };
+    vec![
+        action(
+            "explain-error",
+            "Explain this error",
+            "Explain this error from {app_name} and suggest how to fix it:\n\n{ocr_text}",
+            OutputFormat::Markdown,
+        ),
+        action(
+            "translate-english",
+            "Translate to English",
+            "Translate the text in this capture to English. Keep the original formatting:\n\n{ocr_text}",
+            OutputFormat::Text,
+        ),
+        action(
+            "jira-ticket",
+            "Turn into a Jira ticket",
+            "Write a Jira ticket (summary, description, steps to reproduce, expected and actual result) for what this {app_name} capture shows:\n\n{ocr_text}",
+            OutputFormat::Markdown,
+        ),
+        action("summarize", "Summarize", "Summarize this capture:\n\n{ocr_text}", OutputFormat::Bullets),
+    ]
+}
+
+impl QuickAction {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Quick action needs a name".to_string());
+        }
+        if self.prompt.trim().is_empty() {
+            return Err(format!("Quick action '{}' has an empty prompt", self.name));
+        }
+        if let Some(hotkey) = &self.hotkey {
+            if hotkey_key(hotkey) == hotkey_key(RESERVED_HOTKEY) {
+                return Err(format!("{} is reserved for opening FrameSense", RESERVED_HOTKEY));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ActionStore {
+    // A missing or unreadable file means the built-in actions
+    pub fn load(path: PathBuf) -> Self {
+        let actions = match fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str::<ActionBundle>(&json) {
+                Ok(bundle) => bundle.actions,
+                Err(e) => {
+                    println!("⚠️ Failed to parse quick actions, using built-in actions: {}", e);
+                    default_actions()
+                },
+            },
+            Err(_) => default_actions(),
+        };
+        Self { path, actions }
+    }
+
+    pub fn actions(&self) -> &[QuickAction] {
+        &self.actions
+    }
+
+    pub fn get(&self, id: &str) -> Option<&QuickAction> {
+        self.actions.iter().find(|action| action.id == id)
+    }
+
+    // Hotkeys currently bound to actions
+    pub fn hotkeys(&self) -> Vec<String> {
+        self.actions.iter().filter_map(|action| action.hotkey.clone()).collect()
+    }
+
+    fn hotkey_owner(&self, hotkey: &str, except_id: &str) -> Option<&QuickAction> {
+        let key = hotkey_key(hotkey);
+        self.actions
+            .iter()
+            .find(|action| action.id != except_id && action.hotkey.as_deref().map(hotkey_key) == Some(key.clone()))
+    }
+
+    fn unique_id(&self, name: &str) -> String {
+        let base = slugify(name);
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while self.get(&id).is_some() {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        id
+    }
+
+    // Adds a new action (empty id) or replaces the one with the same id
+    pub fn upsert(&mut self, mut action: QuickAction) -> Result<QuickAction, String> {
+        action.hotkey = action.hotkey.filter(|hotkey| !hotkey.trim().is_empty());
+        action.validate()?;
+        if action.id.is_empty() {
+            action.id = self.unique_id(&action.name);
+        }
+        if let Some(hotkey) = &action.hotkey {
+            if let Some(owner) = self.hotkey_owner(hotkey, &action.id) {
+                return Err(format!("{} is already used by '{}'", hotkey, owner.name));
+            }
+        }
+
+        match self.actions.iter_mut().find(|existing| existing.id == action.id) {
+            Some(existing) => *existing = action.clone(),
+            None => self.actions.push(action.clone()),
+        }
+        self.save()?;
+        println!("⚡ Saved quick action '{}' ({})", action.name, action.id);
+        Ok(action)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<QuickAction, String> {
+        let index = self
+            .actions
+            .iter()
+            .position(|action| action.id == id)
+            .ok_or_else(|| format!("Quick action not found: {}", id))?;
+        let action = self.actions.remove(index);
+        self.save()?;
+        println!("🗑️ Deleted quick action '{}'", action.name);
+        Ok(action)
+    }
+
+    // Shareable JSON for the given actions (all of them without ids). Hotkeys are
+    // left out since they depend on the sharer's keyboard and other apps.
+    pub fn export(&self, ids: Option<&[String]>) -> Result<String, String> {
+        let actions = self
+            .actions
+            .iter()
+            .filter(|action| ids.is_none_or(|ids| ids.contains(&action.id)))
+            .map(|action| QuickAction {
+                hotkey: None,
+                ..action.clone()
+            })
+            .collect();
+        let bundle = ActionBundle {
+            version: ACTIONS_FORMAT_VERSION,
+            actions,
+        };
+        serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to serialize quick actions: {}", e))
+    }
+
+    // Imports shared actions (a bundle or a bare array) alongside the existing ones.
+    // Clashing ids get a new id and clashing hotkeys are dropped, so imports never
+    // overwrite the user's own actions or bindings.
+    pub fn import(&mut self, json: &str) -> Result<Vec<QuickAction>, String> {
+        let incoming = match serde_json::from_str::<ActionBundle>(json) {
+            Ok(bundle) if bundle.version > ACTIONS_FORMAT_VERSION => {
+                return Err(format!("Quick actions were exported by a newer version (format {})", bundle.version));
+            },
+            Ok(bundle) => bundle.actions,
+            Err(_) => serde_json::from_str::<Vec<QuickAction>>(json)
+                .map_err(|e| format!("Not a quick actions file: {}", e))?,
+        };
+        if incoming.is_empty() {
+            return Err("No quick actions to import".to_string());
+        }
+        for action in &incoming {
+            action.validate()?;
+        }
+
+        let mut imported = Vec::new();
+        for mut action in incoming {
+            if action.id.is_empty() || self.get(&action.id).is_some() {
+                action.id = self.unique_id(&action.name);
+            }
+            if let Some(hotkey) = &action.hotkey {
+                if self.hotkey_owner(hotkey, &action.id).is_some() {
+                    println!("⚠️ Dropping hotkey {} of imported action '{}', already in use", hotkey, action.name);
+                    action.hotkey = None;
+                }
+            }
+            self.actions.push(action.clone());
+            imported.push(action);
+        }
+        self.save()?;
+        println!("📥 Imported {} quick actions", imported.len());
+        Ok(imported)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+        let bundle = ActionBundle {
+            version: ACTIONS_FORMAT_VERSION,
+            actions: self.actions.clone(),
+        };
+        let json = serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to serialize quick actions: {}", e))?;
+        fs::write(&self.path, json).map_err(|e| format!("Failed to write quick actions: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> ActionStore {
+        let path = std::env::temp_dir().join(format!("framesense-actions-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        ActionStore::load(path)
+    }
+
+    fn action(id: &str, name: &str, hotkey: Option<&str>) -> QuickAction {
+        QuickAction {
+            id: id.to_string(),
+            name: name.to_string(),
+            prompt: "Explain:\n{ocr_text}".to_string(),
+            model: None,
+            output_format: OutputFormat::Text,
+            capture: CaptureTarget::LastSelection,
+            hotkey: hotkey.map(str::to_string),
+            pipeline: None,
+        }
+    }
+
+    #[test]
+    fn templates_are_rendered_in_a_single_pass() {
+        let vars = TemplateVars {
+            ocr_text: "error in {app_name} at {selection_size}".to_string(),
+            app_name: "Terminal".to_string(),
+            selection_size: "1280x720".to_string(),
+        };
+        // Placeholders inside the OCR text stay as captured; unknown or unclosed ones stay as typed
+        assert_eq!(
+            render_template("{app_name} ({selection_size}): {ocr_text} {unknown} {app_name", &vars),
+            "Terminal (1280x720): error in {app_name} at {selection_size} {unknown} {app_name"
+        );
+        assert_eq!(render_template("{}{{ocr_text}}", &vars), "{}{error in {app_name} at {selection_size}}");
+    }
+
+    #[test]
+    fn imports_never_overwrite_existing_ids_or_hotkeys() {
+        let mut store = temp_store("import");
+        store.upsert(action("", "Mine", Some("Ctrl+Alt+S"))).unwrap();
+
+        let bundle = serde_json::json!({
+            "version": ACTIONS_FORMAT_VERSION,
+            "actions": [
+                action("summarize", "Summarize", Some("ctrl + alt + s")),
+                action("", "Fix grammar", Some("CmdOrCtrl+Alt+G")),
+                action("fresh", "Fresh", None),
+                action("", "Also grammar", Some("cmdorctrl+alt+g")),
+            ]
+        });
+        let imported = store.import(&bundle.to_string()).unwrap();
+        let summary: Vec<(&str, Option<&str>)> = imported.iter().map(|action| (action.id.as_str(), action.hotkey.as_deref())).collect();
+        assert_eq!(summary, [
+            ("summarize-2", None), // Built-in id and "Mine"'s hotkey are both taken
+            ("fix-grammar", Some("CmdOrCtrl+Alt+G")),
+            ("fresh", None),
+            ("also-grammar", None), // Taken by the action imported just before it
+        ]);
+        assert_eq!(store.get("summarize").unwrap().hotkey, None);
+        assert_eq!(store.get("mine").unwrap().hotkey.as_deref(), Some("Ctrl+Alt+S"));
+        assert_eq!(ActionStore::load(store.path.clone()).actions().len(), default_actions().len() + 5);
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn invalid_imports_are_refused_whole() {
+        let mut store = temp_store("invalid");
+        let count = store.actions().len();
+
+        let reserved = serde_json::to_string(&[action("", "Fine", None), action("", "Taken", Some("cmdorctrl + shift + f"))]).unwrap();
+        assert!(store.import(&reserved).unwrap_err().contains("reserved"));
+        let newer = serde_json::json!({ "version": ACTIONS_FORMAT_VERSION + 1, "actions": [action("", "Later", None)] });
+        assert!(store.import(&newer.to_string()).unwrap_err().contains("newer version"));
+        assert!(store.import("[]").is_err());
+        assert!(store.import("{\"name\": \"not a list\"}").is_err());
+        assert_eq!(store.actions().len(), count);
+
+        // A bare array works as well as a bundle
+        let bare = serde_json::to_string(&[action("", "Bare", None)]).unwrap();
+        assert_eq!(store.import(&bare).unwrap()[0].id, "bare");
+        let _ = fs::remove_file(&store.path);
+    }
+}
@@ -0,0 +1,309 @@
+// FrameSense backend provider - posts captures and prompts to /api/analyze with
+// the session token and streams the answer back (SSE, chunked text or plain JSON)
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::stream::{content_type, decode_data_url, provider_error, read_stream};
+use super::{AnalysisRequest, AnalysisResult};
+use crate::errors::FrameSenseError;
+
+// Final JSON body of a non-streaming backend: { answer | message, tokensUsed, model }
+#[derive(Debug, Deserialize)]
+struct AnalyzeResponse {
+    answer: Option<String>,
+    message: Option<String>,
+    #[serde(rename = "tokensUsed")]
+    tokens_used: Option<u64>,
+    model: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct BackendClient {
+    api_url: String,
+    token: Option<String>,
+    timeout: Duration,
+}
+
+impl BackendClient {
+    pub fn new(api_url: &str) -> Self {
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            token: None,
+            timeout: Duration::from_secs(120),
+        }
+    }
+
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    // Calls `on_chunk` with each piece of answer text as it arrives and returns
+    // the assembled answer once the stream ends
+    pub async fn analyze(
+        &self,
+        request: &AnalysisRequest,
+        mut on_chunk: impl FnMut(&str),
+    ) -> Result<AnalysisResult, FrameSenseError> {
+        let mut form = reqwest::multipart::Form::new()
+            .text("question", request.question.clone())
+            .text("stream", "true");
+        if let Some(model) = &request.model {
+            form = form.text("model", model.clone());
+        }
+        if let Some(ocr_text) = &request.ocr_text {
+            form = form.text("ocr_text", ocr_text.clone());
+        }
+        if !request.history.is_empty() {
+            let history = serde_json::to_string(&request.history)
+                .map_err(|e| format!("Failed to serialize conversation history: {}", e))?;
+            form = form.text("history", history);
+        }
+        // A single capture goes in `image`; comparisons send every capture as `images`
+        // with their labels, in the same order, as a JSON array in `labels`
+        let images = request.images();
+        let comparison = !request.captures.is_empty();
+        for (index, (_, image_data)) in images.iter().enumerate() {
+            let (mime, bytes) = decode_data_url(image_data)?;
+            let extension = mime.trim_start_matches("image/").to_string();
+            let file_name = if comparison {
+                format!("capture-{}.{}", index + 1, extension)
+            } else {
+                format!("screenshot.{}", extension)
+            };
+            let part = reqwest::multipart::Part::bytes(bytes)
+                .file_name(file_name)
+                .mime_str(&mime)
+                .map_err(|e| format!("Invalid image type {}: {}", mime, e))?;
+            form = form.part(if comparison { "images" } else { "image" }, part);
+        }
+        if comparison {
+            let labels: Vec<&str> = images.iter().filter_map(|(label, _)| *label).collect();
+            let labels = serde_json::to_string(&labels).map_err(|e| format!("Failed to serialize capture labels: {}", e))?;
+            form = form.text("labels", labels);
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let mut http_request = client
+            .post(format!("{}/api/analyze", self.api_url))
+            .header("Accept", "text/event-stream, application/json")
+            .multipart(form);
+        if let Some(token) = &self.token {
+            http_request = http_request.header("Authorization", format!("Bearer {}", token));
+        }
+
+        let response = http_request.send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error = provider_error(status.as_u16(), &response.text().await.unwrap_or_default());
+            return Err(match error {
+                FrameSenseError::Unauthorized(message) if status.as_u16() == 401 => {
+                    FrameSenseError::Unauthorized(format!("Session expired or invalid, please log in again ({})", message))
+                },
+                error => error.map_message(|message| format!("Analysis failed: {}", message)),
+            });
+        }
+
+        if content_type(&response).starts_with("application/json") {
+            let body: AnalyzeResponse = response.json().await.map_err(|e| FrameSenseError::Server {
+                message: format!("Unreadable analysis response: {}", e),
+                status: None,
+            })?;
+            let answer = body.answer.or(body.message).unwrap_or_else(|| "No response".to_string());
+            on_chunk(&answer);
+            return Ok(AnalysisResult {
+                answer,
+                model: body.model,
+                tokens_used: body.tokens_used,
+                streamed: false,
+                cached: false,
+            });
+        }
+
+        let mut result = AnalysisResult {
+            answer: String::new(),
+            model: request.model.clone(),
+            tokens_used: None,
+            streamed: true,
+            cached: false,
+        };
+        read_stream(response, &mut result, &mut on_chunk).await?;
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_server::{serve_once, text_chunks};
+    use super::super::LabeledCapture;
+    use base64::Engine;
+
+    fn request() -> AnalysisRequest {
+        AnalysisRequest {
+            question: "What does this error mean?".to_string(),
+            image_data: Some(format!(
+                "data:image/png;base64,{}",
+                base64::engine::general_purpose::STANDARD.encode(b"\x89PNG fake")
+            )),
+            model: Some("GPT-4o-mini".to_string()),
+            ocr_text: None,
+            history: Vec::new(),
+            captures: Vec::new(),
+            force_refresh: false,
+            region_references: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_sse_chunks_in_order() {
+        let (url, server) = serve_once("200 OK", "text/event-stream", text_chunks(&[
+            ": keep-alive\n\n",
+            "data: {\"delta\":\"The build \"}\n\n",
+            "data: {\"delta\":\"failed bec",
+            "ause of a missing crate.\"}\n\ndata: {\"done\":true,\"tokensUsed\":42,\"model\":\"gpt-4o-mini\"}\n\n",
+        ]))
+        .await;
+
+        let client = BackendClient::new(&url).with_token(Some("session-token".to_string()));
+        let mut chunks = Vec::new();
+        let result = client.analyze(&request(), |chunk| chunks.push(chunk.to_string())).await.unwrap();
+
+        assert_eq!(chunks, vec!["The build ", "failed because of a missing crate."]);
+        assert_eq!(result.answer, "The build failed because of a missing crate.");
+        assert_eq!(result.tokens_used, Some(42));
+        assert_eq!(result.model.as_deref(), Some("gpt-4o-mini"));
+        assert!(result.streamed);
+
+        let captured = server.await.unwrap();
+        assert!(captured.head.starts_with("POST /api/analyze "));
+        assert!(captured.head.to_lowercase().contains("authorization: bearer session-token"));
+        let body = String::from_utf8_lossy(&captured.body);
+        assert!(body.contains("name=\"question\""));
+        assert!(body.contains("What does this error mean?"));
+        assert!(body.contains("filename=\"screenshot.png\""));
+        assert!(captured.body.windows(8).any(|window| window == b"PNG fake"));
+    }
+
+    #[tokio::test]
+    async fn accepts_openai_style_events_and_done_marker() {
+        let (url, _server) = serve_once("200 OK", "text/event-stream", text_chunks(&[
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hej\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" då\"}}]}\n\ndata: [DONE]\n\n",
+        ]))
+        .await;
+
+        let result = BackendClient::new(&url).analyze(&request(), |_| {}).await.unwrap();
+        assert_eq!(result.answer, "Hej då");
+    }
+
+    #[tokio::test]
+    async fn streams_plain_chunked_text_across_utf8_boundaries() {
+        // "å" is 0xC3 0xA5; split it across two chunks
+        let (url, _server) = serve_once("200 OK", "text/plain; charset=utf-8", vec![
+            b"Sm".to_vec(),
+            vec![0xC3],
+            b"\xA5land".to_vec(),
+        ])
+        .await;
+
+        let mut chunks = Vec::new();
+        let result = BackendClient::new(&url)
+            .analyze(&request(), |chunk| chunks.push(chunk.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(result.answer, "Småland");
+        assert!(chunks.iter().all(|chunk| !chunk.contains('\u{FFFD}')));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_single_json_answer() {
+        let (url, _server) = serve_once("200 OK", "application/json", text_chunks(&[
+            "{\"answer\":\"It is a login form.\",\"tokensUsed\":17}",
+        ]))
+        .await;
+
+        let mut chunks = Vec::new();
+        let result = BackendClient::new(&url)
+            .analyze(&request(), |chunk| chunks.push(chunk.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(chunks, vec!["It is a login form."]);
+        assert_eq!(result.tokens_used, Some(17));
+        assert!(!result.streamed);
+    }
+
+    #[tokio::test]
+    async fn reports_expired_session() {
+        let (url, _server) = serve_once("401 Unauthorized", "application/json", text_chunks(&[
+            "{\"message\":\"Invalid token\"}",
+        ]))
+        .await;
+
+        let error = BackendClient::new(&url).analyze(&request(), |_| {}).await.unwrap_err();
+        assert_eq!(error.code(), "unauthorized");
+        assert!(error.to_string().contains("log in again"), "{}", error);
+        assert!(error.to_string().contains("Invalid token"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn fails_on_an_error_event_mid_stream() {
+        let (url, _server) = serve_once("200 OK", "text/event-stream", text_chunks(&[
+            "data: {\"delta\":\"The build \"}\n\n",
+            "event: error\ndata: {\"message\":\"Upstream model overloaded\",\"status\":503}\n\n",
+            "data: {\"delta\":\"never sent\"}\n\n",
+        ]))
+        .await;
+
+        let mut chunks = Vec::new();
+        let error = BackendClient::new(&url)
+            .analyze(&request(), |chunk| chunks.push(chunk.to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!((error.code(), error.status()), ("server", Some(503)));
+        assert!(error.to_string().contains("Upstream model overloaded"), "{}", error);
+        assert!(error.is_transient());
+        assert_eq!(chunks, vec!["The build "]);
+    }
+
+    #[tokio::test]
+    async fn error_events_without_a_status_are_server_errors() {
+        let (url, _server) = serve_once("200 OK", "text/event-stream", text_chunks(&[
+            "event: error\ndata: {\"message\":\"Provider closed the connection\"}\n\n",
+        ]))
+        .await;
+
+        let error = BackendClient::new(&url).analyze(&request(), |_| {}).await.unwrap_err();
+        assert_eq!((error.code(), error.status()), ("server", None));
+        assert_eq!(error.to_string(), "Provider closed the connection");
+    }
+
+    #[tokio::test]
+    async fn sends_comparison_captures_with_labels() {
+        let (url, server) = serve_once("200 OK", "application/json", text_chunks(&[
+            "{\"answer\":\"The second build has an extra button.\"}",
+        ]))
+        .await;
+
+        let image = request().image_data.unwrap();
+        let mut comparison = request();
+        comparison.image_data = None;
+        comparison.captures = vec![
+            LabeledCapture { label: "Build 1.4".to_string(), image_data: image.clone() },
+            LabeledCapture { label: "Build 1.5".to_string(), image_data: image },
+        ];
+        BackendClient::new(&url).analyze(&comparison, |_| {}).await.unwrap();
+
+        let body = String::from_utf8_lossy(&server.await.unwrap().body).to_string();
+        assert!(body.contains("filename=\"capture-1.png\""));
+        assert!(body.contains("filename=\"capture-2.png\""));
+        assert_eq!(body.matches("name=\"images\"").count(), 2);
+        assert!(!body.contains("name=\"image\""));
+        assert!(body.contains("[\"Build 1.4\",\"Build 1.5\"]"));
+    }
+}
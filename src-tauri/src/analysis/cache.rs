@@ -0,0 +1,179 @@
+// Persistent analysis answer cache, so re-asking the same question about the same
+// capture (e.g. after reopening the window) doesn't spend quota again
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use super::stream::decode_data_url;
+use super::{AnalysisRequest, AnalysisResult};
+use crate::lru_store::{Limits, LruStore};
+
+const LIMITS: Limits = Limits {
+    max_entries: 200,
+    max_size_bytes: 5 * 1024 * 1024, // 5MB of answers
+    ttl_secs: Some(24 * 60 * 60),    // Screens change; a day-old answer is likely stale
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedAnswer {
+    answer: String,
+    model: Option<String>,
+    tokens_used: Option<u64>,
+}
+
+pub struct ResponseCache {
+    store: LruStore<CachedAnswer>,
+}
+
+// "  What does THIS error mean? " and "what does this error mean" ask the same thing
+fn normalize_prompt(question: &str) -> String {
+    let collapsed = question.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    collapsed.trim_end_matches(['?', '.', '!', ' ']).to_string()
+}
+
+// Hashes the decoded capture bytes (not the data URL text), the normalized prompt,
+// earlier thread turns, the answering model and the provider. `provider` should tell
+// endpoints apart, since two BYOK servers can answer differently with the same model name.
+pub fn cache_key(request: &AnalysisRequest, model: Option<&str>, provider: &str) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    for (label, image_data) in request.images() {
+        let (_, bytes) = decode_data_url(image_data)?;
+        hasher.update(label.unwrap_or_default().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(&bytes);
+        hasher.update([0u8]);
+    }
+    hasher.update(normalize_prompt(&request.question).as_bytes());
+    for turn in &request.history {
+        hasher.update([0u8]);
+        hasher.update(turn.role.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(turn.content.as_bytes());
+    }
+    hasher.update([0u8]);
+    hasher.update(model.unwrap_or_default().as_bytes());
+    hasher.update([0u8]);
+    hasher.update(provider.as_bytes());
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self { store: LruStore::new("analysis cache", LIMITS) }
+    }
+
+    // Loads the cache file if present; a missing or unreadable file starts empty
+    pub fn load(storage_path: PathBuf) -> Self {
+        Self { store: LruStore::load("analysis cache", LIMITS, storage_path) }
+    }
+
+    // The cached answer flagged as such, or None on a miss or an expired entry
+    pub fn get(&mut self, key: &str) -> Option<AnalysisResult> {
+        self.store.get(key).map(|cached| AnalysisResult {
+            answer: cached.answer.clone(),
+            model: cached.model.clone(),
+            tokens_used: cached.tokens_used,
+            streamed: false,
+            cached: true,
+        })
+    }
+
+    pub fn insert(&mut self, key: String, result: &AnalysisResult) {
+        if result.answer.trim().is_empty() {
+            return;
+        }
+        let cached = CachedAnswer {
+            answer: result.answer.clone(),
+            model: result.model.clone(),
+            tokens_used: result.tokens_used,
+        };
+        self.store.insert(key, cached, result.answer.len());
+    }
+
+    pub fn clear(&mut self) {
+        self.store.clear();
+    }
+
+    // Writes the cache file if anything changed since the last write
+    pub fn flush(&mut self) {
+        self.store.flush();
+    }
+
+    // (entries, total size in bytes, hits, misses) for this session
+    pub fn get_cache_stats(&self) -> (usize, usize, u64, u64) {
+        self.store.stats()
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ChatTurn;
+
+    fn request(question: &str, image_data: &str) -> AnalysisRequest {
+        AnalysisRequest {
+            question: question.to_string(),
+            image_data: Some(image_data.to_string()),
+            model: None,
+            ocr_text: None,
+            history: Vec::new(),
+            captures: Vec::new(),
+            force_refresh: false,
+            region_references: false,
+        }
+    }
+
+    fn answer(text: &str) -> AnalysisResult {
+        AnalysisResult {
+            answer: text.to_string(),
+            model: Some("gpt-4o-mini".to_string()),
+            tokens_used: Some(12),
+            streamed: true,
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn key_ignores_prompt_formatting_but_not_content() {
+        let key = |request: &AnalysisRequest, model, provider| cache_key(request, model, provider).unwrap();
+        let base = key(&request("What does this error mean?", "AAAA"), Some("gpt-4o-mini"), "backend");
+
+        // Same capture bytes as a data URL, prompt differing only in case and spacing
+        let same = request("  what does this   ERROR mean ", "data:image/png;base64,AAAA");
+        assert_eq!(key(&same, Some("gpt-4o-mini"), "backend"), base);
+
+        assert_ne!(key(&request("What does this error mean?", "BBBB"), Some("gpt-4o-mini"), "backend"), base);
+        assert_ne!(key(&request("What does this warning mean?", "AAAA"), Some("gpt-4o-mini"), "backend"), base);
+        assert_ne!(key(&same, Some("gpt-4o"), "backend"), base);
+        assert_ne!(key(&same, Some("gpt-4o-mini"), "openai_compatible:http://localhost:11434/v1"), base);
+
+        let mut follow_up = same.clone();
+        follow_up.history.push(ChatTurn { role: "user".to_string(), content: "Earlier question".to_string() });
+        assert_ne!(key(&follow_up, Some("gpt-4o-mini"), "backend"), base);
+    }
+
+    #[test]
+    fn serves_answers_flagged_as_cached() {
+        let mut cache = ResponseCache::new();
+        cache.insert("a".to_string(), &answer("First"));
+        let hit = cache.get("a").unwrap();
+        assert!(hit.cached && !hit.streamed);
+        assert_eq!((hit.answer.as_str(), hit.model.as_deref(), hit.tokens_used), ("First", Some("gpt-4o-mini"), Some(12)));
+
+        // Blank answers aren't worth keeping
+        cache.insert("b".to_string(), &answer("  "));
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.get_cache_stats(), (1, 5, 1, 1));
+    }
+}
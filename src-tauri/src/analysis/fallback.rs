@@ -0,0 +1,249 @@
+// Model fallback - when the chosen model is rate limited, down, too slow or refuses the
+// content, the analysis moves down the tier's fallback chain instead of failing outright
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+
+use super::AnalysisResult;
+use crate::errors::FrameSenseError;
+use crate::model_registry::ModelRegistry;
+
+// A 5xx is often a blip, so the same model gets one more try before falling back
+const SERVER_ERROR_RETRIES: u32 = 1;
+const SERVER_ERROR_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    RateLimited,   // HTTP 429: another provider's model, no point retrying right away
+    ServerError,   // HTTP 5xx or a dropped stream: retried once, then the next model
+    Timeout,       // The next model, a retry would likely time out as well
+    ContentPolicy, // The next model from a different provider
+    Other,         // Auth, tier, bad request, offline: every model would fail the same way
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainModel {
+    pub model: Option<String>, // None lets the backend pick its default model
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedAttempt {
+    pub model: Option<String>,
+    pub kind: FailureKind,
+    pub error: String,
+}
+
+impl FailureKind {
+    pub fn of(error: &FrameSenseError) -> Self {
+        match error {
+            FrameSenseError::RateLimited(_) => Self::RateLimited,
+            FrameSenseError::Server { .. } => Self::ServerError,
+            FrameSenseError::Timeout(_) => Self::Timeout,
+            FrameSenseError::ContentPolicy(_) => Self::ContentPolicy,
+            _ => Self::Other,
+        }
+    }
+}
+
+// The requested model, then the tier's chain. Chain models are kept only if the tier may
+// use them and they can take the request's images; the requested one was gated already.
+pub fn fallback_chain(registry: &ModelRegistry, user_tier: &str, requested: Option<&str>, images: usize) -> Vec<ChainModel> {
+    let provider = |model: &str| registry.find(model).map(|entry| entry.provider.clone());
+    let mut chain = vec![ChainModel {
+        model: requested.map(str::to_string),
+        provider: requested.and_then(provider),
+    }];
+
+    for model in registry.fallback_chain(user_tier) {
+        let Some(entry) = registry.find(model) else {
+            continue;
+        };
+        let fits = images == 0 || (entry.vision && images as u32 <= entry.max_images);
+        let listed = chain
+            .iter()
+            .any(|listed| listed.model.as_deref().and_then(|listed| registry.find(listed)).is_some_and(|listed| listed.id == entry.id));
+        if fits && !listed && registry.can_use_model(user_tier, &entry.id) {
+            chain.push(ChainModel {
+                model: Some(entry.id.clone()),
+                provider: Some(entry.provider.clone()),
+            });
+        }
+    }
+    chain
+}
+
+// Tries each model of the chain with `attempt` until one answers. `on_failure` hears about
+// every failed attempt that's followed by another one, so a half-streamed answer can be
+// cleared. Returns the answer, with the model that gave it, and the attempts that failed.
+pub async fn run_chain<F, Fut>(
+    chain: &[ChainModel],
+    mut attempt: F,
+    mut on_failure: impl FnMut(&FailedAttempt),
+) -> Result<(AnalysisResult, Vec<FailedAttempt>), FrameSenseError>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<AnalysisResult, FrameSenseError>>,
+{
+    let mut failed: Vec<FailedAttempt> = Vec::new();
+    let mut last_error: Option<FrameSenseError> = None;
+    let mut refusing_providers: Vec<String> = Vec::new();
+
+    for candidate in chain {
+        if candidate.provider.as_ref().is_some_and(|provider| refusing_providers.contains(provider)) {
+            continue;
+        }
+
+        let mut retries = 0;
+        let (failure, error) = loop {
+            match attempt(candidate.model.clone()).await {
+                Ok(mut result) => {
+                    if result.model.is_none() {
+                        result.model = candidate.model.clone();
+                    }
+                    if !failed.is_empty() {
+                        println!("🔀 Answered by fallback model {:?} after {} failed attempts", result.model, failed.len());
+                    }
+                    return Ok((result, failed));
+                },
+                Err(error) => {
+                    let kind = FailureKind::of(&error);
+                    if kind == FailureKind::ServerError && retries < SERVER_ERROR_RETRIES {
+                        retries += 1;
+                        println!("🔁 {:?} failed with a server error, retrying: {}", candidate.model, error);
+                        tokio::time::sleep(SERVER_ERROR_DELAY).await;
+                        continue;
+                    }
+                    let failure = FailedAttempt {
+                        model: candidate.model.clone(),
+                        kind,
+                        error: error.to_string(),
+                    };
+                    break (failure, error);
+                },
+            }
+        };
+
+        if failure.kind == FailureKind::Other {
+            return Err(error);
+        }
+        if let (FailureKind::ContentPolicy, Some(provider)) = (failure.kind, &candidate.provider) {
+            refusing_providers.push(provider.clone());
+        }
+        println!("⚠️ {:?} failed ({:?}), falling back: {}", failure.model, failure.kind, failure.error);
+        on_failure(&failure);
+        failed.push(failure);
+        last_error = Some(error);
+    }
+
+    // The last error's kind is kept, so callers can still tell a rate limit or outage apart
+    let tried: Vec<String> = failed
+        .iter()
+        .map(|attempt| attempt.model.clone().unwrap_or_else(|| "default model".to_string()))
+        .collect();
+    match last_error {
+        Some(last) => Err(last.map_message(|message| format!("{} (tried {})", message, tried.join(", ")))),
+        None => Err(FrameSenseError::Other("No model in the fallback chain can take this request".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn answer() -> AnalysisResult {
+        AnalysisResult {
+            answer: "It's a login form.".to_string(),
+            model: None,
+            tokens_used: None,
+            streamed: true,
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn chain_keeps_only_models_the_tier_may_use_for_the_images() {
+        let registry = ModelRegistry::bundled();
+        let models = |tier, requested, images| -> Vec<String> {
+            fallback_chain(&registry, tier, requested, images)
+                .into_iter()
+                .map(|entry| entry.model.unwrap_or_default())
+                .collect()
+        };
+
+        assert_eq!(models("pro", Some("GPT-4o"), 1), vec!["GPT-4o", "claude-3.5-sonnet", "gemini-pro", "gpt-4o-mini"]);
+        // Premium can't use the pro models of the chain
+        let premium = models("premium", Some("gpt-4o-mini"), 1);
+        assert!(!premium.iter().any(|model| model == "gpt-4o" || model == "claude-3.5-sonnet"));
+        assert_eq!(premium[0], "gpt-4o-mini");
+        // Eleven captures are more than the OpenAI models take
+        assert!(!models("enterprise", None, 11).iter().any(|model| model.starts_with("gpt")));
+    }
+
+    #[tokio::test]
+    async fn falls_back_by_error_kind_and_reports_the_answering_model() {
+        let chain = vec![
+            ChainModel { model: Some("gpt-4o".to_string()), provider: Some("openai".to_string()) },
+            ChainModel { model: Some("claude-3.5-sonnet".to_string()), provider: Some("anthropic".to_string()) },
+            ChainModel { model: Some("claude-3-haiku".to_string()), provider: Some("anthropic".to_string()) },
+            ChainModel { model: Some("gemini-pro".to_string()), provider: Some("google".to_string()) },
+        ];
+        let calls = RefCell::new(Vec::new());
+        let mut notified = Vec::new();
+
+        let (result, failed) = run_chain(
+            &chain,
+            |model| {
+                calls.borrow_mut().push(model.clone().unwrap());
+                async move {
+                    match model.as_deref() {
+                        Some("gpt-4o") => Err(FrameSenseError::from_status(429, "Rate limit reached")),
+                        Some("claude-3.5-sonnet") => Err(FrameSenseError::ContentPolicy("Output blocked by content filtering policy".to_string())),
+                        _ => Ok(answer()),
+                    }
+                }
+            },
+            |failure| notified.push(failure.kind),
+        )
+        .await
+        .unwrap();
+
+        // Haiku is skipped: its provider already refused the content
+        assert_eq!(*calls.borrow(), vec!["gpt-4o", "claude-3.5-sonnet", "gemini-pro"]);
+        assert_eq!(result.model.as_deref(), Some("gemini-pro"));
+        assert_eq!(notified, vec![FailureKind::RateLimited, FailureKind::ContentPolicy]);
+        assert_eq!(failed.len(), 2);
+
+        // Auth errors stop the chain, server errors get one retry before moving on
+        let calls = RefCell::new(0);
+        let error = run_chain(
+            &chain,
+            |_| {
+                *calls.borrow_mut() += 1;
+                async { Err::<AnalysisResult, _>(FrameSenseError::Unauthorized("Session expired or invalid, please log in again".to_string())) }
+            },
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code(), "unauthorized");
+        assert_eq!(*calls.borrow(), 1);
+
+        let calls = RefCell::new(0);
+        let error = run_chain(
+            &chain[..1],
+            |_| {
+                *calls.borrow_mut() += 1;
+                async { Err::<AnalysisResult, _>(FrameSenseError::from_status(503, "Service unavailable")) }
+            },
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(*calls.borrow(), 2);
+        assert_eq!((error.code(), error.status()), ("server", Some(503)));
+        assert_eq!(error.to_string(), "Service unavailable (tried gpt-4o)");
+    }
+}
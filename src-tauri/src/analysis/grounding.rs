@@ -0,0 +1,286 @@
+// Answer grounding - finds the capture text an answer talks about (quoted strings and
+// explicit [[ref: ...]] markers) among the OCR words, so the overlay can outline it
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use super::AnalysisRequest;
+use crate::ocr::{OCRWord, TextBox};
+
+// OCR misreads a character or two ("Connecti0n refused"), so matches are fuzzy down to this
+const MIN_SCORE: f32 = 0.8;
+// Shorter references ("OK", "Save") only count when they match exactly
+const EXACT_BELOW_CHARS: usize = 5;
+// Longer quotes are prose, not something on screen
+const MAX_REFERENCE_WORDS: usize = 24;
+
+const REGION_INSTRUCTION: &str = "When you refer to text that is visible in the capture, wrap it exactly as it \
+appears on screen in [[ref: ...]], for example [[ref: Connection refused]]. Use one marker for each place you point at.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceKind {
+    Quote,     // Quoted or backticked text in the answer
+    Reference, // A [[ref: ...]] marker the model was asked to add
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Highlight {
+    pub text: String,    // As written in the answer
+    pub matched: String, // The OCR words it matched
+    pub kind: ReferenceKind,
+    pub score: f32,          // Similarity, 1.0 for an exact match
+    pub boxes: Vec<TextBox>, // One rectangle per text line, in capture coordinates
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Grounding {
+    pub answer: String, // The answer with [[ref: ...]] markers reduced to their text, for display
+    pub highlights: Vec<Highlight>,
+    pub unmatched: Vec<String>, // References that couldn't be found in the capture
+}
+
+fn marker_regex() -> &'static Regex {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    MARKER.get_or_init(|| Regex::new(r"\[\[ref:\s*(.+?)\s*\]\]").unwrap())
+}
+
+// "double", “curly”, `backticked` and 'single' quotes. A single quote only opens after
+// whitespace or a bracket, so apostrophes ("don't", "users'") aren't read as quotes.
+fn quote_regex() -> &'static Regex {
+    static QUOTE: OnceLock<Regex> = OnceLock::new();
+    QUOTE.get_or_init(|| {
+        Regex::new(r#""([^"\n]{2,200})"|“([^”\n]{2,200})”|`([^`\n]{2,200})`|(?:^|[\s(\[])'([^'\n]{2,200})'"#).unwrap()
+    })
+}
+
+// The request with the instruction asking for [[ref: ...]] markers, when it wants them
+pub fn with_region_instruction(request: &AnalysisRequest) -> Option<AnalysisRequest> {
+    if !request.region_references {
+        return None;
+    }
+    let mut request = request.clone();
+    request.question = format!("{}\n\n{}", request.question, REGION_INSTRUCTION);
+    Some(request)
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// 1 - Levenshtein distance / longer length
+fn similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    1.0 - previous[b.len()] as f32 / longest as f32
+}
+
+// Word ranges (first, last, score) where `reference` appears. Windows of one word more
+// or less than the reference are tried too, since OCR splits and merges words. Every
+// non-overlapping occurrence about as good as the best one is returned.
+fn find_matches(reference: &str, words: &[OCRWord]) -> Vec<(usize, usize, f32)> {
+    let target = normalize(reference);
+    let target_chars = target.chars().count();
+    let reference_words = reference.split_whitespace().filter(|word| !normalize(word).is_empty()).count();
+    if target_chars < 2 || reference_words > MAX_REFERENCE_WORDS {
+        return Vec::new();
+    }
+    let min_score = if target_chars < EXACT_BELOW_CHARS { 1.0 } else { MIN_SCORE };
+
+    let tokens: Vec<(usize, String)> = words
+        .iter()
+        .enumerate()
+        .map(|(index, word)| (index, normalize(&word.text)))
+        .filter(|(_, token)| !token.is_empty())
+        .collect();
+
+    let mut candidates = Vec::new();
+    for start in 0..tokens.len() {
+        let mut joined = String::new();
+        for end in start..tokens.len().min(start + reference_words + 1) {
+            joined.push_str(&tokens[end].1);
+            if end - start + 2 < reference_words {
+                continue;
+            }
+            // The length difference alone caps the similarity; skip hopeless windows cheaply
+            let joined_chars = joined.chars().count();
+            let cap = 1.0 - target_chars.abs_diff(joined_chars) as f32 / target_chars.max(joined_chars) as f32;
+            if cap < min_score {
+                continue;
+            }
+            let score = similarity(&target, &joined);
+            if score >= min_score {
+                candidates.push((tokens[start].0, tokens[end].0, score));
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2).then((a.1 - a.0).cmp(&(b.1 - b.0))));
+    let best = candidates.first().map(|candidate| candidate.2).unwrap_or_default();
+    let mut chosen: Vec<(usize, usize, f32)> = Vec::new();
+    for candidate in candidates.into_iter().take_while(|candidate| candidate.2 >= best - 0.05) {
+        if chosen.iter().all(|other| candidate.1 < other.0 || other.1 < candidate.0) {
+            chosen.push(candidate);
+        }
+    }
+    chosen.sort_by_key(|(first, _, _)| *first);
+    chosen
+}
+
+// One rectangle per OCR line, so a match wrapping onto the next line isn't one big box
+fn line_boxes(words: &[OCRWord]) -> Vec<TextBox> {
+    let mut lines: Vec<(u32, TextBox)> = Vec::new();
+    for word in words.iter().filter(|word| !normalize(&word.text).is_empty()) {
+        match lines.iter_mut().find(|(line, _)| *line == word.line) {
+            Some((_, rect)) => {
+                let right = (rect.x + rect.width).max(word.bbox.x + word.bbox.width);
+                let bottom = (rect.y + rect.height).max(word.bbox.y + word.bbox.height);
+                rect.x = rect.x.min(word.bbox.x);
+                rect.y = rect.y.min(word.bbox.y);
+                rect.width = right - rect.x;
+                rect.height = bottom - rect.y;
+            },
+            None => lines.push((word.line, word.bbox)),
+        }
+    }
+    lines.into_iter().map(|(_, rect)| rect).collect()
+}
+
+// Grounds the answer's references in the capture's OCR words. Explicit markers come first,
+// then (with `quotes`) quoted text; the same text referenced twice is matched once.
+pub fn ground_answer(answer: &str, words: &[OCRWord], quotes: bool) -> Grounding {
+    let mut references: Vec<(ReferenceKind, String)> = marker_regex()
+        .captures_iter(answer)
+        .map(|captures| (ReferenceKind::Reference, captures[1].to_string()))
+        .collect();
+    let display = marker_regex().replace_all(answer, "$1").to_string();
+    if quotes {
+        for captures in quote_regex().captures_iter(&display) {
+            if let Some(quoted) = (1..=4).find_map(|group| captures.get(group)) {
+                references.push((ReferenceKind::Quote, quoted.as_str().trim().to_string()));
+            }
+        }
+    }
+
+    let mut grounding = Grounding {
+        answer: display,
+        ..Grounding::default()
+    };
+    let mut seen: Vec<String> = Vec::new();
+    for (kind, text) in references {
+        let key = normalize(&text);
+        if key.is_empty() || seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+
+        let matches = find_matches(&text, words);
+        if matches.is_empty() {
+            grounding.unmatched.push(text);
+            continue;
+        }
+        for (first, last, score) in matches {
+            let matched = &words[first..=last];
+            grounding.highlights.push(Highlight {
+                text: text.clone(),
+                matched: matched.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" "),
+                kind,
+                score,
+                boxes: line_boxes(matched),
+            });
+        }
+    }
+
+    if !grounding.highlights.is_empty() || !grounding.unmatched.is_empty() {
+        println!("🎯 Grounded {} highlights, {} references not found in the capture",
+                 grounding.highlights.len(), grounding.unmatched.len());
+    }
+    grounding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Words laid out left to right, 10px per character plus a space, one row per line
+    fn words(lines: &[&str]) -> Vec<OCRWord> {
+        let mut words = Vec::new();
+        for (line, text) in lines.iter().enumerate() {
+            let mut x = 0;
+            for word in text.split_whitespace() {
+                let width = word.chars().count() as u32 * 10;
+                words.push(OCRWord {
+                    text: word.to_string(),
+                    confidence: 0.9,
+                    bbox: TextBox { x, y: line as u32 * 20, width, height: 16 },
+                    line: line as u32,
+                });
+                x += width + 10;
+            }
+        }
+        words
+    }
+
+    #[test]
+    fn matches_quotes_despite_ocr_misreads_and_line_breaks() {
+        let capture = words(&["error: Connecti0n refused (os", "error 111) while reading", "[ OK ] [ Cancel ]"]);
+        let answer = "The \"Connection refused (os error 111)\" message means nothing is listening. \
+                      Don't click 'OK' yet, and ignore the \"stack trace\" for now.";
+        let grounding = ground_answer(answer, &capture, true);
+
+        assert_eq!(grounding.highlights.len(), 2);
+        let refused = &grounding.highlights[0];
+        assert_eq!(refused.kind, ReferenceKind::Quote);
+        assert_eq!(refused.matched, "Connecti0n refused (os error 111)");
+        assert!(refused.score >= MIN_SCORE && refused.score < 1.0);
+        assert_eq!(refused.boxes, vec![
+            TextBox { x: 70, y: 0, width: 220, height: 16 },
+            TextBox { x: 0, y: 20, width: 100, height: 16 },
+        ]);
+        assert_eq!(grounding.highlights[1].matched, "OK");
+        assert_eq!(grounding.unmatched, vec!["stack trace".to_string()]);
+    }
+
+    #[test]
+    fn explicit_references_are_matched_and_stripped_for_display() {
+        let capture = words(&["Name Price Qty", "Widget 4.99 3", "Gadget 12.50 1"]);
+        let answer = "The total is wrong because [[ref: 12.50]] is counted twice; \
+                      \"Gadget\" [[ref: Widget 4.99]] are fine.";
+
+        let grounding = ground_answer(answer, &capture, false);
+        assert_eq!(grounding.answer, "The total is wrong because 12.50 is counted twice; \"Gadget\" Widget 4.99 are fine.");
+        let matched: Vec<_> = grounding.highlights.iter().map(|highlight| (highlight.kind, highlight.matched.as_str())).collect();
+        assert_eq!(matched, vec![(ReferenceKind::Reference, "12.50"), (ReferenceKind::Reference, "Widget 4.99")]);
+        assert_eq!(grounding.highlights[0].boxes, vec![TextBox { x: 70, y: 40, width: 50, height: 16 }]);
+
+        let request = AnalysisRequest {
+            question: "Why is the total wrong?".to_string(),
+            image_data: None,
+            model: None,
+            ocr_text: None,
+            history: Vec::new(),
+            captures: Vec::new(),
+            force_refresh: false,
+            region_references: true,
+        };
+        assert!(with_region_instruction(&request).unwrap().question.contains("[[ref: ...]]"));
+    }
+}
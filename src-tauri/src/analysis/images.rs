@@ -0,0 +1,412 @@
+// Prepares a request's images for the answering model before upload: no more images
+// than it takes per request, each sent at the size the model would scale it to anyway
+// (or split into tiles when that would leave the text unreadable), photos as JPEG,
+// and an estimate of what the images will cost in tokens
+use base64::Engine;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use serde::Serialize;
+use std::io::Cursor;
+
+use super::stream::decode_data_url;
+use super::{AnalysisRequest, LabeledCapture};
+use crate::model_registry::{ImagePricing, ModelEntry};
+
+const JPEG_QUALITY: u8 = 85;
+// Below this scale small UI text stops being legible, so elongated captures are
+// tiled instead when the model takes enough images
+const MIN_READABLE_SCALE: f64 = 0.5;
+// Tiles overlap so a line of text cut by one edge is whole in the next tile
+const TILE_OVERLAP: u32 = 32;
+// Screenshots are mostly flat UI colour, photos almost never repeat a pixel exactly
+const MAX_PHOTO_FLAT_SHARE: f64 = 0.35;
+
+// One uploaded image: a crop of the capture and the size it's sent at
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Piece {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    out_width: u32,
+    out_height: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageCostEstimate {
+    pub model: String,
+    pub images: u32,               // Images uploaded, after tiling
+    pub image_tokens: Option<u32>, // None when the model's image pricing is unknown
+    pub downscaled: bool,
+    pub tiled: bool,
+}
+
+// What the model scales an image of this size down to (never up)
+fn fitted_scale(width: u32, height: u32, model: &ModelEntry) -> f64 {
+    let mut scale: f64 = 1.0;
+    if model.max_image_size > 0 {
+        scale = scale.min(model.max_image_size as f64 / width.max(height) as f64);
+    }
+    if let Some(ImagePricing::Tiles { short_side, .. }) = model.image_pricing {
+        if short_side > 0 {
+            scale = scale.min(short_side as f64 / width.min(height) as f64);
+        }
+    }
+    scale
+}
+
+fn fitted_piece(x: u32, y: u32, width: u32, height: u32, model: &ModelEntry) -> Piece {
+    let scale = fitted_scale(width, height, model);
+    Piece {
+        x,
+        y,
+        width,
+        height,
+        out_width: ((width as f64 * scale).round() as u32).max(1),
+        out_height: ((height as f64 * scale).round() as u32).max(1),
+    }
+}
+
+// Splits a capture that would be shrunk past legibility into roughly square tiles
+// along its long side, using at most `spare` extra images. Tiles keep the capture's
+// short side, so a model that scales the short side down (gpt-4o) shrinks them just as
+// much; tiling is only worth it when the tiles are sent larger than the whole capture.
+fn plan(width: u32, height: u32, model: &ModelEntry, spare: u32) -> Vec<Piece> {
+    let whole = fitted_piece(0, 0, width, height, model);
+    let whole_scale = fitted_scale(width, height, model);
+    if spare == 0 || whole_scale >= MIN_READABLE_SCALE {
+        return vec![whole];
+    }
+
+    let (long, short) = (width.max(height), width.min(height));
+    let count = long.div_ceil(short.max(1)).min(spare + 1);
+    if count < 2 {
+        return vec![whole];
+    }
+    let length = (long + (count - 1) * TILE_OVERLAP).div_ceil(count);
+    let (tile_width, tile_height) = if width >= height { (length, height) } else { (width, length) };
+    if fitted_scale(tile_width, tile_height, model) <= whole_scale {
+        return vec![whole];
+    }
+    (0..count)
+        .map(|index| {
+            let offset = (index * (length - TILE_OVERLAP)).min(long - length);
+            if width >= height {
+                fitted_piece(offset, 0, length, height, model)
+            } else {
+                fitted_piece(0, offset, width, length, model)
+            }
+        })
+        .collect()
+}
+
+// Checks the model takes this many images at all and plans every one of them,
+// sharing the model's spare image slots between the captures in order
+fn plan_request(sizes: &[(u32, u32)], model: &ModelEntry) -> Result<Vec<Vec<Piece>>, String> {
+    if !model.vision || model.max_images == 0 {
+        return Err(format!("{} can't read images, pick a vision model", model.display_name));
+    }
+    if sizes.len() as u32 > model.max_images {
+        return Err(format!(
+            "{} accepts at most {} image(s) per request, {} were selected",
+            model.display_name,
+            model.max_images,
+            sizes.len()
+        ));
+    }
+
+    let mut spare = model.max_images - sizes.len() as u32;
+    Ok(sizes
+        .iter()
+        .map(|(width, height)| {
+            let pieces = plan(*width, *height, model, spare);
+            spare -= pieces.len() as u32 - 1;
+            pieces
+        })
+        .collect())
+}
+
+fn image_size(image_data: &str) -> Result<(u32, u32), String> {
+    let (_, bytes) = decode_data_url(image_data)?;
+    image::io::Reader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to read capture: {}", e))?
+        .into_dimensions()
+        .map_err(|e| format!("Failed to decode capture: {}", e))
+}
+
+fn looks_photographic(image: &DynamicImage) -> bool {
+    let rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    if width < 2 {
+        return false;
+    }
+
+    // Every few rows is plenty to tell a UI from a photo
+    let step = (height / 128).max(1) as usize;
+    let (mut flat, mut pairs) = (0u64, 0u64);
+    for y in (0..height).step_by(step) {
+        for x in 1..width {
+            pairs += 1;
+            if rgb.get_pixel(x, y) == rgb.get_pixel(x - 1, y) {
+                flat += 1;
+            }
+        }
+    }
+    (flat as f64 / pairs as f64) < MAX_PHOTO_FLAT_SHARE
+}
+
+fn encode(image: &DynamicImage, jpeg: bool) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    if jpeg {
+        let rgb = image.to_rgb8();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)
+            .encode(rgb.as_raw(), rgb.width(), rgb.height(), image::ColorType::Rgb8)
+            .map_err(|e| format!("Failed to encode capture: {}", e))?;
+    } else {
+        image
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .map_err(|e| format!("Failed to encode capture: {}", e))?;
+    }
+    Ok(format!(
+        "data:image/{};base64,{}",
+        if jpeg { "jpeg" } else { "png" },
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+// The data URLs to upload for one capture, or None when it's best sent as it is
+fn prepare_image(image_data: &str, pieces: &[Piece]) -> Result<Option<Vec<String>>, String> {
+    let (mime, bytes) = decode_data_url(image_data)?;
+    let image = image::load_from_memory(&bytes).map_err(|e| format!("Failed to decode capture: {}", e))?;
+    let (width, height) = image.dimensions();
+    let photographic = looks_photographic(&image);
+    let jpeg = photographic || mime == "image/jpeg";
+
+    let untouched = pieces.len() == 1 && (pieces[0].out_width, pieces[0].out_height) == (width, height);
+    if untouched && (!photographic || mime == "image/jpeg") {
+        return Ok(None);
+    }
+
+    let prepared = pieces
+        .iter()
+        .map(|piece| {
+            let mut part = image.crop_imm(piece.x, piece.y, piece.width, piece.height);
+            if (piece.out_width, piece.out_height) != (piece.width, piece.height) {
+                part = part.resize_exact(piece.out_width, piece.out_height, FilterType::Lanczos3);
+            }
+            encode(&part, jpeg)
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+    println!(
+        "🗜️ Prepared {}x{} capture as {} {} image(s), {}x{} each",
+        width,
+        height,
+        prepared.len(),
+        if jpeg { "JPEG" } else { "PNG" },
+        pieces[0].out_width,
+        pieces[0].out_height
+    );
+    Ok(Some(prepared))
+}
+
+fn tile_label(label: Option<&str>, index: usize, count: usize) -> String {
+    match label {
+        Some(label) => format!("{} (part {} of {})", label, index + 1, count),
+        None => format!("Part {} of {}", index + 1, count),
+    }
+}
+
+fn estimate_pieces(model: &ModelEntry, planned: &[Vec<Piece>]) -> ImageCostEstimate {
+    let pieces: Vec<&Piece> = planned.iter().flatten().collect();
+    ImageCostEstimate {
+        model: model.id.clone(),
+        images: pieces.len() as u32,
+        image_tokens: model
+            .image_pricing
+            .map(|pricing| pieces.iter().map(|piece| pricing.tokens(piece.out_width, piece.out_height)).sum()),
+        downscaled: pieces.iter().any(|piece| (piece.out_width, piece.out_height) != (piece.width, piece.height)),
+        tiled: planned.iter().any(|pieces| pieces.len() > 1),
+    }
+}
+
+// Image token cost of a request without touching its pixels, for showing before sending
+pub fn estimate(request: &AnalysisRequest, model: &ModelEntry) -> Result<ImageCostEstimate, String> {
+    let sizes = request
+        .images()
+        .iter()
+        .map(|(_, image_data)| image_size(image_data))
+        .collect::<Result<Vec<_>, String>>()?;
+    if sizes.is_empty() {
+        return Ok(estimate_pieces(model, &[]));
+    }
+    let planned = plan_request(&sizes, model)?;
+    Ok(estimate_pieces(model, &planned))
+}
+
+// Models outside the registry (e.g. a local BYOK model) have no known limits, so
+// their requests are sent as they are. Tiled captures turn the request into a
+// comparison of labelled parts.
+pub fn prepare_for_model(
+    request: &mut AnalysisRequest,
+    model: Option<&ModelEntry>,
+) -> Result<Option<ImageCostEstimate>, String> {
+    let Some(model) = model else {
+        return Ok(None);
+    };
+    let images: Vec<(Option<String>, String)> = request
+        .images()
+        .iter()
+        .map(|(label, image_data)| (label.map(str::to_string), image_data.to_string()))
+        .collect();
+    if images.is_empty() {
+        return Ok(None);
+    }
+
+    let sizes = images
+        .iter()
+        .map(|(_, image_data)| image_size(image_data))
+        .collect::<Result<Vec<_>, String>>()?;
+    let planned = plan_request(&sizes, model)?;
+
+    let mut captures = Vec::new();
+    for ((label, image_data), pieces) in images.iter().zip(&planned) {
+        let prepared = prepare_image(image_data, pieces)?.unwrap_or_else(|| vec![image_data.clone()]);
+        let count = prepared.len();
+        for (index, image_data) in prepared.into_iter().enumerate() {
+            let label = match count {
+                1 => label.clone(),
+                _ => Some(tile_label(label.as_deref(), index, count)),
+            };
+            captures.push((label, image_data));
+        }
+    }
+
+    if captures.len() == 1 && captures[0].0.is_none() {
+        request.image_data = captures.pop().map(|(_, image_data)| image_data);
+    } else {
+        request.image_data = None;
+        request.captures = captures
+            .into_iter()
+            .map(|(label, image_data)| LabeledCapture {
+                label: label.unwrap_or_default(),
+                image_data,
+            })
+            .collect();
+    }
+    Ok(Some(estimate_pieces(model, &planned)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn model(max_image_size: u32, max_images: u32, image_pricing: Option<ImagePricing>) -> ModelEntry {
+        ModelEntry {
+            id: "test-vision".to_string(),
+            display_name: "Test Vision".to_string(),
+            provider: "openai".to_string(),
+            required_tier: "free".to_string(),
+            vision: true,
+            context_window: 128000,
+            max_image_size,
+            max_images,
+            image_pricing,
+            cost_per_call: 0.0,
+        }
+    }
+
+    fn png(image: RgbImage) -> String {
+        encode(&DynamicImage::ImageRgb8(image), false).unwrap()
+    }
+
+    fn request(image_data: String) -> AnalysisRequest {
+        AnalysisRequest {
+            question: "What does this say?".to_string(),
+            image_data: Some(image_data),
+            model: None,
+            ocr_text: None,
+            history: Vec::new(),
+            captures: Vec::new(),
+            force_refresh: false,
+            region_references: false,
+        }
+    }
+
+    #[test]
+    fn prices_tiles_and_area() {
+        let openai = ImagePricing::Tiles { tile_size: 512, tokens_per_tile: 170, base_tokens: 85, short_side: 768 };
+        // A 2048x4096 capture is sent as 768x1536: 2x3 tiles
+        let sent = fitted_piece(0, 0, 2048, 4096, &model(2048, 1, Some(openai)));
+        assert_eq!((sent.out_width, sent.out_height), (768, 1536));
+        assert_eq!(openai.tokens(sent.out_width, sent.out_height), 85 + 6 * 170);
+        assert_eq!(ImagePricing::Area { pixels_per_token: 750 }.tokens(1000, 1000), 1334);
+    }
+
+    #[test]
+    fn tiles_tall_captures_instead_of_shrinking_them() {
+        let claude = model(1568, 20, Some(ImagePricing::Area { pixels_per_token: 750 }));
+        let pieces = plan(1000, 6000, &claude, 19);
+        assert_eq!(pieces.len(), 6);
+        assert!(pieces.iter().all(|piece| (piece.out_width, piece.out_height) == (piece.width, piece.height)));
+        assert_eq!(pieces.last().map(|piece| piece.y + piece.height), Some(6000));
+        assert!(pieces.windows(2).all(|pair| pair[1].y < pair[0].y + pair[0].height));
+
+        // Without spare images it can only be shrunk
+        let single = plan(1000, 6000, &model(1568, 1, None), 0);
+        assert_eq!(single.len(), 1);
+        assert_eq!((single[0].out_width, single[0].out_height), (261, 1568));
+    }
+
+    #[test]
+    fn tiles_only_when_the_tiles_are_sent_larger() {
+        let openai = ImagePricing::Tiles { tile_size: 512, tokens_per_tile: 170, base_tokens: 85, short_side: 768 };
+        let gpt_4o = model(2048, 10, Some(openai));
+
+        // Tiles of a wide capture keep its 2000px short side, which is scaled to 768 either way
+        let wide = plan(4000, 2000, &gpt_4o, 9);
+        assert_eq!(wide.len(), 1);
+        assert_eq!((wide[0].out_width, wide[0].out_height), (1536, 768));
+
+        // A tall, narrow capture is shrunk by its long side, which tiles do fix
+        let tall = plan(1000, 6000, &gpt_4o, 9);
+        assert_eq!(tall.len(), 6);
+        assert!(tall.iter().all(|piece| piece.out_width == 768));
+    }
+
+    #[test]
+    fn prepares_screenshots_as_png_and_photos_as_jpeg() {
+        let screenshot = RgbImage::from_fn(800, 600, |x, _| if x < 200 { Rgb([30, 30, 30]) } else { Rgb([250, 250, 250]) });
+        let photo = RgbImage::from_fn(800, 600, |x, y| {
+            let noise = (x.wrapping_mul(7919) ^ y.wrapping_mul(104729)).wrapping_mul(2654435761) >> 24;
+            Rgb([noise as u8, (x / 4) as u8, (y / 3) as u8])
+        });
+        let small = model(400, 1, Some(ImagePricing::Tiles { tile_size: 512, tokens_per_tile: 170, base_tokens: 85, short_side: 0 }));
+
+        let mut screenshot_request = request(png(screenshot));
+        let estimate = prepare_for_model(&mut screenshot_request, Some(&small)).unwrap().unwrap();
+        assert!(screenshot_request.image_data.as_deref().unwrap().starts_with("data:image/png"));
+        assert_eq!(image_size(screenshot_request.image_data.as_deref().unwrap()).unwrap(), (400, 300));
+        assert_eq!(estimate.image_tokens, Some(85 + 170));
+        assert!(estimate.downscaled && !estimate.tiled);
+
+        let mut photo_request = request(png(photo));
+        prepare_for_model(&mut photo_request, Some(&model(2048, 1, None))).unwrap();
+        assert!(photo_request.image_data.as_deref().unwrap().starts_with("data:image/jpeg"));
+    }
+
+    #[test]
+    fn rejects_more_images_than_the_model_takes() {
+        let mut comparison = request(String::new());
+        comparison.image_data = None;
+        let image = png(RgbImage::new(10, 10));
+        comparison.captures = (1..=3)
+            .map(|index| LabeledCapture { label: format!("Capture {}", index), image_data: image.clone() })
+            .collect();
+
+        let error = prepare_for_model(&mut comparison, Some(&model(2048, 2, None))).unwrap_err();
+        assert!(error.contains("at most 2"), "{}", error);
+        assert!(estimate(&comparison, &model(2048, 4, None)).is_ok());
+    }
+}
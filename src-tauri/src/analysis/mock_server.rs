@@ -0,0 +1,75 @@
+// One-shot local HTTP server standing in for analysis endpoints in tests
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+pub struct CapturedRequest {
+    pub head: String,
+    pub body: Vec<u8>,
+}
+
+impl CapturedRequest {
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).expect("request body is not JSON")
+    }
+}
+
+// Records the first request and replies with `chunks`, each written as its
+// own chunked-encoding frame so clients see them arrive separately
+pub async fn serve_once(
+    status: &'static str,
+    content_type: &'static str,
+    chunks: Vec<Vec<u8>>,
+) -> (String, tokio::task::JoinHandle<CapturedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 8192];
+        let head_end = loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            received.extend_from_slice(&buffer[..read]);
+            if let Some(position) = received.windows(4).position(|window| window == b"\r\n\r\n") {
+                break position + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&received[..head_end]).to_string();
+        let content_length = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        while received.len() < head_end + content_length {
+            let read = socket.read(&mut buffer).await.unwrap();
+            received.extend_from_slice(&buffer[..read]);
+        }
+
+        let header = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            status, content_type
+        );
+        socket.write_all(header.as_bytes()).await.unwrap();
+        for chunk in chunks {
+            socket.write_all(format!("{:x}\r\n", chunk.len()).as_bytes()).await.unwrap();
+            socket.write_all(&chunk).await.unwrap();
+            socket.write_all(b"\r\n").await.unwrap();
+            socket.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        socket.write_all(b"0\r\n\r\n").await.unwrap();
+
+        CapturedRequest {
+            head,
+            body: received[head_end..].to_vec(),
+        }
+    });
+
+    (url, handle)
+}
+
+pub fn text_chunks(chunks: &[&str]) -> Vec<Vec<u8>> {
+    chunks.iter().map(|chunk| chunk.as_bytes().to_vec()).collect()
+}
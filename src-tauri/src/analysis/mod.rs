@@ -0,0 +1,277 @@
+// Analysis providers - the FrameSense backend by default, or a bring-your-own-key
+// OpenAI-compatible endpoint for users who can't route captures through us
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::errors::FrameSenseError;
+use crate::model_registry::ModelRegistry;
+use crate::secrets::SecretStore;
+
+pub mod backend;
+pub mod cache;
+pub mod fallback;
+pub mod grounding;
+pub mod images;
+pub mod openai_compatible;
+mod stream;
+#[cfg(test)]
+mod mock_server;
+
+pub use backend::BackendClient;
+pub use cache::ResponseCache;
+pub use images::prepare_for_model;
+pub use openai_compatible::OpenAiCompatibleClient;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisRequest {
+    pub question: String,
+    pub image_data: Option<String>, // Data URL or bare base64, same as capture results
+    pub model: Option<String>,
+    pub ocr_text: Option<String>,
+    #[serde(default)]
+    pub history: Vec<ChatTurn>, // Earlier turns of the thread, oldest first
+    #[serde(default)]
+    pub captures: Vec<LabeledCapture>, // Several captures to compare; replaces image_data when set
+    #[serde(default)]
+    pub force_refresh: bool, // Skip the response cache and ask the model again
+    #[serde(default)]
+    pub region_references: bool, // Ask the model to mark the capture text it refers to, for grounding
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LabeledCapture {
+    pub label: String, // e.g. "Build 1.4" or "Staging config"
+    pub image_data: String,
+}
+
+impl AnalysisRequest {
+    // Every image the request carries, with its label when it's part of a comparison
+    pub fn images(&self) -> Vec<(Option<&str>, &str)> {
+        if self.captures.is_empty() {
+            self.image_data.iter().map(|image| (None, image.as_str())).collect()
+        } else {
+            self.captures
+                .iter()
+                .map(|capture| (Some(capture.label.as_str()), capture.image_data.as_str()))
+                .collect()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChatTurn {
+    pub role: String, // "user" or "assistant"
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisResult {
+    pub answer: String,
+    pub model: Option<String>,
+    pub tokens_used: Option<u64>,
+    pub streamed: bool, // False when the provider answered with a single JSON body
+    pub cached: bool, // True when served from the response cache instead of the provider
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ProviderKind {
+    #[serde(rename = "backend")]
+    Backend,
+    #[serde(rename = "openai_compatible")]
+    OpenAiCompatible,
+}
+
+// Secret store entry holding the OpenAI-compatible provider's API key
+const API_KEY_SECRET: &str = "analysis_provider_api_key";
+
+// Persisted provider choice; the API key is only used for the OpenAI-compatible provider.
+// It lives in the secret store; the settings file only ever had it in older versions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderSettings {
+    pub kind: ProviderKind,
+    #[serde(default)]
+    pub base_url: String,
+    #[serde(default, skip_serializing)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub model: String,
+}
+
+impl Default for ProviderSettings {
+    fn default() -> Self {
+        Self {
+            kind: ProviderKind::Backend,
+            base_url: String::new(),
+            api_key: None,
+            model: String::new(),
+        }
+    }
+}
+
+impl ProviderSettings {
+    // A missing or unreadable file means the default backend provider. A key still in
+    // the file is moved into the secret store.
+    pub fn load(path: &Path, secrets: &dyn SecretStore) -> Self {
+        let mut settings: Self = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                println!("⚠️ Failed to parse analysis provider settings, using backend: {}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+
+        if settings.api_key.is_some() {
+            match settings.save(path, secrets) {
+                Ok(()) => println!("🔐 Moved the analysis provider API key into the {} store", secrets.backend()),
+                Err(e) => println!("⚠️ Failed to move the analysis provider API key out of {:?}: {}", path, e),
+            }
+        } else {
+            settings.api_key = secrets.get(API_KEY_SECRET).unwrap_or_else(|e| {
+                println!("⚠️ Failed to read the analysis provider API key: {}", e);
+                None
+            });
+        }
+        settings
+    }
+
+    // Tells providers apart for the response cache; the backend by environment, BYOK
+    // endpoints by URL
+    pub fn cache_scope(&self, backend_url: &str) -> String {
+        match self.kind {
+            ProviderKind::Backend => format!("backend:{}", backend_url.trim_end_matches('/')),
+            ProviderKind::OpenAiCompatible => format!("openai_compatible:{}", self.base_url.trim_end_matches('/')),
+        }
+    }
+
+    // The key goes to the secret store first, so a failure there leaves the file untouched
+    pub fn save(&self, path: &Path, secrets: &dyn SecretStore) -> Result<(), String> {
+        match &self.api_key {
+            Some(api_key) => secrets.set(API_KEY_SECRET, api_key)?,
+            None => secrets.delete(API_KEY_SECRET)?,
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize provider settings: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write provider settings: {}", e))
+    }
+}
+
+pub enum AnalysisProvider {
+    Backend(BackendClient),
+    OpenAiCompatible(OpenAiCompatibleClient),
+}
+
+impl AnalysisProvider {
+    pub fn from_settings(
+        settings: &ProviderSettings,
+        backend_url: &str,
+        session_token: Option<String>,
+    ) -> Result<Self, String> {
+        match settings.kind {
+            ProviderKind::Backend => Ok(Self::Backend(BackendClient::new(backend_url).with_token(session_token))),
+            ProviderKind::OpenAiCompatible => Ok(Self::OpenAiCompatible(
+                OpenAiCompatibleClient::new(&settings.base_url, &settings.model)?
+                    .with_api_key(settings.api_key.clone()),
+            )),
+        }
+    }
+
+    // The model that will actually answer; BYOK always uses its configured model
+    pub fn effective_model(&self, request: &AnalysisRequest) -> Option<String> {
+        match self {
+            Self::Backend(_) => request.model.clone(),
+            Self::OpenAiCompatible(client) => Some(client.model().to_string()),
+        }
+    }
+
+    pub async fn analyze(
+        &self,
+        request: &AnalysisRequest,
+        on_chunk: impl FnMut(&str),
+    ) -> Result<AnalysisResult, FrameSenseError> {
+        match self {
+            Self::Backend(client) => client.analyze(request, on_chunk).await,
+            Self::OpenAiCompatible(client) => client.analyze(request, on_chunk).await,
+        }
+    }
+}
+
+// Tier and model gating hooks every provider goes through before a request is sent
+pub trait ModelGate {
+    fn can_use_model(&self, user_tier: &str, model: &str) -> bool;
+    fn required_tier(&self, model: &str) -> Option<String>; // None for models outside the catalog
+}
+
+impl ModelGate for ModelRegistry {
+    fn can_use_model(&self, user_tier: &str, model: &str) -> bool {
+        ModelRegistry::can_use_model(self, user_tier, model)
+    }
+
+    fn required_tier(&self, model: &str) -> Option<String> {
+        ModelRegistry::required_tier(self, model).map(str::to_string)
+    }
+}
+
+// Catalog models are gated by tier whichever provider serves them. Models outside
+// the catalog are refused on our backend, which only serves catalog models, but
+// aren't ours to gate on a BYOK endpoint (e.g. a local llava).
+pub fn check_model_access(gate: &impl ModelGate, kind: ProviderKind, user_tier: &str, model: &str) -> Result<(), FrameSenseError> {
+    match gate.required_tier(model) {
+        Some(required) if !gate.can_use_model(user_tier, model) => Err(FrameSenseError::TierRequired {
+            model: model.to_string(),
+            required_tier: required,
+            current_tier: user_tier.to_string(),
+        }),
+        None if kind == ProviderKind::Backend => Err(FrameSenseError::Other(format!(
+            "Model {} is not offered by FrameSense; pick one from the model list or use your own endpoint",
+            model
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalog_models_are_gated_for_every_provider() {
+        let registry = ModelRegistry::bundled();
+        let byok = ProviderKind::OpenAiCompatible;
+        assert!(check_model_access(&registry, ProviderKind::Backend, "free", "GPT-3.5-turbo").is_ok());
+        assert!(check_model_access(&registry, ProviderKind::Backend, "pro", "gpt-4o").is_ok());
+
+        // API-style ids on a BYOK endpoint still map onto the catalog
+        let error = check_model_access(&registry, byok, "free", "gpt-4o-mini").unwrap_err();
+        assert!(matches!(&error, FrameSenseError::TierRequired { required_tier, .. } if required_tier == "premium"), "{}", error);
+        assert!(check_model_access(&registry, ProviderKind::Backend, "premium", "claude-3-opus").is_err());
+
+        // Self-hosted models outside the catalog are not gated; our backend doesn't serve them
+        assert!(check_model_access(&registry, byok, "free", "llava:13b").is_ok());
+        let error = check_model_access(&registry, ProviderKind::Backend, "enterprise", "llava:13b").unwrap_err();
+        assert_eq!(error.code(), "other");
+    }
+
+    #[test]
+    fn api_keys_are_kept_out_of_the_settings_file() {
+        let dir = std::env::temp_dir().join(format!("framesense-provider-{}", std::process::id()));
+        let path = dir.join("analysis_provider.json");
+        let secrets = crate::secrets::EncryptedFileStore::with_machine_secret(dir.join("secrets.enc"), b"test-machine".to_vec());
+
+        // Older versions wrote the key into the file; loading moves it
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, r#"{"kind":"openai_compatible","base_url":"http://localhost:11434/v1","api_key":"sk-legacy","model":"llava"}"#).unwrap();
+        let settings = ProviderSettings::load(&path, &secrets);
+        assert_eq!(settings.api_key.as_deref(), Some("sk-legacy"));
+        assert!(!fs::read_to_string(&path).unwrap().contains("sk-legacy"));
+        assert_eq!(ProviderSettings::load(&path, &secrets).api_key.as_deref(), Some("sk-legacy"));
+
+        // Saving without a key removes it from the store too
+        ProviderSettings { api_key: None, ..settings }.save(&path, &secrets).unwrap();
+        assert_eq!(ProviderSettings::load(&path, &secrets).api_key, None);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,317 @@
+// Bring-your-own-key provider - speaks chat completions with vision content parts
+// directly to any OpenAI-compatible endpoint (OpenAI itself, proxies, or local
+// servers such as Ollama, LM Studio and vLLM), so captures never touch our backend
+use std::time::Duration;
+
+use super::stream::{content_type, provider_error, read_stream};
+use super::{AnalysisRequest, AnalysisResult};
+use crate::errors::FrameSenseError;
+
+#[derive(Clone)]
+pub struct OpenAiCompatibleClient {
+    endpoint: String,
+    api_key: Option<String>,
+    model: String,
+    timeout: Duration,
+}
+
+// Accepts either the API root (".../v1") or the full chat completions URL
+pub fn chat_completions_url(base_url: &str) -> Result<String, String> {
+    let base_url = base_url.trim().trim_end_matches('/');
+    let parsed = url::Url::parse(base_url).map_err(|e| format!("Invalid provider URL '{}': {}", base_url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Provider URL must be http or https: {}", base_url));
+    }
+
+    if base_url.ends_with("/chat/completions") {
+        Ok(base_url.to_string())
+    } else {
+        Ok(format!("{}/chat/completions", base_url))
+    }
+}
+
+impl OpenAiCompatibleClient {
+    pub fn new(base_url: &str, model: &str) -> Result<Self, String> {
+        if model.trim().is_empty() {
+            return Err("No model configured for the OpenAI-compatible provider".to_string());
+        }
+        Ok(Self {
+            endpoint: chat_completions_url(base_url)?,
+            api_key: None,
+            model: model.trim().to_string(),
+            // Local servers can take a while to load a vision model on first use
+            timeout: Duration::from_secs(300),
+        })
+    }
+
+    // Local servers usually run without a key, so an empty key sends no Authorization header
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key.filter(|key| !key.trim().is_empty());
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    // The capture (image and OCR text) rides on the first user message; follow-ups
+    // replay the thread so the model sees what it already said about the capture
+    fn request_body(&self, request: &AnalysisRequest) -> serde_json::Value {
+        let mut messages: Vec<serde_json::Value> = request
+            .history
+            .iter()
+            .map(|turn| serde_json::json!({ "role": turn.role, "content": turn.content }))
+            .collect();
+        messages.push(serde_json::json!({ "role": "user", "content": request.question }));
+
+        // Labelled captures are introduced by a text part so the model can refer to them by name
+        let mut capture = vec![serde_json::json!({ "type": "text", "text": "" })];
+        for (index, (label, image_data)) in request.images().into_iter().enumerate() {
+            if let Some(label) = label {
+                capture.push(serde_json::json!({ "type": "text", "text": format!("Capture {}: {}", index + 1, label) }));
+            }
+            let url = if image_data.starts_with("data:") {
+                image_data.to_string()
+            } else {
+                format!("data:image/png;base64,{}", image_data)
+            };
+            capture.push(serde_json::json!({ "type": "image_url", "image_url": { "url": url } }));
+        }
+
+        if let Some(first_user) = messages.iter_mut().find(|message| message["role"] == "user") {
+            let mut text = first_user["content"].as_str().unwrap_or_default().to_string();
+            if let Some(ocr_text) = request.ocr_text.as_ref().filter(|text| !text.trim().is_empty()) {
+                text.push_str("\n\nText recognized in the capture:\n");
+                text.push_str(ocr_text);
+            }
+            capture[0]["text"] = serde_json::json!(text);
+            first_user["content"] = serde_json::json!(capture);
+        }
+
+        serde_json::json!({
+            "model": self.model,
+            "stream": true,
+            "messages": messages
+        })
+    }
+
+    // Calls `on_chunk` with each piece of answer text as it arrives and returns
+    // the assembled answer once the stream ends
+    pub async fn analyze(
+        &self,
+        request: &AnalysisRequest,
+        mut on_chunk: impl FnMut(&str),
+    ) -> Result<AnalysisResult, FrameSenseError> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let mut http_request = client
+            .post(&self.endpoint)
+            .header("Accept", "text/event-stream, application/json")
+            .json(&self.request_body(request));
+        if let Some(api_key) = &self.api_key {
+            http_request = http_request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = http_request
+            .send()
+            .await
+            .map_err(|e| FrameSenseError::from(e).map_message(|message| format!("{} ({})", message, self.endpoint)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error = provider_error(status.as_u16(), &response.text().await.unwrap_or_default());
+            return Err(match error {
+                FrameSenseError::Unauthorized(message) => {
+                    FrameSenseError::Unauthorized(format!("API key rejected by {} ({})", self.endpoint, message))
+                },
+                error => error.map_message(|message| format!("Provider error: {}", message)),
+            });
+        }
+
+        // Servers that ignore `stream` answer with a regular completion object
+        if content_type(&response).starts_with("application/json") {
+            let body: serde_json::Value = response.json().await.map_err(|e| FrameSenseError::Server {
+                message: format!("Unreadable provider response: {}", e),
+                status: None,
+            })?;
+            let answer = body["choices"][0]["message"]["content"]
+                .as_str()
+                .unwrap_or("No response")
+                .to_string();
+            on_chunk(&answer);
+            return Ok(AnalysisResult {
+                answer,
+                model: body["model"].as_str().map(str::to_string).or_else(|| Some(self.model.clone())),
+                tokens_used: body["usage"]["total_tokens"].as_u64(),
+                streamed: false,
+                cached: false,
+            });
+        }
+
+        let mut result = AnalysisResult {
+            answer: String::new(),
+            model: Some(self.model.clone()),
+            tokens_used: None,
+            streamed: true,
+            cached: false,
+        };
+        read_stream(response, &mut result, &mut on_chunk).await?;
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_server::{serve_once, text_chunks};
+    use super::super::{ChatTurn, LabeledCapture};
+
+    fn request() -> AnalysisRequest {
+        AnalysisRequest {
+            question: "Summarize this dialog".to_string(),
+            image_data: Some("iVBORw0KGgo=".to_string()),
+            model: None,
+            ocr_text: Some("Save changes before closing?".to_string()),
+            history: Vec::new(),
+            captures: Vec::new(),
+            force_refresh: false,
+            region_references: false,
+        }
+    }
+
+    #[test]
+    fn normalizes_endpoint_urls() {
+        assert_eq!(
+            chat_completions_url("http://localhost:11434/v1/").unwrap(),
+            "http://localhost:11434/v1/chat/completions"
+        );
+        assert_eq!(
+            chat_completions_url("https://api.openai.com/v1/chat/completions").unwrap(),
+            "https://api.openai.com/v1/chat/completions"
+        );
+        assert!(chat_completions_url("ftp://example.com").is_err());
+        assert!(chat_completions_url("not a url").is_err());
+    }
+
+    #[test]
+    fn follow_ups_replay_the_thread_with_the_capture_first() {
+        let client = OpenAiCompatibleClient::new("http://localhost:11434/v1", "llava").unwrap();
+        let mut follow_up = request();
+        follow_up.question = "And what about line 3?".to_string();
+        follow_up.history = vec![
+            ChatTurn { role: "user".to_string(), content: "Summarize this dialog".to_string() },
+            ChatTurn { role: "assistant".to_string(), content: "It asks to save changes.".to_string() },
+        ];
+
+        let body = client.request_body(&follow_up);
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["content"][0]["text"], "Summarize this dialog\n\nText recognized in the capture:\nSave changes before closing?");
+        assert_eq!(messages[0]["content"][1]["type"], "image_url");
+        assert_eq!(messages[1]["role"], "assistant");
+        assert_eq!(messages[1]["content"], "It asks to save changes.");
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"], "And what about line 3?");
+    }
+
+    #[test]
+    fn comparison_captures_are_introduced_by_their_labels() {
+        let client = OpenAiCompatibleClient::new("http://localhost:11434/v1", "llava").unwrap();
+        let mut comparison = request();
+        comparison.image_data = None;
+        comparison.captures = vec![
+            LabeledCapture { label: "Staging config".to_string(), image_data: "AAAA".to_string() },
+            LabeledCapture { label: "Production config".to_string(), image_data: "data:image/jpeg;base64,BBBB".to_string() },
+        ];
+
+        let body = client.request_body(&comparison);
+        let content = body["messages"][0]["content"].as_array().unwrap();
+        assert_eq!(content.len(), 5);
+        assert_eq!(content[1]["text"], "Capture 1: Staging config");
+        assert_eq!(content[2]["image_url"]["url"], "data:image/png;base64,AAAA");
+        assert_eq!(content[3]["text"], "Capture 2: Production config");
+        assert_eq!(content[4]["image_url"]["url"], "data:image/jpeg;base64,BBBB");
+    }
+
+    #[tokio::test]
+    async fn streams_chat_completion_deltas() {
+        let (url, server) = serve_once("200 OK", "text/event-stream", text_chunks(&[
+            "data: {\"model\":\"llava:13b\",\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"A save \"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"prompt.\"}}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"total_tokens\":321}}\n\ndata: [DONE]\n\n",
+        ]))
+        .await;
+
+        let client = OpenAiCompatibleClient::new(&format!("{}/v1", url), "llava:13b")
+            .unwrap()
+            .with_api_key(Some("sk-test".to_string()));
+        let mut chunks = Vec::new();
+        let result = client.analyze(&request(), |chunk| chunks.push(chunk.to_string())).await.unwrap();
+
+        assert_eq!(chunks, vec!["A save ", "prompt."]);
+        assert_eq!(result.answer, "A save prompt.");
+        assert_eq!(result.model.as_deref(), Some("llava:13b"));
+        assert_eq!(result.tokens_used, Some(321));
+
+        let captured = server.await.unwrap();
+        assert!(captured.head.starts_with("POST /v1/chat/completions "));
+        assert!(captured.head.to_lowercase().contains("authorization: bearer sk-test"));
+
+        let body = captured.json();
+        assert_eq!(body["model"], "llava:13b");
+        assert_eq!(body["stream"], true);
+        let content = &body["messages"][0]["content"];
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(content[0]["type"], "text");
+        assert!(content[0]["text"].as_str().unwrap().contains("Save changes before closing?"));
+        assert_eq!(content[1]["type"], "image_url");
+        assert_eq!(content[1]["image_url"]["url"], "data:image/png;base64,iVBORw0KGgo=");
+    }
+
+    #[tokio::test]
+    async fn local_servers_need_no_key_and_may_not_stream() {
+        let (url, server) = serve_once("200 OK", "application/json", text_chunks(&[
+            "{\"model\":\"qwen2-vl\",\"choices\":[{\"message\":{\"role\":\"assistant\",\"content\":\"Looks fine.\"}}],\"usage\":{\"total_tokens\":12}}",
+        ]))
+        .await;
+
+        let client = OpenAiCompatibleClient::new(&url, "qwen2-vl").unwrap().with_api_key(Some(String::new()));
+        let result = client.analyze(&request(), |_| {}).await.unwrap();
+        assert_eq!(result.answer, "Looks fine.");
+        assert_eq!(result.tokens_used, Some(12));
+        assert!(!result.streamed);
+
+        let captured = server.await.unwrap();
+        assert!(!captured.head.to_lowercase().contains("authorization:"));
+    }
+
+    #[tokio::test]
+    async fn surfaces_openai_error_bodies() {
+        let (url, _server) = serve_once("401 Unauthorized", "application/json", text_chunks(&[
+            "{\"error\":{\"message\":\"Incorrect API key provided\",\"type\":\"invalid_request_error\"}}",
+        ]))
+        .await;
+
+        let client = OpenAiCompatibleClient::new(&url, "gpt-4o-mini").unwrap().with_api_key(Some("bad".to_string()));
+        let error = client.analyze(&request(), |_| {}).await.unwrap_err();
+        assert_eq!(error.code(), "unauthorized");
+        assert!(error.to_string().contains("Incorrect API key provided"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn content_policy_refusals_keep_their_kind() {
+        let (url, _server) = serve_once("400 Bad Request", "application/json", text_chunks(&[
+            "{\"error\":{\"message\":\"Your request was rejected by the safety system\",\"code\":\"content_policy_violation\"}}",
+        ]))
+        .await;
+
+        let client = OpenAiCompatibleClient::new(&url, "gpt-4o").unwrap();
+        let error = client.analyze(&request(), |_| {}).await.unwrap_err();
+        assert_eq!(error.code(), "content_policy");
+        assert!(!error.is_transient());
+    }
+}
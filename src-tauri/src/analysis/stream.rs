@@ -0,0 +1,250 @@
+// Streaming response plumbing shared by the analysis providers
+use base64::Engine;
+
+use super::AnalysisResult;
+use crate::errors::FrameSenseError;
+
+// Consumes a streamed body into `result`, forwarding each piece of answer text.
+// SSE bodies are parsed as events; anything else is treated as raw chunked text.
+pub(super) async fn read_stream(
+    mut response: reqwest::Response,
+    result: &mut AnalysisResult,
+    on_chunk: &mut impl FnMut(&str),
+) -> Result<(), FrameSenseError> {
+    let is_sse = content_type(&response).starts_with("text/event-stream");
+    let mut sse = SseParser::new();
+    let mut text = Utf8Buffer::new();
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(stream_interrupted)?
+    {
+        if is_sse {
+            for event in sse.push(&chunk) {
+                if apply_sse_event(&event, result, on_chunk)? {
+                    return Ok(());
+                }
+            }
+        } else {
+            let delta = text.push(&chunk);
+            if !delta.is_empty() {
+                result.answer.push_str(&delta);
+                on_chunk(&delta);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) fn content_type(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+fn stream_interrupted(error: reqwest::Error) -> FrameSenseError {
+    if error.is_timeout() {
+        FrameSenseError::Timeout(format!("Answer stream timed out: {}", error))
+    } else {
+        FrameSenseError::Server { message: format!("Stream interrupted: {}", error), status: None }
+    }
+}
+
+// Pulls a readable message out of an error body: { message }, { error: { message } } or { error }
+fn error_message(body: &serde_json::Value) -> Option<String> {
+    body["message"]
+        .as_str()
+        .or_else(|| body["error"]["message"].as_str())
+        .or_else(|| body["error"].as_str())
+        .map(str::to_string)
+}
+
+// OpenAI reports refusals as { error: { code: "content_policy_violation" } }, Azure as
+// "content_filter"; the type field is checked too since proxies move it around
+fn is_content_policy(body: &serde_json::Value) -> bool {
+    [&body["code"], &body["type"], &body["error"]["code"], &body["error"]["type"]]
+        .iter()
+        .filter_map(|value| value.as_str())
+        .any(|code| code.contains("content_policy") || code.contains("content_filter"))
+}
+
+// The typed error for a non-success response: the kind follows the HTTP status unless
+// the body says the content was refused
+pub(super) fn provider_error(status: u16, body: &str) -> FrameSenseError {
+    let body = serde_json::from_str::<serde_json::Value>(body).unwrap_or_default();
+    let message = error_message(&body).unwrap_or_else(|| format!("HTTP {}", status));
+    if is_content_policy(&body) {
+        return FrameSenseError::ContentPolicy(message);
+    }
+    FrameSenseError::from_status(status, format!("{} (HTTP {})", message, status))
+}
+
+// An `event: error` sent after the stream started: the HTTP status was already 200, so
+// the kind comes from a status in the payload; without one it's a server failure
+fn stream_error(data: &str) -> FrameSenseError {
+    let body = serde_json::from_str::<serde_json::Value>(data).unwrap_or_default();
+    let message = error_message(&body).unwrap_or_else(|| data.trim().to_string());
+    if is_content_policy(&body) {
+        return FrameSenseError::ContentPolicy(message);
+    }
+    let status = [&body["status"], &body["error"]["status"], &body["error"]["code"], &body["code"]]
+        .iter()
+        .find_map(|value| value.as_u64())
+        .and_then(|status| u16::try_from(status).ok())
+        .filter(|status| (400..600).contains(status));
+    match status {
+        Some(status) => FrameSenseError::from_status(status, format!("{} (HTTP {})", message, status)),
+        None => FrameSenseError::Server { message, status: None },
+    }
+}
+
+// Splits `data:image/png;base64,...` into mime type and bytes; bare base64 is assumed PNG
+pub(super) fn decode_data_url(image_data: &str) -> Result<(String, Vec<u8>), String> {
+    let (mime, base64_data) = match image_data.strip_prefix("data:") {
+        Some(rest) => {
+            let (header, data) = rest.split_once(',').ok_or("Malformed image data URL")?;
+            (header.trim_end_matches(";base64").to_string(), data)
+        },
+        None => ("image/png".to_string(), image_data),
+    };
+
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64_data)
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    Ok((mime, bytes))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+// Incremental text/event-stream parser; chunks can split lines and even UTF-8 sequences
+pub(super) struct SseParser {
+    pending: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+            event: None,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.pending.extend_from_slice(bytes);
+        let mut events = Vec::new();
+
+        while let Some(newline) = self.pending.iter().position(|&byte| byte == b'\n') {
+            let line_bytes: Vec<u8> = self.pending.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line_bytes);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                // Blank line dispatches the event
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take(),
+                        data: self.data.join("\n"),
+                    });
+                }
+                self.data.clear();
+                self.event = None;
+                continue;
+            }
+            if line.starts_with(':') {
+                continue; // Keep-alive comment
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "data" => self.data.push(value.to_string()),
+                "event" => self.event = Some(value.to_string()),
+                _ => {},
+            }
+        }
+
+        events
+    }
+}
+
+// Applies one SSE event to the result; returns true once the stream says it's done.
+// Accepts our backend's { delta } / { done, tokensUsed, model } events, OpenAI-style
+// { choices: [{ delta: { content } }] } passthrough and bare text data. An error event
+// fails the whole answer, so a half-written one is never cached or billed as complete.
+pub(super) fn apply_sse_event(
+    event: &SseEvent,
+    result: &mut AnalysisResult,
+    on_chunk: &mut impl FnMut(&str),
+) -> Result<bool, FrameSenseError> {
+    if event.data == "[DONE]" || (event.event.as_deref() == Some("done") && event.data.is_empty()) {
+        return Ok(true);
+    }
+    if event.event.as_deref() == Some("error") {
+        return Err(stream_error(&event.data));
+    }
+
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(&event.data) else {
+        result.answer.push_str(&event.data);
+        on_chunk(&event.data);
+        return Ok(false);
+    };
+
+    let delta = value["delta"]
+        .as_str()
+        .or_else(|| value["content"].as_str())
+        .or_else(|| value["choices"][0]["delta"]["content"].as_str());
+    if let Some(delta) = delta {
+        if !delta.is_empty() {
+            result.answer.push_str(delta);
+            on_chunk(delta);
+        }
+    }
+    if let Some(model) = value["model"].as_str() {
+        result.model = Some(model.to_string());
+    }
+    if let Some(tokens) = value["tokensUsed"].as_u64().or_else(|| value["usage"]["total_tokens"].as_u64()) {
+        result.tokens_used = Some(tokens);
+    }
+
+    Ok(value["done"].as_bool().unwrap_or(false) || event.event.as_deref() == Some("done"))
+}
+
+// Decodes a chunked text body without breaking multi-byte characters across chunks
+pub(super) struct Utf8Buffer {
+    pending: Vec<u8>,
+}
+
+impl Utf8Buffer {
+    pub fn new() -> Self {
+        Self { pending: Vec::new() }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let valid_up_to = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(), // Incomplete sequence at the end
+            Err(_) => {
+                // Genuinely invalid bytes: don't stall the stream on them
+                let text = String::from_utf8_lossy(&self.pending).to_string();
+                self.pending.clear();
+                return text;
+            },
+        };
+        let text = String::from_utf8_lossy(&self.pending[..valid_up_to]).to_string();
+        self.pending.drain(..valid_up_to);
+        text
+    }
+}
@@ -0,0 +1,341 @@
+// Offline analysis queue - analyses that failed for lack of a connection are kept on
+// disk with their capture and retried with exponential backoff until they go through
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::analysis::AnalysisRequest;
+use crate::errors::FrameSenseError;
+
+const BASE_DELAY_MS: u64 = 30 * 1000;
+const MAX_DELAY_MS: u64 = 30 * 60 * 1000;
+const MAX_ATTEMPTS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Failed, // Out of attempts or a permanent error; kept until retried or cancelled
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedJob {
+    pub id: String,
+    pub request: AnalysisRequest, // Includes the capture, so nothing is lost if the app quits
+    pub thread_id: Option<String>, // Conversation thread the answer belongs to
+    pub created_at: u64,
+    pub attempts: u32,
+    pub next_attempt_at: u64,
+    pub last_error: Option<String>,
+    pub status: JobStatus,
+}
+
+// What the queue view shows; the capture itself stays in Rust
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSummary {
+    pub id: String,
+    pub question: String,
+    pub model: Option<String>,
+    pub thread_id: Option<String>,
+    pub has_capture: bool,
+    pub created_at: u64,
+    pub attempts: u32,
+    pub next_attempt_at: u64,
+    pub last_error: Option<String>,
+    pub status: JobStatus,
+}
+
+pub struct AnalysisQueue {
+    dir: PathBuf,
+    jobs: Vec<QueuedJob>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+// Exponential backoff with "equal jitter": somewhere between half and all of the
+// doubled delay, so queued jobs from many clients don't all retry in lockstep
+fn backoff_ms(attempts: u32) -> u64 {
+    let delay = BASE_DELAY_MS.saturating_mul(1 << attempts.min(16)).min(MAX_DELAY_MS);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(now_millis());
+    delay / 2 + hasher.finish() % (delay / 2 + 1)
+}
+
+impl QueuedJob {
+    pub fn summary(&self) -> JobSummary {
+        JobSummary {
+            id: self.id.clone(),
+            question: self.request.question.clone(),
+            model: self.request.model.clone(),
+            thread_id: self.thread_id.clone(),
+            has_capture: self.request.image_data.is_some(),
+            created_at: self.created_at,
+            attempts: self.attempts,
+            next_attempt_at: self.next_attempt_at,
+            last_error: self.last_error.clone(),
+            status: self.status,
+        }
+    }
+}
+
+impl AnalysisQueue {
+    // Jobs that were mid-attempt when the app quit are simply tried again
+    pub fn load(dir: PathBuf) -> Self {
+        let mut jobs: Vec<QueuedJob> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+                    .filter_map(|path| {
+                        let json = fs::read_to_string(&path).ok()?;
+                        match serde_json::from_str::<QueuedJob>(&json) {
+                            Ok(job) => Some(job),
+                            Err(e) => {
+                                println!("⚠️ Skipping unreadable queued analysis {:?}: {}", path, e);
+                                None
+                            },
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for job in jobs.iter_mut().filter(|job| job.status == JobStatus::Running) {
+            job.status = JobStatus::Pending;
+        }
+        jobs.sort_by_key(|job| job.created_at);
+        if !jobs.is_empty() {
+            println!("📬 Loaded {} queued analyses", jobs.len());
+        }
+        Self { dir, jobs }
+    }
+
+    pub fn list(&self) -> Vec<JobSummary> {
+        self.jobs.iter().map(QueuedJob::summary).collect()
+    }
+
+    pub fn enqueue(&mut self, request: AnalysisRequest, thread_id: Option<String>, error: &str) -> Result<QueuedJob, String> {
+        let now = now_millis();
+        let mut id = format!("job-{}", now);
+        let mut suffix = 1;
+        while self.jobs.iter().any(|job| job.id == id) {
+            id = format!("job-{}-{}", now, suffix);
+            suffix += 1;
+        }
+
+        let job = QueuedJob {
+            id,
+            request,
+            thread_id,
+            created_at: now,
+            attempts: 1, // The original request counts as the first attempt
+            next_attempt_at: now + backoff_ms(0),
+            last_error: Some(error.to_string()),
+            status: JobStatus::Pending,
+        };
+        self.save(&job)?;
+        self.jobs.push(job.clone());
+        println!("📬 Queued analysis {} for retry: {}", job.id, error);
+        Ok(job)
+    }
+
+    // The oldest pending job whose retry time has come, marked as running
+    pub fn take_next_due(&mut self) -> Option<QueuedJob> {
+        let now = now_millis();
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.status == JobStatus::Pending && job.next_attempt_at <= now)?;
+        job.status = JobStatus::Running;
+        Some(job.clone())
+    }
+
+    // Removes a job that went through. None means it was cancelled while running,
+    // so the caller should drop the answer.
+    pub fn complete(&mut self, id: &str) -> Option<QueuedJob> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        let job = self.jobs.remove(index);
+        self.remove_file(id);
+        Some(job)
+    }
+
+    // Schedules the next attempt, or gives up on permanent errors and after MAX_ATTEMPTS
+    pub fn fail(&mut self, id: &str, error: &FrameSenseError) -> Option<QueuedJob> {
+        let job = self.jobs.iter_mut().find(|job| job.id == id)?;
+        job.attempts += 1;
+        job.last_error = Some(error.to_string());
+        if error.is_transient() && job.attempts < MAX_ATTEMPTS {
+            job.status = JobStatus::Pending;
+            job.next_attempt_at = now_millis() + backoff_ms(job.attempts - 1);
+        } else {
+            job.status = JobStatus::Failed;
+        }
+
+        let job = job.clone();
+        if let Err(e) = self.save(&job) {
+            println!("⚠️ {}", e);
+        }
+        Some(job)
+    }
+
+    // A job just went through, so the connection is back: don't make the rest wait out their backoff
+    pub fn connectivity_restored(&mut self) {
+        let now = now_millis();
+        for job in self.jobs.iter_mut().filter(|job| job.status == JobStatus::Pending) {
+            job.next_attempt_at = job.next_attempt_at.min(now);
+        }
+    }
+
+    // Retry now, including jobs that already gave up
+    pub fn retry(&mut self, id: &str) -> Result<JobSummary, String> {
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| format!("Queued analysis not found: {}", id))?;
+        if job.status != JobStatus::Running {
+            job.status = JobStatus::Pending;
+            job.next_attempt_at = now_millis();
+        }
+        let job = job.clone();
+        self.save(&job)?;
+        Ok(job.summary())
+    }
+
+    pub fn cancel(&mut self, id: &str) -> Result<QueuedJob, String> {
+        let index = self
+            .jobs
+            .iter()
+            .position(|job| job.id == id)
+            .ok_or_else(|| format!("Queued analysis not found: {}", id))?;
+        let job = self.jobs.remove(index);
+        self.remove_file(id);
+        println!("🗑️ Cancelled queued analysis {}", id);
+        Ok(job)
+    }
+
+    fn job_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn save(&self, job: &QueuedJob) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create queue directory: {}", e))?;
+        let json = serde_json::to_string(job).map_err(|e| format!("Failed to serialize queued analysis: {}", e))?;
+        fs::write(self.job_path(&job.id), json).map_err(|e| format!("Failed to save queued analysis: {}", e))
+    }
+
+    fn remove_file(&self, id: &str) {
+        let _ = fs::remove_file(self.job_path(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> AnalysisRequest {
+        AnalysisRequest {
+            question: "What does this error mean?".to_string(),
+            image_data: Some("iVBORw0KGgo=".to_string()),
+            model: None,
+            ocr_text: None,
+            history: Vec::new(),
+            captures: Vec::new(),
+            force_refresh: false,
+            region_references: false,
+        }
+    }
+
+    fn queue(name: &str) -> AnalysisQueue {
+        let dir = std::env::temp_dir().join(format!("framesense-queue-{}-{}", name, now_millis()));
+        let _ = fs::remove_dir_all(&dir);
+        AnalysisQueue::load(dir)
+    }
+
+    fn offline() -> FrameSenseError {
+        FrameSenseError::Network("Network error: connection refused".to_string())
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter_up_to_the_cap() {
+        for attempts in 0..20 {
+            let delay = (BASE_DELAY_MS << attempts.min(16)).min(MAX_DELAY_MS);
+            for _ in 0..20 {
+                let backoff = backoff_ms(attempts);
+                assert!(backoff >= delay / 2 && backoff <= delay, "attempt {}: {} not in {}..={}", attempts, backoff, delay / 2, delay);
+            }
+        }
+        assert!(backoff_ms(0) <= BASE_DELAY_MS);
+        assert!(backoff_ms(30) >= MAX_DELAY_MS / 2);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts_or_a_permanent_error() {
+        let mut queue = queue("give-up");
+        let job = queue.enqueue(request(), None, &offline().to_string()).unwrap();
+        let mut failed = job.clone();
+        while failed.status == JobStatus::Pending {
+            failed = queue.fail(&job.id, &offline()).unwrap();
+            assert!(failed.attempts <= MAX_ATTEMPTS);
+        }
+        assert_eq!((failed.status, failed.attempts), (JobStatus::Failed, MAX_ATTEMPTS));
+        assert_eq!(failed.last_error.as_deref(), Some("Network error: connection refused"));
+
+        let job = queue.enqueue(request(), None, &offline().to_string()).unwrap();
+        let failed = queue.fail(&job.id, &FrameSenseError::Unauthorized("Session expired".to_string())).unwrap();
+        assert_eq!((failed.status, failed.attempts), (JobStatus::Failed, 2));
+
+        // A rate limit is worth another try later
+        queue.retry(&job.id).unwrap();
+        let failed = queue.fail(&job.id, &FrameSenseError::from_status(429, "Slow down")).unwrap();
+        assert_eq!(failed.status, JobStatus::Pending);
+        assert!(failed.next_attempt_at > now_millis());
+        let _ = fs::remove_dir_all(&queue.dir);
+    }
+
+    #[test]
+    fn only_due_pending_jobs_are_taken() {
+        let mut queue = queue("due");
+        let job = queue.enqueue(request(), Some("thread-1".to_string()), "offline").unwrap();
+        assert!(job.next_attempt_at > now_millis());
+        assert!(queue.take_next_due().is_none());
+
+        queue.connectivity_restored();
+        let taken = queue.take_next_due().unwrap();
+        assert_eq!((taken.id.as_str(), taken.status), (job.id.as_str(), JobStatus::Running));
+        // Running jobs aren't handed out twice
+        assert!(queue.take_next_due().is_none());
+
+        // A restart picks running jobs up again
+        let mut reloaded = AnalysisQueue::load(queue.dir.clone());
+        assert_eq!(reloaded.list()[0].status, JobStatus::Pending);
+        assert!(reloaded.complete(&job.id).is_some());
+        assert!(AnalysisQueue::load(queue.dir.clone()).list().is_empty());
+        let _ = fs::remove_dir_all(&queue.dir);
+    }
+
+    #[test]
+    fn cancel_removes_the_job_and_its_file() {
+        let mut queue = queue("cancel");
+        let job = queue.enqueue(request(), None, "offline").unwrap();
+        assert!(queue.job_path(&job.id).exists());
+
+        queue.cancel(&job.id).unwrap();
+        assert!(!queue.job_path(&job.id).exists());
+        assert!(queue.list().is_empty());
+        assert!(queue.cancel(&job.id).is_err());
+        // A job cancelled while it was running is dropped when its answer arrives
+        assert!(queue.complete(&job.id).is_none());
+        let _ = fs::remove_dir_all(&queue.dir);
+    }
+}
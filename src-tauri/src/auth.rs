@@ -1,10 +1,20 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
-use reqwest;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
-use chrono;
+
+use crate::environment::BackendEnvironment;
+use crate::errors::FrameSenseError;
+use crate::oauth::{self, LoopbackRedirect, Pkce};
+use crate::secrets::SecretStore;
+
+// Tokens last 30 days; they're refreshed once less than this is left
+const REFRESH_AHEAD_SECS: u64 = 3 * 24 * 60 * 60;
+const SESSION_KEY: &str = "session";
+const LEGACY_SESSION_FILE: &str = "user_session.json";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct User {
@@ -75,69 +85,208 @@ pub struct AuthResponse {
     pub message: Option<String>,
 }
 
+// Token endpoint answer for browser sign-in (RFC 6749 section 5.1, with the user when the
+// backend includes it) or its error (section 5.2)
+#[derive(Debug, Deserialize)]
+pub struct OAuthTokenResponse {
+    pub access_token: Option<String>,
+    pub user: Option<BackendUser>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
+    #[serde(rename = "userId", alias = "user_id")]
     pub user_id: String,
     pub email: String,
     pub exp: usize,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenState {
+    Valid,
+    RefreshDue, // Still valid, but close enough to expiry to refresh now
+    Expired,
+    Unreadable, // Not a JWT we can read; only the backend can tell
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStatus {
+    pub state: TokenState,
+    pub user_id: Option<String>,
+    pub expires_at: Option<u64>, // Unix seconds
+}
+
+// Outcome of checking the stored session before using it
+pub enum SessionCheck {
+    NoSession,
+    Valid(User),
+    Refreshed(User), // The token was replaced; the new session is already saved
+    Expired(String), // Refresh failed after expiry; the session was cleared. Holds the reason.
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// Reads the claims without checking the signature: the secret stays on the backend, which
+// still verifies every request. Locally this only tells when the token runs out.
+pub fn decode_claims(token: &str) -> Result<Claims, String> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims = HashSet::from(["exp".to_string()]);
+
+    decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)
+        .map(|data| data.claims)
+        .map_err(|e| format!("Unreadable session token: {}", e))
+}
+
+impl SessionStatus {
+    pub fn for_token(token: &str) -> Self {
+        Self::at(token, now_secs())
+    }
+
+    fn at(token: &str, now: u64) -> Self {
+        match decode_claims(token) {
+            Ok(claims) => {
+                let expires_at = claims.exp as u64;
+                let state = if expires_at <= now {
+                    TokenState::Expired
+                } else if expires_at - now <= REFRESH_AHEAD_SECS {
+                    TokenState::RefreshDue
+                } else {
+                    TokenState::Valid
+                };
+                Self {
+                    state,
+                    user_id: Some(claims.user_id),
+                    expires_at: Some(expires_at),
+                }
+            },
+            Err(_) => Self {
+                state: TokenState::Unreadable,
+                user_id: None,
+                expires_at: None,
+            },
+        }
+    }
+}
+
+fn network_error(e: reqwest::Error) -> FrameSenseError {
+    FrameSenseError::from(e)
+}
+
+fn unreadable_response(e: reqwest::Error) -> FrameSenseError {
+    invalid_response(format!("Parse error: {}", e))
+}
+
+fn invalid_response(message: impl Into<String>) -> FrameSenseError {
+    FrameSenseError::Server { message: message.into(), status: None }
+}
+
+// A backend answer that isn't a success: 401/403 turn the credentials or token down
+fn status_error(status: reqwest::StatusCode, message: &str) -> FrameSenseError {
+    match status.as_u16() {
+        401 | 403 => FrameSenseError::Unauthorized(message.to_string()),
+        code => FrameSenseError::from_status(code, format!("{} (HTTP {})", message, code)),
+    }
+}
+
+// Convert backend user format to frontend User format
+fn user_from_backend(backend_user: BackendUser, token: String) -> User {
+    User {
+        id: backend_user.id,
+        email: backend_user.email,
+        name: backend_user.name,
+        tier: backend_user.tier,
+        token,
+        usage: UserUsage {
+            daily: backend_user.usage_daily.unwrap_or(0),
+            total: backend_user.usage_total.unwrap_or(0),
+            last_reset: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+        },
+        created_at: backend_user.created_at.unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+        subscription_status: backend_user.subscription_status,
+        stripe_customer_id: backend_user.stripe_customer_id,
+        usage_daily: backend_user.usage_daily,
+        usage_total: backend_user.usage_total,
+        updated_at: backend_user.updated_at,
+    }
+}
+
 #[derive(Clone)]
 pub struct AuthService {
-    api_url: String,
+    environment: BackendEnvironment,
     storage_path: Option<PathBuf>,
+    secrets: Option<Arc<dyn SecretStore>>,
 }
 
 impl AuthService {
     pub fn new() -> Self {
+        Self::for_environment(BackendEnvironment::production())
+    }
+
+    pub fn for_environment(environment: BackendEnvironment) -> Self {
         Self {
-            api_url: "https://api.finalyze.pro".to_string(), // Railway backend URL
+            environment,
             storage_path: None,
+            secrets: None,
         }
     }
 
-    pub fn with_storage_path(mut self, path: PathBuf) -> Self {
+    pub fn with_storage_path(mut self, path: PathBuf, secrets: Arc<dyn SecretStore>) -> Self {
+        self.secrets = Some(secrets);
         self.storage_path = Some(path);
         self
     }
 
-    pub async fn login_user(&self, email: String, password: String) -> Result<User, String> {
+    pub fn api_url(&self) -> &str {
+        &self.environment.api_url
+    }
+
+    pub fn environment(&self) -> &BackendEnvironment {
+        &self.environment
+    }
+
+    pub fn secret_store_backend(&self) -> Option<&'static str> {
+        self.secrets.as_ref().map(|secrets| secrets.backend())
+    }
+
+    // Each backend has its own session, so a staging token is never sent to production
+    fn session_key(&self) -> String {
+        if self.environment.is_production() {
+            SESSION_KEY.to_string()
+        } else {
+            format!("{}:{}", SESSION_KEY, self.api_url())
+        }
+    }
+
+    pub async fn login_user(&self, email: String, password: String) -> Result<User, FrameSenseError> {
         let client = reqwest::Client::new();
         
         let login_data = LoginRequest { email, password };
         
         let response = client
-            .post(&format!("{}/api/auth/login", self.api_url))
+            .post(format!("{}/api/auth/login", self.api_url()))
             .json(&login_data)
             .send()
             .await
-            .map_err(|e| format!("Network error: {}", e))?;
+            .map_err(network_error)?;
         
         if response.status().is_success() {
             let auth_response: AuthResponse = response.json().await
-                .map_err(|e| format!("Parse error: {}", e))?;
+                .map_err(unreadable_response)?;
             
             if auth_response.success {
                 if let (Some(backend_user), Some(token)) = (auth_response.user, auth_response.token) {
-                    // Convert backend user format to frontend User format
-                    let user = User {
-                        id: backend_user.id,
-                        email: backend_user.email,
-                        name: backend_user.name,
-                        tier: backend_user.tier,
-                        token: token.clone(),
-                        usage: UserUsage {
-                            daily: backend_user.usage_daily.unwrap_or(0),
-                            total: backend_user.usage_total.unwrap_or(0),
-                            last_reset: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-                        },
-                        created_at: backend_user.created_at.unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
-                        subscription_status: backend_user.subscription_status,
-                        stripe_customer_id: backend_user.stripe_customer_id,
-                        usage_daily: backend_user.usage_daily,
-                        usage_total: backend_user.usage_total,
-                        updated_at: backend_user.updated_at,
-                    };
+                    let user = user_from_backend(backend_user, token.clone());
                     
                     // Save user session locally
                     self.save_user_session(&user).await?;
@@ -145,52 +294,105 @@ impl AuthService {
                     println!("✅ User logged in successfully: {} ({})", user.email, user.tier);
                     Ok(user)
                 } else {
-                    Err("Invalid response format".to_string())
+                    Err(invalid_response("Invalid response format"))
                 }
             } else {
-                Err(auth_response.message.unwrap_or("Login failed".to_string()))
+                Err(FrameSenseError::Unauthorized(auth_response.message.unwrap_or("Login failed".to_string())))
             }
         } else {
-            Err("Authentication failed".to_string())
+            Err(status_error(response.status(), "Authentication failed"))
+        }
+    }
+
+    // Browser sign-in through the backend's SSO: opens the authorization page with
+    // `open_browser`, waits for the redirect on a loopback port, exchanges the code and
+    // saves the session like login_user does
+    pub async fn login_with_browser(
+        &self,
+        provider: Option<&str>,
+        open_browser: impl FnOnce(&str) -> Result<(), String>,
+    ) -> Result<User, FrameSenseError> {
+        let redirect = LoopbackRedirect::bind().await?;
+        let pkce = Pkce::generate()?;
+        let state = oauth::random_state()?;
+        let authorize_url = oauth::authorize_url(self.api_url(), redirect.redirect_uri(), &pkce, &state, provider)?;
+        let redirect_uri = redirect.redirect_uri().to_string();
+
+        println!("🌐 Opening the browser for sign-in (redirect to {})", redirect_uri);
+        open_browser(&authorize_url)?;
+        let code = redirect.wait_for_code(&state, oauth::CALLBACK_TIMEOUT).await?;
+
+        let (token, backend_user) = self.exchange_authorization_code(&code, &redirect_uri, &pkce.verifier).await?;
+        let user = match backend_user {
+            Some(backend_user) => user_from_backend(backend_user, token),
+            None => self.verify_token(token).await?,
+        };
+        self.save_user_session(&user).await?;
+
+        println!("✅ User signed in through the browser: {} ({})", user.email, user.tier);
+        Ok(user)
+    }
+
+    async fn exchange_authorization_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        verifier: &str,
+    ) -> Result<(String, Option<BackendUser>), FrameSenseError> {
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}{}", self.api_url(), oauth::TOKEN_PATH))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("client_id", oauth::CLIENT_ID),
+                ("code_verifier", verifier),
+            ])
+            .send()
+            .await
+            .map_err(network_error)?;
+
+        let status = response.status();
+        let token_response: OAuthTokenResponse = response.json().await
+            .map_err(unreadable_response)?;
+
+        match token_response.access_token {
+            Some(token) if status.is_success() => Ok((token, token_response.user)),
+            _ => {
+                let message = token_response
+                    .error_description
+                    .or(token_response.error)
+                    .unwrap_or_else(|| "Sign-in code exchange failed".to_string());
+                // invalid_grant comes back as 400: the code expired, was used, or the verifier didn't match
+                if status.as_u16() == 400 {
+                    Err(FrameSenseError::Unauthorized(message))
+                } else {
+                    Err(status_error(status, &message))
+                }
+            },
         }
     }
 
-    pub async fn handle_payment_success(&self, token: String, plan: String) -> Result<User, String> {
+    pub async fn handle_payment_success(&self, token: String, plan: String) -> Result<User, FrameSenseError> {
         // Real payment verification with backend - no more test mode
         let client = reqwest::Client::new();
         
         let response = client
-            .get(&format!("{}/api/auth/verify", self.api_url))
+            .get(format!("{}/api/auth/verify", self.api_url()))
             .header("Authorization", format!("Bearer {}", token))
             .send()
             .await
-            .map_err(|e| format!("Verification error: {}", e))?;
+            .map_err(network_error)?;
         
         if response.status().is_success() {
             let auth_response: AuthResponse = response.json().await
-                .map_err(|e| format!("Parse error: {}", e))?;
+                .map_err(unreadable_response)?;
             
             if auth_response.success {
                 if let Some(backend_user) = auth_response.user {
-                    // Convert backend user format to frontend User format
-                    let user = User {
-                        id: backend_user.id,
-                        email: backend_user.email,
-                        name: backend_user.name,
-                        tier: backend_user.tier,
-                        token: token.clone(),
-                        usage: UserUsage {
-                            daily: backend_user.usage_daily.unwrap_or(0),
-                            total: backend_user.usage_total.unwrap_or(0),
-                            last_reset: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-                        },
-                        created_at: backend_user.created_at.unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
-                        subscription_status: backend_user.subscription_status,
-                        stripe_customer_id: backend_user.stripe_customer_id,
-                        usage_daily: backend_user.usage_daily,
-                        usage_total: backend_user.usage_total,
-                        updated_at: backend_user.updated_at,
-                    };
+                    let user = user_from_backend(backend_user, token.clone());
                     
                     // Clear any old session before saving new one
                     self.clear_user_session().await?;
@@ -203,20 +405,25 @@ impl AuthService {
                     
                     Ok(user)
                 } else {
-                    Err("Invalid response format".to_string())
+                    Err(invalid_response("Invalid response format"))
                 }
             } else {
-                Err("Token verification failed".to_string())
+                Err(FrameSenseError::Unauthorized("Token verification failed".to_string()))
             }
         } else {
-            Err("Authentication failed".to_string())
+            Err(status_error(response.status(), "Authentication failed"))
         }
     }
 
     // Manual payment verification - loads fresh user data from backend
-    pub async fn verify_payment_and_update(&self) -> Result<Option<User>, String> {
+    pub async fn verify_payment_and_update(&self) -> Result<Option<User>, FrameSenseError> {
         // First check if we have a current session
         if let Some(current_user) = self.load_user_session().await? {
+            // An expired token would only be turned down by the backend
+            if SessionStatus::for_token(&current_user.token).state == TokenState::Expired {
+                return Err(FrameSenseError::Unauthorized("Session expired, please log in again".to_string()));
+            }
+            
             // Verify current token with backend to get latest user data
             let updated_user = self.verify_token(current_user.token).await?;
             
@@ -233,192 +440,203 @@ impl AuthService {
         }
     }
 
-    pub async fn verify_token(&self, token: String) -> Result<User, String> {
+    pub async fn verify_token(&self, token: String) -> Result<User, FrameSenseError> {
         let client = reqwest::Client::new();
         
         let response = client
-            .get(&format!("{}/api/auth/verify", self.api_url))
+            .get(format!("{}/api/auth/verify", self.api_url()))
             .header("Authorization", format!("Bearer {}", token))
             .send()
             .await
-            .map_err(|e| format!("Verification error: {}", e))?;
+            .map_err(network_error)?;
         
         if response.status().is_success() {
             let auth_response: AuthResponse = response.json().await
-                .map_err(|e| format!("Parse error: {}", e))?;
+                .map_err(unreadable_response)?;
             
             if auth_response.success {
                 if let Some(backend_user) = auth_response.user {
-                    // Convert backend user format to frontend User format
-                    let user = User {
-                        id: backend_user.id,
-                        email: backend_user.email,
-                        name: backend_user.name,
-                        tier: backend_user.tier,
-                        token: token.clone(),
-                        usage: UserUsage {
-                            daily: backend_user.usage_daily.unwrap_or(0),
-                            total: backend_user.usage_total.unwrap_or(0),
-                            last_reset: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-                        },
-                        created_at: backend_user.created_at.unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
-                        subscription_status: backend_user.subscription_status,
-                        stripe_customer_id: backend_user.stripe_customer_id,
-                        usage_daily: backend_user.usage_daily,
-                        usage_total: backend_user.usage_total,
-                        updated_at: backend_user.updated_at,
-                    };
+                    let user = user_from_backend(backend_user, token.clone());
                     Ok(user)
                 } else {
-                    Err("Invalid response format".to_string())
+                    Err(invalid_response("Invalid response format"))
                 }
             } else {
-                Err("Token verification failed".to_string())
+                Err(FrameSenseError::Unauthorized("Token verification failed".to_string()))
             }
         } else {
-            Err("Authentication failed".to_string())
+            Err(status_error(response.status(), "Authentication failed"))
+        }
+    }
+
+    // Trades the current token for a new one before it expires
+    pub async fn refresh_token(&self, token: &str) -> Result<User, FrameSenseError> {
+        let client = reqwest::Client::new();
+        
+        let response = client
+            .post(format!("{}/api/auth/refresh", self.api_url()))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .map_err(network_error)?;
+        
+        let status = response.status();
+        if status.as_u16() == 401 || status.as_u16() == 403 {
+            return Err(FrameSenseError::Unauthorized("The backend no longer accepts this session".to_string()));
+        }
+        if !status.is_success() {
+            return Err(status_error(status, "Token refresh failed"));
+        }
+        
+        let auth_response: AuthResponse = response.json().await
+            .map_err(unreadable_response)?;
+        match (auth_response.success, auth_response.user, auth_response.token) {
+            (true, Some(backend_user), Some(token)) => {
+                let user = user_from_backend(backend_user, token);
+                self.save_user_session(&user).await?;
+                println!("🔑 Session token refreshed for {}, expires at {:?}", 
+                         user.email, SessionStatus::for_token(&user.token).expires_at);
+                Ok(user)
+            },
+            (true, _, _) => Err(invalid_response("Invalid response format")),
+            (false, _, _) => Err(FrameSenseError::Unauthorized(auth_response.message.unwrap_or("Token refresh failed".to_string()))),
         }
     }
 
-    pub async fn logout_user(&self) -> Result<(), String> {
+    // Loads the session and refreshes its token when expiry is near (or always, with
+    // `force`). A failed refresh of a token that still works is retried next time. An
+    // expired session is cleared only when the backend refuses it; when the backend can't
+    // be reached it's kept and the error returned, so the refresh is tried again later.
+    pub async fn ensure_fresh_session(&self, force: bool) -> Result<SessionCheck, FrameSenseError> {
+        let Some(user) = self.load_user_session().await? else {
+            return Ok(SessionCheck::NoSession);
+        };
+        
+        let status = SessionStatus::for_token(&user.token);
+        let refresh = match status.state {
+            TokenState::Valid | TokenState::Unreadable => force,
+            TokenState::RefreshDue | TokenState::Expired => true,
+        };
+        if !refresh {
+            return Ok(SessionCheck::Valid(user));
+        }
+        
+        match self.refresh_token(&user.token).await {
+            Ok(refreshed) => Ok(SessionCheck::Refreshed(refreshed)),
+            Err(e) if status.state != TokenState::Expired => {
+                println!("⚠️ Token refresh failed, keeping the current token: {}", e);
+                Ok(SessionCheck::Valid(user))
+            },
+            Err(e @ FrameSenseError::Unauthorized(_)) => {
+                println!("🔒 Session of {} expired and couldn't be refreshed: {}", user.email, e);
+                self.clear_user_session().await?;
+                Ok(SessionCheck::Expired(e.to_string()))
+            },
+            Err(e) => {
+                println!("⚠️ Session of {} expired, refresh failed and will be retried: {}", user.email, e);
+                Err(e)
+            },
+        }
+    }
+
+    pub async fn logout_user(&self) -> Result<(), FrameSenseError> {
         self.clear_user_session().await
     }
 
-    pub async fn get_current_user(&self) -> Result<Option<User>, String> {
+    pub async fn get_current_user(&self) -> Result<Option<User>, FrameSenseError> {
         self.load_user_session().await
     }
 
-    pub fn get_available_models(&self, user_tier: &str) -> Vec<&'static str> {
-        match user_tier {
-            "free" => vec!["GPT-3.5-turbo", "Gemini Flash"],
-            "premium" => vec![
-                "GPT-3.5-turbo", "Gemini Flash",
-                "GPT-4o-mini", "Claude 3 Haiku", "Gemini Pro"
-            ],
-            "pro" => vec![
-                "GPT-3.5-turbo", "Gemini Flash",
-                "GPT-4o-mini", "Claude 3 Haiku", "Gemini Pro",
-                "GPT-4o", "Claude 3.5 Sonnet", "Llama 3.1 70B"
-            ],
-            "enterprise" => vec![
-                "GPT-3.5-turbo", "Gemini Flash",
-                "GPT-4o-mini", "Claude 3 Haiku", "Gemini Pro",
-                "GPT-4o", "Claude 3.5 Sonnet", "Llama 3.1 70B",
-                "GPT-4o 32k", "Claude 3 Opus", "Llama 3.1 405B"
-            ],
-            _ => vec!["GPT-3.5-turbo"] // Fallback
+    // Local storage functions. The session (with its bearer token) lives in the secret
+    // store; `user_session.json` is where older versions kept it in plain text.
+    // Older versions only ever talked to production, so other environments leave it alone
+    fn legacy_session_file(&self) -> Option<PathBuf> {
+        if !self.environment.is_production() {
+            return None;
         }
+        self.storage_path.as_ref().map(|path| path.join(LEGACY_SESSION_FILE))
     }
-    
-    pub fn can_use_model(&self, user_tier: &str, model: &str) -> bool {
-        self.get_available_models(user_tier).contains(&model)
-    }
-    
-    pub fn get_daily_limit(&self, user_tier: &str) -> i32 {
-        match user_tier {
-            "free" => 50,
-            "premium" => 1000,
-            "pro" => 5000,
-            "enterprise" => -1, // Unlimited
-            _ => 10 // Very limited fallback
+
+    fn remove_legacy_session(&self) -> Result<(), FrameSenseError> {
+        if let Some(user_file) = self.legacy_session_file().filter(|file| file.exists()) {
+            fs::remove_file(&user_file).map_err(|e| FrameSenseError::Storage(format!("Failed to remove plaintext user session: {}", e)))?;
+            println!("🧹 Removed plaintext session file {:?}", user_file);
         }
+        Ok(())
     }
 
-    pub fn get_required_tier(&self, model: &str) -> &'static str {
-        if ["GPT-4o 32k", "Claude 3 Opus", "Llama 3.1 405B"].contains(&model) {
-            "enterprise"
-        } else if ["GPT-4o", "Claude 3.5 Sonnet", "Llama 3.1 70B"].contains(&model) {
-            "pro"
-        } else if ["GPT-4o-mini", "Claude 3 Haiku", "Gemini Pro"].contains(&model) {
-            "premium"
-        } else {
-            "free"
-        }
+    // Moves a plaintext session into the secret store, then deletes the file
+    fn migrate_legacy_session(&self, secrets: &dyn SecretStore) -> Result<Option<User>, FrameSenseError> {
+        let Some(user_file) = self.legacy_session_file().filter(|file| file.exists()) else {
+            return Ok(None);
+        };
+        let user_json = fs::read_to_string(&user_file).map_err(|e| FrameSenseError::Storage(format!("Failed to read user session: {}", e)))?;
+        let user: User = match serde_json::from_str(&user_json) {
+            Ok(user) => user,
+            Err(e) => {
+                // Unreadable either way; don't leave a token lying around
+                println!("⚠️ Discarding unreadable plaintext session: {}", e);
+                self.remove_legacy_session()?;
+                return Ok(None);
+            },
+        };
+
+        secrets.set(&self.session_key(), &user_json).map_err(FrameSenseError::Storage)?;
+        self.remove_legacy_session()?;
+        println!("🔐 Migrated the plaintext session for {} into the {} store", user.email, secrets.backend());
+        Ok(Some(user))
     }
 
-    // Local storage functions
-    pub async fn save_user_session(&self, user: &User) -> Result<(), String> {
+    pub async fn save_user_session(&self, user: &User) -> Result<(), FrameSenseError> {
         println!("🔍 DEBUG: save_user_session called for user: {} ({})", user.email, user.tier);
-        
-        if let Some(storage_path) = &self.storage_path {
-            let user_file = storage_path.join("user_session.json");
-            println!("🔍 DEBUG: Storage path: {:?}", user_file);
-            
-            // Ensure directory exists
-            if let Some(parent) = user_file.parent() {
-                if !parent.exists() {
-                    println!("🔍 DEBUG: Creating storage directory: {:?}", parent);
-                    fs::create_dir_all(parent)
-                        .map_err(|e| format!("Failed to create storage directory: {}", e))?;
-                    println!("✅ DEBUG: Storage directory created");
-                } else {
-                    println!("🔍 DEBUG: Storage directory already exists");
-                }
-            }
-            
-            let user_json = serde_json::to_string_pretty(user)
-                .map_err(|e| format!("Failed to serialize user: {}", e))?;
-            
-            println!("🔍 DEBUG: About to write {} bytes to file", user_json.len());
-            fs::write(&user_file, &user_json)
-                .map_err(|e| format!("Failed to write user session: {}", e))?;
-            
-            println!("✅ DEBUG: User session saved to Tauri storage at: {:?}", user_file);
-        } else {
+
+        let Some(secrets) = &self.secrets else {
             println!("❌ DEBUG: No storage path configured!");
-            return Err("No storage path configured".to_string());
-        }
+            return Err(FrameSenseError::Storage("No storage path configured".to_string()));
+        };
+        let user_json = serde_json::to_string(user)
+            .map_err(|e| FrameSenseError::Storage(format!("Failed to serialize user: {}", e)))?;
+        secrets.set(&self.session_key(), &user_json).map_err(FrameSenseError::Storage)?;
+        self.remove_legacy_session()?;
+
+        println!("✅ DEBUG: User session saved to the {} store", secrets.backend());
         Ok(())
     }
 
-    pub async fn clear_user_session(&self) -> Result<(), String> {
+    pub async fn clear_user_session(&self) -> Result<(), FrameSenseError> {
         println!("🔍 DEBUG: clear_user_session called");
-        
-        if let Some(storage_path) = &self.storage_path {
-            let user_file = storage_path.join("user_session.json");
-            println!("🔍 DEBUG: Looking for session file to delete at: {:?}", user_file);
-            
-            if user_file.exists() {
-                println!("🔍 DEBUG: Session file exists, deleting...");
-                fs::remove_file(&user_file)
-                    .map_err(|e| format!("Failed to remove user session: {}", e))?;
-                println!("✅ DEBUG: User session file deleted successfully");
-            } else {
-                println!("ℹ️ DEBUG: No session file to delete (already cleared)");
-            }
-        } else {
+
+        let Some(secrets) = &self.secrets else {
             println!("❌ DEBUG: No storage path configured for clearing!");
-        }
+            return Ok(());
+        };
+        secrets.delete(&self.session_key()).map_err(FrameSenseError::Storage)?;
+        self.remove_legacy_session()?;
+        println!("✅ DEBUG: User session cleared");
         Ok(())
     }
 
-    pub async fn load_user_session(&self) -> Result<Option<User>, String> {
+    pub async fn load_user_session(&self) -> Result<Option<User>, FrameSenseError> {
         println!("🔍 DEBUG: load_user_session called");
-        
-        if let Some(storage_path) = &self.storage_path {
-            let user_file = storage_path.join("user_session.json");
-            println!("🔍 DEBUG: Looking for session file at: {:?}", user_file);
-            
-            if user_file.exists() {
-                println!("✅ DEBUG: Session file exists, reading...");
-                let user_json = fs::read_to_string(&user_file)
-                    .map_err(|e| format!("Failed to read user session: {}", e))?;
-                
-                println!("🔍 DEBUG: Read {} bytes from session file", user_json.len());
-                
-                let user: User = serde_json::from_str(&user_json)
-                    .map_err(|e| format!("Failed to parse user session: {}", e))?;
-                
-                println!("✅ DEBUG: User session loaded successfully: {} ({})", user.email, user.tier);
-                return Ok(Some(user));
-            } else {
-                println!("❌ DEBUG: Session file does not exist at: {:?}", user_file);
-            }
-        } else {
+
+        let Some(secrets) = &self.secrets else {
             println!("❌ DEBUG: No storage path configured for loading!");
-        }
-        println!("❌ DEBUG: Returning None from load_user_session");
-        Ok(None)
+            return Ok(None);
+        };
+        let user_json = match secrets.get(&self.session_key()) {
+            Ok(Some(user_json)) => user_json,
+            Ok(None) => return self.migrate_legacy_session(secrets.as_ref()),
+            Err(e) => {
+                // E.g. an encrypted file from another machine: behave as logged out
+                println!("⚠️ Stored session can't be read, ignoring it: {}", e);
+                return Ok(None);
+            },
+        };
+
+        let user: User = serde_json::from_str(&user_json)
+            .map_err(|e| FrameSenseError::Storage(format!("Failed to parse user session: {}", e)))?;
+        println!("✅ DEBUG: User session loaded successfully: {} ({})", user.email, user.tier);
+        Ok(Some(user))
     }
 }
 
@@ -426,4 +644,183 @@ impl Default for AuthService {
     fn default() -> Self {
         Self::new()
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    fn token(exp: u64) -> String {
+        let claims = serde_json::json!({ "userId": "42", "email": "ada@example.com", "exp": exp, "iat": exp - 30 * 24 * 60 * 60 });
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(b"backend-only-secret")).unwrap()
+    }
+
+    // Authorization server standing in for the backend: serves the authorize redirect, the
+    // token exchange (checking the PKCE verifier) and /api/auth/verify, one connection each
+    async fn mock_authorization_server() -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let mut handled = Vec::new();
+            let mut challenge = String::new();
+            let mut redirect_uri = String::new();
+            while handled.len() < 3 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut received = Vec::new();
+                let mut buffer = [0u8; 8192];
+                let head_end = loop {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    received.extend_from_slice(&buffer[..read]);
+                    if let Some(position) = received.windows(4).position(|window| window == b"\r\n\r\n") {
+                        break position + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&received[..head_end]).to_string();
+                let content_length = head
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                while received.len() < head_end + content_length {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    received.extend_from_slice(&buffer[..read]);
+                }
+                let target = head.split_whitespace().nth(1).unwrap().to_string();
+                let url = url::Url::parse(&format!("http://mock{}", target)).unwrap();
+                let query: std::collections::HashMap<String, String> = url.query_pairs().into_owned().collect();
+                let form: std::collections::HashMap<String, String> =
+                    url::form_urlencoded::parse(&received[head_end..]).into_owned().collect();
+
+                let (status, headers, body) = match url.path() {
+                    oauth::AUTHORIZE_PATH => {
+                        assert_eq!((query["client_id"].as_str(), query["code_challenge_method"].as_str()), (oauth::CLIENT_ID, "S256"));
+                        assert_eq!(query["provider"], "google");
+                        challenge = query["code_challenge"].clone();
+                        redirect_uri = query["redirect_uri"].clone();
+                        let location = format!("{}?code=mock-code&state={}", redirect_uri, query["state"]);
+                        ("302 Found", format!("Location: {}\r\n", location), String::new())
+                    },
+                    oauth::TOKEN_PATH => {
+                        let verified = form["grant_type"] == "authorization_code"
+                            && form["code"] == "mock-code"
+                            && form["redirect_uri"] == redirect_uri
+                            && Pkce::from_verifier(form["code_verifier"].clone()).challenge == challenge;
+                        if verified {
+                            ("200 OK", String::new(), r#"{"access_token":"sso-token","token_type":"Bearer"}"#.to_string())
+                        } else {
+                            ("400 Bad Request", String::new(), r#"{"error":"invalid_grant"}"#.to_string())
+                        }
+                    },
+                    "/api/auth/verify" => {
+                        assert!(head.contains("Bearer sso-token"));
+                        let user = r#"{"id":"42","email":"ada@example.com","name":"Ada","tier":"pro","subscription_status":null,"stripe_customer_id":null,"usage_daily":1,"usage_total":7,"created_at":null,"updated_at":null}"#;
+                        ("200 OK", String::new(), format!(r#"{{"success":true,"user":{}}}"#, user))
+                    },
+                    path => panic!("unexpected request to {}", path),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, headers, body.len(), body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                handled.push(format!("{} {}", status, url.path()));
+            }
+            handled
+        });
+
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn browser_login_exchanges_the_code_and_saves_the_session() {
+        let (api_url, server) = mock_authorization_server().await;
+        let dir = std::env::temp_dir().join(format!("framesense-oauth-{}", std::process::id()));
+        let environment = BackendEnvironment::resolve(&api_url, crate::environment::EnvironmentSource::CliFlag, &Default::default()).unwrap();
+        let service = AuthService {
+            secrets: Some(Arc::new(secrets::EncryptedFileStore::with_machine_secret(dir.join("secrets.enc"), b"test-machine".to_vec()))),
+            ..AuthService::for_environment(environment)
+        };
+
+        // The "browser" follows the authorization redirect to the loopback listener
+        let mut browser = None;
+        let user = service
+            .login_with_browser(Some("google"), |url| {
+                let url = url.to_string();
+                browser = Some(tokio::spawn(async move { reqwest::get(url).await.unwrap().text().await.unwrap() }));
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!((user.email.as_str(), user.tier.as_str(), user.token.as_str()), ("ada@example.com", "pro", "sso-token"));
+        assert!(browser.unwrap().await.unwrap().contains("Signed in to FrameSense"));
+        assert_eq!(server.await.unwrap(), vec![
+            "302 Found /api/auth/oauth/authorize",
+            "200 OK /api/auth/oauth/token",
+            "200 OK /api/auth/verify",
+        ]);
+        assert_eq!(service.load_user_session().await.unwrap().map(|user| user.token), Some("sso-token".to_string()));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn expired_sessions_are_only_cleared_when_the_backend_refuses_them() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let dir = std::env::temp_dir().join(format!("framesense-refresh-{}", std::process::id()));
+        let service_for = |api_url: &str, file: &str| AuthService {
+            secrets: Some(Arc::new(secrets::EncryptedFileStore::with_machine_secret(dir.join(file), b"test-machine".to_vec()))),
+            ..AuthService::for_environment(
+                BackendEnvironment::resolve(api_url, crate::environment::EnvironmentSource::CliFlag, &Default::default()).unwrap(),
+            )
+        };
+        let backend_user = serde_json::from_str(r#"{"id":"42","email":"ada@example.com","name":"Ada","tier":"pro"}"#).unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let expired = user_from_backend(backend_user, token(now - 60));
+
+        // Nothing listens on this port: the session is kept for the next try
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let offline = service_for(&format!("http://127.0.0.1:{}", port), "offline.enc");
+        offline.save_user_session(&expired).await.unwrap();
+        assert!(matches!(offline.ensure_fresh_session(false).await, Err(FrameSenseError::Network(_))));
+        assert!(offline.load_user_session().await.unwrap().is_some());
+
+        // A backend that refuses the token ends the session
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = [0u8; 4096];
+            let _ = socket.read(&mut buffer).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let refused = service_for(&api_url, "refused.enc");
+        refused.save_user_session(&expired).await.unwrap();
+        assert!(matches!(refused.ensure_fresh_session(false).await, Ok(SessionCheck::Expired(_))));
+        assert!(refused.load_user_session().await.unwrap().is_none());
+        server.await.unwrap();
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reads_expiry_locally_without_the_signing_secret() {
+        let now = 1_750_000_000;
+        let status = |exp| SessionStatus::at(&token(exp), now);
+
+        assert_eq!(status(now + 20 * 24 * 60 * 60).state, TokenState::Valid);
+        assert_eq!(status(now + 24 * 60 * 60).state, TokenState::RefreshDue);
+        assert_eq!(status(now - 1).state, TokenState::Expired);
+        assert_eq!(status(now - 1).user_id.as_deref(), Some("42"));
+        assert_eq!(status(now + 60).expires_at, Some(now + 60));
+        assert_eq!(SessionStatus::at("not-a-jwt", now).state, TokenState::Unreadable);
+    }
+}
@@ -0,0 +1,351 @@
+// Conversation threads - a capture, its OCR text and the questions asked about it,
+// persisted so follow-ups ("and what about line 3?") don't need a new capture
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::analysis::ChatTurn;
+
+// Older turns are still stored, just not sent, so long threads don't blow the context window
+const MAX_CONTEXT_MESSAGES: usize = 20;
+const TITLE_MAX_CHARS: usize = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadMessage {
+    pub role: String, // "user" or "assistant"
+    pub content: String,
+    pub created_at: u64,
+    #[serde(default)]
+    pub model: Option<String>, // Model that produced an assistant message
+}
+
+// The capture image lives next to the thread JSON instead of inline as base64
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureRef {
+    pub file: String,
+    pub mime: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thread {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub capture: Option<CaptureRef>,
+    pub ocr_text: Option<String>,
+    pub messages: Vec<ThreadMessage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub message_count: usize,
+    pub has_capture: bool,
+    pub preview: String, // Start of the last message
+}
+
+pub struct ConversationStore {
+    dir: PathBuf,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    let text = text.trim().replace('\n', " ");
+    if text.chars().count() <= max_chars {
+        text
+    } else {
+        format!("{}…", text.chars().take(max_chars - 1).collect::<String>().trim_end())
+    }
+}
+
+impl Thread {
+    // Prior turns to send with the next prompt, oldest first
+    pub fn context(&self) -> Vec<ChatTurn> {
+        let start = self.messages.len().saturating_sub(MAX_CONTEXT_MESSAGES);
+        self.messages[start..]
+            .iter()
+            .map(|message| ChatTurn {
+                role: message.role.clone(),
+                content: message.content.clone(),
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> ThreadSummary {
+        ThreadSummary {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            message_count: self.messages.len(),
+            has_capture: self.capture.is_some(),
+            preview: self
+                .messages
+                .last()
+                .map(|message| truncate_chars(&message.content, 80))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl ConversationStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn thread_path(&self, id: &str) -> Result<PathBuf, String> {
+        // Ids come from the frontend; keep them from escaping the threads directory
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid thread id: {}", id));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    pub fn create(&self, first_question: &str, image_data: Option<&str>, ocr_text: Option<String>) -> Result<Thread, String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create threads directory: {}", e))?;
+
+        let now = now_millis();
+        let mut id = format!("thread-{}", now);
+        let mut suffix = 1;
+        while self.dir.join(format!("{}.json", id)).exists() {
+            id = format!("thread-{}-{}", now, suffix);
+            suffix += 1;
+        }
+
+        let capture = match image_data {
+            Some(image_data) => Some(self.save_capture(&id, image_data)?),
+            None => None,
+        };
+
+        let thread = Thread {
+            id,
+            title: truncate_chars(first_question, TITLE_MAX_CHARS),
+            created_at: now,
+            updated_at: now,
+            capture,
+            ocr_text,
+            messages: Vec::new(),
+        };
+        self.save(&thread)?;
+        println!("🧵 Created conversation thread {}", thread.id);
+        Ok(thread)
+    }
+
+    fn save_capture(&self, id: &str, image_data: &str) -> Result<CaptureRef, String> {
+        let (mime, base64_data) = match image_data.strip_prefix("data:") {
+            Some(rest) => {
+                let (header, data) = rest.split_once(',').ok_or("Malformed image data URL")?;
+                (header.trim_end_matches(";base64").to_string(), data)
+            },
+            None => ("image/png".to_string(), image_data),
+        };
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(base64_data)
+            .map_err(|e| format!("Failed to decode image: {}", e))?;
+
+        let extension = match mime.as_str() {
+            "image/jpeg" => "jpg",
+            "image/webp" => "webp",
+            _ => "png",
+        };
+        let file = format!("{}.{}", id, extension);
+        fs::write(self.dir.join(&file), bytes).map_err(|e| format!("Failed to save capture: {}", e))?;
+        Ok(CaptureRef { file, mime })
+    }
+
+    // Capture as a data URL, ready to send along with a follow-up
+    pub fn capture_data(&self, thread: &Thread) -> Result<Option<String>, String> {
+        let Some(capture) = &thread.capture else {
+            return Ok(None);
+        };
+        let bytes = fs::read(self.dir.join(&capture.file))
+            .map_err(|e| format!("Capture for thread {} is missing: {}", thread.id, e))?;
+        Ok(Some(format!(
+            "data:{};base64,{}",
+            capture.mime,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        )))
+    }
+
+    pub fn get(&self, id: &str) -> Result<Thread, String> {
+        let json = fs::read_to_string(self.thread_path(id)?).map_err(|_| format!("Thread not found: {}", id))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse thread {}: {}", id, e))
+    }
+
+    pub fn save(&self, thread: &Thread) -> Result<(), String> {
+        let path = self.thread_path(&thread.id)?;
+        let json = serde_json::to_string_pretty(thread).map_err(|e| format!("Failed to serialize thread: {}", e))?;
+        // Write-then-rename so a crash mid-write can't truncate the thread
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .map_err(|e| format!("Failed to save thread: {}", e))
+    }
+
+    pub fn append_exchange(
+        &self,
+        thread: &mut Thread,
+        question: &str,
+        answer: &str,
+        model: Option<String>,
+    ) -> Result<(), String> {
+        let now = now_millis();
+        thread.messages.push(ThreadMessage {
+            role: "user".to_string(),
+            content: question.to_string(),
+            created_at: now,
+            model: None,
+        });
+        thread.messages.push(ThreadMessage {
+            role: "assistant".to_string(),
+            content: answer.to_string(),
+            created_at: now,
+            model,
+        });
+        thread.updated_at = now;
+        self.save(thread)
+    }
+
+    // Most recently active first; unreadable files are skipped rather than failing the list
+    pub fn list(&self) -> Result<Vec<ThreadSummary>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let mut summaries: Vec<ThreadSummary> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+            .filter_map(|path| {
+                let json = fs::read_to_string(&path).ok()?;
+                match serde_json::from_str::<Thread>(&json) {
+                    Ok(thread) => Some(thread.summary()),
+                    Err(e) => {
+                        println!("⚠️ Skipping unreadable thread {:?}: {}", path, e);
+                        None
+                    },
+                }
+            })
+            .collect();
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.updated_at));
+        Ok(summaries)
+    }
+
+    pub fn rename(&self, id: &str, title: &str) -> Result<Thread, String> {
+        let title = truncate_chars(title, TITLE_MAX_CHARS);
+        if title.is_empty() {
+            return Err("Thread title can't be empty".to_string());
+        }
+        let mut thread = self.get(id)?;
+        thread.title = title;
+        self.save(&thread)?;
+        Ok(thread)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        let thread = self.get(id)?;
+        if let Some(capture) = &thread.capture {
+            let _ = fs::remove_file(self.dir.join(&capture.file));
+        }
+        fs::remove_file(self.thread_path(id)?).map_err(|e| format!("Failed to delete thread: {}", e))?;
+        println!("🗑️ Deleted conversation thread {}", id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> ConversationStore {
+        let dir = std::env::temp_dir().join(format!("framesense-threads-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ConversationStore::new(dir)
+    }
+
+    #[test]
+    fn thread_ids_cannot_leave_the_threads_directory() {
+        let store = temp_store("ids");
+        for id in ["../settings", "..", "a/b", "a\\b", "thread.json", ""] {
+            assert!(store.thread_path(id).is_err(), "{:?} was accepted", id);
+            assert!(store.get(id).is_err() && store.delete(id).is_err());
+        }
+        assert_eq!(store.thread_path("thread-1700000000000-2").unwrap(), store.dir.join("thread-1700000000000-2.json"));
+    }
+
+    #[test]
+    fn threads_are_created_appended_listed_renamed_and_deleted() {
+        let store = temp_store("lifecycle");
+        let question = "What does this stack trace say about the failing request handler in production?";
+        let mut thread = store.create(question, Some("data:image/jpeg;base64,/9j/4AAQ"), Some("panicked at".to_string())).unwrap();
+        assert!(thread.title.ends_with('…') && thread.title.chars().count() <= TITLE_MAX_CHARS);
+        let capture_file = store.dir.join(&thread.capture.as_ref().unwrap().file);
+        assert_eq!(capture_file.extension().unwrap(), "jpg");
+        assert_eq!(store.capture_data(&thread).unwrap().unwrap(), "data:image/jpeg;base64,/9j/4AAQ");
+
+        store.append_exchange(&mut thread, "And line 3?", "A null session token.", Some("gpt-4o".to_string())).unwrap();
+        let stored = store.get(&thread.id).unwrap();
+        assert_eq!(stored.messages.len(), 2);
+        assert_eq!((stored.messages[1].role.as_str(), stored.messages[1].model.as_deref()), ("assistant", Some("gpt-4o")));
+        assert_eq!(stored.ocr_text.as_deref(), Some("panicked at"));
+
+        // Ids stay unique within a millisecond; the second thread is backdated to list after the first
+        let mut older = store.create("Older question", None, None).unwrap();
+        assert_ne!(older.id, thread.id);
+        older.updated_at = thread.updated_at - 1_000;
+        store.save(&older).unwrap();
+        let listed: Vec<String> = store.list().unwrap().into_iter().map(|summary| summary.id).collect();
+        assert_eq!(listed, [thread.id.clone(), older.id.clone()]);
+        assert_eq!(store.list().unwrap()[0].preview, "A null session token.");
+
+        assert_eq!(store.rename(&thread.id, "  Stack trace\n").unwrap().title, "Stack trace");
+        assert_eq!(store.get(&thread.id).unwrap().title, "Stack trace");
+        assert!(store.rename(&thread.id, "   ").is_err());
+
+        store.delete(&thread.id).unwrap();
+        assert!(!capture_file.exists());
+        assert!(store.get(&thread.id).is_err());
+        assert_eq!(store.list().unwrap().len(), 1);
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn context_sends_only_the_latest_messages() {
+        let messages = (0..MAX_CONTEXT_MESSAGES + 5)
+            .map(|index| ThreadMessage {
+                role: if index % 2 == 0 { "user" } else { "assistant" }.to_string(),
+                content: format!("message {}", index),
+                created_at: index as u64,
+                model: None,
+            })
+            .collect();
+        let thread = Thread {
+            id: "thread-1".to_string(),
+            title: "Long thread".to_string(),
+            created_at: 0,
+            updated_at: 0,
+            capture: None,
+            ocr_text: None,
+            messages,
+        };
+
+        let context = thread.context();
+        assert_eq!(context.len(), MAX_CONTEXT_MESSAGES);
+        assert_eq!(context[0].content, "message 5");
+        assert_eq!(context.last().unwrap().content, format!("message {}", MAX_CONTEXT_MESSAGES + 4));
+        assert_eq!(thread.summary().message_count, MAX_CONTEXT_MESSAGES + 5);
+    }
+}
@@ -0,0 +1,205 @@
+// Backend environment profiles - which FrameSense backend the app talks to. Chosen with
+// `--env <name|url>`, then FRAMESENSE_ENV, then `environment.json`, else production. A
+// selection that doesn't resolve stops the app at startup instead of quietly using production.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const PRODUCTION: &str = "production";
+pub const ENV_VAR: &str = "FRAMESENSE_ENV";
+const CLI_FLAG: &str = "--env";
+
+const BUILT_IN_PROFILES: [(&str, &str); 3] = [
+    (PRODUCTION, "https://api.finalyze.pro"), // Railway backend
+    ("staging", "https://staging.finalyze.pro"),
+    ("local", "http://localhost:8080"), // backend/ with its default PORT
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvironmentSource {
+    Default,
+    ConfigFile,
+    EnvVar,
+    CliFlag,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendEnvironment {
+    pub name: String, // A profile name, or "custom" for a URL given directly
+    pub api_url: String,
+    pub source: EnvironmentSource,
+}
+
+// environment.json: { "environment": "staging", "profiles": { "selfhosted": "https://..." } }
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EnvironmentConfig {
+    #[serde(default)]
+    pub environment: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, String>, // Extra profiles; may also override the built-in URLs
+}
+
+impl EnvironmentConfig {
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                println!("⚠️ Failed to parse {:?}, ignoring it: {}", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+fn normalize_url(raw: &str) -> Result<String, String> {
+    let parsed = url::Url::parse(raw).map_err(|e| format!("Invalid backend URL '{}': {}", raw, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!("Backend URL '{}' must be http(s) with a host", raw));
+    }
+    Ok(raw.trim_end_matches('/').to_string())
+}
+
+// `--env staging`, `--env=staging`
+fn cli_selector(args: &[String]) -> Option<String> {
+    let prefix = format!("{}=", CLI_FLAG);
+    args.iter().enumerate().find_map(|(index, arg)| {
+        if arg == CLI_FLAG {
+            args.get(index + 1).cloned()
+        } else {
+            arg.strip_prefix(&prefix).map(str::to_string)
+        }
+    })
+}
+
+impl BackendEnvironment {
+    pub fn production() -> Self {
+        Self {
+            name: PRODUCTION.to_string(),
+            api_url: BUILT_IN_PROFILES[0].1.to_string(),
+            source: EnvironmentSource::Default,
+        }
+    }
+
+    // A profile name or a URL (a custom environment)
+    pub fn resolve(selector: &str, source: EnvironmentSource, config: &EnvironmentConfig) -> Result<Self, String> {
+        let selector = selector.trim();
+        if selector.contains("://") {
+            return Ok(Self {
+                name: "custom".to_string(),
+                api_url: normalize_url(selector)?,
+                source,
+            });
+        }
+
+        let name = selector.to_lowercase();
+        let url = config.profiles.get(&name).map(String::as_str).or_else(|| {
+            BUILT_IN_PROFILES
+                .iter()
+                .find(|(profile, _)| *profile == name)
+                .map(|(_, url)| *url)
+        });
+        match url {
+            Some(url) => Ok(Self {
+                api_url: normalize_url(url)?,
+                name,
+                source,
+            }),
+            None => {
+                let mut known: Vec<&str> = BUILT_IN_PROFILES.iter().map(|(profile, _)| *profile).collect();
+                for profile in config.profiles.keys() {
+                    if !known.contains(&profile.as_str()) {
+                        known.push(profile);
+                    }
+                }
+                Err(format!("Unknown backend environment '{}' (known: {}, or a URL)", selector, known.join(", ")))
+            },
+        }
+    }
+
+    // The first selection from the CLI flag, the environment variable and the config file.
+    // A mistyped selection is an error, so the app doesn't talk to production by accident.
+    pub fn select(args: &[String], env_value: Option<String>, config: &EnvironmentConfig) -> Result<Self, String> {
+        let selection = [
+            (cli_selector(args), EnvironmentSource::CliFlag),
+            (env_value, EnvironmentSource::EnvVar),
+            (config.environment.clone(), EnvironmentSource::ConfigFile),
+        ]
+        .into_iter()
+        .find_map(|(selector, source)| selector.filter(|selector| !selector.trim().is_empty()).map(|selector| (selector, source)));
+
+        let Some((selector, source)) = selection else {
+            return Ok(Self::production());
+        };
+        Self::resolve(&selector, source, config)
+    }
+
+    // The process's own arguments, FRAMESENSE_ENV and `config_path`
+    pub fn from_startup(config_path: &Path) -> Result<Self, String> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let environment = Self::select(&args, std::env::var(ENV_VAR).ok(), &EnvironmentConfig::load(config_path))?;
+        println!("🌐 Backend environment: {} ({}, from {:?})", environment.name, environment.api_url, environment.source);
+        Ok(environment)
+    }
+
+    pub fn is_production(&self) -> bool {
+        self.name == PRODUCTION
+    }
+
+    // Per-environment name for files that hold backend data, so staging data never
+    // stands in for production's: model_registry.json, model_registry.staging.json
+    pub fn scoped_file_name(&self, file_name: &str) -> String {
+        if self.is_production() {
+            return file_name.to_string();
+        }
+        match file_name.rsplit_once('.') {
+            Some((stem, extension)) => format!("{}.{}.{}", stem, self.name, extension),
+            None => format!("{}.{}", file_name, self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn cli_flag_beats_env_var_beats_config_file() {
+        let config: EnvironmentConfig = serde_json::from_str(
+            r#"{ "environment": "selfhosted", "profiles": { "selfhosted": "https://framesense.example.com/" } }"#,
+        )
+        .unwrap();
+
+        let from_config = BackendEnvironment::select(&[], None, &config).unwrap();
+        assert_eq!((from_config.name.as_str(), from_config.api_url.as_str()), ("selfhosted", "https://framesense.example.com"));
+        assert_eq!(from_config.source, EnvironmentSource::ConfigFile);
+
+        let from_env = BackendEnvironment::select(&[], Some("Staging".to_string()), &config).unwrap();
+        assert_eq!((from_env.name.as_str(), from_env.source), ("staging", EnvironmentSource::EnvVar));
+
+        let from_cli = BackendEnvironment::select(&args(&["--env=http://127.0.0.1:8787"]), Some("staging".to_string()), &config).unwrap();
+        assert_eq!((from_cli.name.as_str(), from_cli.api_url.as_str()), ("custom", "http://127.0.0.1:8787"));
+        let from_cli = BackendEnvironment::select(&args(&["--env", "local"]), None, &config).unwrap();
+        assert_eq!((from_cli.api_url.as_str(), from_cli.source), ("http://localhost:8080", EnvironmentSource::CliFlag));
+
+        let default = BackendEnvironment::select(&[], None, &EnvironmentConfig::default()).unwrap();
+        assert!(default.is_production());
+        assert_eq!(default.scoped_file_name("model_registry.json"), "model_registry.json");
+        assert_eq!(from_env.scoped_file_name("model_registry.json"), "model_registry.staging.json");
+    }
+
+    #[test]
+    fn unknown_or_invalid_selection_is_an_error() {
+        let config = EnvironmentConfig::default();
+        let unknown = BackendEnvironment::select(&args(&["--env", "qa"]), None, &config).unwrap_err();
+        assert!(unknown.contains("known: production, staging, local"));
+
+        let invalid = BackendEnvironment::select(&[], Some("ftp://files.example.com".to_string()), &config);
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,277 @@
+// FrameSenseError - what commands return to the frontend. Serialized as
+// { "code": "tier_required", "message": "...", ...details }, where the codes are stable
+// and the message is for display only, so the frontend never matches on English text.
+use serde::{Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+// Message of FrameSenseError::Cancelled, the error cancelled jobs return
+pub const CANCELLED: &str = "Cancelled";
+
+#[derive(Debug, Clone)]
+pub enum FrameSenseError {
+    Network(String),      // No connection to the backend or provider
+    Timeout(String),      // The request or the answer stream took too long
+    Unauthorized(String), // Not logged in, bad credentials, or a session that expired
+    TierRequired {
+        model: String,
+        required_tier: String,
+        current_tier: String,
+    },
+    QuotaExceeded {
+        tier: String,
+        used: i32,
+        limit: i32,
+        resets_at: String, // RFC 3339, next local midnight
+    },
+    RateLimited(String),
+    Server {
+        message: String,
+        status: Option<u16>, // None for a dropped stream or an unusable answer
+    },
+    ContentPolicy(String), // The provider refused the content; another provider may not
+    Rejected {
+        message: String,
+        status: u16, // Any other 4xx: the request itself is wrong, retrying won't help
+    },
+    Queued {
+        message: String,
+        job_id: String, // Retried from the offline queue once the connection is back
+    },
+    CapturePermission(String), // The OS refused screen capture
+    NoDisplay,
+    BoundsInvalid(String),
+    InvalidImage(String), // Image data that isn't base64 or doesn't decode as an image
+    OcrUnavailable(String),
+    Storage(String), // Reading or writing local data (session, settings) failed
+    Cancelled,
+    Other(String), // Not classified; the message says what happened
+}
+
+impl FrameSenseError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Network(_) => "network",
+            Self::Timeout(_) => "timeout",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::TierRequired { .. } => "tier_required",
+            Self::QuotaExceeded { .. } => "quota_exceeded",
+            Self::RateLimited(_) => "rate_limited",
+            Self::Server { .. } => "server",
+            Self::ContentPolicy(_) => "content_policy",
+            Self::Rejected { .. } => "rejected",
+            Self::Queued { .. } => "queued",
+            Self::CapturePermission(_) => "capture_permission",
+            Self::NoDisplay => "no_display",
+            Self::BoundsInvalid(_) => "bounds_invalid",
+            Self::InvalidImage(_) => "invalid_image",
+            Self::OcrUnavailable(_) => "ocr_unavailable",
+            Self::Storage(_) => "storage",
+            Self::Cancelled => "cancelled",
+            Self::Other(_) => "other",
+        }
+    }
+
+    // Fields beyond code and message, for the variants that carry any
+    fn details(&self) -> Map<String, Value> {
+        let details = match self {
+            Self::TierRequired { model, required_tier, current_tier } => json!({
+                "model": model,
+                "required_tier": required_tier,
+                "current_tier": current_tier
+            }),
+            Self::QuotaExceeded { tier, used, limit, resets_at } => json!({
+                "tier": tier,
+                "used": used,
+                "limit": limit,
+                "resets_at": resets_at
+            }),
+            Self::Queued { job_id, .. } => json!({ "job_id": job_id }),
+            Self::Server { status: Some(status), .. } | Self::Rejected { status, .. } => json!({ "status": status }),
+            _ => json!({}),
+        };
+        match details {
+            Value::Object(details) => details,
+            _ => Map::new(),
+        }
+    }
+
+    // Maps an HTTP error status from the backend or a provider onto a kind
+    pub fn from_status(status: u16, message: impl Into<String>) -> Self {
+        let message = message.into();
+        match status {
+            401 | 403 => Self::Unauthorized(message),
+            408 => Self::Timeout(message),
+            429 => Self::RateLimited(message),
+            500..=599 => Self::Server { message, status: Some(status) },
+            _ => Self::Rejected { message, status },
+        }
+    }
+
+    // The HTTP status behind the error, where there was one
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Server { status, .. } => *status,
+            Self::Rejected { status, .. } => Some(*status),
+            Self::RateLimited(_) => Some(429),
+            _ => None,
+        }
+    }
+
+    // Worth retrying later from the offline queue; the same request would fail again otherwise
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Network(_) | Self::Timeout(_) | Self::RateLimited(_) | Self::Server { .. }
+        )
+    }
+
+    // Rewrites the message and keeps the kind (and with it the code and status)
+    pub fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            Self::Network(message) => Self::Network(f(message)),
+            Self::Timeout(message) => Self::Timeout(f(message)),
+            Self::Unauthorized(message) => Self::Unauthorized(f(message)),
+            Self::RateLimited(message) => Self::RateLimited(f(message)),
+            Self::Server { message, status } => Self::Server { message: f(message), status },
+            Self::ContentPolicy(message) => Self::ContentPolicy(f(message)),
+            Self::Rejected { message, status } => Self::Rejected { message: f(message), status },
+            Self::Queued { message, job_id } => Self::Queued { message: f(message), job_id },
+            Self::CapturePermission(message) => Self::CapturePermission(f(message)),
+            Self::BoundsInvalid(message) => Self::BoundsInvalid(f(message)),
+            Self::InvalidImage(message) => Self::InvalidImage(f(message)),
+            Self::OcrUnavailable(message) => Self::OcrUnavailable(f(message)),
+            Self::Storage(message) => Self::Storage(f(message)),
+            Self::Other(message) => Self::Other(f(message)),
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for FrameSenseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(message)
+            | Self::Timeout(message)
+            | Self::Unauthorized(message)
+            | Self::RateLimited(message)
+            | Self::Server { message, .. }
+            | Self::ContentPolicy(message)
+            | Self::Rejected { message, .. }
+            | Self::Queued { message, .. }
+            | Self::CapturePermission(message)
+            | Self::BoundsInvalid(message)
+            | Self::InvalidImage(message)
+            | Self::OcrUnavailable(message)
+            | Self::Storage(message)
+            | Self::Other(message) => write!(f, "{}", message),
+            Self::TierRequired { model, required_tier, current_tier } => write!(
+                f,
+                "{} requires the {} plan (current plan: {})",
+                model, required_tier, current_tier
+            ),
+            Self::QuotaExceeded { tier, used, limit, .. } => write!(
+                f,
+                "Daily limit reached: {} of {} analyses used on the {} plan",
+                used, limit, tier
+            ),
+            Self::NoDisplay => write!(f, "No display available for capture"),
+            Self::Cancelled => write!(f, "{}", CANCELLED),
+        }
+    }
+}
+
+impl Serialize for FrameSenseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut payload = self.details();
+        payload.insert("code".to_string(), json!(self.code()));
+        payload.insert("message".to_string(), json!(self.to_string()));
+        payload.serialize(serializer)
+    }
+}
+
+// Plain string errors (settings, templates, validation) carry no kind of their own
+impl From<String> for FrameSenseError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+// A request that never got an answer: timed out, or couldn't connect at all
+impl From<reqwest::Error> for FrameSenseError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            Self::Timeout(format!("Request timed out: {}", error))
+        } else {
+            Self::Network(format!("Network error: {}", error))
+        }
+    }
+}
+
+impl From<&str> for FrameSenseError {
+    fn from(message: &str) -> Self {
+        Self::from(message.to_string())
+    }
+}
+
+// For the modules and callbacks that still take string errors
+impl From<FrameSenseError> for String {
+    fn from(error: FrameSenseError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_message_and_details() {
+        let tier = FrameSenseError::TierRequired {
+            model: "gpt-4o".to_string(),
+            required_tier: "pro".to_string(),
+            current_tier: "free".to_string(),
+        };
+        assert_eq!(serde_json::to_value(&tier).unwrap(), json!({
+            "code": "tier_required",
+            "message": "gpt-4o requires the pro plan (current plan: free)",
+            "model": "gpt-4o",
+            "required_tier": "pro",
+            "current_tier": "free"
+        }));
+
+        let quota = FrameSenseError::QuotaExceeded {
+            tier: "free".to_string(),
+            used: 10,
+            limit: 10,
+            resets_at: "2024-05-02T00:00:00+02:00".to_string(),
+        };
+        let quota = serde_json::to_value(&quota).unwrap();
+        assert_eq!((quota["code"].as_str(), quota["limit"].as_i64()), (Some("quota_exceeded"), Some(10)));
+
+        assert_eq!(serde_json::to_value(FrameSenseError::NoDisplay).unwrap()["code"], "no_display");
+    }
+
+    #[test]
+    fn classifies_http_statuses() {
+        let code = |status: u16| FrameSenseError::from_status(status, "failed").code();
+        assert_eq!(code(401), "unauthorized");
+        assert_eq!(code(403), "unauthorized");
+        assert_eq!(code(429), "rate_limited");
+        assert_eq!(code(502), "server");
+        assert_eq!(code(400), "rejected");
+
+        let server = FrameSenseError::from_status(503, "Overloaded");
+        assert!(server.is_transient());
+        assert_eq!(serde_json::to_value(&server).unwrap()["status"], 503);
+        assert!(!FrameSenseError::from_status(422, "Bad image").is_transient());
+
+        // The fallback chain's suffix keeps the kind
+        let tried = server.map_message(|message| format!("{} (tried gpt-4o)", message));
+        assert_eq!((tried.code(), tried.status()), ("server", Some(503)));
+        assert_eq!(tried.to_string(), "Overloaded (tried gpt-4o)");
+
+        // Plain strings are no longer classified by their wording
+        assert_eq!(FrameSenseError::from("Network error: nope").code(), "other");
+    }
+}
@@ -0,0 +1,273 @@
+// Structured extraction - the model fills in a JSON Schema instead of answering in
+// prose, and the answer is validated here before anyone pastes it into a spreadsheet
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+pub mod schema;
+
+pub const MAX_ATTEMPTS: u32 = 3;
+
+const BUILTIN_SCHEMAS: [(&str, &str); 3] = [
+    ("invoice", include_str!("../../resources/schemas/invoice.json")),
+    ("contact", include_str!("../../resources/schemas/contact.json")),
+    ("stack_trace", include_str!("../../resources/schemas/stack_trace.json")),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSchema {
+    pub name: String, // File name and lookup key, e.g. "invoice"
+    pub title: String,
+    pub description: String,
+    pub schema: Value,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractionResult {
+    pub data: Value, // Conforms to the schema
+    pub schema_name: Option<String>,
+    pub attempts: u32,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtractionExport {
+    Json,
+    Csv,
+}
+
+impl ExtractionExport {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExtractionExport::Json => "json",
+            ExtractionExport::Csv => "csv",
+        }
+    }
+}
+
+// Built-in schemas plus the user's own, saved as <name>.json
+pub struct SchemaStore {
+    dir: PathBuf,
+}
+
+fn describe(name: &str, schema: Value, builtin: bool) -> SavedSchema {
+    SavedSchema {
+        name: name.to_string(),
+        title: schema["title"].as_str().unwrap_or(name).to_string(),
+        description: schema["description"].as_str().unwrap_or_default().to_string(),
+        schema,
+        builtin,
+    }
+}
+
+fn builtin(name: &str) -> Option<SavedSchema> {
+    BUILTIN_SCHEMAS
+        .iter()
+        .find(|(builtin_name, _)| *builtin_name == name)
+        .map(|(name, json)| describe(name, serde_json::from_str(json).expect("bundled schema is invalid"), true))
+}
+
+impl SchemaStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn schema_path(&self, name: &str) -> Result<PathBuf, String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("Invalid schema name '{}': use letters, digits, '-' and '_'", name));
+        }
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+
+    pub fn list(&self) -> Vec<SavedSchema> {
+        let mut schemas: Vec<SavedSchema> = BUILTIN_SCHEMAS.iter().filter_map(|(name, _)| builtin(name)).collect();
+
+        let mut saved: Vec<SavedSchema> = fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+                    .filter_map(|path| {
+                        let name = path.file_stem()?.to_str()?.to_string();
+                        let json = fs::read_to_string(&path).ok()?;
+                        match serde_json::from_str::<Value>(&json) {
+                            Ok(schema) => Some(describe(&name, schema, false)),
+                            Err(e) => {
+                                println!("⚠️ Skipping unreadable schema {:?}: {}", path, e);
+                                None
+                            },
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        saved.sort_by(|a, b| a.name.cmp(&b.name));
+        schemas.extend(saved);
+        schemas
+    }
+
+    pub fn get(&self, name: &str) -> Result<SavedSchema, String> {
+        if let Some(schema) = builtin(name) {
+            return Ok(schema);
+        }
+        let json = fs::read_to_string(self.schema_path(name)?).map_err(|_| format!("Schema not found: {}", name))?;
+        let schema = serde_json::from_str(&json).map_err(|e| format!("Failed to parse schema {}: {}", name, e))?;
+        Ok(describe(name, schema, false))
+    }
+
+    pub fn save(&self, name: &str, schema: Value) -> Result<SavedSchema, String> {
+        if builtin(name).is_some() {
+            return Err(format!("'{}' is a built-in schema, save yours under another name", name));
+        }
+        let path = self.schema_path(name)?;
+        schema::check_schema(&schema)?;
+
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create schemas directory: {}", e))?;
+        let json = serde_json::to_string_pretty(&schema).map_err(|e| format!("Failed to serialize schema: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to save schema: {}", e))?;
+        println!("📐 Saved extraction schema '{}'", name);
+        Ok(describe(name, schema, false))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        if builtin(name).is_some() {
+            return Err(format!("'{}' is a built-in schema and can't be deleted", name));
+        }
+        fs::remove_file(self.schema_path(name)?).map_err(|_| format!("Schema not found: {}", name))
+    }
+}
+
+pub fn extraction_prompt(schema: &Value) -> String {
+    format!(
+        "Extract the information in this capture as JSON that conforms to this JSON Schema:\n\n{}\n\n\
+         Reply with the JSON object only, no explanation and no code fences. \
+         Use null for optional values that aren't visible; don't guess.",
+        serde_json::to_string_pretty(schema).unwrap_or_default()
+    )
+}
+
+pub fn retry_prompt(errors: &[String]) -> String {
+    format!(
+        "That JSON doesn't conform to the schema:\n- {}\n\nReply with the corrected JSON object only.",
+        errors.join("\n- ")
+    )
+}
+
+// Models like to wrap JSON in code fences or a sentence, so take the outermost object or array
+pub fn parse_answer(answer: &str) -> Result<Value, String> {
+    let trimmed = answer.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Ok(value);
+    }
+
+    let start = trimmed.find(['{', '[']).ok_or("The answer contains no JSON")?;
+    let close = if trimmed[start..].starts_with('{') { '}' } else { ']' };
+    let end = trimmed.rfind(close).filter(|end| *end > start).ok_or("The answer contains incomplete JSON")?;
+    serde_json::from_str(&trimmed[start..=end]).map_err(|e| format!("The answer is not valid JSON: {}", e))
+}
+
+// The conforming value, or the problems to send back to the model
+pub fn check_answer(schema: &Value, answer: &str) -> Result<Value, Vec<String>> {
+    let value = parse_answer(answer).map_err(|error| vec![error])?;
+    let errors = schema::validate(schema, &value);
+    if errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(errors)
+    }
+}
+
+fn flatten(value: &Value, prefix: &str, rows: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) => {
+            for (key, nested) in object {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(nested, &key, rows);
+            }
+        },
+        Value::Array(items) => {
+            for (index, nested) in items.iter().enumerate() {
+                let key = if prefix.is_empty() { index.to_string() } else { format!("{}.{}", prefix, index) };
+                flatten(nested, &key, rows);
+            }
+        },
+        Value::String(text) => rows.push((prefix.to_string(), text.clone())),
+        Value::Null => rows.push((prefix.to_string(), String::new())),
+        other => rows.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// JSON as-is, or CSV with one "field,value" row per leaf ("line_items.0.amount,12.5")
+pub fn export(data: &Value, format: ExtractionExport) -> Result<Vec<u8>, String> {
+    match format {
+        ExtractionExport::Json => serde_json::to_vec_pretty(data).map_err(|e| format!("Failed to serialize extraction: {}", e)),
+        ExtractionExport::Csv => {
+            let mut rows = Vec::new();
+            flatten(data, "", &mut rows);
+            let mut csv = String::from("field,value\n");
+            for (field, value) in rows {
+                csv.push_str(&format!("{},{}\n", csv_field(&field), csv_field(&value)));
+            }
+            Ok(csv.into_bytes())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn bundled_schemas_pass_the_schema_check() {
+        for (name, _) in BUILTIN_SCHEMAS {
+            schema::check_schema(&builtin(name).unwrap().schema).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        }
+    }
+
+    #[test]
+    fn parses_fenced_and_prose_wrapped_answers() {
+        let expected = json!({ "total": 12.5, "currency": "EUR" });
+        assert_eq!(parse_answer("{\"total\": 12.5, \"currency\": \"EUR\"}").unwrap(), expected);
+        assert_eq!(parse_answer("```json\n{\"total\": 12.5, \"currency\": \"EUR\"}\n```").unwrap(), expected);
+        assert_eq!(
+            parse_answer("Here is the invoice:\n{\"total\": 12.5, \"currency\": \"EUR\"}\nLet me know if you need more.").unwrap(),
+            expected
+        );
+        assert_eq!(parse_answer("The rows: [1, 2, 3].").unwrap(), json!([1, 2, 3]));
+
+        assert!(parse_answer("I can't read the total.").is_err());
+        assert!(parse_answer("{\"total\": 12.5").is_err());
+    }
+
+    #[test]
+    fn csv_export_has_one_row_per_leaf() {
+        let data = json!({
+            "supplier": { "name": "Acme, Inc." },
+            "line_items": [{ "amount": 12.5 }, { "amount": null }],
+            "note": "Said \"thanks\""
+        });
+        let csv = String::from_utf8(export(&data, ExtractionExport::Csv).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "field,value\nline_items.0.amount,12.5\nline_items.1.amount,\nnote,\"Said \"\"thanks\"\"\"\nsupplier.name,\"Acme, Inc.\"\n"
+        );
+
+        // A top-level array is indexed from its first level, without a leading dot
+        let csv = String::from_utf8(export(&json!([{ "name": "Anna" }, "Bo"]), ExtractionExport::Csv).unwrap()).unwrap();
+        assert_eq!(csv, "field,value\n0.name,Anna\n1,Bo\n");
+    }
+}
@@ -0,0 +1,364 @@
+// JSON Schema validation for extraction results. Covers the subset that describes
+// extracted records (types, properties, items, enums, bounds, patterns, anyOf/oneOf).
+// Schemas using any other keyword are refused when saved rather than half-checked.
+use regex::Regex;
+use serde_json::Value;
+
+const SUPPORTED_TYPES: [&str; 7] = ["object", "array", "string", "number", "integer", "boolean", "null"];
+
+const SUPPORTED_KEYWORDS: [&str; 21] = [
+    "type", "enum", "const",
+    "minLength", "maxLength", "pattern",
+    "minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum", "multipleOf",
+    "items", "minItems", "maxItems", "uniqueItems",
+    "properties", "required", "additionalProperties",
+    "allOf", "anyOf", "oneOf",
+];
+
+// Annotations that don't constrain the value
+const ANNOTATION_KEYWORDS: [&str; 10] = [
+    "$schema", "$id", "$comment", "title", "description", "default", "examples", "readOnly", "writeOnly", "deprecated",
+];
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    let actual = type_name(value);
+    actual == expected
+        || (expected == "number" && actual == "integer")
+        // 3.0 is an integer as far as JSON Schema is concerned
+        || (expected == "integer" && value.as_f64().is_some_and(|number| number.fract() == 0.0))
+}
+
+fn pointer(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+// Catches schemas that can't describe anything, or use keywords `validate` would
+// silently skip ($ref, format, patternProperties, ...), before a request is spent on them
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    check_schema_at(schema, "")
+}
+
+fn check_schema_at(schema: &Value, path: &str) -> Result<(), String> {
+    let object = match schema {
+        Value::Object(object) => object,
+        Value::Bool(_) if !path.is_empty() => return Ok(()),
+        _ => return Err(format!("{}: schema must be a JSON object", pointer(path))),
+    };
+
+    if let Some(keyword) = object
+        .keys()
+        .find(|key| !SUPPORTED_KEYWORDS.contains(&key.as_str()) && !ANNOTATION_KEYWORDS.contains(&key.as_str()))
+    {
+        return Err(format!("{}: unsupported schema keyword \"{}\"", pointer(path), keyword));
+    }
+
+    let types: Vec<&Value> = match object.get("type") {
+        Some(Value::Array(types)) => types.iter().collect(),
+        Some(single) => vec![single],
+        None => Vec::new(),
+    };
+    for schema_type in types {
+        match schema_type.as_str() {
+            Some(name) if SUPPORTED_TYPES.contains(&name) => {},
+            _ => return Err(format!("{}: unknown schema type {}", pointer(path), schema_type)),
+        }
+    }
+
+    if let Some(pattern) = object.get("pattern").and_then(Value::as_str) {
+        Regex::new(pattern).map_err(|e| format!("{}: invalid pattern '{}': {}", pointer(path), pattern, e))?;
+    }
+    if object.get("multipleOf").is_some_and(|divisor| !divisor.as_f64().is_some_and(|divisor| divisor > 0.0)) {
+        return Err(format!("{}: multipleOf must be a number above 0", pointer(path)));
+    }
+    if let Some(properties) = object.get("properties").and_then(Value::as_object) {
+        for (name, property) in properties {
+            check_schema_at(property, &format!("{}/properties/{}", path, name))?;
+        }
+    }
+    for key in ["items", "additionalProperties"] {
+        match object.get(key) {
+            Some(Value::Array(_)) => return Err(format!("{}/{}: tuple items are not supported", path, key)),
+            Some(nested) => check_schema_at(nested, &format!("{}/{}", path, key))?,
+            None => {},
+        }
+    }
+    for key in ["anyOf", "oneOf", "allOf"] {
+        if let Some(variants) = object.get(key).and_then(Value::as_array) {
+            for (index, variant) in variants.iter().enumerate() {
+                check_schema_at(variant, &format!("{}/{}/{}", path, key, index))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Every violation as "<JSON pointer>: <problem>"; empty when the instance conforms
+pub fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, instance, "", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        // `true` / `{}` accept anything, `false` nothing
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: no value is allowed here", pointer(path)));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            other => other.as_str().into_iter().collect(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|expected| matches_type(value, expected)) {
+            errors.push(format!("{}: expected {}, got {}", pointer(path), allowed.join(" or "), type_name(value)));
+            // Further keywords would only repeat the type mismatch
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            let options: Vec<String> = options.iter().map(Value::to_string).collect();
+            errors.push(format!("{}: must be one of {}", pointer(path), options.join(", ")));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            errors.push(format!("{}: must be {}", pointer(path), constant));
+        }
+    }
+
+    match value {
+        Value::String(text) => validate_string(schema, text, path, errors),
+        Value::Number(_) => validate_number(schema, value.as_f64().unwrap_or_default(), path, errors),
+        Value::Array(items) => validate_array(schema, items, path, errors),
+        Value::Object(object) => validate_object(schema, object, path, errors),
+        _ => {},
+    }
+
+    validate_combinators(schema, value, path, errors);
+}
+
+fn validate_string(schema: &serde_json::Map<String, Value>, text: &str, path: &str, errors: &mut Vec<String>) {
+    let length = text.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < min {
+            errors.push(format!("{}: must be at least {} characters", pointer(path), min));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > max {
+            errors.push(format!("{}: must be at most {} characters", pointer(path), max));
+        }
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        if let Ok(regex) = Regex::new(pattern) {
+            if !regex.is_match(text) {
+                errors.push(format!("{}: must match {}", pointer(path), pattern));
+            }
+        }
+    }
+}
+
+fn validate_number(schema: &serde_json::Map<String, Value>, number: f64, path: &str, errors: &mut Vec<String>) {
+    if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+        if number < min {
+            errors.push(format!("{}: must be at least {}", pointer(path), min));
+        }
+    }
+    if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+        if number > max {
+            errors.push(format!("{}: must be at most {}", pointer(path), max));
+        }
+    }
+    if let Some(min) = schema.get("exclusiveMinimum").and_then(Value::as_f64) {
+        if number <= min {
+            errors.push(format!("{}: must be more than {}", pointer(path), min));
+        }
+    }
+    if let Some(max) = schema.get("exclusiveMaximum").and_then(Value::as_f64) {
+        if number >= max {
+            errors.push(format!("{}: must be less than {}", pointer(path), max));
+        }
+    }
+    if let Some(divisor) = schema.get("multipleOf").and_then(Value::as_f64).filter(|divisor| *divisor > 0.0) {
+        // Amounts like 12.35 with multipleOf 0.01 aren't exact in floating point
+        let quotient = number / divisor;
+        if (quotient - quotient.round()).abs() > 1e-9 {
+            errors.push(format!("{}: must be a multiple of {}", pointer(path), divisor));
+        }
+    }
+}
+
+fn validate_array(schema: &serde_json::Map<String, Value>, items: &[Value], path: &str, errors: &mut Vec<String>) {
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if (items.len() as u64) < min {
+            errors.push(format!("{}: must have at least {} items", pointer(path), min));
+        }
+    }
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if items.len() as u64 > max {
+            errors.push(format!("{}: must have at most {} items", pointer(path), max));
+        }
+    }
+    if schema.get("uniqueItems").and_then(Value::as_bool) == Some(true) {
+        if let Some(index) = (1..items.len()).find(|&index| items[..index].contains(&items[index])) {
+            errors.push(format!("{}/{}: duplicate item, items must be unique", path, index));
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        for (index, item) in items.iter().enumerate() {
+            validate_at(item_schema, item, &format!("{}/{}", path, index), errors);
+        }
+    }
+}
+
+fn validate_object(
+    schema: &serde_json::Map<String, Value>,
+    object: &serde_json::Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                errors.push(format!("{}: missing required property \"{}\"", pointer(path), key));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (key, property_value) in object {
+        let property_path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+        match properties.and_then(|properties| properties.get(key)) {
+            Some(property_schema) => validate_at(property_schema, property_value, &property_path, errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => errors.push(format!("{}: unexpected property", property_path)),
+                Some(additional) => validate_at(additional, property_value, &property_path, errors),
+                None => {},
+            },
+        }
+    }
+}
+
+fn validate_combinators(schema: &serde_json::Map<String, Value>, value: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(variants) = schema.get("allOf").and_then(Value::as_array) {
+        for variant in variants {
+            validate_at(variant, value, path, errors);
+        }
+    }
+    if let Some(variants) = schema.get("anyOf").and_then(Value::as_array) {
+        if !variants.iter().any(|variant| validate(variant, value).is_empty()) {
+            errors.push(format!("{}: doesn't match any of the allowed shapes", pointer(path)));
+        }
+    }
+    if let Some(variants) = schema.get("oneOf").and_then(Value::as_array) {
+        let matching = variants.iter().filter(|variant| validate(variant, value).is_empty()).count();
+        if matching != 1 {
+            errors.push(format!("{}: must match exactly one of the allowed shapes (matched {})", pointer(path), matching));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn invoice() -> Value {
+        json!({
+            "type": "object",
+            "title": "Invoice",
+            "required": ["total", "currency"],
+            "additionalProperties": false,
+            "properties": {
+                "total": { "type": "number", "exclusiveMinimum": 0, "multipleOf": 0.01 },
+                "currency": { "type": "string", "enum": ["EUR", "SEK"] },
+                "reference": { "type": ["string", "null"], "pattern": "^INV-[0-9]+$" },
+                "tags": { "type": "array", "items": { "type": "string", "minLength": 1 }, "uniqueItems": true, "maxItems": 3 },
+                "quantity": { "type": "integer", "minimum": 1 }
+            }
+        })
+    }
+
+    #[test]
+    fn validates_against_the_supported_keywords() {
+        let schema = invoice();
+        check_schema(&schema).unwrap();
+
+        let valid = json!({ "total": 12.35, "currency": "EUR", "reference": null, "tags": ["a", "b"], "quantity": 3.0 });
+        assert!(validate(&schema, &valid).is_empty(), "{:?}", validate(&schema, &valid));
+
+        let invalid = json!({
+            "total": 0,
+            "currency": "USD",
+            "reference": "INV-x",
+            "tags": ["a", "", "a", "b"],
+            "quantity": "3",
+            "extra": true
+        });
+        let errors = validate(&schema, &invalid);
+        for expected in [
+            "/total: must be more than 0",
+            "/currency: must be one of \"EUR\", \"SEK\"",
+            "/reference: must match ^INV-[0-9]+$",
+            "/tags: must have at most 3 items",
+            "/tags/2: duplicate item, items must be unique",
+            "/tags/1: must be at least 1 characters",
+            "/quantity: expected integer, got string",
+            "/extra: unexpected property",
+        ] {
+            assert!(errors.iter().any(|error| error == expected), "missing {:?} in {:?}", expected, errors);
+        }
+
+        assert_eq!(validate(&schema, &json!({ "total": 10.005, "currency": "SEK" })), vec!["/total: must be a multiple of 0.01"]);
+        assert_eq!(validate(&schema, &json!({ "currency": "SEK" })), vec!["/: missing required property \"total\""]);
+    }
+
+    #[test]
+    fn combinators_need_the_right_number_of_matches() {
+        let schema = json!({ "oneOf": [{ "type": "integer" }, { "type": "number", "minimum": 10 }] });
+        assert!(validate(&schema, &json!(3)).is_empty());
+        assert_eq!(validate(&schema, &json!(12)), vec!["/: must match exactly one of the allowed shapes (matched 2)"]);
+        let schema = json!({ "anyOf": [{ "type": "string" }, { "type": "null" }] });
+        assert!(validate(&schema, &json!(null)).is_empty());
+        assert_eq!(validate(&schema, &json!(1)).len(), 1);
+    }
+
+    #[test]
+    fn refuses_keywords_it_cannot_check() {
+        let refused = |schema: Value| check_schema(&schema).unwrap_err();
+        assert_eq!(refused(json!({ "$ref": "#/$defs/money" })), "/: unsupported schema keyword \"$ref\"");
+        assert_eq!(
+            refused(json!({ "properties": { "due": { "type": "string", "format": "date" } } })),
+            "/properties/due: unsupported schema keyword \"format\""
+        );
+        for keyword in ["$defs", "definitions", "patternProperties", "dependentRequired"] {
+            assert!(check_schema(&json!({ "type": "object", keyword: {} })).is_err(), "{}", keyword);
+        }
+        assert!(refused(json!({ "type": "array", "items": [{ "type": "string" }] })).contains("tuple items"));
+        assert!(refused(json!({ "type": "decimal" })).contains("unknown schema type"));
+        assert!(refused(json!({ "type": "number", "multipleOf": 0 })).contains("multipleOf"));
+        assert!(refused(json!({ "type": "string", "pattern": "(" })).contains("invalid pattern"));
+    }
+}
@@ -0,0 +1,200 @@
+// In-flight jobs (analyses and OCR runs) with cancellation tokens, so a job started on
+// the wrong region can be stopped instead of waited out
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+
+use crate::errors::FrameSenseError;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Analysis,
+    Ocr,
+    Pipeline, // A batch run through a processing pipeline
+    Login,    // Browser sign-in waiting for the redirect
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub kind: JobKind,
+    pub window: String,
+    pub started_at: u64,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    serial: u64,
+    cancel: watch::Sender<bool>,
+}
+
+pub struct CancelToken {
+    id: String,
+    serial: u64,
+    cancelled: watch::Receiver<bool>,
+}
+
+#[derive(Default)]
+pub struct JobManager {
+    jobs: HashMap<String, JobEntry>,
+    next_serial: u64,
+}
+
+impl CancelToken {
+    // Runs `job` until it finishes or the token is cancelled. Cancelling drops the
+    // future, which for reqwest closes the connection and aborts the request.
+    pub async fn run<T, E: From<FrameSenseError>>(&self, job: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let mut cancelled = self.cancelled.clone();
+        tokio::select! {
+            biased;
+            _ = cancelled.wait_for(|cancelled| *cancelled) => Err(FrameSenseError::Cancelled.into()),
+            result = job => result,
+        }
+    }
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Reusing the id of a job that's still running replaces (and cancels) that job
+    pub fn register(&mut self, id: &str, kind: JobKind, window: &str) -> CancelToken {
+        if let Some(previous) = self.jobs.remove(id) {
+            let _ = previous.cancel.send(true);
+        }
+
+        self.next_serial += 1;
+        let (cancel, cancelled) = watch::channel(false);
+        self.jobs.insert(id.to_string(), JobEntry {
+            info: JobInfo {
+                id: id.to_string(),
+                kind,
+                window: window.to_string(),
+                started_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+            },
+            serial: self.next_serial,
+            cancel,
+        });
+        CancelToken {
+            id: id.to_string(),
+            serial: self.next_serial,
+            cancelled,
+        }
+    }
+
+    // Called when a job ends either way
+    pub fn finish(&mut self, token: &CancelToken) {
+        if self.jobs.get(&token.id).is_some_and(|entry| entry.serial == token.serial) {
+            self.jobs.remove(&token.id);
+        }
+    }
+
+    pub fn cancel(&mut self, id: &str) -> bool {
+        match self.jobs.remove(id) {
+            Some(entry) => {
+                let _ = entry.cancel.send(true);
+                println!("🛑 Cancelled {:?} job {}", entry.info.kind, id);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Cancels every job started from (and reporting to) a window
+    pub fn cancel_window(&mut self, window: &str) -> Vec<String> {
+        let ids: Vec<String> = self
+            .jobs
+            .values()
+            .filter(|entry| entry.info.window == window)
+            .map(|entry| entry.info.id.clone())
+            .collect();
+        for id in &ids {
+            self.cancel(id);
+        }
+        ids
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.jobs.values().map(|entry| entry.info.clone()).collect();
+        jobs.sort_by_key(|job| job.started_at);
+        jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // Sets the flag when dropped, to see whether a cancelled future was dropped
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn cancel_and_cancel_window_remove_jobs() {
+        let mut jobs = JobManager::new();
+        let analysis = jobs.register("analysis-1", JobKind::Analysis, "main");
+        let _ocr = jobs.register("ocr-1", JobKind::Ocr, "main");
+        let _login = jobs.register("browser-login", JobKind::Login, "login");
+        assert_eq!(jobs.list().len(), 3);
+
+        assert!(jobs.cancel("analysis-1"));
+        assert!(*analysis.cancelled.borrow());
+        assert!(!jobs.cancel("analysis-1"));
+
+        assert_eq!(jobs.cancel_window("main"), vec!["ocr-1".to_string()]);
+        let remaining: Vec<String> = jobs.list().into_iter().map(|job| job.id).collect();
+        assert_eq!(remaining, vec!["browser-login"]);
+    }
+
+    #[test]
+    fn replaced_jobs_are_cancelled_and_only_the_newest_finishes() {
+        let mut jobs = JobManager::new();
+        let first = jobs.register("chat", JobKind::Analysis, "main");
+        let second = jobs.register("chat", JobKind::Analysis, "main");
+        assert!(*first.cancelled.borrow());
+        assert!(!*second.cancelled.borrow());
+
+        // The replaced job ending late must not take its replacement out of the list
+        jobs.finish(&first);
+        assert_eq!(jobs.list().len(), 1);
+        jobs.finish(&second);
+        assert!(jobs.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_runs_return_cancelled_and_drop_the_job() {
+        let mut jobs = JobManager::new();
+        let token = jobs.register("ocr-1", JobKind::Ocr, "main");
+        let dropped = Arc::new(AtomicBool::new(false));
+        let flag = DropFlag(dropped.clone());
+        let pending = async move {
+            let _flag = flag;
+            std::future::pending::<Result<(), FrameSenseError>>().await
+        };
+
+        let run = token.run(pending);
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            jobs.cancel("ocr-1");
+        };
+        let (result, _) = tokio::join!(run, cancel);
+        assert!(matches!(result, Err(FrameSenseError::Cancelled)));
+        assert!(dropped.load(Ordering::SeqCst));
+
+        // Finished jobs pass their result through
+        let token = jobs.register("ocr-2", JobKind::Ocr, "main");
+        assert_eq!(token.run(async { Ok::<_, FrameSenseError>(7) }).await.unwrap(), 7);
+    }
+}
@@ -0,0 +1,5 @@
+// Library half of the crate - the OCR stack, shared by the app and the standalone
+// regression run (src/bin/test_ocr_standalone.rs) so both build the same modules
+pub mod errors;
+pub mod lru_store;
+pub mod ocr;
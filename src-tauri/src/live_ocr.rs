@@ -0,0 +1,285 @@
+// Live region OCR - re-captures a fixed screen region on an interval and streams
+// text changes to the frontend as `live-ocr-update` events
+use image::{DynamicImage, RgbaImage};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+
+use crate::actions::CaptureTarget;
+use crate::analysis::{AnalysisRequest, AnalysisResult};
+use crate::errors::FrameSenseError;
+use crate::ocr::{LiveOcrTracker, LiveOcrUpdate, OCRResult, OCRService};
+use crate::pipeline::{PipelineContext, PipelineDefinition, PipelineHost};
+use crate::CaptureBounds;
+
+const MIN_INTERVAL_MS: u64 = 250;
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+const MAX_SESSIONS: usize = 4;
+
+#[derive(Clone, Serialize)]
+pub struct LiveOcrEvent {
+    pub session_id: String,
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub update: LiveOcrUpdate,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LiveOcrErrorEvent {
+    pub session_id: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LiveOcrSessionInfo {
+    pub session_id: String,
+    pub bounds: CaptureBounds,
+    pub interval_ms: u64,
+    pub paused: bool,
+    pub pipeline: String,
+}
+
+struct LiveOcrSession {
+    bounds: CaptureBounds,
+    interval_ms: u64,
+    paused: Arc<AtomicBool>,
+    pipeline: String,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+// Frames come from the session itself, so the pipeline's capture stage keeps the
+// supplied frame; live text is never sent to a model
+struct LiveOcrHost<'a> {
+    ocr_service: &'a Mutex<OCRService>,
+}
+
+impl PipelineHost for LiveOcrHost<'_> {
+    fn capture(&self, _target: CaptureTarget) -> Result<DynamicImage, FrameSenseError> {
+        Err("Live OCR supplies its own frames".into())
+    }
+
+    fn recognize(&self, image: &DynamicImage) -> Result<OCRResult, FrameSenseError> {
+        self.ocr_service.lock().unwrap().extract_from_image(image)
+    }
+
+    fn analyze(&self, _request: &AnalysisRequest) -> Result<AnalysisResult, FrameSenseError> {
+        Err("Live OCR pipelines can't have an AI stage".into())
+    }
+}
+
+pub struct LiveOcrManager {
+    sessions: HashMap<String, LiveOcrSession>,
+    next_id: u64,
+}
+
+impl LiveOcrManager {
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    // `ocr_service` is a cache-less service with the app's OCR settings; live
+    // frames are one-offs and would only evict useful OCR cache entries. Each
+    // changed frame goes through `pipeline`.
+    pub fn start(
+        &mut self,
+        app: AppHandle,
+        bounds: CaptureBounds,
+        interval_ms: Option<u64>,
+        ocr_service: OCRService,
+        pipeline: PipelineDefinition,
+    ) -> Result<String, String> {
+        if bounds.width < 10 || bounds.height < 10 {
+            return Err(format!("Live OCR region too small: {}x{}", bounds.width, bounds.height));
+        }
+        if pipeline.has_ai_stage() {
+            return Err(format!("Pipeline '{}' asks a model, live OCR can only read text", pipeline.id));
+        }
+        if self.sessions.len() >= MAX_SESSIONS {
+            return Err(format!("At most {} live OCR sessions can run at once", MAX_SESSIONS));
+        }
+
+        let interval_ms = interval_ms.unwrap_or(DEFAULT_INTERVAL_MS).max(MIN_INTERVAL_MS);
+        let session_id = format!("live-{}", self.next_id);
+        self.next_id += 1;
+
+        let paused = Arc::new(AtomicBool::new(false));
+        let pipeline_id = pipeline.id.clone();
+        let task = tauri::async_runtime::spawn(run_session(
+            app,
+            session_id.clone(),
+            bounds.clone(),
+            Duration::from_millis(interval_ms),
+            paused.clone(),
+            ocr_service,
+            pipeline,
+        ));
+
+        println!("🔴 Live OCR session {} started: {}x{} at ({}, {}) every {}ms ({})",
+                 session_id, bounds.width, bounds.height, bounds.x, bounds.y, interval_ms, pipeline_id);
+        self.sessions.insert(session_id.clone(), LiveOcrSession {
+            bounds,
+            interval_ms,
+            paused,
+            pipeline: pipeline_id,
+            task,
+        });
+        Ok(session_id)
+    }
+
+    pub fn stop(&mut self, session_id: &str) -> Result<(), String> {
+        let session = self
+            .sessions
+            .remove(session_id)
+            .ok_or_else(|| format!("No live OCR session {}", session_id))?;
+        session.task.abort();
+        println!("⏹️ Live OCR session {} stopped", session_id);
+        Ok(())
+    }
+
+    pub fn stop_all(&mut self) {
+        for (session_id, session) in self.sessions.drain() {
+            session.task.abort();
+            println!("⏹️ Live OCR session {} stopped", session_id);
+        }
+    }
+
+    pub fn set_paused(&mut self, session_id: &str, paused: bool) -> Result<(), String> {
+        let session = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| format!("No live OCR session {}", session_id))?;
+        session.paused.store(paused, Ordering::Relaxed);
+        println!("{} Live OCR session {} {}", if paused { "⏸️" } else { "▶️" }, session_id,
+                 if paused { "paused" } else { "resumed" });
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<LiveOcrSessionInfo> {
+        self.sessions
+            .iter()
+            .map(|(session_id, session)| LiveOcrSessionInfo {
+                session_id: session_id.clone(),
+                bounds: session.bounds.clone(),
+                interval_ms: session.interval_ms,
+                paused: session.paused.load(Ordering::Relaxed),
+                pipeline: session.pipeline.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Default for LiveOcrManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn run_session(
+    app: AppHandle,
+    session_id: String,
+    bounds: CaptureBounds,
+    interval: Duration,
+    paused: Arc<AtomicBool>,
+    ocr_service: OCRService,
+    pipeline: PipelineDefinition,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    // A slow OCR pass shouldn't be followed by a burst of catch-up captures
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let ocr_service = Arc::new(Mutex::new(ocr_service));
+    let tracker = Arc::new(Mutex::new(LiveOcrTracker::new()));
+    let pipeline = Arc::new(pipeline);
+
+    loop {
+        ticker.tick().await;
+        if paused.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        // Capture and OCR are blocking, keep them off the async workers
+        let frame_bounds = bounds.clone();
+        let ocr_service = ocr_service.clone();
+        let tracker = tracker.clone();
+        let pipeline = pipeline.clone();
+        let outcome = tauri::async_runtime::spawn_blocking(move || {
+            let frame = capture_region(&frame_bounds)?;
+            let host = LiveOcrHost { ocr_service: &ocr_service };
+            tracker.lock().unwrap().process_frame(&frame, |frame| {
+                let mut context = PipelineContext::from_image(frame.clone());
+                let report = pipeline.run(&mut context, &host, None);
+                match report.error {
+                    Some(error) => Err(error),
+                    None => context.ocr_result(),
+                }
+            })
+        })
+        .await
+        .map_err(|e| FrameSenseError::Other(format!("Live OCR worker failed: {}", e)))
+        .and_then(|outcome| outcome);
+
+        match outcome {
+            Ok(Some(update)) => {
+                println!("📝 Live OCR {}: +{} / -{} lines", session_id, update.diff.added, update.diff.removed);
+                let event = LiveOcrEvent {
+                    session_id: session_id.clone(),
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_millis() as u64,
+                    update,
+                };
+                if let Err(e) = app.emit("live-ocr-update", event) {
+                    println!("❌ Failed to emit live OCR update: {}", e);
+                }
+            },
+            Ok(None) => {},
+            Err(error) => {
+                // Keep going: a single failed frame (e.g. display asleep) shouldn't end the session
+                println!("⚠️ Live OCR {} frame failed: {}", session_id, error);
+                let _ = app.emit("live-ocr-error", LiveOcrErrorEvent {
+                    session_id: session_id.clone(),
+                    code: error.code(),
+                    message: error.to_string(),
+                });
+            },
+        }
+    }
+}
+
+// Captures the region clamped to the primary screen as an RGBA image
+pub fn capture_region(bounds: &CaptureBounds) -> Result<DynamicImage, FrameSenseError> {
+    let screens = screenshots::Screen::all()
+        .map_err(|e| FrameSenseError::CapturePermission(format!("Failed to access screens: {}", e)))?;
+    let screen = screens.first().ok_or(FrameSenseError::NoDisplay)?;
+    let screen_width = screen.display_info.width as i32;
+    let screen_height = screen.display_info.height as i32;
+
+    // Validate and clamp coordinates to screen bounds
+    let safe_x = bounds.x.clamp(0, (screen_width - 1).max(0));
+    let safe_y = bounds.y.clamp(0, (screen_height - 1).max(0));
+    let safe_width = (bounds.width as i32).min(screen_width - safe_x).max(0) as u32;
+    let safe_height = (bounds.height as i32).min(screen_height - safe_y).max(0) as u32;
+
+    // Ensure minimum size
+    if safe_width < 10 || safe_height < 10 {
+        return Err(FrameSenseError::BoundsInvalid(format!(
+            "Capture area too small after adjustment: {}x{}",
+            safe_width, safe_height
+        )));
+    }
+
+    let image = screen
+        .capture_area(safe_x, safe_y, safe_width, safe_height)
+        .map_err(|e| FrameSenseError::CapturePermission(format!("Screen capture failed: {}", e)))?;
+    let rgba = RgbaImage::from_raw(image.width(), image.height(), image.rgba().clone())
+        .ok_or("Screen capture returned a malformed buffer")?;
+    Ok(DynamicImage::ImageRgba8(rgba))
+}
@@ -0,0 +1,325 @@
+// Persisted key/value store with least-recently-used eviction, bounded by entry count,
+// total size and optionally age. The OCR and analysis caches keep their values in one.
+// Changes stay in memory until `flush`, which the app calls periodically and at exit,
+// so a cache miss doesn't rewrite the whole file.
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const STORE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_entries: usize,
+    pub max_size_bytes: usize,
+    pub ttl_secs: Option<u64>, // None keeps entries until they're evicted
+}
+
+// The value's own fields sit next to the bookkeeping in the file, so values are structs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry<V> {
+    #[serde(flatten)]
+    value: V,
+    size_bytes: usize,
+    created_at: u64,
+    last_used_at: u64,
+    hits: u32,
+    #[serde(default)]
+    used_seq: u64, // Orders uses within the same second; 0 for entries from older files
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "V: DeserializeOwned")]
+struct Persisted<V> {
+    version: u32,
+    entries: HashMap<String, Entry<V>>,
+}
+
+#[derive(Serialize)]
+#[serde(bound = "V: Serialize")]
+struct PersistedRef<'a, V> {
+    version: u32,
+    entries: &'a HashMap<String, Entry<V>>,
+}
+
+pub struct LruStore<V> {
+    name: &'static str, // For log lines, e.g. "OCR cache"
+    entries: HashMap<String, Entry<V>>,
+    storage_path: Option<PathBuf>,
+    limits: Limits,
+    hits: u64,
+    misses: u64,
+    seq: u64,    // Last `used_seq` handed out
+    dirty: bool, // Changed since the file was last written
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl<V: Serialize + DeserializeOwned> LruStore<V> {
+    // An in-memory store that is never written to disk
+    pub fn new(name: &'static str, limits: Limits) -> Self {
+        Self {
+            name,
+            entries: HashMap::new(),
+            storage_path: None,
+            limits,
+            hits: 0,
+            misses: 0,
+            seq: 0,
+            dirty: false,
+        }
+    }
+
+    // Loads the store file if present; a missing or unreadable file starts empty
+    pub fn load(name: &'static str, limits: Limits, storage_path: PathBuf) -> Self {
+        let mut store = Self::new(name, limits);
+
+        match fs::read_to_string(&storage_path) {
+            Ok(json) => match serde_json::from_str::<Persisted<V>>(&json) {
+                Ok(persisted) if persisted.version == STORE_FORMAT_VERSION => {
+                    store.entries = persisted.entries;
+                    store.seq = store.entries.values().map(|entry| entry.used_seq).max().unwrap_or(0);
+                    println!("💾 Loaded {} {} entries from {:?}", store.entries.len(), name, storage_path);
+                },
+                Ok(_) => println!("⚠️ The {} format changed, starting empty", name),
+                Err(e) => println!("⚠️ Failed to parse the {}, starting empty: {}", name, e),
+            },
+            Err(_) => println!("ℹ️ No {} file yet at {:?}", name, storage_path),
+        }
+
+        store.storage_path = Some(storage_path);
+        store.enforce_limits();
+        store
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn is_expired(&self, entry: &Entry<V>, now: u64) -> bool {
+        self.limits.ttl_secs.is_some_and(|ttl| now.saturating_sub(entry.created_at) > ttl)
+    }
+
+    // The stored value, or None on a miss or an expired entry
+    pub fn get(&mut self, key: &str) -> Option<&V> {
+        let now = now_secs();
+        if self.entries.get(key).is_some_and(|entry| self.is_expired(entry, now)) {
+            self.entries.remove(key);
+            self.dirty = true;
+        }
+
+        let used_seq = self.next_seq();
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used_at = now;
+                entry.used_seq = used_seq;
+                entry.hits += 1;
+                self.dirty = true;
+                self.hits += 1;
+                println!("💰 {} hit ({} previous hits)", self.name, entry.hits - 1);
+                Some(&entry.value)
+            },
+            None => {
+                self.misses += 1;
+                None
+            },
+        }
+    }
+
+    // `size_bytes` is what the value counts against the size limit
+    pub fn insert(&mut self, key: String, value: V, size_bytes: usize) {
+        if size_bytes > self.limits.max_size_bytes {
+            println!("⚠️ Value too large for the {}: {}KB", self.name, size_bytes / 1024);
+            return;
+        }
+
+        let now = now_secs();
+        let used_seq = self.next_seq();
+        self.entries.insert(key, Entry {
+            value,
+            size_bytes,
+            created_at: now,
+            last_used_at: now,
+            hits: 0,
+            used_seq,
+        });
+        self.dirty = true;
+        self.enforce_limits();
+    }
+
+    // Writes the store file if anything changed since the last write
+    pub fn flush(&mut self) {
+        if self.dirty && self.persist() {
+            self.dirty = false;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.hits = 0;
+        self.misses = 0;
+        self.dirty = false;
+
+        if let Some(path) = &self.storage_path {
+            if path.exists() {
+                if let Err(e) = fs::remove_file(path) {
+                    println!("⚠️ Failed to remove the {} file: {}", self.name, e);
+                }
+            }
+        }
+        println!("🗑️ {} cleared", self.name);
+    }
+
+    // (entries, total size in bytes, hits, misses) for this session
+    pub fn stats(&self) -> (usize, usize, u64, u64) {
+        (self.entries.len(), self.total_size(), self.hits, self.misses)
+    }
+
+    fn total_size(&self) -> usize {
+        self.entries.values().map(|entry| entry.size_bytes).sum()
+    }
+
+    // Drops expired entries, then evicts least recently used ones until both limits hold
+    fn enforce_limits(&mut self) {
+        if let Some(ttl) = self.limits.ttl_secs {
+            let now = now_secs();
+            let before = self.entries.len();
+            self.entries.retain(|_, entry| now.saturating_sub(entry.created_at) <= ttl);
+            self.dirty |= self.entries.len() != before;
+        }
+        let Limits { max_entries, max_size_bytes, .. } = self.limits;
+        if self.entries.len() <= max_entries && self.total_size() <= max_size_bytes {
+            return;
+        }
+
+        let mut by_age: Vec<(String, (u64, u64, u64), usize)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), (entry.last_used_at, entry.used_seq, entry.created_at), entry.size_bytes))
+            .collect();
+        by_age.sort_by_key(|(_, age, _)| *age);
+
+        let mut count = self.entries.len();
+        let mut size = self.total_size();
+        let mut evicted = 0;
+        for (key, _, entry_size) in by_age {
+            if count <= max_entries && size <= max_size_bytes {
+                break;
+            }
+            self.entries.remove(&key);
+            count -= 1;
+            size -= entry_size;
+            evicted += 1;
+        }
+        self.dirty = true;
+
+        println!("🗑️ Evicted {} {} entries ({} left, {}KB)", evicted, self.name, count, size / 1024);
+    }
+
+    // False when the file couldn't be written, so the next flush tries again
+    fn persist(&self) -> bool {
+        let Some(path) = &self.storage_path else {
+            return true;
+        };
+
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                if let Err(e) = fs::create_dir_all(parent) {
+                    println!("⚠️ Failed to create the {} directory: {}", self.name, e);
+                    return false;
+                }
+            }
+        }
+
+        let persisted = PersistedRef {
+            version: STORE_FORMAT_VERSION,
+            entries: &self.entries,
+        };
+        match serde_json::to_string(&persisted) {
+            Ok(json) => {
+                // Write-then-rename so a crash mid-write can't leave a truncated file
+                let tmp_path = path.with_extension("json.tmp");
+                match fs::write(&tmp_path, json).and_then(|_| fs::rename(&tmp_path, path)) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        println!("⚠️ Failed to persist the {}: {}", self.name, e);
+                        false
+                    },
+                }
+            },
+            Err(e) => {
+                println!("⚠️ Failed to serialize the {}: {}", self.name, e);
+                false
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_entries: usize, max_size_bytes: usize, ttl_secs: Option<u64>) -> Limits {
+        Limits { max_entries, max_size_bytes, ttl_secs }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries_past_either_limit() {
+        // Everything here happens within a second, so the order comes from the use sequence
+        let mut store = LruStore::new("test store", limits(2, 100, None));
+        store.insert("a".to_string(), 1, 10);
+        store.insert("b".to_string(), 2, 10);
+        assert_eq!(store.get("a"), Some(&1)); // Now the most recently used
+
+        store.insert("c".to_string(), 3, 10);
+        assert!(store.get("b").is_none());
+        assert_eq!(store.stats().0, 2);
+
+        // One large value pushes out everything older than it
+        store.insert("d".to_string(), 4, 95);
+        assert_eq!(store.stats().0, 1);
+        assert_eq!(store.get("d"), Some(&4));
+
+        store.insert("e".to_string(), 5, 101);
+        assert!(store.get("e").is_none());
+    }
+
+    #[test]
+    fn expired_entries_are_misses() {
+        let mut store = LruStore::new("test store", limits(10, 100, Some(60)));
+        store.insert("a".to_string(), 1, 1);
+        store.entries.get_mut("a").unwrap().created_at -= 61;
+        assert!(store.get("a").is_none());
+        assert_eq!(store.stats(), (0, 0, 0, 1));
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        text: String,
+    }
+
+    #[test]
+    fn writes_its_file_on_flush() {
+        let path = std::env::temp_dir().join(format!("framesense-lru-store-{}.json", std::process::id()));
+        let mut store = LruStore::load("test store", limits(10, 100, None), path.clone());
+        store.insert("a".to_string(), Note { text: "first".to_string() }, 5);
+        store.insert("b".to_string(), Note { text: "second".to_string() }, 5);
+        assert!(!path.exists());
+
+        store.flush();
+        let mut reloaded: LruStore<Note> = LruStore::load("test store", limits(1, 100, None), path.clone());
+        assert_eq!(reloaded.get("b"), Some(&Note { text: "second".to_string() }));
+        assert!(reloaded.get("a").is_none()); // The older entry didn't fit the smaller limit
+        reloaded.clear();
+        assert!(!path.exists());
+    }
+}
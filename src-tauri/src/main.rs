@@ -7,6 +7,8 @@ use tauri::{
     Manager, Emitter, WebviewUrl, WebviewWindowBuilder,
 };
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_shell::ShellExt;
 use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
 use base64::Engine;
@@ -20,23 +22,80 @@ use overlay::{OverlayManager, ScreenshotCache};
 
 // FAS 2: Import permission cache system
 mod system;
-use system::{PermissionCache, Permission};
+use system::{PermissionCache, Permission, frontmost_app_name};
 
-// OCR module for Tesseract integration
-mod ocr;
-use ocr::{OCRService, OCRResult};
+// OCR module for Tesseract integration (lives in the library, see lib.rs)
+use framesense_lib::ocr;
+use ocr::{OCRService, OCRResult, OCRWord, OcrCache, TextEntity, EntityAction, ExportFormat};
 
 // OCR test module
 mod test_ocr;
 
+// Live region OCR sessions
+mod live_ocr;
+use live_ocr::{LiveOcrManager, LiveOcrSessionInfo};
+
 // Authentication module
 mod auth;
 // Using API approach - no direct database connection
-use auth::{AuthService, User};
+use auth::{AuthService, SessionCheck, SessionStatus, User};
+// Session storage in the OS keyring or an encrypted file
+mod secrets;
+use secrets::SecretStore;
+// Browser sign-in (OAuth authorization code + PKCE, loopback redirect)
+mod oauth;
+
+// Backend environment profiles (production, staging, local, custom URL)
+mod environment;
+use environment::BackendEnvironment;
+
+// Structured errors with stable codes for every command
+use framesense_lib::errors;
+use errors::FrameSenseError;
+
+// Conversation threads (follow-up questions on the same capture)
+mod conversations;
+use conversations::{ConversationStore, ThreadSummary};
+
+// Quick actions: prompt templates bound to hotkeys
+mod actions;
+use actions::{ActionStore, CaptureTarget, QuickAction};
+
+// Model registry (backend-managed model catalog and tier rules)
+mod model_registry;
+use model_registry::ModelRegistry;
+
+// Local usage metering and daily limits
+mod usage;
+use usage::{UsageMeter, UsageSnapshot};
+
+// Analysis providers (FrameSense backend or bring-your-own-key endpoint)
+mod analysis;
+use analysis::{AnalysisProvider, AnalysisRequest, AnalysisResult, ProviderKind, ProviderSettings, ResponseCache};
+use analysis::fallback::{self, FailedAttempt};
+use analysis::grounding::Grounding;
+
+// Structured extraction against JSON Schemas
+mod extraction;
+use extraction::{ExtractionExport, ExtractionResult, SavedSchema, SchemaStore};
+
+// Offline queue for analyses that couldn't be sent
+mod analysis_queue;
+use analysis_queue::{AnalysisQueue, JobSummary};
+
+// In-flight job tracking and cancellation
+mod jobs;
+use jobs::{JobInfo, JobKind, JobManager};
+
+// Persisted LRU store behind the OCR and analysis caches
+use framesense_lib::lru_store;
+
+// Configurable capture -> OCR -> AI processing pipelines
+mod pipeline;
+use pipeline::{PipelineContext, PipelineDefinition, PipelineHost, PipelineReport, PipelineStore};
 
 // Global OCR service (reuse instance for performance)
-static mut OCR_SERVICE: Option<std::sync::Mutex<OCRService>> = None;
-static OCR_INIT: std::sync::Once = std::sync::Once::new();
+static OCR_SERVICE: std::sync::OnceLock<Option<Mutex<OCRService>>> = std::sync::OnceLock::new();
 
 // Note: macOS-specific imports removed since we're using native egui overlay
 
@@ -84,86 +143,83 @@ type SharedScreenshotCache = Arc<Mutex<ScreenshotCache>>;
 // Authentication service manager
 type SharedAuthService = Arc<Mutex<AuthService>>;
 
+// Keyring-backed store for the session and the BYOK API key, opened once at startup
+type SharedSecretStore = Arc<dyn SecretStore>;
+
+// Model registry manager
+type SharedModelRegistry = Arc<Mutex<ModelRegistry>>;
+
+// Usage meter
+type SharedUsageMeter = Arc<Mutex<UsageMeter>>;
+
+// In-flight analysis and OCR jobs
+type SharedJobManager = Arc<Mutex<JobManager>>;
+
+// Cached analysis answers
+type SharedResponseCache = Arc<Mutex<ResponseCache>>;
+
+// Offline analysis queue
+type SharedAnalysisQueue = Arc<Mutex<AnalysisQueue>>;
+
+// Conversation thread store
+type SharedConversationStore = Arc<Mutex<ConversationStore>>;
+
+// Quick action store
+type SharedActionStore = Arc<Mutex<ActionStore>>;
+
+// Live OCR session manager
+type SharedLiveOcrManager = Arc<Mutex<LiveOcrManager>>;
+
+// Built-in and user-defined processing pipelines
+type SharedPipelineStore = Arc<Mutex<PipelineStore>>;
+
 // Test screen capture capability with detailed diagnostics
 #[tauri::command]
-async fn test_screen_capture() -> Result<CaptureResult, String> {
+async fn test_screen_capture() -> Result<CaptureResult, FrameSenseError> {
     println!("🧪 Testing screen capture capability...");
     
-    match screenshots::Screen::all() {
-        Ok(screens) => {
-            if let Some(screen) = screens.first() {
-                println!("✅ Screen access working. Available: {} screen(s)", screens.len());
-                println!("📺 Screen info: {}x{} @ {}x scale", 
-                         screen.display_info.width, screen.display_info.height, screen.display_info.scale_factor);
-                
-                // Try to actually capture a small area to test permissions
-                match screen.capture_area(100, 100, 200, 200) {
-                    Ok(image) => {
-                        println!("✅ Screen capture permission granted!");
-                        
-                        // Test if we can convert to PNG (this sometimes fails with permission issues)
-                        match image.to_png(None) {
-                            Ok(png_data) => {
-                                let base64_data = base64::engine::general_purpose::STANDARD.encode(&png_data);
-                                let pixel_count = image.width() * image.height();
-                                println!("✅ PNG conversion successful! Image: {}x{} ({} pixels, {}KB)", 
-                                         image.width(), image.height(), pixel_count, png_data.len() / 1024);
-                                
-                                // Return success with actual image data for visual verification
-                                Ok(CaptureResult {
-                                    success: true,
-                                    message: format!("✅ Screen capture FULLY working!\n\n📊 Details:\n- Screens: {}\n- Captured: {}x{} pixels\n- Size: {}KB\n- Format: PNG\n\n🎯 If you see only background in captures, this indicates macOS permission restrictions for unsigned apps.", 
-                                                   screens.len(), image.width(), image.height(), png_data.len() / 1024),
-                                    bounds: Some(CaptureBounds { x: 100, y: 100, width: 200, height: 200 }),
-                                    image_data: Some(format!("data:image/png;base64,{}", base64_data)),
-                                })
-                            },
-                            Err(e) => {
-                                println!("❌ PNG conversion failed: {}", e);
-                                Ok(CaptureResult {
-                                    success: false,
-                                    message: format!("❌ Screen capture works but PNG conversion failed: {}\n\nThis could indicate memory or permission issues.", e),
-                                    bounds: None,
-                                    image_data: None,
-                                })
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        println!("❌ Screen capture blocked by macOS: {}", e);
-                        Ok(CaptureResult {
-                            success: false,
-                            message: format!("❌ macOS blocked screen capture: {}\n\n🔧 SOLUTIONS:\n\n1. **System Preferences Fix:**\n   • System Preferences → Privacy & Security → Screen Recording\n   • Add FrameSense and enable it\n   • If already added: remove, restart app, re-add\n\n2. **For Unsigned Apps (likely cause):**\n   • macOS restricts unsigned apps to desktop/background only\n   • Other app windows may not be capturable\n   • This is a macOS security limitation\n\n3. **Developer Mode:**\n   • Enable Developer Mode in macOS settings\n   • Or use a signed version of the app\n\n⚠️ Note: You may only see background/desktop in captures due to macOS unsigned app restrictions.", e),
-                            bounds: None,
-                            image_data: None,
-                        })
-                    }
-                }
-            } else {
-                println!("❌ No screens available");
-                Ok(CaptureResult {
-                    success: false,
-                    message: "❌ No screens available for capture".to_string(),
-                    bounds: None,
-                    image_data: None,
-                })
-            }
-        },
-        Err(e) => {
-            println!("❌ Screen capture test failed: {}", e);
-            Ok(CaptureResult {
-                success: false,
-                message: format!("❌ Screen access failed: {}\n\n🔧 This is likely a macOS permission or code signing issue.\n\nTry running from Terminal to see more detailed error messages.", e),
-                bounds: None,
-                image_data: None,
-            })
-        }
-    }
+    let screens = screenshots::Screen::all().map_err(|e| {
+        println!("❌ Screen capture test failed: {}", e);
+        FrameSenseError::CapturePermission(format!("❌ Screen access failed: {}\n\n🔧 This is likely a macOS permission or code signing issue.\n\nTry running from Terminal to see more detailed error messages.", e))
+    })?;
+    let Some(screen) = screens.first() else {
+        println!("❌ No screens available");
+        return Err(FrameSenseError::NoDisplay);
+    };
+    println!("✅ Screen access working. Available: {} screen(s)", screens.len());
+    println!("📺 Screen info: {}x{} @ {}x scale", 
+             screen.display_info.width, screen.display_info.height, screen.display_info.scale_factor);
+    
+    // Try to actually capture a small area to test permissions
+    let image = screen.capture_area(100, 100, 200, 200).map_err(|e| {
+        println!("❌ Screen capture blocked by macOS: {}", e);
+        FrameSenseError::CapturePermission(format!("❌ macOS blocked screen capture: {}\n\n🔧 SOLUTIONS:\n\n1. **System Preferences Fix:**\n   • System Preferences → Privacy & Security → Screen Recording\n   • Add FrameSense and enable it\n   • If already added: remove, restart app, re-add\n\n2. **For Unsigned Apps (likely cause):**\n   • macOS restricts unsigned apps to desktop/background only\n   • Other app windows may not be capturable\n   • This is a macOS security limitation\n\n3. **Developer Mode:**\n   • Enable Developer Mode in macOS settings\n   • Or use a signed version of the app\n\n⚠️ Note: You may only see background/desktop in captures due to macOS unsigned app restrictions.", e))
+    })?;
+    println!("✅ Screen capture permission granted!");
+    
+    // Test if we can convert to PNG (this sometimes fails with permission issues)
+    let png_data = image.to_png(None).map_err(|e| {
+        println!("❌ PNG conversion failed: {}", e);
+        FrameSenseError::Other(format!("❌ Screen capture works but PNG conversion failed: {}\n\nThis could indicate memory or permission issues.", e))
+    })?;
+    let base64_data = base64::engine::general_purpose::STANDARD.encode(&png_data);
+    let pixel_count = image.width() * image.height();
+    println!("✅ PNG conversion successful! Image: {}x{} ({} pixels, {}KB)", 
+             image.width(), image.height(), pixel_count, png_data.len() / 1024);
+    
+    // Return success with actual image data for visual verification
+    Ok(CaptureResult {
+        success: true,
+        message: format!("✅ Screen capture FULLY working!\n\n📊 Details:\n- Screens: {}\n- Captured: {}x{} pixels\n- Size: {}KB\n- Format: PNG\n\n🎯 If you see only background in captures, this indicates macOS permission restrictions for unsigned apps.", 
+                       screens.len(), image.width(), image.height(), png_data.len() / 1024),
+        bounds: Some(CaptureBounds { x: 100, y: 100, width: 200, height: 200 }),
+        image_data: Some(format!("data:image/png;base64,{}", base64_data)),
+    })
 }
 
 // Capture a specific area of the screen
 #[tauri::command]
-async fn capture_screen_area(bounds: CaptureBounds) -> Result<CaptureResult, String> {
+async fn capture_screen_area(bounds: CaptureBounds) -> Result<CaptureResult, FrameSenseError> {
     println!("📸 Capturing screen area: {}x{} at ({}, {})", bounds.width, bounds.height, bounds.x, bounds.y);
     
     match screenshots::Screen::all() {
@@ -187,12 +243,7 @@ async fn capture_screen_area(bounds: CaptureBounds) -> Result<CaptureResult, Str
                 // Ensure minimum size
                 if safe_width < 10 || safe_height < 10 {
                     println!("❌ Adjusted area too small: {}x{}", safe_width, safe_height);
-                    return Ok(CaptureResult {
-                        success: false,
-                        message: format!("Capture area too small after adjustment: {}x{}", safe_width, safe_height),
-                        bounds: None,
-                        image_data: None,
-                    });
+                    return Err(FrameSenseError::BoundsInvalid(format!("Capture area too small after adjustment: {}x{}", safe_width, safe_height)));
                 }
                 
                 match screen.capture_area(safe_x, safe_y, safe_width, safe_height) {
@@ -218,50 +269,30 @@ async fn capture_screen_area(bounds: CaptureBounds) -> Result<CaptureResult, Str
                             },
                             Err(e) => {
                                 println!("❌ PNG conversion failed: {}", e);
-                                Ok(CaptureResult {
-                                    success: false,
-                                    message: format!("PNG conversion failed: {}", e),
-                                    bounds: None,
-                                    image_data: None,
-                                })
+                                Err(FrameSenseError::Other(format!("PNG conversion failed: {}", e)))
                             }
                         }
                     },
                     Err(e) => {
                         println!("❌ Screen capture failed: {}", e);
-                        Ok(CaptureResult {
-                            success: false,
-                            message: format!("Screen capture failed: {}", e),
-                            bounds: None,
-                            image_data: None,
-                        })
+                        Err(FrameSenseError::CapturePermission(format!("Screen capture failed: {}", e)))
                     }
                 }
             } else {
                 println!("❌ No screens available");
-                Ok(CaptureResult {
-                    success: false,
-                    message: "No screens available for capture".to_string(),
-                    bounds: None,
-                    image_data: None,
-                })
+                Err(FrameSenseError::NoDisplay)
             }
         },
         Err(e) => {
             println!("❌ Failed to access screens: {}", e);
-            Ok(CaptureResult {
-                success: false,
-                message: format!("Failed to access screens: {}", e),
-                bounds: None,
-                image_data: None,
-            })
+            Err(FrameSenseError::CapturePermission(format!("Failed to access screens: {}", e)))
         }
     }
 }
 
 // Single test command
 #[tauri::command]
-async fn test_command() -> Result<AppResult, String> {
+async fn test_command() -> Result<AppResult, FrameSenseError> {
     Ok(AppResult {
         success: true,
         message: "FrameSense systemtray test".to_string(),
@@ -270,7 +301,7 @@ async fn test_command() -> Result<AppResult, String> {
 
 // Test quick command functionality
 #[tauri::command]
-async fn test_quick_command(app: tauri::AppHandle) -> Result<AppResult, String> {
+async fn test_quick_command(app: tauri::AppHandle) -> Result<AppResult, FrameSenseError> {
     println!("🧪 Testing quick command functionality...");
     
     // Simulate global shortcut trigger
@@ -289,112 +320,72 @@ async fn test_quick_command(app: tauri::AppHandle) -> Result<AppResult, String>
             },
             Err(e) => {
                 println!("❌ Quick command test failed to show window: {}", e);
-                Ok(AppResult {
-                    success: false,
-                    message: format!("Failed to show main window: {}", e),
-                })
+                Err(FrameSenseError::Other(format!("Failed to show main window: {}", e)))
             }
         }
     } else {
         println!("❌ Quick command test: No main window found");
-        Ok(AppResult {
-            success: false,
-            message: "No main window found - this may indicate a startup issue".to_string(),
-        })
+        Err(FrameSenseError::Other("No main window found - this may indicate a startup issue".to_string()))
     }
 }
 
 // Alternative screen capture method for macOS unsigned apps
 #[tauri::command]
-async fn test_alternative_capture() -> Result<CaptureResult, String> {
+async fn test_alternative_capture() -> Result<CaptureResult, FrameSenseError> {
     println!("🧪 Testing alternative screen capture method...");
     
     // Try to get ALL screen info first
-    match screenshots::Screen::all() {
-        Ok(screens) => {
-            println!("✅ Found {} screen(s)", screens.len());
-            
-            for (i, screen) in screens.iter().enumerate() {
-                println!("📺 Screen {}: {}x{} @ {}x scale", 
-                         i, screen.display_info.width, screen.display_info.height, screen.display_info.scale_factor);
-            }
-            
-            if let Some(screen) = screens.first() {
-                // Try fullscreen capture first (often works better than area capture)
-                println!("🖼️ Trying fullscreen capture...");
-                match screen.capture() {
-                    Ok(image) => {
-                        println!("✅ Fullscreen capture successful!");
-                        
-                        // Crop to a smaller area for testing
-                        let width = (image.width() / 4).min(400);
-                        let height = (image.height() / 4).min(300);
-                        let x = image.width() / 4;
-                        let y = image.height() / 4;
-                        
-                        println!("✂️ Cropping {}x{} area from ({}, {})...", width, height, x, y);
-                        
-                        // Convert to PNG first, then crop if needed
-                        match image.to_png(None) {
-                            Ok(png_data) => {
-                                let base64_data = base64::engine::general_purpose::STANDARD.encode(&png_data);
-                                
-                                Ok(CaptureResult {
-                                    success: true,
-                                    message: format!("✅ Alternative capture method working!\n\n📊 Fullscreen capture details:\n- Size: {}x{} pixels\n- Data: {}KB\n- Method: Fullscreen → Crop\n\n🎯 This method often works better for unsigned apps.", 
-                                                   image.width(), image.height(), png_data.len() / 1024),
-                                    bounds: Some(CaptureBounds { 
-                                        x: x as i32, 
-                                        y: y as i32, 
-                                        width: width, 
-                                        height: height 
-                                    }),
-                                    image_data: Some(format!("data:image/png;base64,{}", base64_data)),
-                                })
-                            },
-                            Err(e) => {
-                                Ok(CaptureResult {
-                                    success: false,
-                                    message: format!("❌ Fullscreen capture worked but PNG encoding failed: {}", e),
-                                    bounds: None,
-                                    image_data: None,
-                                })
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        println!("❌ Fullscreen capture failed: {}", e);
-                        Ok(CaptureResult {
-                            success: false,
-                            message: format!("❌ Alternative capture method also failed: {}\n\nThis indicates a fundamental macOS permission issue.\n\n🔧 Your app likely needs:\n1. Proper code signing\n2. Developer ID certificate\n3. Notarization\n\nOR the user needs to manually grant screen recording permission.", e),
-                            bounds: None,
-                            image_data: None,
-                        })
-                    }
-                }
-            } else {
-                Ok(CaptureResult {
-                    success: false,
-                    message: "❌ No screens available for alternative capture".to_string(),
-                    bounds: None,
-                    image_data: None,
-                })
-            }
-        },
-        Err(e) => {
-            Ok(CaptureResult {
-                success: false,
-                message: format!("❌ Failed to access screens for alternative capture: {}", e),
-                bounds: None,
-                image_data: None,
-            })
-        }
+    let screens = screenshots::Screen::all().map_err(|e| {
+        FrameSenseError::CapturePermission(format!("❌ Failed to access screens for alternative capture: {}", e))
+    })?;
+    println!("✅ Found {} screen(s)", screens.len());
+    
+    for (i, screen) in screens.iter().enumerate() {
+        println!("📺 Screen {}: {}x{} @ {}x scale", 
+                 i, screen.display_info.width, screen.display_info.height, screen.display_info.scale_factor);
     }
+    
+    let screen = screens.first().ok_or(FrameSenseError::NoDisplay)?;
+    
+    // Try fullscreen capture first (often works better than area capture)
+    println!("🖼️ Trying fullscreen capture...");
+    let image = screen.capture().map_err(|e| {
+        println!("❌ Fullscreen capture failed: {}", e);
+        FrameSenseError::CapturePermission(format!("❌ Alternative capture method also failed: {}\n\nThis indicates a fundamental macOS permission issue.\n\n🔧 Your app likely needs:\n1. Proper code signing\n2. Developer ID certificate\n3. Notarization\n\nOR the user needs to manually grant screen recording permission.", e))
+    })?;
+    println!("✅ Fullscreen capture successful!");
+    
+    // Crop to a smaller area for testing
+    let width = (image.width() / 4).min(400);
+    let height = (image.height() / 4).min(300);
+    let x = image.width() / 4;
+    let y = image.height() / 4;
+    
+    println!("✂️ Cropping {}x{} area from ({}, {})...", width, height, x, y);
+    
+    // Convert to PNG first, then crop if needed
+    let png_data = image.to_png(None).map_err(|e| {
+        FrameSenseError::Other(format!("❌ Fullscreen capture worked but PNG encoding failed: {}", e))
+    })?;
+    let base64_data = base64::engine::general_purpose::STANDARD.encode(&png_data);
+    
+    Ok(CaptureResult {
+        success: true,
+        message: format!("✅ Alternative capture method working!\n\n📊 Fullscreen capture details:\n- Size: {}x{} pixels\n- Data: {}KB\n- Method: Fullscreen → Crop\n\n🎯 This method often works better for unsigned apps.", 
+                       image.width(), image.height(), png_data.len() / 1024),
+        bounds: Some(CaptureBounds { 
+            x: x as i32, 
+            y: y as i32, 
+            width, 
+            height 
+        }),
+        image_data: Some(format!("data:image/png;base64,{}", base64_data)),
+    })
 }
 
 // Debug capture flow - test the entire capture process
 #[tauri::command]
-async fn debug_capture_flow(app: tauri::AppHandle) -> Result<AppResult, String> {
+async fn debug_capture_flow(app: tauri::AppHandle) -> Result<AppResult, FrameSenseError> {
     println!("🔍 DEBUG: Testing complete capture flow...");
     
     // Test 1: Check screen access
@@ -437,57 +428,39 @@ async fn debug_capture_flow(app: tauri::AppHandle) -> Result<AppResult, String>
                                         },
                                         Err(e) => {
                                             println!("❌ DEBUG: Event emission failed: {}", e);
-                                            Ok(AppResult {
-                                                success: false,
-                                                message: format!("❌ Event emission failed: {}", e),
-                                            })
+                                            Err(FrameSenseError::Other(format!("❌ Event emission failed: {}", e)))
                                         }
                                     }
                                 } else {
                                     println!("❌ DEBUG: No main window found for event emission");
-                                    Ok(AppResult {
-                                        success: false,
-                                        message: "❌ No main window found for event emission".to_string(),
-                                    })
+                                    Err(FrameSenseError::Other("❌ No main window found for event emission".to_string()))
                                 }
                             },
                             Err(e) => {
                                 println!("❌ DEBUG: PNG encoding failed: {}", e);
-                                Ok(AppResult {
-                                    success: false,
-                                    message: format!("❌ PNG encoding failed: {}", e),
-                                })
+                                Err(FrameSenseError::Other(format!("❌ PNG encoding failed: {}", e)))
                             }
                         }
                     },
                     Err(e) => {
                         println!("❌ DEBUG: Screen capture failed: {}", e);
-                        Ok(AppResult {
-                            success: false,
-                            message: format!("❌ Screen capture blocked: {}\n\n🔧 This is the root cause! Check System Preferences → Privacy & Security → Screen Recording", e),
-                        })
+                        Err(FrameSenseError::CapturePermission(format!("❌ Screen capture blocked: {}\n\n🔧 This is the root cause! Check System Preferences → Privacy & Security → Screen Recording", e)))
                     }
                 }
             } else {
-                Ok(AppResult {
-                    success: false,
-                    message: "❌ No screens available".to_string(),
-                })
+                Err(FrameSenseError::NoDisplay)
             }
         },
         Err(e) => {
             println!("❌ DEBUG: Failed to access screens: {}", e);
-            Ok(AppResult {
-                success: false,
-                message: format!("❌ Failed to access screens: {}", e),
-            })
+            Err(FrameSenseError::CapturePermission(format!("❌ Failed to access screens: {}", e)))
         }
     }
 }
 
 // Test OCR functionality (Step 1B from AI.txt)
 #[tauri::command]
-async fn test_ocr() -> Result<AppResult, String> {
+async fn test_ocr() -> Result<AppResult, FrameSenseError> {
     println!("🧪 Testing OCR (Tesseract) functionality...");
     
     match OCRService::test_ocr() {
@@ -500,68 +473,256 @@ async fn test_ocr() -> Result<AppResult, String> {
         },
         Err(error) => {
             println!("❌ OCR test failed: {}", error);
-            Ok(AppResult {
-                success: false,
-                message: error,
-            })
+            Err(FrameSenseError::OcrUnavailable(error))
         }
     }
 }
 
 // Run comprehensive OCR verification tests
 #[tauri::command]
-async fn run_ocr_verification() -> Result<AppResult, String> {
+async fn run_ocr_verification() -> Result<AppResult, FrameSenseError> {
     println!("🚀 Running comprehensive OCR verification...");
     
-    // Run all tests and capture output
-    test_ocr::run_all_tests();
+    match test_ocr::run_all_tests() {
+        Ok(summary) => Ok(AppResult {
+            success: true,
+            message: format!("🎉 All OCR verification tests passed!\n\n{}", summary),
+        }),
+        Err(report) => Err(FrameSenseError::Other(format!("❌ OCR verification failed:\n\n{}", report))),
+    }
+}
+
+// Local data directory shared by the session file and caches
+fn framesense_data_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".framesense")
+}
+
+// Run `f` against the shared OCR service, initializing it with its persisted cache on first use
+fn with_ocr_service<T>(f: impl FnOnce(&mut OCRService) -> Result<T, FrameSenseError>) -> Result<T, FrameSenseError> {
+    let service = OCR_SERVICE.get_or_init(|| match OCRService::new() {
+        Ok(service) => {
+            let cache = OcrCache::load(framesense_data_dir().join("ocr_cache.json"));
+            println!("✅ OCR service initialized successfully");
+            Some(Mutex::new(service.with_cache(cache)))
+        },
+        Err(e) => {
+            println!("❌ Failed to initialize OCR service: {}", e);
+            None
+        }
+    });
     
-    // If we get here, tests passed (would have panicked otherwise)
-    Ok(AppResult {
-        success: true,
-        message: "🎉 All OCR verification tests passed! Tesseract is working correctly.".to_string(),
-    })
+    match service {
+        Some(service_mutex) => {
+            let mut service = service_mutex.lock().unwrap();
+            f(&mut service)
+        },
+        None => {
+            let error_msg = "OCR service not initialized".to_string();
+            println!("❌ {}", error_msg);
+            Err(FrameSenseError::OcrUnavailable(error_msg))
+        }
+    }
 }
 
-// Extract text from image using OCR (Step 2-3 from AI.txt)
+// Extract text from image using OCR (Step 2-3 from AI.txt), through the ocr pipeline
 #[tauri::command]
-async fn extract_text_ocr(image_data: String) -> Result<OCRResult, String> {
+async fn extract_text_ocr(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    image_data: String,
+    job_id: Option<String>,
+    job_manager: tauri::State<'_, SharedJobManager>,
+    pipeline_store: tauri::State<'_, SharedPipelineStore>
+) -> Result<OCRResult, FrameSenseError> {
     println!("📝 Extracting text from image using OCR...");
     
-    unsafe {
-        OCR_INIT.call_once(|| {
-            if let Ok(service) = OCRService::new() {
-                OCR_SERVICE = Some(std::sync::Mutex::new(service));
-                println!("✅ OCR service initialized successfully");
-            } else {
-                println!("❌ Failed to initialize OCR service");
-            }
+    let pipeline = pipeline_store.lock().unwrap().get(pipeline::OCR_PIPELINE)?;
+    let job_id = job_id.unwrap_or_else(|| {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        format!("ocr-{}", millis)
+    });
+    let token = job_manager.lock().unwrap().register(&job_id, JobKind::Ocr, window.label());
+    let host = AppPipelineHost::new(&app, window.label(), job_id.clone());
+    
+    // Tesseract runs on a blocking thread; cancelling stops waiting for it and drops its result
+    let result = token
+        .run(async {
+            tauri::async_runtime::spawn_blocking(move || {
+                let mut context = PipelineContext::from_image(ocr::decode_image(&image_data)?);
+                let report = pipeline.run(&mut context, &host, pipeline_artifacts_root(&pipeline, false).as_deref());
+                match report.error {
+                    Some(error) => Err(error),
+                    None => context.ocr_result(),
+                }
+            })
+            .await
+            .map_err(|e| format!("OCR task failed: {}", e))?
+        })
+        .await;
+    job_manager.lock().unwrap().finish(&token);
+    
+    match &result {
+        Ok(result) => println!("✅ OCR extraction successful - Text: '{}', Confidence: {:.2}%, Cached: {}", 
+                               result.text, result.confidence * 100.0, result.cache_hit),
+        Err(error) => println!("❌ OCR extraction {} failed: {}", job_id, error),
+    }
+    result
+}
+
+// Clear the persisted OCR result cache
+#[tauri::command]
+fn clear_ocr_cache() -> Result<(), FrameSenseError> {
+    with_ocr_service(|service| {
+        if let Some(cache) = service.cache_mut() {
+            cache.clear();
+        }
+        Ok(())
+    })
+}
+
+// Get OCR cache statistics
+#[tauri::command]
+fn get_ocr_cache_stats() -> Result<serde_json::Value, FrameSenseError> {
+    with_ocr_service(|service| {
+        let (total_entries, total_size, hits, misses) = service
+            .cache_mut()
+            .map(|cache| cache.get_cache_stats())
+            .unwrap_or((0, 0, 0, 0));
+        
+        let stats = serde_json::json!({
+            "total_entries": total_entries,
+            "total_size_bytes": total_size,
+            "session_hits": hits,
+            "session_misses": misses,
+            "engine": service.settings().engine,
+            "language": service.settings().language
         });
         
-        if let Some(ref service_mutex) = OCR_SERVICE {
-            let service = service_mutex.lock().unwrap();
-            match service.extract_text(&image_data) {
-                Ok(result) => {
-                    println!("✅ OCR extraction successful - Text: '{}', Confidence: {:.2}%", 
-                             result.text, result.confidence * 100.0);
-                    Ok(result)
-                },
-                Err(error) => {
-                    println!("❌ OCR extraction failed: {}", error);
-                    Err(error)
-                }
-            }
-        } else {
-            let error_msg = "OCR service not initialized".to_string();
-            println!("❌ {}", error_msg);
-            Err(error_msg)
+        println!("📊 OCR cache stats: {} entries, {}KB, {} hits / {} misses", 
+                 total_entries, total_size / 1024, hits, misses);
+        Ok(stats)
+    })
+}
+
+// Export an OCR result as hOCR, ALTO XML or searchable PDF to a user-chosen path
+#[tauri::command]
+async fn export_ocr_result(
+    image_data: String,
+    ocr_result: OCRResult,
+    format: ExportFormat,
+    path: String
+) -> Result<AppResult, FrameSenseError> {
+    let mut target = PathBuf::from(&path);
+    if !target.is_absolute() {
+        return Err(format!("Export path must be absolute: {}", path).into());
+    }
+    if target.extension().is_none() {
+        target.set_extension(format.extension());
+    }
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            return Err(format!("Export folder does not exist: {:?}", parent).into());
+        }
+    }
+    
+    println!("📤 Exporting OCR result as {:?} to {:?}", format, target);
+    let image = ocr::decode_image(&image_data)?;
+    let bytes = ocr::export::export(&ocr_result, &image, format)?;
+    fs::write(&target, &bytes).map_err(|e| format!("Failed to write export: {}", e))?;
+    
+    println!("✅ Exported {}KB to {:?}", bytes.len() / 1024, target);
+    Ok(AppResult {
+        success: true,
+        message: format!("Exported to {}", target.display()),
+    })
+}
+
+// Start re-capturing a region on an interval through an OCR pipeline (live_ocr by default);
+// changed text arrives as `live-ocr-update` events
+#[tauri::command]
+fn start_live_ocr(
+    app: tauri::AppHandle,
+    bounds: CaptureBounds,
+    interval_ms: Option<u64>,
+    pipeline: Option<String>,
+    live_ocr: tauri::State<'_, SharedLiveOcrManager>,
+    pipeline_store: tauri::State<'_, SharedPipelineStore>
+) -> Result<String, FrameSenseError> {
+    let pipeline = pipeline_store
+        .lock()
+        .unwrap()
+        .get(pipeline.as_deref().unwrap_or(pipeline::LIVE_OCR_PIPELINE))?;
+    
+    // Same settings as one-shot OCR, but without the persistent cache
+    let settings = with_ocr_service(|service| Ok(service.settings().clone()))?;
+    let ocr_service = OCRService::new()?.with_settings(settings);
+    
+    let mut manager = live_ocr.lock().unwrap();
+    manager.start(app, bounds, interval_ms, ocr_service, pipeline).map_err(FrameSenseError::from)
+}
+
+// Stop a live OCR session
+#[tauri::command]
+fn stop_live_ocr(
+    session_id: String,
+    live_ocr: tauri::State<'_, SharedLiveOcrManager>
+) -> Result<(), FrameSenseError> {
+    let mut manager = live_ocr.lock().unwrap();
+    manager.stop(&session_id).map_err(FrameSenseError::from)
+}
+
+// Pause or resume a live OCR session without losing its text history
+#[tauri::command]
+fn pause_live_ocr(
+    session_id: String,
+    paused: bool,
+    live_ocr: tauri::State<'_, SharedLiveOcrManager>
+) -> Result<(), FrameSenseError> {
+    let mut manager = live_ocr.lock().unwrap();
+    manager.set_paused(&session_id, paused).map_err(FrameSenseError::from)
+}
+
+// List running live OCR sessions
+#[tauri::command]
+fn list_live_ocr_sessions(
+    live_ocr: tauri::State<'_, SharedLiveOcrManager>
+) -> Result<Vec<LiveOcrSessionInfo>, FrameSenseError> {
+    let manager = live_ocr.lock().unwrap();
+    Ok(manager.list())
+}
+
+// Find actionable entities (URLs, emails, phone numbers, dates, ...) in captured text
+#[tauri::command]
+fn extract_text_entities(text: String, words: Option<Vec<OCRWord>>) -> Result<Vec<TextEntity>, FrameSenseError> {
+    let entities = ocr::extract_entities(&text, &words.unwrap_or_default());
+    println!("🔗 Extracted {} entities from {} chars of text", entities.len(), text.len());
+    Ok(entities)
+}
+
+// Run an entity action (open URL, compose email, call, show in folder) through the shell plugin
+#[tauri::command]
+#[allow(deprecated)] // shell().open is deprecated in favor of tauri-plugin-opener, which isn't registered
+fn run_entity_action(app: tauri::AppHandle, action: EntityAction) -> Result<AppResult, FrameSenseError> {
+    let target = action.open_target()?;
+    println!("🔗 Running entity action {:?}: {}", action.kind, target);
+    
+    match app.shell().open(target.clone(), None) {
+        Ok(_) => Ok(AppResult {
+            success: true,
+            message: format!("Opened {}", target),
+        }),
+        Err(e) => {
+            println!("❌ Failed to open {}: {}", target, e);
+            Err(format!("Failed to open {}: {}", target, e).into())
         }
     }
 }
 
 // Check permissions (simplified for now)
 #[tauri::command]
-async fn check_permissions() -> Result<bool, String> {
+async fn check_permissions() -> Result<bool, FrameSenseError> {
     // For now, just return true since we handle permissions via macOS system prompts
     // In a real app, you might want to check specific permissions here
     println!("🔐 Checking permissions...");
@@ -574,7 +735,7 @@ async fn check_permissions() -> Result<bool, String> {
 #[tauri::command]
 fn check_permissions_cached(
     cache: tauri::State<'_, SharedPermissionCache>
-) -> Result<bool, String> {
+) -> Result<bool, FrameSenseError> {
     let mut permission_cache = cache.lock().unwrap();
     
     // Check all necessary permissions with caching
@@ -590,7 +751,7 @@ fn check_permissions_cached(
 #[tauri::command]
 fn clear_permission_cache(
     cache: tauri::State<'_, SharedPermissionCache>
-) -> Result<(), String> {
+) -> Result<(), FrameSenseError> {
     let mut permission_cache = cache.lock().unwrap();
     permission_cache.clear_cache();
     println!("🗑️ Permission cache cleared");
@@ -601,7 +762,7 @@ fn clear_permission_cache(
 #[tauri::command]
 fn get_permission_cache_stats(
     cache: tauri::State<'_, SharedPermissionCache>
-) -> Result<serde_json::Value, String> {
+) -> Result<serde_json::Value, FrameSenseError> {
     let permission_cache = cache.lock().unwrap();
     let (total, expired) = permission_cache.get_cache_stats();
     
@@ -620,7 +781,7 @@ fn get_permission_cache_stats(
 #[tauri::command]
 fn cleanup_permission_cache(
     cache: tauri::State<'_, SharedPermissionCache>
-) -> Result<(), String> {
+) -> Result<(), FrameSenseError> {
     let mut permission_cache = cache.lock().unwrap();
     permission_cache.cleanup_expired();
     println!("🧹 Permission cache cleanup completed");
@@ -634,185 +795,1535 @@ fn cleanup_permission_cache(
 fn capture_screen_area_optimized(
     bounds: CaptureBounds,
     cache: tauri::State<'_, SharedScreenshotCache>
-) -> Result<CaptureResult, String> {
+) -> Result<CaptureResult, FrameSenseError> {
+    let image_data = cache.lock().unwrap().capture_optimized(bounds.clone())?;
+    Ok(CaptureResult {
+        success: true,
+        message: "Optimized screen capture successful!".to_string(),
+        bounds: Some(bounds),
+        image_data: Some(image_data),
+    })
+}
+
+// Clear screenshot cache (for testing or memory management)
+#[tauri::command]
+fn clear_screenshot_cache(
+    cache: tauri::State<'_, SharedScreenshotCache>
+) -> Result<(), FrameSenseError> {
+    let mut screenshot_cache = cache.lock().unwrap();
+    screenshot_cache.clear_cache();
+    println!("🗑️ Screenshot cache cleared");
+    Ok(())
+}
+
+// Get screenshot cache statistics
+#[tauri::command]
+fn get_screenshot_cache_stats(
+    cache: tauri::State<'_, SharedScreenshotCache>
+) -> Result<serde_json::Value, FrameSenseError> {
+    let screenshot_cache = cache.lock().unwrap();
+    let (total_entries, total_size, expired_entries) = screenshot_cache.get_cache_stats();
+    
+    let stats = serde_json::json!({
+        "total_entries": total_entries,
+        "total_size_bytes": total_size,
+        "total_size_mb": total_size / (1024 * 1024),
+        "expired_entries": expired_entries,
+        "active_entries": total_entries - expired_entries
+    });
+    
+    println!("📊 Screenshot cache stats: {} entries, {}MB, {} expired", 
+             total_entries, total_size / (1024 * 1024), expired_entries);
+    Ok(stats)
+}
+
+// Cleanup expired screenshot cache entries
+#[tauri::command]
+fn cleanup_screenshot_cache(
+    cache: tauri::State<'_, SharedScreenshotCache>
+) -> Result<(), FrameSenseError> {
+    let mut screenshot_cache = cache.lock().unwrap();
+    screenshot_cache.cleanup_expired();
+    println!("🧹 Screenshot cache cleanup completed");
+    Ok(())
+}
+
+// Resize screenshot buffer (for memory optimization)
+#[tauri::command]
+fn resize_screenshot_buffer(
+    new_size_mb: usize,
+    cache: tauri::State<'_, SharedScreenshotCache>
+) -> Result<(), FrameSenseError> {
     let mut screenshot_cache = cache.lock().unwrap();
+    let new_size_bytes = new_size_mb * 1024 * 1024;
+    screenshot_cache.resize_buffer(new_size_bytes);
+    println!("📏 Screenshot buffer resized to {}MB", new_size_mb);
+    Ok(())
+}
+
+// 🚀 AUTHENTICATION COMMANDS
+
+// Login user with credentials
+#[tauri::command]
+async fn login_user(
+    app: tauri::AppHandle,
+    email: String, 
+    password: String, 
+    auth_service: tauri::State<'_, SharedAuthService>
+) -> Result<User, FrameSenseError> {
+    // Clone the auth service to avoid holding the lock across await
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    let user = service.login_user(email, password).await?;
+    Ok(reconcile_usage(&app, user))
+}
+
+// Sign in through the backend's SSO in the system browser. Runs as a job, so cancel_job
+// with BROWSER_LOGIN_JOB stops waiting for the redirect.
+const BROWSER_LOGIN_JOB: &str = "browser-login";
+
+#[tauri::command]
+#[allow(deprecated)] // shell().open is deprecated in favor of tauri-plugin-opener, which isn't registered
+async fn login_with_browser(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    provider: Option<String>,
+    auth_service: tauri::State<'_, SharedAuthService>,
+    job_manager: tauri::State<'_, SharedJobManager>
+) -> Result<User, FrameSenseError> {
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    
+    // Starting again replaces a sign-in still waiting on an abandoned browser tab
+    let token = job_manager.lock().unwrap().register(BROWSER_LOGIN_JOB, JobKind::Login, window.label());
+    let result = token
+        .run(service.login_with_browser(provider.as_deref(), |url| {
+            app.shell().open(url, None).map_err(|e| format!("Failed to open the browser: {}", e))
+        }))
+        .await;
+    job_manager.lock().unwrap().finish(&token);
+    
+    match result {
+        Ok(user) => Ok(reconcile_usage(&app, user)),
+        Err(error) => {
+            println!("❌ Browser sign-in failed: {}", error);
+            Err(error)
+        },
+    }
+}
+
+// Logout current user
+#[tauri::command]
+async fn logout_user(
+    auth_service: tauri::State<'_, SharedAuthService>
+) -> Result<(), FrameSenseError> {
+    // Clone the auth service to avoid holding the lock across await
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    service.logout_user().await
+}
+
+// Get current logged in user
+#[tauri::command]
+async fn get_current_user(
+    auth_service: tauri::State<'_, SharedAuthService>
+) -> Result<Option<User>, FrameSenseError> {
+    // Clone the auth service to avoid holding the lock across await
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    service.get_current_user().await
+}
+
+// Save user session to storage
+#[tauri::command]
+async fn save_user_session(
+    user: User,
+    auth_service: tauri::State<'_, SharedAuthService>
+) -> Result<(), FrameSenseError> {
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    service.save_user_session(&user).await
+}
+
+// Load user session from storage
+#[tauri::command]
+async fn load_user_session(
+    auth_service: tauri::State<'_, SharedAuthService>
+) -> Result<Option<User>, FrameSenseError> {
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    service.load_user_session().await
+}
+
+// Lines the local usage counts up with the backend's whenever it hands over a fresh user,
+// and reports the reconciled counts in that user
+fn reconcile_usage(app: &tauri::AppHandle, mut user: User) -> User {
+    let backend = app.state::<SharedAuthService>().lock().unwrap().api_url().to_string();
+    let usage_meter = app.state::<SharedUsageMeter>();
+    let mut meter = usage_meter.lock().unwrap();
+    meter.reconcile(&user.id, &backend, user.usage_daily, user.usage_total, user.updated_at.as_deref());
+    let snapshot = meter.snapshot(-1);
+    user.usage.daily = snapshot.daily;
+    user.usage.total = snapshot.total;
+    user.usage.last_reset = snapshot.day;
+    user
+}
+
+// The stored session with its token refreshed when it's close to expiry. A session that
+// expired and couldn't be refreshed is cleared, and windows get `session-expired`.
+async fn current_session(app: &tauri::AppHandle, service: &AuthService, force_refresh: bool) -> Result<Option<User>, FrameSenseError> {
+    match service.ensure_fresh_session(force_refresh).await? {
+        SessionCheck::NoSession => Ok(None),
+        SessionCheck::Valid(user) => Ok(Some(user)),
+        SessionCheck::Refreshed(user) => {
+            let user = reconcile_usage(app, user);
+            let _ = app.emit("session-refreshed", &user);
+            Ok(Some(user))
+        },
+        SessionCheck::Expired(reason) => {
+            let _ = app.emit("session-expired", serde_json::json!({ "reason": reason }));
+            Err(FrameSenseError::Unauthorized("Session expired, please log in again".to_string()))
+        },
+    }
+}
+
+// Writes the OCR and analysis caches to disk if they changed; an OCR service that was
+// never started has nothing to write
+fn flush_caches(app: &tauri::AppHandle) {
+    if let Some(Some(service)) = OCR_SERVICE.get() {
+        if let Some(cache) = service.lock().unwrap().cache_mut() {
+            cache.flush();
+        }
+    }
+    app.state::<SharedResponseCache>().lock().unwrap().flush();
+}
+
+// Cache changes are written once a minute rather than on every miss, and again at exit
+async fn flush_caches_periodically(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        flush_caches(&app);
+    }
+}
+
+// Refresh the session token well before it expires; runs at startup and every few hours
+async fn keep_session_fresh(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(6 * 60 * 60));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let service = app.state::<SharedAuthService>().lock().unwrap().clone();
+        if let Err(e) = current_session(&app, &service, false).await {
+            println!("⚠️ Session check failed: {}", e);
+        }
+    }
+}
+
+// Expiry of the stored session token, read locally without a backend round trip
+#[tauri::command]
+async fn get_session_status(
+    auth_service: tauri::State<'_, SharedAuthService>
+) -> Result<Option<SessionStatus>, FrameSenseError> {
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    Ok(service
+        .load_user_session()
+        .await?
+        .map(|user| SessionStatus::for_token(&user.token)))
+}
+
+// Refresh the session token now, whatever its expiry
+#[tauri::command]
+async fn refresh_session(
+    app: tauri::AppHandle,
+    auth_service: tauri::State<'_, SharedAuthService>
+) -> Result<Option<User>, FrameSenseError> {
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    current_session(&app, &service, true).await
+}
+
+// Handle payment success from deep link
+#[tauri::command]
+async fn handle_payment_success(
+    app: tauri::AppHandle,
+    token: String, 
+    plan: String, 
+    auth_service: tauri::State<'_, SharedAuthService>
+) -> Result<User, FrameSenseError> {
+    // Clone the auth service to avoid holding the lock across await
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    let user = service.handle_payment_success(token, plan).await?;
+    Ok(reconcile_usage(&app, user))
+}
+
+// Get available models for user tier
+#[tauri::command]
+fn get_available_models(
+    user_tier: String,
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<Vec<String>, FrameSenseError> {
+    println!("🔍 DEBUG: get_available_models called for tier: {}", user_tier);
+    
+    let registry = model_registry.lock().unwrap();
+    let models: Vec<String> = registry
+        .available_models(&user_tier)
+        .iter()
+        .map(|model| model.display_name.clone())
+        .collect();
+    
+    println!("✅ DEBUG: get_available_models returning {} models: {:?}", models.len(), models);
+    Ok(models)
+}
+
+fn analysis_provider_path() -> PathBuf {
+    framesense_data_dir().join("analysis_provider.json")
+}
+
+fn load_provider_settings(app: &tauri::AppHandle) -> ProviderSettings {
+    ProviderSettings::load(&analysis_provider_path(), app.state::<SharedSecretStore>().as_ref())
+}
+
+// How a run is cached and metered. An extraction attempt may be thrown away by the schema
+// check, so it neither reads nor fills the response cache and the whole extraction is
+// metered once by the caller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AnalysisRun {
+    Answer,
+    ExtractionAttempt,
+}
+
+// Run one analysis with the configured provider. The answer streams to `target` as
+// `analysis-chunk` events, followed by a single `analysis-done`.
+async fn run_analysis(
+    app: &tauri::AppHandle,
+    target: &str,
+    request: &AnalysisRequest,
+    request_id: Option<String>,
+    service: AuthService,
+    model_registry: &SharedModelRegistry
+) -> Result<AnalysisResult, FrameSenseError> {
+    run_analysis_as(app, target, request, request_id, service, model_registry, AnalysisRun::Answer).await
+}
+
+// Count one answered analysis against the day's usage, priced by the model that answered
+fn record_usage(app: &tauri::AppHandle, model_registry: &SharedModelRegistry, model: Option<String>, kind: ProviderKind) {
+    let cost = model
+        .as_deref()
+        .and_then(|model| model_registry.lock().unwrap().find(model).map(|entry| entry.cost_per_call))
+        .unwrap_or(0.0);
+    app.state::<SharedUsageMeter>().lock().unwrap().record(model, kind, cost);
+}
+
+async fn run_analysis_as(
+    app: &tauri::AppHandle,
+    target: &str,
+    request: &AnalysisRequest,
+    request_id: Option<String>,
+    service: AuthService,
+    model_registry: &SharedModelRegistry,
+    run: AnalysisRun
+) -> Result<AnalysisResult, FrameSenseError> {
+    let request_id = request_id.unwrap_or_else(|| {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        format!("analysis-{}", millis)
+    });
+    
+    let token = app.state::<SharedJobManager>().lock().unwrap().register(&request_id, JobKind::Analysis, target);
+    
+    let result: Result<(AnalysisResult, Vec<FailedAttempt>), FrameSenseError> = token.run(async {
+        let user = current_session(app, &service, false).await?;
+        let user_tier = user.as_ref().map(|user| user.tier.clone()).unwrap_or_else(|| "free".to_string());
+        let settings = load_provider_settings(app);
+        let provider = AnalysisProvider::from_settings(&settings, service.api_url(), user.map(|user| user.token))?;
+        
+        // Same tier gating whether the request goes to our backend or the user's own endpoint
+        if let Some(model) = provider.effective_model(request) {
+            let registry = model_registry.lock().unwrap();
+            analysis::check_model_access(&*registry, settings.kind, &user_tier, &model)?;
+        }
+        
+        // Region references are asked for in the prompt itself, so they're part of the cache key too
+        let with_instruction = analysis::grounding::with_region_instruction(request);
+        let request = with_instruction.as_ref().unwrap_or(request);
+        
+        // The same question about the same capture, model and provider is answered from the
+        // cache without touching quota, unless the caller asked for a fresh answer
+        let cache_key = analysis::cache::cache_key(request, provider.effective_model(request).as_deref(), &settings.cache_scope(service.api_url()))?;
+        if !request.force_refresh && run == AnalysisRun::Answer {
+            let cached = app.state::<SharedResponseCache>().lock().unwrap().get(&cache_key);
+            if let Some(cached) = cached {
+                println!("💾 Analysis {} answered from cache", request_id);
+                let _ = app.emit_to(target, "analysis-chunk", serde_json::json!({
+                    "request_id": request_id,
+                    "delta": cached.answer,
+                    "cached": true
+                }));
+                return Ok((cached, Vec::new()));
+            }
+        }
+        
+        // The daily limit covers our backend only; BYOK calls run on the user's own key
+        if settings.kind == ProviderKind::Backend {
+            let limit = model_registry.lock().unwrap().daily_limit(&user_tier);
+            let usage_meter = app.state::<SharedUsageMeter>();
+            let check = usage_meter.lock().unwrap().check(&user_tier, limit);
+            if let Err(error) = check {
+                println!("🚫 Analysis {} refused: {}", request_id, error);
+                let _ = app.emit_to(target, "usage-limit-reached", &error);
+                return Err(error.into());
+            }
+        }
+        
+        // Our backend can answer with the next model of the tier's fallback chain when the chosen
+        // one is rate limited, down or refuses; a BYOK endpoint only has its configured model
+        let chain = match settings.kind {
+            ProviderKind::Backend => {
+                let registry = model_registry.lock().unwrap();
+                fallback::fallback_chain(&registry, &user_tier, request.model.as_deref(), request.images().len())
+            },
+            ProviderKind::OpenAiCompatible => vec![fallback::ChainModel {
+                model: provider.effective_model(request),
+                provider: None,
+            }],
+        };
+        
+        let (provider, request_id, provider_kind) = (&provider, &request_id, settings.kind);
+        let attempt = |model: Option<String>| {
+            let mut attempt = request.clone();
+            attempt.model = model;
+            async move {
+                // Fit the images to the answering model (count, size, tiles, format) before anything is uploaded
+                let model_entry = provider
+                    .effective_model(&attempt)
+                    .and_then(|model| model_registry.lock().unwrap().find(&model).cloned());
+                let (prepared, estimate) = tauri::async_runtime::spawn_blocking(move || {
+                    analysis::prepare_for_model(&mut attempt, model_entry.as_ref()).map(|estimate| (attempt, estimate))
+                })
+                .await
+                .map_err(|e| format!("Image preparation failed: {}", e))??;
+                if let Some(estimate) = &estimate {
+                    println!("🖼️ Analysis {} sends {} image(s) to {}, ~{:?} image tokens", 
+                             request_id, estimate.images, estimate.model, estimate.image_tokens);
+                    let _ = app.emit_to(target, "analysis-image-estimate", serde_json::json!({
+                        "request_id": request_id,
+                        "estimate": estimate
+                    }));
+                }
+                
+                println!("🤖 Analysis {} started for window '{}' ({:?}, model: {:?}, {} prior turns)", 
+                         request_id, target, provider_kind, provider.effective_model(&prepared), prepared.history.len());
+                provider
+                    .analyze(&prepared, |delta| {
+                        let _ = app.emit_to(target, "analysis-chunk", serde_json::json!({
+                            "request_id": request_id,
+                            "delta": delta
+                        }));
+                    })
+                    .await
+            }
+        };
+        // A failed attempt may have streamed part of an answer; the window drops it on this event
+        let (analysis, failed) = fallback::run_chain(&chain, attempt, |failure| {
+            let _ = app.emit_to(target, "analysis-fallback", serde_json::json!({
+                "request_id": request_id,
+                "failed": failure
+            }));
+        })
+        .await?;
+        
+        if run == AnalysisRun::ExtractionAttempt {
+            return Ok((analysis, failed));
+        }
+        record_usage(app, model_registry, analysis.model.clone(), settings.kind);
+        // Fallback answers aren't cached, so the chosen model is asked again next time
+        if failed.is_empty() {
+            app.state::<SharedResponseCache>().lock().unwrap().insert(cache_key, &analysis);
+        }
+        Ok((analysis, failed))
+    })
+    .await;
+    app.state::<SharedJobManager>().lock().unwrap().finish(&token);
+    
+    let done = match &result {
+        Ok((analysis, failed)) => {
+            println!("✅ Analysis {} done: {} chars, {:?} tokens, model: {:?}, cached: {}", 
+                     request_id, analysis.answer.len(), analysis.tokens_used, analysis.model, analysis.cached);
+            serde_json::json!({
+                "request_id": request_id,
+                "success": true,
+                "answer": analysis.answer,
+                "model": analysis.model,
+                "requested_model": request.model,
+                "fallbacks": failed,
+                "tokens_used": analysis.tokens_used,
+                "cached": analysis.cached
+            })
+        },
+        Err(error) => {
+            println!("❌ Analysis {} failed: {}", request_id, error);
+            serde_json::json!({
+                "request_id": request_id,
+                "success": false,
+                "cancelled": matches!(error, FrameSenseError::Cancelled),
+                "error": error.to_string(),
+                "code": error.code()
+            })
+        }
+    };
+    let _ = app.emit_to(target, "analysis-done", done);
+    
+    result.map(|(analysis, _)| analysis)
+}
+
+// Keep an analysis that failed for lack of a connection so it can be retried later.
+// Returns the error to report: unchanged, or noting that the analysis was queued.
+fn queue_failed_analysis(app: &tauri::AppHandle, request: &AnalysisRequest, thread_id: Option<String>, error: FrameSenseError) -> FrameSenseError {
+    if !error.is_transient() {
+        return error;
+    }
+    let queue = app.state::<SharedAnalysisQueue>();
+    let mut queue = queue.lock().unwrap();
+    match queue.enqueue(request.clone(), thread_id, &error.to_string()) {
+        Ok(job) => {
+            let _ = app.emit("analysis-queue-updated", queue.list());
+            FrameSenseError::Queued {
+                message: format!("{} - queued as {}, it will be retried when the connection is back", error, job.id),
+                job_id: job.id.clone(),
+            }
+        },
+        Err(e) => {
+            println!("❌ Failed to queue analysis: {}", e);
+            error
+        }
+    }
+}
+
+fn notify(app: &tauri::AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        println!("⚠️ Failed to show notification: {}", e);
+    }
+}
+
+// Event target for queued retries; no window has this label
+const QUEUE_TARGET: &str = "analysis-queue";
+
+// Background worker for the offline queue: sends due jobs one at a time and stops for
+// this round at the first connection failure, since the rest would fail the same way
+async fn process_analysis_queue(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(5));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let queue = app.state::<SharedAnalysisQueue>().inner().clone();
+    
+    loop {
+        interval.tick().await;
+        
+        loop {
+            let Some(job) = queue.lock().unwrap().take_next_due() else { break };
+            println!("📬 Retrying queued analysis {} (attempt {})", job.id, job.attempts + 1);
+            
+            let service = app.state::<SharedAuthService>().lock().unwrap().clone();
+            let model_registry = app.state::<SharedModelRegistry>().inner().clone();
+            // Not streamed to the chat window (whatever it shows now is unrelated), and kept out
+            // of its jobs so starting a new capture doesn't cancel the retry
+            let result = run_analysis(&app, QUEUE_TARGET, &job.request, Some(job.id.clone()), service, &model_registry).await;
+            
+            let mut offline = false;
+            match result {
+                Ok(analysis) => {
+                    let completed = {
+                        let mut queue = queue.lock().unwrap();
+                        queue.connectivity_restored();
+                        queue.complete(&job.id)
+                    };
+                    // Cancelled while the request was in flight
+                    if completed.is_none() {
+                        continue;
+                    }
+                    
+                    if let Some(thread_id) = &job.thread_id {
+                        let conversations = app.state::<SharedConversationStore>();
+                        let store = conversations.lock().unwrap();
+                        let appended = store.get(thread_id).and_then(|mut thread| {
+                            store.append_exchange(&mut thread, &job.request.question, &analysis.answer, analysis.model.clone())
+                        });
+                        if let Err(e) = appended {
+                            println!("⚠️ Failed to add queued answer to thread {}: {}", thread_id, e);
+                        }
+                    }
+                    
+                    let _ = app.emit("analysis-queue-completed", serde_json::json!({
+                        "job_id": job.id,
+                        "thread_id": job.thread_id,
+                        "result": analysis
+                    }));
+                    notify(&app, "Analysis ready", &job.request.question);
+                },
+                Err(error) => {
+                    let failed = queue.lock().unwrap().fail(&job.id, &error);
+                    match failed {
+                        Some(failed) if failed.status == analysis_queue::JobStatus::Failed => {
+                            notify(&app, "Queued analysis failed", &format!("{}: {}", failed.request.question, error));
+                        },
+                        Some(_) => offline = true,
+                        None => {},
+                    }
+                }
+            }
+            
+            let _ = app.emit("analysis-queue-updated", queue.lock().unwrap().list());
+            if offline {
+                break;
+            }
+        }
+    }
+}
+
+// Analyze a capture with the configured provider, streaming to the calling window
+#[tauri::command]
+async fn analyze_capture(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    request: AnalysisRequest,
+    request_id: Option<String>,
+    auth_service: tauri::State<'_, SharedAuthService>,
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<AnalysisResult, FrameSenseError> {
+    // Clone the auth service to avoid holding the lock across await
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    run_analysis(&app, window.label(), &request, request_id, service, model_registry.inner())
+        .await
+        .map_err(|error| queue_failed_analysis(&app, &request, None, error))
+}
+
+// Ask a question in a conversation thread. Without `thread_id` a new thread is started
+// from the capture; follow-ups reuse the stored capture, OCR text and earlier turns.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn send_thread_message(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    thread_id: Option<String>,
+    question: String,
+    image_data: Option<String>,
+    ocr_text: Option<String>,
+    model: Option<String>,
+    request_id: Option<String>,
+    force_refresh: Option<bool>,
+    region_references: Option<bool>,
+    auth_service: tauri::State<'_, SharedAuthService>,
+    model_registry: tauri::State<'_, SharedModelRegistry>,
+    conversations: tauri::State<'_, SharedConversationStore>
+) -> Result<serde_json::Value, FrameSenseError> {
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    
+    let (mut thread, capture) = {
+        let store = conversations.lock().unwrap();
+        let thread = match &thread_id {
+            Some(id) => store.get(id)?,
+            None => store.create(&question, image_data.as_deref(), ocr_text)?,
+        };
+        let capture = store.capture_data(&thread)?;
+        (thread, capture)
+    };
+    
+    let request = AnalysisRequest {
+        question: question.clone(),
+        image_data: capture,
+        model,
+        ocr_text: thread.ocr_text.clone(),
+        history: thread.context(),
+        captures: Vec::new(),
+        force_refresh: force_refresh.unwrap_or(false),
+        region_references: region_references.unwrap_or(false),
+    };
+    let result = match run_analysis(&app, window.label(), &request, request_id, service, model_registry.inner()).await {
+        Ok(result) => result,
+        Err(error) if error.is_transient() => {
+            // Keep the thread; the queued answer is added to it once it goes through
+            return Err(queue_failed_analysis(&app, &request, Some(thread.id.clone()), error));
+        },
+        Err(error) => {
+            // Don't leave an empty thread behind when its very first question fails
+            if thread_id.is_none() {
+                let _ = conversations.lock().unwrap().delete(&thread.id);
+            }
+            return Err(error);
+        }
+    };
+    
+    // Only completed exchanges are stored, so a failed request can simply be retried
+    conversations.lock().unwrap().append_exchange(&mut thread, &question, &result.answer, result.model.clone())?;
+    
+    Ok(serde_json::json!({
+        "thread_id": thread.id,
+        "title": thread.title,
+        "result": result
+    }))
+}
+
+// One capture picked for a comparison: a fresh capture, or the capture of an earlier thread
+#[derive(Clone, Deserialize)]
+pub struct ComparisonCapture {
+    pub label: Option<String>,
+    pub image_data: Option<String>,
+    pub thread_id: Option<String>,
+}
+
+// Ask one question about several labelled captures at once ("what changed between these builds?")
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn compare_captures(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    question: String,
+    captures: Vec<ComparisonCapture>,
+    model: Option<String>,
+    request_id: Option<String>,
+    force_refresh: Option<bool>,
+    auth_service: tauri::State<'_, SharedAuthService>,
+    model_registry: tauri::State<'_, SharedModelRegistry>,
+    conversations: tauri::State<'_, SharedConversationStore>
+) -> Result<AnalysisResult, FrameSenseError> {
+    if captures.len() < 2 {
+        return Err("Select at least two captures to compare".into());
+    }
+    
+    let labeled = {
+        let store = conversations.lock().unwrap();
+        captures
+            .into_iter()
+            .enumerate()
+            .map(|(index, capture)| {
+                let image_data = match (capture.image_data, &capture.thread_id) {
+                    (Some(image_data), _) => image_data,
+                    (None, Some(thread_id)) => store
+                        .capture_data(&store.get(thread_id)?)?
+                        .ok_or_else(|| format!("Thread {} has no capture", thread_id))?,
+                    (None, None) => return Err(format!("Capture {} has no image", index + 1)),
+                };
+                let label = capture
+                    .label
+                    .filter(|label| !label.trim().is_empty())
+                    .unwrap_or_else(|| format!("Capture {}", index + 1));
+                Ok(analysis::LabeledCapture { label, image_data })
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    };
+    
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    let request = AnalysisRequest {
+        question,
+        image_data: None,
+        model,
+        ocr_text: None,
+        history: Vec::new(),
+        captures: labeled,
+        force_refresh: force_refresh.unwrap_or(false),
+        region_references: false,
+    };
+    println!("🔀 Comparing {} captures", request.captures.len());
+    run_analysis(&app, window.label(), &request, request_id, service, model_registry.inner())
+        .await
+        .map_err(|error| queue_failed_analysis(&app, &request, None, error))
+}
+
+// List conversation threads, most recently active first
+#[tauri::command]
+fn list_threads(
+    conversations: tauri::State<'_, SharedConversationStore>
+) -> Result<Vec<ThreadSummary>, FrameSenseError> {
+    conversations.lock().unwrap().list().map_err(FrameSenseError::from)
+}
+
+// Load a thread to resume it: messages, OCR text and the capture as a data URL
+#[tauri::command]
+fn resume_thread(
+    thread_id: String,
+    conversations: tauri::State<'_, SharedConversationStore>
+) -> Result<serde_json::Value, FrameSenseError> {
+    let store = conversations.lock().unwrap();
+    let thread = store.get(&thread_id)?;
+    let image_data = store.capture_data(&thread).unwrap_or_else(|e| {
+        println!("⚠️ {}", e);
+        None
+    });
+    
+    println!("🧵 Resuming thread {} ({} messages)", thread.id, thread.messages.len());
+    Ok(serde_json::json!({
+        "thread": thread,
+        "image_data": image_data
+    }))
+}
+
+// Rename a conversation thread
+#[tauri::command]
+fn rename_thread(
+    thread_id: String,
+    title: String,
+    conversations: tauri::State<'_, SharedConversationStore>
+) -> Result<ThreadSummary, FrameSenseError> {
+    let thread = conversations.lock().unwrap().rename(&thread_id, &title)?;
+    Ok(thread.summary())
+}
+
+// Delete a conversation thread and its stored capture
+#[tauri::command]
+fn delete_thread(
+    thread_id: String,
+    conversations: tauri::State<'_, SharedConversationStore>
+) -> Result<(), FrameSenseError> {
+    conversations.lock().unwrap().delete(&thread_id).map_err(FrameSenseError::from)
+}
+
+fn quick_actions_path() -> PathBuf {
+    framesense_data_dir().join("quick_actions.json")
+}
+
+// Swap the global shortcuts bound to quick actions: drop the old bindings, register the current ones.
+// A shortcut another app already owns is reported and skipped rather than failing the save.
+fn sync_action_hotkeys(app: &tauri::AppHandle, previous: &[String], actions: &[QuickAction]) {
+    for hotkey in previous {
+        if let Err(e) = app.global_shortcut().unregister(hotkey.as_str()) {
+            println!("⚠️ Failed to unregister quick action shortcut {}: {}", hotkey, e);
+        }
+    }
+    
+    for action in actions {
+        let Some(hotkey) = &action.hotkey else { continue };
+        let action_id = action.id.clone();
+        let result = app.global_shortcut().on_shortcut(hotkey.as_str(), move |app, _shortcut, event| {
+            if event.state() != ShortcutState::Pressed {
+                return;
+            }
+            println!("⚡ Quick action shortcut triggered: {}", action_id);
+            let app = app.clone();
+            let action_id = action_id.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = trigger_quick_action(app, action_id.clone()).await {
+                    println!("❌ Quick action {} failed: {}", action_id, e);
+                }
+            });
+        });
+        match result {
+            Ok(_) => println!("✅ Quick action '{}' bound to {}", action.name, hotkey),
+            Err(e) => println!("⚠️ Failed to bind {} to quick action '{}': {}", hotkey, action.name, e),
+        }
+    }
+}
+
+// Runs pipeline stages against the app: screen capture, the shared OCR service and the
+// configured analysis provider. Used from blocking threads, so async work goes through block_on.
+struct AppPipelineHost {
+    app: tauri::AppHandle,
+    target: String,
+    request_id: String,
+    captured_bounds: Mutex<Option<CaptureBounds>>,
+    selection: Option<CaptureBounds>, // Area just selected, captured instead of the last saved selection
+    selection_png: Mutex<Option<String>>, // The selection as the screenshot cache encoded it
+    quick_action: Option<QuickAction>, // Announced in the main window right before the answer streams
+}
+
+impl AppPipelineHost {
+    fn new(app: &tauri::AppHandle, target: &str, request_id: String) -> Self {
+        Self {
+            app: app.clone(),
+            target: target.to_string(),
+            request_id,
+            captured_bounds: Mutex::new(None),
+            selection: None,
+            selection_png: Mutex::new(None),
+            quick_action: None,
+        }
+    }
+    
+    // Show the result window before the answer starts streaming into it
+    fn announce_quick_action(&self, action: &QuickAction, request: &AnalysisRequest) -> Result<(), String> {
+        match self.app.get_webview_window("main") {
+            Some(window) => {
+                let _ = window.show();
+                let _ = window.set_focus();
+            },
+            None => tauri::async_runtime::block_on(create_main_window(self.app.clone()))?,
+        }
+        let _ = self.app.emit_to("main", "quick-action-started", serde_json::json!({
+            "request_id": self.request_id,
+            "action_id": action.id,
+            "action_name": action.name,
+            "output_format": action.output_format,
+            "prompt": request.question,
+            "bounds": self.captured_bounds.lock().unwrap().clone(),
+            "image_data": request.image_data
+        }));
+        Ok(())
+    }
+}
+
+impl PipelineHost for AppPipelineHost {
+    fn capture(&self, target: CaptureTarget) -> Result<image::DynamicImage, FrameSenseError> {
+        let last_bounds = match &self.selection {
+            Some(selection) => Some(selection.clone()),
+            None => self.app.state::<SharedState>().lock().unwrap().last_bounds.clone(),
+        };
+        let bounds = match (target, last_bounds) {
+            (CaptureTarget::LastSelection, Some(bounds)) => bounds,
+            _ => {
+                let screens = screenshots::Screen::all()
+                    .map_err(|e| FrameSenseError::CapturePermission(format!("Failed to access screens: {}", e)))?;
+                let screen = screens.first().ok_or(FrameSenseError::NoDisplay)?;
+                CaptureBounds {
+                    x: 0,
+                    y: 0,
+                    width: screen.display_info.width,
+                    height: screen.display_info.height,
+                }
+            }
+        };
+        
+        // A fresh selection goes through the screenshot cache, so reselecting the same
+        // area within its TTL skips the capture and the PNG encode
+        let image = match (&self.selection, target) {
+            (Some(_), CaptureTarget::LastSelection) => {
+                let png = self.app.state::<SharedScreenshotCache>().lock().unwrap().capture_optimized(bounds.clone())?;
+                let image = ocr::decode_image(&png)?;
+                *self.selection_png.lock().unwrap() = Some(png);
+                image
+            },
+            _ => live_ocr::capture_region(&bounds)?,
+        };
+        *self.captured_bounds.lock().unwrap() = Some(bounds);
+        Ok(image)
+    }
+    
+    fn recognize(&self, image: &image::DynamicImage) -> Result<OCRResult, FrameSenseError> {
+        with_ocr_service(|service| service.extract_from_image(image))
+    }
+    
+    fn analyze(&self, request: &AnalysisRequest) -> Result<AnalysisResult, FrameSenseError> {
+        if let Some(action) = &self.quick_action {
+            self.announce_quick_action(action, request)?;
+        }
+        let service = self.app.state::<SharedAuthService>().lock().unwrap().clone();
+        let model_registry = self.app.state::<SharedModelRegistry>().inner().clone();
+        tauri::async_runtime::block_on(run_analysis(
+            &self.app,
+            &self.target,
+            request,
+            Some(self.request_id.clone()),
+            service,
+            &model_registry,
+        ))
+    }
+}
+
+fn pipelines_path() -> PathBuf {
+    framesense_data_dir().join("pipelines.json")
+}
+
+// Where a run saves its intermediate images and report, if it keeps them at all
+fn pipeline_artifacts_root(pipeline: &PipelineDefinition, keep_artifacts: bool) -> Option<PathBuf> {
+    (pipeline.keep_artifacts || keep_artifacts).then(|| framesense_data_dir().join("pipeline_runs"))
+}
+
+// Run the action's pipeline (quick_action unless it names another) and stream the answer to the main window
+async fn trigger_quick_action(app: tauri::AppHandle, action_id: String) -> Result<AnalysisResult, FrameSenseError> {
+    let action = app
+        .state::<SharedActionStore>()
+        .lock()
+        .unwrap()
+        .get(&action_id)
+        .cloned()
+        .ok_or_else(|| format!("Quick action not found: {}", action_id))?;
+    let pipeline = app
+        .state::<SharedPipelineStore>()
+        .lock()
+        .unwrap()
+        .get(action.pipeline.as_deref().unwrap_or(pipeline::QUICK_ACTION_PIPELINE))?;
+    if !pipeline.has_ai_stage() {
+        return Err(format!("Pipeline '{}' has no AI stage to answer the action", pipeline.id).into());
+    }
+    cancel_chat_jobs(&app);
+    
+    let request_id = format!(
+        "action-{}-{}",
+        action.id,
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+    );
+    let mut host = AppPipelineHost::new(&app, "main", request_id.clone());
+    host.quick_action = Some(action.clone());
+    
+    // Stages run off the async runtime; the app name is read first, before our window takes focus
+    let (report, request, bounds) = tauri::async_runtime::spawn_blocking(move || {
+        let mut context = PipelineContext {
+            capture_target: action.capture,
+            app_name: frontmost_app_name().unwrap_or_else(|| "an unknown app".to_string()),
+            prompt: Some(action.prompt.clone()),
+            model: action.model.clone(),
+            output_format: action.output_format,
+            ..Default::default()
+        };
+        let report = pipeline.run(&mut context, &host, pipeline_artifacts_root(&pipeline, false).as_deref());
+        (
+            report,
+            context.request,
+            host.captured_bounds.into_inner().ok().flatten(),
+        )
+    })
+    .await
+    .map_err(|e| format!("Quick action pipeline task failed: {}", e))?;
+    
+    // Highlights are in capture coordinates; with the bounds the overlay can place them on screen
+    if !report.highlights.is_empty() {
+        let _ = app.emit("answer-grounding", serde_json::json!({
+            "request_id": request_id,
+            "bounds": bounds,
+            "highlights": report.highlights
+        }));
+    }
+    
+    match (report.answer, report.error) {
+        (Some(mut answer), None) => {
+            if let Some(output) = report.output {
+                answer.answer = output;
+            }
+            Ok(answer)
+        },
+        (Some(answer), Some(error)) => {
+            // Only a stage after the AI stage failed; the raw answer is still worth showing
+            println!("⚠️ Quick action postprocessing failed, returning the raw answer: {}", error);
+            Ok(answer)
+        },
+        (None, error) => {
+            let error = error.unwrap_or_else(|| "The pipeline finished without an answer".into());
+            match request {
+                // The AI stage was reached, so the capture can be retried once back online
+                Some(request) => Err(queue_failed_analysis(&app, &request, None, error)),
+                None => Err(error),
+            }
+        },
+    }
+}
+
+// List quick actions
+#[tauri::command]
+fn list_quick_actions(
+    action_store: tauri::State<'_, SharedActionStore>
+) -> Result<Vec<QuickAction>, FrameSenseError> {
+    Ok(action_store.lock().unwrap().actions().to_vec())
+}
+
+// Create (empty id) or update a quick action and rebind its shortcut
+#[tauri::command]
+fn save_quick_action(
+    app: tauri::AppHandle,
+    action: QuickAction,
+    action_store: tauri::State<'_, SharedActionStore>,
+    pipeline_store: tauri::State<'_, SharedPipelineStore>
+) -> Result<QuickAction, FrameSenseError> {
+    if let Some(pipeline_id) = &action.pipeline {
+        pipeline_store.lock().unwrap().get(pipeline_id)?;
+    }
+    if let Some(hotkey) = action.hotkey.as_deref().filter(|hotkey| !hotkey.trim().is_empty()) {
+        hotkey.parse::<Shortcut>().map_err(|e| format!("Invalid shortcut '{}': {}", hotkey, e))?;
+    }
+    
+    let mut store = action_store.lock().unwrap();
+    let previous = store.hotkeys();
+    let saved = store.upsert(action)?;
+    sync_action_hotkeys(&app, &previous, store.actions());
+    Ok(saved)
+}
+
+// Delete a quick action and release its shortcut
+#[tauri::command]
+fn delete_quick_action(
+    app: tauri::AppHandle,
+    action_id: String,
+    action_store: tauri::State<'_, SharedActionStore>
+) -> Result<(), FrameSenseError> {
+    let mut store = action_store.lock().unwrap();
+    let previous = store.hotkeys();
+    store.remove(&action_id)?;
+    sync_action_hotkeys(&app, &previous, store.actions());
+    Ok(())
+}
+
+// Export quick actions as shareable JSON (all of them when no ids are given)
+#[tauri::command]
+fn export_quick_actions(
+    action_ids: Option<Vec<String>>,
+    action_store: tauri::State<'_, SharedActionStore>
+) -> Result<String, FrameSenseError> {
+    action_store.lock().unwrap().export(action_ids.as_deref()).map_err(FrameSenseError::from)
+}
+
+// Import quick actions shared as JSON
+#[tauri::command]
+fn import_quick_actions(
+    app: tauri::AppHandle,
+    json: String,
+    action_store: tauri::State<'_, SharedActionStore>
+) -> Result<Vec<QuickAction>, FrameSenseError> {
+    let mut store = action_store.lock().unwrap();
+    let previous = store.hotkeys();
+    let imported = store.import(&json)?;
+    sync_action_hotkeys(&app, &previous, store.actions());
+    Ok(imported)
+}
+
+// Run a quick action as if its shortcut had been pressed
+#[tauri::command]
+async fn run_quick_action(app: tauri::AppHandle, action_id: String) -> Result<AnalysisResult, FrameSenseError> {
+    trigger_quick_action(app, action_id).await
+}
+
+// Find where an answer's quoted and [[ref: ...]] text is in the capture, as highlight
+// rectangles in capture coordinates. Words come from the capture's OCR result; without
+// them the image is read again (usually an OCR cache hit).
+#[tauri::command]
+async fn ground_answer(
+    answer: String,
+    words: Option<Vec<OCRWord>>,
+    image_data: Option<String>,
+    quotes: Option<bool>
+) -> Result<Grounding, FrameSenseError> {
+    let words = match (words, image_data) {
+        (Some(words), _) if !words.is_empty() => words,
+        (_, Some(image_data)) => tauri::async_runtime::spawn_blocking(move || {
+            with_ocr_service(|service| service.extract_text(&image_data))
+        })
+        .await
+        .map_err(|e| format!("OCR task failed: {}", e))??
+        .words,
+        _ => return Err("Grounding needs the capture's OCR words or the capture itself".into()),
+    };
+    Ok(analysis::grounding::ground_answer(&answer, &words, quotes.unwrap_or(true)))
+}
+
+// Built-in and user-defined processing pipelines
+#[tauri::command]
+fn list_pipelines(
+    pipeline_store: tauri::State<'_, SharedPipelineStore>
+) -> Result<Vec<PipelineDefinition>, FrameSenseError> {
+    Ok(pipeline_store.lock().unwrap().list())
+}
+
+// Create or update a pipeline; saving over a built-in id customizes that built-in
+#[tauri::command]
+fn save_pipeline(
+    pipeline: PipelineDefinition,
+    pipeline_store: tauri::State<'_, SharedPipelineStore>
+) -> Result<PipelineDefinition, FrameSenseError> {
+    pipeline_store.lock().unwrap().upsert(pipeline).map_err(FrameSenseError::from)
+}
+
+// Delete a user pipeline, or restore a customized built-in to its default
+#[tauri::command]
+fn delete_pipeline(
+    pipeline_id: String,
+    pipeline_store: tauri::State<'_, SharedPipelineStore>
+) -> Result<(), FrameSenseError> {
+    pipeline_store.lock().unwrap().remove(&pipeline_id).map_err(FrameSenseError::from)
+}
+
+// Run a pipeline once, on a supplied image or (without one) a fresh capture. The report
+// lists every stage with its timing and output; AI answers stream to the calling window.
+#[tauri::command]
+async fn run_pipeline(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    pipeline_id: String,
+    image_data: Option<String>,
+    prompt: Option<String>,
+    model: Option<String>,
+    keep_artifacts: Option<bool>,
+    pipeline_store: tauri::State<'_, SharedPipelineStore>
+) -> Result<PipelineReport, FrameSenseError> {
+    let pipeline = pipeline_store.lock().unwrap().get(&pipeline_id)?;
+    let request_id = format!(
+        "pipeline-{}-{}",
+        pipeline.id,
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+    );
+    let host = AppPipelineHost::new(&app, window.label(), request_id);
+    
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let mut context = match image_data {
+            Some(image_data) => PipelineContext::from_image(ocr::decode_image(&image_data)?),
+            None => PipelineContext::default(),
+        };
+        context.app_name = frontmost_app_name().unwrap_or_else(|| "an unknown app".to_string());
+        context.prompt = prompt;
+        context.model = model;
+        let artifacts_root = pipeline_artifacts_root(&pipeline, keep_artifacts.unwrap_or(false));
+        Ok::<_, FrameSenseError>(pipeline.run(&mut context, &host, artifacts_root.as_deref()))
+    })
+    .await
+    .map_err(|e| format!("Pipeline task failed: {}", e))??;
+    
+    Ok(report)
+}
+
+// Run every image through one pipeline (batch_ocr by default), one after another. Progress
+// arrives as `pipeline-batch-progress` events; cancelling the job skips the remaining images.
+#[tauri::command]
+async fn run_pipeline_batch(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    pipeline_id: Option<String>,
+    images: Vec<String>,
+    job_id: Option<String>,
+    prompt: Option<String>,
+    model: Option<String>,
+    pipeline_store: tauri::State<'_, SharedPipelineStore>,
+    job_manager: tauri::State<'_, SharedJobManager>
+) -> Result<Vec<PipelineReport>, FrameSenseError> {
+    let pipeline = pipeline_store
+        .lock()
+        .unwrap()
+        .get(pipeline_id.as_deref().unwrap_or(pipeline::BATCH_OCR_PIPELINE))?;
+    let job_id = job_id.unwrap_or_else(|| {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        format!("pipeline-batch-{}", millis)
+    });
+    let token = job_manager.lock().unwrap().register(&job_id, JobKind::Pipeline, window.label());
+    println!("🧪 Running {} images through pipeline '{}'", images.len(), pipeline.id);
     
-    match screenshot_cache.capture_optimized(bounds.clone()) {
-        Ok(image_data) => {
-            Ok(CaptureResult {
-                success: true,
-                message: "Optimized screen capture successful!".to_string(),
-                bounds: Some(bounds),
-                image_data: Some(image_data),
-            })
-        },
-        Err(e) => {
-            Ok(CaptureResult {
-                success: false, 
-                message: e,
-                bounds: None,
-                image_data: None,
+    let total = images.len();
+    let mut reports = Vec::with_capacity(total);
+    let mut outcome: Result<(), FrameSenseError> = Ok(());
+    for (index, image_data) in images.into_iter().enumerate() {
+        let pipeline = pipeline.clone();
+        let (prompt, model) = (prompt.clone(), model.clone());
+        let host = AppPipelineHost::new(&app, window.label(), format!("{}-{}", job_id, index + 1));
+        let result = token
+            .run(async {
+                tauri::async_runtime::spawn_blocking(move || {
+                    let mut context = PipelineContext::from_image(ocr::decode_image(&image_data)?);
+                    context.prompt = prompt;
+                    context.model = model;
+                    let artifacts_root = pipeline_artifacts_root(&pipeline, false);
+                    Ok::<_, FrameSenseError>(pipeline.run(&mut context, &host, artifacts_root.as_deref()))
+                })
+                .await
+                .map_err(|e| format!("Pipeline task failed: {}", e))?
             })
+            .await;
+        
+        match result {
+            Ok(report) => {
+                let _ = app.emit_to(window.label(), "pipeline-batch-progress", serde_json::json!({
+                    "job_id": job_id,
+                    "completed": index + 1,
+                    "total": total,
+                    "success": report.success,
+                    "error": report.error
+                }));
+                reports.push(report);
+            },
+            Err(error) => {
+                outcome = Err(error);
+                break;
+            },
         }
     }
+    job_manager.lock().unwrap().finish(&token);
+    
+    outcome?;
+    let failed = reports.iter().filter(|report| !report.success).count();
+    println!("✅ Pipeline batch {} done: {} images, {} failed", job_id, reports.len(), failed);
+    Ok(reports)
 }
 
-// Clear screenshot cache (for testing or memory management)
+// Analysis and OCR jobs currently running
 #[tauri::command]
-fn clear_screenshot_cache(
-    cache: tauri::State<'_, SharedScreenshotCache>
-) -> Result<(), String> {
-    let mut screenshot_cache = cache.lock().unwrap();
-    screenshot_cache.clear_cache();
-    println!("🗑️ Screenshot cache cleared");
-    Ok(())
+fn list_jobs(
+    job_manager: tauri::State<'_, SharedJobManager>
+) -> Result<Vec<JobInfo>, FrameSenseError> {
+    Ok(job_manager.lock().unwrap().list())
 }
 
-// Get screenshot cache statistics
+// Cancel a running analysis or OCR job by id (the request_id / job_id it was started with)
 #[tauri::command]
-fn get_screenshot_cache_stats(
-    cache: tauri::State<'_, SharedScreenshotCache>
-) -> Result<serde_json::Value, String> {
-    let screenshot_cache = cache.lock().unwrap();
-    let (total_entries, total_size, expired_entries) = screenshot_cache.get_cache_stats();
-    
-    let stats = serde_json::json!({
-        "total_entries": total_entries,
-        "total_size_bytes": total_size,
-        "total_size_mb": total_size / (1024 * 1024),
-        "expired_entries": expired_entries,
-        "active_entries": total_entries - expired_entries
-    });
-    
-    println!("📊 Screenshot cache stats: {} entries, {}MB, {} expired", 
-             total_entries, total_size / (1024 * 1024), expired_entries);
-    Ok(stats)
+fn cancel_job(
+    job_id: String,
+    job_manager: tauri::State<'_, SharedJobManager>
+) -> Result<bool, FrameSenseError> {
+    Ok(job_manager.lock().unwrap().cancel(&job_id))
 }
 
-// Cleanup expired screenshot cache entries
+// A new capture replaces whatever the chat window was working on
+fn cancel_chat_jobs(app: &tauri::AppHandle) {
+    let cancelled = app.state::<SharedJobManager>().lock().unwrap().cancel_window("main");
+    if !cancelled.is_empty() {
+        println!("🛑 New capture started, cancelled {} running job(s)", cancelled.len());
+    }
+}
+
+// Queued analyses waiting for a connection, oldest first
 #[tauri::command]
-fn cleanup_screenshot_cache(
-    cache: tauri::State<'_, SharedScreenshotCache>
-) -> Result<(), String> {
-    let mut screenshot_cache = cache.lock().unwrap();
-    screenshot_cache.cleanup_expired();
-    println!("🧹 Screenshot cache cleanup completed");
-    Ok(())
+fn list_queued_analyses(
+    analysis_queue: tauri::State<'_, SharedAnalysisQueue>
+) -> Result<Vec<JobSummary>, FrameSenseError> {
+    Ok(analysis_queue.lock().unwrap().list())
 }
 
-// Resize screenshot buffer (for memory optimization)
+// Retry a queued analysis now instead of waiting out its backoff
 #[tauri::command]
-fn resize_screenshot_buffer(
-    new_size_mb: usize,
-    cache: tauri::State<'_, SharedScreenshotCache>
-) -> Result<(), String> {
-    let mut screenshot_cache = cache.lock().unwrap();
-    let new_size_bytes = new_size_mb * 1024 * 1024;
-    screenshot_cache.resize_buffer(new_size_bytes);
-    println!("📏 Screenshot buffer resized to {}MB", new_size_mb);
+fn retry_queued_analysis(
+    job_id: String,
+    analysis_queue: tauri::State<'_, SharedAnalysisQueue>
+) -> Result<JobSummary, FrameSenseError> {
+    analysis_queue.lock().unwrap().retry(&job_id).map_err(FrameSenseError::from)
+}
+
+// Drop a queued analysis and its capture
+#[tauri::command]
+fn cancel_queued_analysis(
+    app: tauri::AppHandle,
+    job_id: String,
+    analysis_queue: tauri::State<'_, SharedAnalysisQueue>
+) -> Result<(), FrameSenseError> {
+    let mut queue = analysis_queue.lock().unwrap();
+    queue.cancel(&job_id)?;
+    // Abort the retry too if it's in flight right now
+    app.state::<SharedJobManager>().lock().unwrap().cancel(&job_id);
+    let _ = app.emit("analysis-queue-updated", queue.list());
     Ok(())
 }
 
-// 🚀 AUTHENTICATION COMMANDS
+fn schema_store() -> SchemaStore {
+    SchemaStore::new(framesense_data_dir().join("schemas"))
+}
 
-// Login user with credentials
+// Built-in and saved extraction schemas
 #[tauri::command]
-async fn login_user(
-    email: String, 
-    password: String, 
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<User, String> {
-    // Clone the auth service to avoid holding the lock across await
-    let service = {
-        let guard = auth_service.lock().unwrap();
-        guard.clone()
-    };
-    service.login_user(email, password).await
+fn list_extraction_schemas() -> Result<Vec<SavedSchema>, FrameSenseError> {
+    Ok(schema_store().list())
 }
 
-// Logout current user
+// Save a JSON Schema for extraction under a name
 #[tauri::command]
-async fn logout_user(
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<(), String> {
-    // Clone the auth service to avoid holding the lock across await
-    let service = {
-        let guard = auth_service.lock().unwrap();
-        guard.clone()
-    };
-    service.logout_user().await
+fn save_extraction_schema(name: String, schema: serde_json::Value) -> Result<SavedSchema, FrameSenseError> {
+    schema_store().save(&name, schema).map_err(FrameSenseError::from)
 }
 
-// Get current logged in user
+// Delete a saved extraction schema
 #[tauri::command]
-async fn get_current_user(
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<Option<User>, String> {
-    // Clone the auth service to avoid holding the lock across await
-    let service = {
-        let guard = auth_service.lock().unwrap();
-        guard.clone()
-    };
-    service.get_current_user().await
+fn delete_extraction_schema(name: String) -> Result<(), FrameSenseError> {
+    schema_store().delete(&name).map_err(FrameSenseError::from)
 }
 
-// Save user session to storage
+// Extract fields from a capture as JSON conforming to a saved schema (`schema_name`) or
+// an ad-hoc one (`schema`). Answers that don't validate are sent back with the errors.
 #[tauri::command]
-async fn save_user_session(
-    user: User,
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<(), String> {
+#[allow(clippy::too_many_arguments)]
+async fn extract_structured(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    image_data: Option<String>,
+    ocr_text: Option<String>,
+    schema_name: Option<String>,
+    schema: Option<serde_json::Value>,
+    model: Option<String>,
+    request_id: Option<String>,
+    auth_service: tauri::State<'_, SharedAuthService>,
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<ExtractionResult, FrameSenseError> {
+    // Saved schemas are checked again: ones saved before a keyword was refused may still use it
+    let schema = match (schema, &schema_name) {
+        (Some(schema), _) => schema,
+        (None, Some(name)) => schema_store().get(name)?.schema,
+        (None, None) => return Err("No schema given for extraction".into()),
+    };
+    extraction::schema::check_schema(&schema)?;
     let service = {
         let guard = auth_service.lock().unwrap();
         guard.clone()
     };
-    service.save_user_session(&user).await
+    // Every attempt streams under the same id, so one cancel_job stops the whole extraction
+    let request_id = request_id.unwrap_or_else(|| {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        format!("extract-{}", millis)
+    });
+    
+    let mut request = AnalysisRequest {
+        question: extraction::extraction_prompt(&schema),
+        image_data,
+        model,
+        ocr_text,
+        history: Vec::new(),
+        captures: Vec::new(),
+        force_refresh: false,
+        region_references: false,
+    };
+    let kind = load_provider_settings(&app).kind;
+    let mut answered_by = None; // Model of the last answered attempt
+    let outcome = async {
+        let mut errors = Vec::new();
+        for attempt in 1..=extraction::MAX_ATTEMPTS {
+            let analysis = run_analysis_as(
+                &app,
+                window.label(),
+                &request,
+                Some(request_id.clone()),
+                service.clone(),
+                model_registry.inner(),
+                AnalysisRun::ExtractionAttempt,
+            )
+            .await?;
+            answered_by = Some(analysis.model.clone());
+            
+            match extraction::check_answer(&schema, &analysis.answer) {
+                Ok(data) => {
+                    println!("✅ Extraction {} valid after {} attempt(s)", request_id, attempt);
+                    return Ok(ExtractionResult {
+                        data,
+                        schema_name: schema_name.clone(),
+                        attempts: attempt,
+                        model: analysis.model,
+                    });
+                },
+                Err(validation_errors) => {
+                    println!("⚠️ Extraction {} attempt {} invalid: {}", request_id, attempt, validation_errors.join("; "));
+                    let _ = app.emit_to(window.label(), "extraction-invalid", serde_json::json!({
+                        "request_id": request_id,
+                        "attempt": attempt,
+                        "errors": validation_errors
+                    }));
+                    
+                    // Show the model its own answer and what's wrong with it
+                    request.history.push(analysis::ChatTurn { role: "user".to_string(), content: request.question.clone() });
+                    request.history.push(analysis::ChatTurn { role: "assistant".to_string(), content: analysis.answer });
+                    request.question = extraction::retry_prompt(&validation_errors);
+                    errors = validation_errors;
+                }
+            }
+        }
+        
+        Err(format!(
+            "Extraction didn't match the schema after {} attempts:\n- {}",
+            extraction::MAX_ATTEMPTS,
+            errors.join("\n- ")
+        )
+        .into())
+    }
+    .await;
+    
+    // However many attempts were answered, the extraction counts as one analysis
+    if let Some(model) = answered_by {
+        record_usage(&app, model_registry.inner(), model, kind);
+    }
+    outcome
 }
 
-// Load user session from storage
+// Export an extraction result as JSON or CSV to a user-chosen path
 #[tauri::command]
-async fn load_user_session(
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<Option<User>, String> {
-    let service = {
-        let guard = auth_service.lock().unwrap();
-        guard.clone()
-    };
-    service.load_user_session().await
+fn export_extraction(
+    data: serde_json::Value,
+    format: ExtractionExport,
+    path: String
+) -> Result<AppResult, FrameSenseError> {
+    let mut target = PathBuf::from(&path);
+    if !target.is_absolute() {
+        return Err(format!("Export path must be absolute: {}", path).into());
+    }
+    if target.extension().is_none() {
+        target.set_extension(format.extension());
+    }
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            return Err(format!("Export folder does not exist: {:?}", parent).into());
+        }
+    }
+    
+    let bytes = extraction::export(&data, format)?;
+    fs::write(&target, &bytes).map_err(|e| format!("Failed to write export: {}", e))?;
+    
+    println!("✅ Exported extraction ({:?}) to {:?}", format, target);
+    Ok(AppResult {
+        success: true,
+        message: format!("Exported to {}", target.display()),
+    })
 }
 
-// Handle payment success from deep link
+// Get the analysis provider settings (the API key itself never leaves Rust)
 #[tauri::command]
-async fn handle_payment_success(
-    token: String, 
-    plan: String, 
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<User, String> {
-    // Clone the auth service to avoid holding the lock across await
-    let service = {
-        let guard = auth_service.lock().unwrap();
-        guard.clone()
-    };
-    service.handle_payment_success(token, plan).await
+fn get_analysis_provider(app: tauri::AppHandle) -> Result<serde_json::Value, FrameSenseError> {
+    let settings = load_provider_settings(&app);
+    Ok(serde_json::json!({
+        "kind": settings.kind,
+        "base_url": settings.base_url,
+        "model": settings.model,
+        "has_api_key": settings.api_key.is_some()
+    }))
 }
 
-// Get available models for user tier
+// Switch between the FrameSense backend and an OpenAI-compatible endpoint.
+// `api_key: None` keeps the stored key, an empty string removes it.
 #[tauri::command]
-fn get_available_models(
-    user_tier: String,
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<Vec<String>, String> {
-    println!("🔍 DEBUG: get_available_models called for tier: {}", user_tier);
+fn set_analysis_provider(
+    kind: ProviderKind,
+    base_url: Option<String>,
+    model: Option<String>,
+    api_key: Option<String>,
+    secret_store: tauri::State<'_, SharedSecretStore>
+) -> Result<AppResult, FrameSenseError> {
+    let path = analysis_provider_path();
+    let current = ProviderSettings::load(&path, secret_store.as_ref());
     
-    let service = auth_service.lock().unwrap();
-    let raw_models = service.get_available_models(&user_tier);
-    let models: Vec<String> = raw_models
-        .iter()
-        .map(|&s| s.to_string())
-        .collect();
+    let settings = ProviderSettings {
+        kind,
+        base_url: base_url.unwrap_or(current.base_url).trim().to_string(),
+        model: model.unwrap_or(current.model).trim().to_string(),
+        api_key: match api_key {
+            None => current.api_key,
+            Some(key) if key.trim().is_empty() => None,
+            Some(key) => Some(key.trim().to_string()),
+        },
+    };
     
-    println!("✅ DEBUG: get_available_models returning {} models: {:?}", models.len(), models);
-    Ok(models)
+    // Validate before saving so a typo doesn't break every later analysis
+    if kind == ProviderKind::OpenAiCompatible {
+        analysis::OpenAiCompatibleClient::new(&settings.base_url, &settings.model)?;
+    }
+    settings.save(&path, secret_store.as_ref())?;
+    
+    println!("🔧 Analysis provider set to {:?} {}", settings.kind, settings.base_url);
+    Ok(AppResult {
+        success: true,
+        message: format!("Analysis provider set to {:?}", settings.kind),
+    })
 }
 
 // Check if user can use specific model
@@ -820,20 +2331,67 @@ fn get_available_models(
 fn can_use_model(
     user_tier: String,
     model: String,
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<bool, String> {
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<bool, FrameSenseError> {
     println!("🔍 DEBUG: can_use_model called - tier: '{}', model: '{}'", user_tier, model);
     
-    let service = auth_service.lock().unwrap();
-    let can_use = service.can_use_model(&user_tier, &model);
+    let registry = model_registry.lock().unwrap();
+    let can_use = registry.can_use_model(&user_tier, &model);
     
     println!("✅ DEBUG: can_use_model result: {} (tier: '{}', model: '{}')", can_use, user_tier, model);
     Ok(can_use)
 }
 
+// can_use_model plus what the capture(s) would cost in image tokens on that model,
+// so the picker can show the price before anything is sent
+#[tauri::command]
+async fn check_model_for_capture(
+    user_tier: String,
+    model: String,
+    image_data: Option<String>,
+    captures: Option<Vec<String>>,
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<serde_json::Value, FrameSenseError> {
+    let (allowed, entry) = {
+        let registry = model_registry.lock().unwrap();
+        (registry.can_use_model(&user_tier, &model), registry.find(&model).cloned())
+    };
+    let Some(entry) = entry else {
+        return Err(format!("Unknown model: {}", model).into());
+    };
+    
+    let request = AnalysisRequest {
+        question: String::new(),
+        image_data,
+        model: Some(model),
+        ocr_text: None,
+        history: Vec::new(),
+        captures: captures
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(index, image_data)| analysis::LabeledCapture {
+                label: format!("Capture {}", index + 1),
+                image_data,
+            })
+            .collect(),
+        force_refresh: false,
+        region_references: false,
+    };
+    let estimate = tauri::async_runtime::spawn_blocking(move || analysis::images::estimate(&request, &entry))
+        .await
+        .map_err(|e| format!("Image cost estimate failed: {}", e))?;
+    
+    Ok(serde_json::json!({
+        "allowed": allowed,
+        "estimate": estimate.as_ref().ok(),
+        "problem": estimate.err()
+    }))
+}
+
 // Test deep link functionality (for development)
 #[tauri::command]
-async fn test_deep_link(app: tauri::AppHandle, token: String, plan: String) -> Result<(), String> {
+async fn test_deep_link(app: tauri::AppHandle, token: String, plan: String) -> Result<(), FrameSenseError> {
     println!("🧪 Testing deep link with token: {} and plan: {}", token, plan);
     
     // Emit payment success event for testing
@@ -849,19 +2407,22 @@ async fn test_deep_link(app: tauri::AppHandle, token: String, plan: String) -> R
 // Verify payment status and update user tier
 #[tauri::command]
 async fn verify_payment_status(
+    app: tauri::AppHandle,
     auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<Option<User>, String> {
+) -> Result<Option<User>, FrameSenseError> {
     println!("🔄 Verifying payment status with backend...");
     
     let service = {
         let guard = auth_service.lock().unwrap();
         guard.clone()
     };
+    // Refresh a token near expiry first, so the check below doesn't run on a dying session
+    current_session(&app, &service, false).await?;
     
     match service.verify_payment_and_update().await {
         Ok(Some(user)) => {
             println!("✅ Payment verification successful: {} ({})", user.email, user.tier);
-            Ok(Some(user))
+            Ok(Some(reconcile_usage(&app, user)))
         },
         Ok(None) => {
             println!("ℹ️ No current session found");
@@ -878,7 +2439,7 @@ async fn verify_payment_status(
 #[tauri::command]
 async fn clear_user_session(
     auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<(), String> {
+) -> Result<(), FrameSenseError> {
     println!("🗑️ Clearing local user session...");
     
     let service = {
@@ -891,22 +2452,148 @@ async fn clear_user_session(
     Ok(())
 }
 
+// Full model registry, with per-model availability when a tier is given
+#[tauri::command]
+fn get_model_registry(
+    user_tier: Option<String>,
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<serde_json::Value, FrameSenseError> {
+    let registry = model_registry.lock().unwrap();
+    let models: Vec<serde_json::Value> = registry
+        .models()
+        .iter()
+        .map(|model| {
+            let mut entry = serde_json::to_value(model).unwrap_or_default();
+            if let Some(tier) = &user_tier {
+                entry["available"] = serde_json::json!(registry.can_use_model(tier, &model.id));
+            }
+            entry
+        })
+        .collect();
+    
+    Ok(serde_json::json!({
+        "source": registry.source(),
+        "fetched_at": registry.fetched_at(),
+        "tiers": registry.tiers(),
+        "models": models,
+        "daily_limit": user_tier.as_ref().map(|tier| registry.daily_limit(tier))
+    }))
+}
+
+// Re-fetch the model registry from the backend now instead of waiting for the daily refresh
+#[tauri::command]
+async fn refresh_model_registry(
+    auth_service: tauri::State<'_, SharedAuthService>,
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<AppResult, FrameSenseError> {
+    let api_url = auth_service.lock().unwrap().api_url().to_string();
+    let data = ModelRegistry::fetch(&api_url).await?;
+    let model_count = data.models.len();
+    model_registry.lock().unwrap().apply_remote(data);
+    
+    Ok(AppResult {
+        success: true,
+        message: format!("Model registry updated ({} models)", model_count),
+    })
+}
+
+// Clear the persisted analysis answer cache
+#[tauri::command]
+fn clear_analysis_cache(response_cache: tauri::State<'_, SharedResponseCache>) -> Result<(), FrameSenseError> {
+    response_cache.lock().unwrap().clear();
+    Ok(())
+}
+
+// Get analysis answer cache statistics
+#[tauri::command]
+fn get_analysis_cache_stats(response_cache: tauri::State<'_, SharedResponseCache>) -> Result<serde_json::Value, FrameSenseError> {
+    let (total_entries, total_size, hits, misses) = response_cache.lock().unwrap().get_cache_stats();
+    println!("📊 Analysis cache stats: {} entries, {}KB, {} hits / {} misses", 
+             total_entries, total_size / 1024, hits, misses);
+    Ok(serde_json::json!({
+        "total_entries": total_entries,
+        "total_size_bytes": total_size,
+        "session_hits": hits,
+        "session_misses": misses
+    }))
+}
+
+// The backend environment this instance talks to, for the frontend's own API calls
+#[tauri::command]
+fn get_backend_environment(auth_service: tauri::State<'_, SharedAuthService>) -> Result<BackendEnvironment, FrameSenseError> {
+    Ok(auth_service.lock().unwrap().environment().clone())
+}
+
+// What this instance is running against, for support and bug reports
+#[tauri::command]
+fn get_diagnostics(
+    app: tauri::AppHandle,
+    auth_service: tauri::State<'_, SharedAuthService>,
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<serde_json::Value, FrameSenseError> {
+    let (environment, secret_store) = {
+        let service = auth_service.lock().unwrap();
+        (service.environment().clone(), service.secret_store_backend())
+    };
+    let registry_source = model_registry.lock().unwrap().source();
+    let provider = load_provider_settings(&app);
+    let ocr_engine = with_ocr_service(|service| Ok(service.settings().engine.clone())).ok();
+
+    Ok(serde_json::json!({
+        "version": app.package_info().version.to_string(),
+        "platform": std::env::consts::OS,
+        "environment": environment,
+        "analysis_provider": provider.kind,
+        "model_registry_source": registry_source,
+        "secret_store": secret_store,
+        "ocr_engine": ocr_engine,
+        "data_dir": framesense_data_dir()
+    }))
+}
+
+// Today's usage against the signed-in user's daily limit
+#[tauri::command]
+async fn get_usage_stats(
+    auth_service: tauri::State<'_, SharedAuthService>,
+    model_registry: tauri::State<'_, SharedModelRegistry>,
+    usage_meter: tauri::State<'_, SharedUsageMeter>
+) -> Result<UsageSnapshot, FrameSenseError> {
+    let service = {
+        let guard = auth_service.lock().unwrap();
+        guard.clone()
+    };
+    let user_tier = service
+        .load_user_session()
+        .await?
+        .map(|user| user.tier)
+        .unwrap_or_else(|| "free".to_string());
+    
+    let limit = model_registry.lock().unwrap().daily_limit(&user_tier);
+    let snapshot = usage_meter.lock().unwrap().snapshot(limit);
+    Ok(snapshot)
+}
+
 // Debug: Test model access for a tier
 #[tauri::command]
 fn debug_test_tier_models(
     tier: String,
-    auth_service: tauri::State<'_, SharedAuthService>
-) -> Result<serde_json::Value, String> {
-    let service = auth_service.lock().unwrap();
-    let models = service.get_available_models(&tier);
+    model_registry: tauri::State<'_, SharedModelRegistry>
+) -> Result<serde_json::Value, FrameSenseError> {
+    let registry = model_registry.lock().unwrap();
+    let models: Vec<&str> = registry
+        .available_models(&tier)
+        .iter()
+        .map(|model| model.display_name.as_str())
+        .collect();
     
     let result = serde_json::json!({
         "tier": tier,
         "available_models": models,
         "model_count": models.len(),
-        "can_use_gpt4o": service.can_use_model(&tier, "GPT-4o"),
-        "can_use_gpt4o_mini": service.can_use_model(&tier, "GPT-4o-mini"),
-        "can_use_claude_haiku": service.can_use_model(&tier, "Claude 3 Haiku")
+        "daily_limit": registry.daily_limit(&tier),
+        "can_use_gpt4o": registry.can_use_model(&tier, "GPT-4o"),
+        "can_use_gpt4o_mini": registry.can_use_model(&tier, "GPT-4o-mini"),
+        "can_use_claude_haiku": registry.can_use_model(&tier, "Claude 3 Haiku")
     });
     
     println!("🧪 DEBUG: Tier {} model access: {}", tier, result);
@@ -919,7 +2606,7 @@ fn debug_test_tier_models(
 
 // Get window position for coordinate conversion
 #[tauri::command]
-async fn get_window_position(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+async fn get_window_position(app: tauri::AppHandle) -> Result<serde_json::Value, FrameSenseError> {
     if let Some(window) = app.get_webview_window("main") {
         match window.outer_position() {
             Ok(position) => {
@@ -932,11 +2619,11 @@ async fn get_window_position(app: tauri::AppHandle) -> Result<serde_json::Value,
             },
             Err(e) => {
                 println!("❌ Failed to get window position: {}", e);
-                Err(format!("Failed to get window position: {}", e))
+                Err(format!("Failed to get window position: {}", e).into())
             }
         }
     } else {
-        Err("Main window not found".to_string())
+        Err("Main window not found".into())
     }
 }
 
@@ -947,7 +2634,7 @@ async fn save_app_state(
     bounds: Option<CaptureBounds>,
     app: tauri::AppHandle,
     state: tauri::State<'_, SharedState>
-) -> Result<(), String> {
+) -> Result<(), FrameSenseError> {
     println!("💾 Saving app state...");
     
     // Update in-memory state
@@ -995,7 +2682,9 @@ async fn save_app_state(
 
 // Create transparent overlay window using React (not HTML)
 #[tauri::command]
-async fn create_transparent_overlay(app: tauri::AppHandle) -> Result<(), String> {
+async fn create_transparent_overlay(app: tauri::AppHandle) -> Result<(), FrameSenseError> {
+    cancel_chat_jobs(&app);
+    
     // Close existing overlay if it exists
     if let Some(existing) = app.get_webview_window("overlay") {
         println!("🗑️ Closing existing React overlay window...");
@@ -1056,7 +2745,7 @@ async fn create_transparent_overlay(app: tauri::AppHandle) -> Result<(), String>
 
 // Close transparent overlay window
 #[tauri::command]
-async fn close_transparent_overlay(app: tauri::AppHandle) -> Result<(), String> {
+async fn close_transparent_overlay(app: tauri::AppHandle) -> Result<(), FrameSenseError> {
     if let Some(overlay) = app.get_webview_window("overlay") {
         match overlay.close() {
             Ok(_) => {
@@ -1065,12 +2754,12 @@ async fn close_transparent_overlay(app: tauri::AppHandle) -> Result<(), String>
             },
             Err(e) => {
                 println!("❌ Failed to close React overlay: {}", e);
-                Err(format!("Failed to close React overlay: {}", e))
+                Err(format!("Failed to close React overlay: {}", e).into())
             }
         }
     } else {
         println!("❌ React overlay window not found");
-        Err("React overlay window not found".to_string())
+        Err("React overlay window not found".into())
     }
 }
 
@@ -1081,8 +2770,9 @@ async fn close_transparent_overlay(app: tauri::AppHandle) -> Result<(), String>
 async fn create_transparent_overlay_optimized(
     app: tauri::AppHandle,
     overlay_manager: tauri::State<'_, SharedOverlayManager>
-) -> Result<(), String> {
+) -> Result<(), FrameSenseError> {
     println!("🎯 Creating optimized overlay and hiding main window...");
+    cancel_chat_jobs(&app);
     
     // 🔧 HIDE main window during capture mode
     if let Some(main_window) = app.get_webview_window("main") {
@@ -1093,7 +2783,7 @@ async fn create_transparent_overlay_optimized(
     }
     
     let mut manager = overlay_manager.lock().unwrap();
-    manager.show_selection_overlay(&app)
+    manager.show_selection_overlay(&app).map_err(FrameSenseError::from)
 }
 
 // Close optimized overlay using OverlayManager
@@ -1101,7 +2791,7 @@ async fn create_transparent_overlay_optimized(
 async fn close_transparent_overlay_optimized(
     app: tauri::AppHandle,
     overlay_manager: tauri::State<'_, SharedOverlayManager>
-) -> Result<(), String> {
+) -> Result<(), FrameSenseError> {
     println!("🎯 Closing optimized overlay and showing main window...");
     
     let mut manager = overlay_manager.lock().unwrap();
@@ -1121,68 +2811,86 @@ async fn close_transparent_overlay_optimized(
         }
     }
     
-    result
+    result.map_err(FrameSenseError::from)
 }
 
-// Process screen selection with React overlay and optimized capture
+// Process screen selection with React overlay: the selection pipeline captures the area
+// and the main window gets it as a `selection-result` event
 #[tauri::command]
 async fn process_screen_selection_optimized(
     app: tauri::AppHandle, 
     bounds: CaptureBounds,
     overlay_manager: tauri::State<'_, SharedOverlayManager>,
-    screenshot_cache: tauri::State<'_, SharedScreenshotCache>
-) -> Result<(), String> {
+    pipeline_store: tauri::State<'_, SharedPipelineStore>
+) -> Result<(), FrameSenseError> {
     println!("📸 Processing optimized screen selection: {}x{} at ({}, {})", 
              bounds.width, bounds.height, bounds.x, bounds.y);
     
-    // Use optimized capture with caching
-    let capture_result = capture_screen_area_optimized(bounds.clone(), screenshot_cache)?;
+    let pipeline = pipeline_store.lock().unwrap().get(pipeline::SELECTION_PIPELINE)?;
+    let request_id = format!(
+        "selection-{}",
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+    );
+    let mut host = AppPipelineHost::new(&app, "main", request_id);
+    host.selection = Some(bounds.clone());
     
-    if capture_result.success && capture_result.image_data.is_some() {
-        let image_data = capture_result.image_data.unwrap();
-        println!("✅ Optimized screen capture successful!");
-        
-        // Send result to React with detailed logging
-        if let Some(window) = app.get_webview_window("main") {
-            let analysis_result = serde_json::json!({
-                "type": "image",
-                "bounds": bounds,
-                "imageData": image_data,
-                "text": null,
-                "success": true,
-                "message": "Optimized screen area captured successfully!"
-            });
-            
-            println!("📤 Emitting 'selection-result' event to main window...");
-            println!("📊 Event payload: type={}, bounds={}x{} at ({},{}), imageSize={}KB", 
-                     "image", bounds.width, bounds.height, bounds.x, bounds.y, 
-                     image_data.len() / 1024);
-            
-            match window.emit("selection-result", analysis_result) {
-                Ok(_) => {
-                    println!("✅ Event emitted successfully to main window!");
-                },
-                Err(e) => {
-                    println!("❌ Failed to emit event to main window: {}", e);
-                }
-            }
-        } else {
-            println!("❌ No main window found to emit event to!");
+    // Only the capture stage leaves the image as the cache encoded it; any other stage
+    // may have changed it, so those pipelines get their final image encoded again
+    let capture_only = pipeline.stages.iter().all(|stage| matches!(stage, pipeline::StageConfig::Capture(_)));
+    let captured = tauri::async_runtime::spawn_blocking(move || {
+        let mut context = PipelineContext::default();
+        let report = pipeline.run(&mut context, &host, pipeline_artifacts_root(&pipeline, false).as_deref());
+        let cached_png = host.selection_png.lock().unwrap().take();
+        match (report.error, context.image, cached_png) {
+            (Some(error), _, _) => Err(error),
+            (None, Some(_), Some(png)) if capture_only => Ok(png),
+            (None, Some(image), _) => pipeline::stages::encode_png(&image).map_err(FrameSenseError::from),
+            (None, None, _) => Err(format!("Pipeline '{}' produced no image", pipeline.id).into()),
         }
+    })
+    .await
+    .map_err(|e| format!("Selection pipeline task failed: {}", e))?;
+    
+    let image_data = captured.inspect_err(|error| println!("❌ Optimized capture failed: {}", error))?;
+    println!("✅ Optimized screen capture successful!");
+    
+    // Send result to React with detailed logging
+    if let Some(window) = app.get_webview_window("main") {
+        let analysis_result = serde_json::json!({
+            "type": "image",
+            "bounds": bounds,
+            "imageData": image_data,
+            "text": null,
+            "success": true,
+            "message": "Optimized screen area captured successfully!"
+        });
         
-        // Hide overlay using optimized manager
-        let _ = close_transparent_overlay_optimized(app, overlay_manager);
+        println!("📤 Emitting 'selection-result' event to main window...");
+        println!("📊 Event payload: type={}, bounds={}x{} at ({},{}), imageSize={}KB", 
+                 "image", bounds.width, bounds.height, bounds.x, bounds.y, 
+                 image_data.len() / 1024);
         
+        match window.emit("selection-result", analysis_result) {
+            Ok(_) => {
+                println!("✅ Event emitted successfully to main window!");
+            },
+            Err(e) => {
+                println!("❌ Failed to emit event to main window: {}", e);
+            }
+        }
     } else {
-        println!("❌ Optimized capture failed: {}", capture_result.message);
+        println!("❌ No main window found to emit event to!");
     }
     
+    // Hide overlay using optimized manager
+    let _ = close_transparent_overlay_optimized(app, overlay_manager);
+    
     Ok(())
 }
 
 // Cleanup old overlays periodically
 #[tauri::command]
-fn cleanup_overlay_manager(overlay_manager: tauri::State<'_, SharedOverlayManager>) -> Result<(), String> {
+fn cleanup_overlay_manager(overlay_manager: tauri::State<'_, SharedOverlayManager>) -> Result<(), FrameSenseError> {
     println!("🗑️ Running overlay cleanup...");
     
     let mut manager = overlay_manager.lock().map_err(|e| format!("Failed to lock overlay manager: {}", e))?;
@@ -1196,7 +2904,7 @@ fn cleanup_overlay_manager(overlay_manager: tauri::State<'_, SharedOverlayManage
 
 // Resize main window for chat expansion/contraction
 #[tauri::command]
-async fn resize_window(app: tauri::AppHandle, width: f64, height: f64) -> Result<(), String> {
+async fn resize_window(app: tauri::AppHandle, width: f64, height: f64) -> Result<(), FrameSenseError> {
     println!("📏 Resizing main window to {}x{}", width, height);
     
     if let Some(window) = app.get_webview_window("main") {
@@ -1207,12 +2915,12 @@ async fn resize_window(app: tauri::AppHandle, width: f64, height: f64) -> Result
             },
             Err(e) => {
                 println!("❌ Failed to resize window: {}", e);
-                Err(format!("Failed to resize window: {}", e))
+                Err(format!("Failed to resize window: {}", e).into())
             }
         }
     } else {
         println!("❌ Main window not found for resize");
-        Err("Main window not found".to_string())
+        Err("Main window not found".into())
     }
 }
 
@@ -1221,7 +2929,7 @@ async fn resize_window(app: tauri::AppHandle, width: f64, height: f64) -> Result
 
 // 🔧 DEBUG COMMAND - Get detailed coordinate info
 #[tauri::command]
-async fn debug_coordinates(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+async fn debug_coordinates(app: tauri::AppHandle) -> Result<serde_json::Value, FrameSenseError> {
     let mut debug_info = serde_json::Map::new();
     
     // Main window info
@@ -1268,7 +2976,7 @@ async fn debug_coordinates(app: tauri::AppHandle) -> Result<serde_json::Value, S
 
 // 🔧 TEST COMMAND - Position ChatBox at specific coordinates
 #[tauri::command]
-async fn test_chatbox_position(app: tauri::AppHandle, x: f64, y: f64) -> Result<(), String> {
+async fn test_chatbox_position(app: tauri::AppHandle, x: f64, y: f64) -> Result<(), FrameSenseError> {
     println!("🧪 Testing ChatBox position at ({}, {})", x, y);
     
     // Close existing chatbox if it exists
@@ -1338,7 +3046,7 @@ async fn test_chatbox_position(app: tauri::AppHandle, x: f64, y: f64) -> Result<
 
 // Create new main window on current Space (like Raycast/Spotlight)
 #[tauri::command]
-async fn create_main_window(app: tauri::AppHandle) -> Result<(), String> {
+async fn create_main_window(app: tauri::AppHandle) -> Result<(), FrameSenseError> {
     // Close existing window if it exists
     if let Some(existing) = app.get_webview_window("main") {
         let _ = existing.close();
@@ -1384,7 +3092,7 @@ async fn create_main_window(app: tauri::AppHandle) -> Result<(), String> {
 
 // 🔧 MOVE WINDOW COMMAND - Move window to correct Y position
 #[tauri::command]
-async fn move_window_to_position(app: tauri::AppHandle) -> Result<(), String> {
+async fn move_window_to_position(app: tauri::AppHandle) -> Result<(), FrameSenseError> {
     use std::fs;
     use std::path::PathBuf;
 
@@ -1434,19 +3142,19 @@ async fn move_window_to_position(app: tauri::AppHandle) -> Result<(), String> {
             },
             Err(e) => {
                 println!("❌ Failed to move window: {}", e);
-                Err(format!("Failed to move window: {}", e))
+                Err(format!("Failed to move window: {}", e).into())
             }
         }
     } else {
         println!("❌ Main window not found for repositioning");
-        Err("Main window not found".to_string())
+        Err("Main window not found".into())
     }
 }
 
 #[tauri::command]
 async fn get_app_state(
     state: tauri::State<'_, SharedState>
-) -> Result<AppState, String> {
+) -> Result<AppState, FrameSenseError> {
     let app_state = state.lock().unwrap().clone();
     println!("📖 App state retrieved");
     Ok(app_state)
@@ -1466,12 +3174,51 @@ fn main() {
     let shared_screenshot_cache: SharedScreenshotCache = Arc::new(Mutex::new(ScreenshotCache::new()));
     
     // Initialize authentication service with storage path
-    let app_data_dir = dirs::home_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
-        .join(".framesense");
-    let auth_service = AuthService::new().with_storage_path(app_data_dir);
+    // Backend environment from --env, FRAMESENSE_ENV or environment.json; production by default
+    let backend_environment = match BackendEnvironment::from_startup(&framesense_data_dir().join("environment.json")) {
+        Ok(environment) => environment,
+        Err(e) => {
+            println!("❌ {}", e);
+            std::process::exit(2);
+        },
+    };
+    let app_data_dir = framesense_data_dir();
+    let shared_secret_store: SharedSecretStore = secrets::open(&app_data_dir);
+    let auth_service = AuthService::for_environment(backend_environment.clone()).with_storage_path(app_data_dir, shared_secret_store.clone());
     let shared_auth_service: SharedAuthService = Arc::new(Mutex::new(auth_service));
     
+    // Model registry: last fetched copy from disk, bundled registry until the first fetch
+    let model_registry = ModelRegistry::load(framesense_data_dir().join(backend_environment.scoped_file_name("model_registry.json")));
+    let shared_model_registry: SharedModelRegistry = Arc::new(Mutex::new(model_registry));
+    
+    // Usage ledger survives restarts so the daily limit can't be reset by relaunching
+    let shared_usage_meter: SharedUsageMeter =
+        Arc::new(Mutex::new(UsageMeter::load(framesense_data_dir().join(backend_environment.scoped_file_name("usage.json")))));
+    
+    // Answers to repeated questions, kept for a day
+    let shared_response_cache: SharedResponseCache =
+        Arc::new(Mutex::new(ResponseCache::load(framesense_data_dir().join(backend_environment.scoped_file_name("analysis_cache.json")))));
+    
+    // Analyses queued while offline are picked up again after a restart
+    let shared_analysis_queue: SharedAnalysisQueue =
+        Arc::new(Mutex::new(AnalysisQueue::load(framesense_data_dir().join(backend_environment.scoped_file_name("queue")))));
+    
+    // Conversation threads persist under the app data dir
+    let shared_conversation_store: SharedConversationStore =
+        Arc::new(Mutex::new(ConversationStore::new(framesense_data_dir().join("threads"))));
+    
+    // Quick actions, seeded with the built-in ones on first run
+    let shared_action_store: SharedActionStore = Arc::new(Mutex::new(ActionStore::load(quick_actions_path())));
+    
+    // Running analysis and OCR jobs, so they can be cancelled
+    let shared_job_manager: SharedJobManager = Arc::new(Mutex::new(JobManager::new()));
+    
+    // Initialize live OCR session manager
+    let shared_live_ocr_manager: SharedLiveOcrManager = Arc::new(Mutex::new(LiveOcrManager::new()));
+    
+    // Processing pipelines, built-ins plus the user's own from pipelines.json
+    let shared_pipeline_store: SharedPipelineStore = Arc::new(Mutex::new(PipelineStore::load(pipelines_path())));
+    
     // Database access through backend API only - no direct connection
     
     // Build Tauri application with plugins
@@ -1485,7 +3232,42 @@ fn main() {
         .manage(shared_permission_cache)
         .manage(shared_screenshot_cache)
         .manage(shared_auth_service)
+        .manage(shared_secret_store)
+        .manage(shared_model_registry)
+        .manage(shared_usage_meter)
+        .manage(shared_response_cache)
+        .manage(shared_analysis_queue)
+        .manage(shared_job_manager)
+        .manage(shared_conversation_store)
+        .manage(shared_action_store)
+        .manage(shared_live_ocr_manager)
+        .manage(shared_pipeline_store)
         .setup(move |app| {
+            // Refresh the model registry from the backend at most once a day
+            let registry = app.state::<SharedModelRegistry>().inner().clone();
+            let api_url = app.state::<SharedAuthService>().lock().unwrap().api_url().to_string();
+            tauri::async_runtime::spawn(async move {
+                if !registry.lock().unwrap().needs_refresh(std::time::Duration::from_secs(24 * 60 * 60)) {
+                    return;
+                }
+                match ModelRegistry::fetch(&api_url).await {
+                    Ok(data) => registry.lock().unwrap().apply_remote(data),
+                    Err(e) => {
+                        let source = registry.lock().unwrap().source();
+                        println!("⚠️ Model registry refresh failed, keeping {:?} registry: {}", source, e);
+                    }
+                }
+            });
+            
+            // Retry queued analyses in the background
+            tauri::async_runtime::spawn(process_analysis_queue(app.handle().clone()));
+            
+            // Keep the session token refreshed ahead of expiry
+            tauri::async_runtime::spawn(keep_session_fresh(app.handle().clone()));
+            
+            // Write cache changes to disk in the background
+            tauri::async_runtime::spawn(flush_caches_periodically(app.handle().clone()));
+            
             // Set up system tray
             let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
             let menu = Menu::with_items(app, &[&quit])?;
@@ -1594,6 +3376,10 @@ fn main() {
             });
             
             println!("✅ Global shortcut event handler set up successfully");
+            
+            // Bind quick action shortcuts
+            let action_store = app.state::<SharedActionStore>().inner().clone();
+            sync_action_hotkeys(&app_handle, &[], action_store.lock().unwrap().actions());
 
             Ok(())
         })
@@ -1605,6 +3391,15 @@ fn main() {
             test_ocr,
             run_ocr_verification,
             extract_text_ocr,
+            extract_text_entities,
+            run_entity_action,
+            clear_ocr_cache,
+            get_ocr_cache_stats,
+            export_ocr_result,
+            start_live_ocr,
+            stop_live_ocr,
+            pause_live_ocr,
+            list_live_ocr_sessions,
             check_permissions,
             test_screen_capture,
             capture_screen_area,
@@ -1631,17 +3426,59 @@ fn main() {
             resize_screenshot_buffer,
             // Authentication commands
             login_user,
+            login_with_browser,
             logout_user,
             get_current_user,
             save_user_session,
             load_user_session,
             handle_payment_success,
+            get_session_status,
+            refresh_session,
             get_available_models,
             can_use_model,
+            check_model_for_capture,
+            analyze_capture,
+            get_analysis_provider,
+            set_analysis_provider,
+            send_thread_message,
+            compare_captures,
+            list_threads,
+            resume_thread,
+            rename_thread,
+            delete_thread,
+            list_quick_actions,
+            save_quick_action,
+            delete_quick_action,
+            export_quick_actions,
+            import_quick_actions,
+            run_quick_action,
+            ground_answer,
+            list_pipelines,
+            save_pipeline,
+            delete_pipeline,
+            run_pipeline,
+            run_pipeline_batch,
+            list_queued_analyses,
+            retry_queued_analysis,
+            cancel_queued_analysis,
+            list_jobs,
+            cancel_job,
+            list_extraction_schemas,
+            save_extraction_schema,
+            delete_extraction_schema,
+            extract_structured,
+            export_extraction,
             test_deep_link,
             verify_payment_status,
             clear_user_session,
             debug_test_tier_models,
+            get_model_registry,
+            refresh_model_registry,
+            get_usage_stats,
+            clear_analysis_cache,
+            get_analysis_cache_stats,
+            get_backend_environment,
+            get_diagnostics,
             // Local session management commands
             // save_user_session_local, // Removed as per edit hint
             // load_user_session_local, // Removed as per edit hint
@@ -1663,6 +3500,9 @@ fn main() {
         ])
         .on_window_event(|window, event| match event {
             WindowEvent::CloseRequested { api, .. } => {
+                // Nobody is left to read the answers of a closed (or hidden) window
+                window.state::<SharedJobManager>().lock().unwrap().cancel_window(window.label());
+                
                 // Only prevent close for overlay windows, let main window close normally
                 if window.label() == "main" {
                     // Let main window close normally for Raycast-style behavior
@@ -1677,7 +3517,7 @@ fn main() {
         })
         .build(tauri::generate_context!())
         .expect("error while running tauri application")
-        .run(|_app_handle, event| {
+        .run(|app_handle, event| {
             match event {
                 RunEvent::Ready => {
                     println!("🎯 App ready!");
@@ -1686,6 +3526,11 @@ fn main() {
                     // Prevent app from closing when last window closes
                     api.prevent_exit();
                 }
+                RunEvent::Exit => {
+                    // Don't leave capture loops running while the runtime shuts down
+                    app_handle.state::<SharedLiveOcrManager>().lock().unwrap().stop_all();
+                    flush_caches(app_handle);
+                }
                 _ => {}
             }
         });
@@ -0,0 +1,382 @@
+// Model registry - which models exist, what they can do and which tier unlocks them.
+// Fetched from the backend and cached on disk, with a bundled copy as the fallback,
+// so new models and price changes don't need an app release.
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BUNDLED_REGISTRY: &str = include_str!("../resources/model_registry.json");
+const REGISTRY_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub id: String,           // API id, e.g. "gpt-4o-mini"
+    pub display_name: String, // Name shown in the model picker, e.g. "GPT-4o-mini"
+    pub provider: String,
+    pub required_tier: String,
+    pub vision: bool,
+    pub context_window: u32,
+    pub max_image_size: u32, // Longest image side in pixels the model accepts, 0 without vision
+    #[serde(default = "default_max_images")]
+    pub max_images: u32, // Images per request, 0 without vision
+    #[serde(default)]
+    pub image_pricing: Option<ImagePricing>, // How the provider bills image input, None when unknown
+    pub cost_per_call: f64,  // Estimated USD per analysis
+}
+
+// Image input pricing schemes used by the vision providers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ImagePricing {
+    // Billed per `tile_size` square after the short side is scaled down to
+    // `short_side` (0 = no such step), plus a fixed base (OpenAI, Gemini)
+    Tiles {
+        tile_size: u32,
+        tokens_per_tile: u32,
+        base_tokens: u32,
+        #[serde(default)]
+        short_side: u32,
+    },
+    // Billed by pixel count (Anthropic)
+    Area { pixels_per_token: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierEntry {
+    pub name: String,
+    pub rank: u32,        // Higher tiers include every model of the lower ones
+    pub daily_limit: i32, // -1 = unlimited
+    #[serde(default)]
+    pub fallback_chain: Vec<String>, // Models tried in order when the chosen one fails
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryData {
+    pub version: u32,
+    pub default_daily_limit: i32, // For tiers the registry doesn't know
+    pub tiers: Vec<TierEntry>,
+    pub models: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrySource {
+    Bundled,
+    Cache,
+    Remote,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedRegistry {
+    fetched_at: u64,
+    data: RegistryData,
+}
+
+pub struct ModelRegistry {
+    data: RegistryData,
+    source: RegistrySource,
+    fetched_at: Option<u64>,
+    cache_path: Option<PathBuf>,
+}
+
+// Registries from before multi-image requests existed
+fn default_max_images() -> u32 {
+    1
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// "GPT-4o-mini", "gpt-4o-mini" and "gpt 4o mini" all compare equal
+fn model_key(model: &str) -> String {
+    model
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '.')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl ImagePricing {
+    // Tokens for one image of this size, as sent (after any downscaling)
+    pub fn tokens(&self, width: u32, height: u32) -> u32 {
+        match *self {
+            ImagePricing::Tiles { tile_size, tokens_per_tile, base_tokens, .. } => {
+                let tile_size = tile_size.max(1);
+                let tiles = width.div_ceil(tile_size) * height.div_ceil(tile_size);
+                base_tokens + tiles * tokens_per_tile
+            },
+            ImagePricing::Area { pixels_per_token } => {
+                (width as u64 * height as u64).div_ceil(pixels_per_token.max(1) as u64) as u32
+            },
+        }
+    }
+}
+
+impl RegistryData {
+    // Rejects registries that would lock users out or reference unknown tiers
+    fn validate(&self) -> Result<(), String> {
+        if self.version != REGISTRY_FORMAT_VERSION {
+            return Err(format!("Unsupported model registry version {}", self.version));
+        }
+        if self.tiers.is_empty() || self.models.is_empty() {
+            return Err("Model registry has no tiers or no models".to_string());
+        }
+        if let Some(model) = self
+            .models
+            .iter()
+            .find(|model| !self.tiers.iter().any(|tier| tier.name == model.required_tier))
+        {
+            return Err(format!("Model {} requires unknown tier {}", model.id, model.required_tier));
+        }
+        for tier in &self.tiers {
+            if let Some(model) = tier
+                .fallback_chain
+                .iter()
+                .find(|model| !self.models.iter().any(|entry| entry.id == **model))
+            {
+                return Err(format!("Fallback chain of tier {} lists unknown model {}", tier.name, model));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ModelRegistry {
+    pub fn bundled() -> Self {
+        let data: RegistryData = serde_json::from_str(BUNDLED_REGISTRY).expect("bundled model registry is invalid");
+        Self {
+            data,
+            source: RegistrySource::Bundled,
+            fetched_at: None,
+            cache_path: None,
+        }
+    }
+
+    // Disk cache from the last successful fetch, or the bundled registry
+    pub fn load(cache_path: PathBuf) -> Self {
+        let mut registry = Self::bundled();
+
+        if let Ok(json) = fs::read_to_string(&cache_path) {
+            match serde_json::from_str::<CachedRegistry>(&json) {
+                Ok(cached) if cached.data.validate().is_ok() => {
+                    println!("💾 Loaded model registry from cache ({} models)", cached.data.models.len());
+                    registry.data = cached.data;
+                    registry.source = RegistrySource::Cache;
+                    registry.fetched_at = Some(cached.fetched_at);
+                },
+                Ok(_) => println!("⚠️ Cached model registry is invalid, using bundled registry"),
+                Err(e) => println!("⚠️ Failed to parse cached model registry, using bundled registry: {}", e),
+            }
+        }
+
+        registry.cache_path = Some(cache_path);
+        registry
+    }
+
+    pub fn needs_refresh(&self, max_age: Duration) -> bool {
+        match self.fetched_at {
+            Some(fetched_at) => now_secs().saturating_sub(fetched_at) > max_age.as_secs(),
+            None => true,
+        }
+    }
+
+    // Fetches the registry from the backend; doesn't touch any state so callers
+    // can await it without holding the registry lock
+    pub async fn fetch(api_url: &str) -> Result<RegistryData, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let response = client
+            .get(format!("{}/api/models", api_url.trim_end_matches('/')))
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Model registry request failed: HTTP {}", response.status().as_u16()));
+        }
+        let data: RegistryData = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse model registry: {}", e))?;
+        data.validate()?;
+        Ok(data)
+    }
+
+    pub fn apply_remote(&mut self, data: RegistryData) {
+        let fetched_at = now_secs();
+        println!("✅ Model registry updated from backend ({} models, {} tiers)", data.models.len(), data.tiers.len());
+
+        if let Some(path) = &self.cache_path {
+            let cached = CachedRegistry {
+                fetched_at,
+                data: data.clone(),
+            };
+            let result = serde_json::to_string_pretty(&cached)
+                .map_err(|e| e.to_string())
+                .and_then(|json| {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                    }
+                    fs::write(path, json).map_err(|e| e.to_string())
+                });
+            if let Err(e) = result {
+                println!("⚠️ Failed to cache model registry: {}", e);
+            }
+        }
+
+        self.data = data;
+        self.source = RegistrySource::Remote;
+        self.fetched_at = Some(fetched_at);
+    }
+
+    pub fn source(&self) -> RegistrySource {
+        self.source
+    }
+
+    pub fn fetched_at(&self) -> Option<u64> {
+        self.fetched_at
+    }
+
+    pub fn models(&self) -> &[ModelEntry] {
+        &self.data.models
+    }
+
+    pub fn tiers(&self) -> &[TierEntry] {
+        &self.data.tiers
+    }
+
+    // Looks a model up by API id or display name
+    pub fn find(&self, model: &str) -> Option<&ModelEntry> {
+        let key = model_key(model);
+        self.data
+            .models
+            .iter()
+            .find(|entry| model_key(&entry.id) == key || model_key(&entry.display_name) == key)
+    }
+
+    // Unknown tiers get the lowest rank
+    fn tier_rank(&self, tier: &str) -> u32 {
+        self.data
+            .tiers
+            .iter()
+            .find(|entry| entry.name == tier)
+            .map(|entry| entry.rank)
+            .unwrap_or(0)
+    }
+
+    pub fn available_models(&self, user_tier: &str) -> Vec<&ModelEntry> {
+        let rank = self.tier_rank(user_tier);
+        self.data
+            .models
+            .iter()
+            .filter(|model| self.tier_rank(&model.required_tier) <= rank)
+            .collect()
+    }
+
+    pub fn can_use_model(&self, user_tier: &str, model: &str) -> bool {
+        match self.find(model) {
+            Some(entry) => self.tier_rank(&entry.required_tier) <= self.tier_rank(user_tier),
+            None => false,
+        }
+    }
+
+    // None for models outside the registry
+    pub fn required_tier(&self, model: &str) -> Option<&str> {
+        self.find(model).map(|entry| entry.required_tier.as_str())
+    }
+
+    // Empty for tiers without a chain (and unknown tiers): no fallback
+    pub fn fallback_chain(&self, user_tier: &str) -> &[String] {
+        self.data
+            .tiers
+            .iter()
+            .find(|entry| entry.name == user_tier)
+            .map(|entry| entry.fallback_chain.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn daily_limit(&self, user_tier: &str) -> i32 {
+        self.data
+            .tiers
+            .iter()
+            .find(|entry| entry.name == user_tier)
+            .map(|entry| entry.daily_limit)
+            .unwrap_or(self.data.default_daily_limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(models: Vec<&ModelEntry>) -> Vec<&str> {
+        models.into_iter().map(|model| model.id.as_str()).collect()
+    }
+
+    #[test]
+    fn registries_that_would_lock_users_out_are_rejected() {
+        let bundled = ModelRegistry::bundled().data;
+        assert!(bundled.validate().is_ok());
+
+        let mut newer = bundled.clone();
+        newer.version = REGISTRY_FORMAT_VERSION + 1;
+        assert!(newer.validate().unwrap_err().contains("version"));
+
+        let mut no_models = bundled.clone();
+        no_models.models.clear();
+        assert!(no_models.validate().is_err());
+
+        let mut unknown_tier = bundled.clone();
+        unknown_tier.models[0].required_tier = "platinum".to_string();
+        assert!(unknown_tier.validate().unwrap_err().contains("unknown tier platinum"));
+
+        let mut unknown_fallback = bundled;
+        unknown_fallback.tiers[0].fallback_chain.push("gpt-5".to_string());
+        assert!(unknown_fallback.validate().unwrap_err().contains("unknown model gpt-5"));
+    }
+
+    #[test]
+    fn models_are_found_by_id_or_display_name() {
+        let registry = ModelRegistry::bundled();
+        for name in ["gpt-4o-mini", "GPT-4o-mini", "gpt 4o mini"] {
+            assert_eq!(registry.find(name).map(|model| model.id.as_str()), Some("gpt-4o-mini"), "{}", name);
+        }
+        assert_eq!(registry.find("Claude 3.5 Sonnet").unwrap().id, "claude-3.5-sonnet");
+        assert_eq!(registry.required_tier("Claude 3 Opus"), Some("enterprise"));
+        assert!(registry.find("llava:13b").is_none() && registry.required_tier("llava:13b").is_none());
+    }
+
+    #[test]
+    fn higher_tiers_include_the_models_of_lower_ones() {
+        let registry = ModelRegistry::bundled();
+        assert!(registry.can_use_model("free", "GPT-3.5-turbo") && registry.can_use_model("free", "Gemini Flash"));
+        assert!(!registry.can_use_model("free", "gpt-4o-mini"));
+        assert!(registry.can_use_model("pro", "gpt-4o-mini") && !registry.can_use_model("pro", "claude-3-opus"));
+        assert!(registry.models().iter().all(|model| registry.can_use_model("enterprise", &model.id)));
+        // Models outside the catalog aren't the registry's to grant
+        assert!(!registry.can_use_model("enterprise", "llava:13b"));
+
+        assert_eq!(registry.daily_limit("free"), 50);
+        assert_eq!(registry.daily_limit("enterprise"), -1);
+        assert_eq!(registry.fallback_chain("premium")[0], "gpt-4o-mini");
+    }
+
+    // Unknown tiers (a tier the backend added after this registry was cached) rank with the
+    // lowest tier: every rank-0 model, the registry's default limit, and no fallback chain
+    #[test]
+    fn unknown_tiers_get_the_lowest_tier_models() {
+        let registry = ModelRegistry::bundled();
+        assert_eq!(ids(registry.available_models("trial")), ids(registry.available_models("free")));
+        assert_eq!(ids(registry.available_models("trial")), ["gpt-3.5-turbo", "gemini-flash"]);
+        assert!(registry.can_use_model("trial", "Gemini Flash") && !registry.can_use_model("trial", "gpt-4o-mini"));
+        assert_eq!(registry.daily_limit("trial"), registry.data.default_daily_limit);
+        assert!(registry.fallback_chain("trial").is_empty());
+    }
+}
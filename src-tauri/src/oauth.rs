@@ -0,0 +1,272 @@
+// Browser sign-in: OAuth 2.0 authorization code with PKCE (RFC 7636) and a loopback
+// redirect (RFC 8252). The backend brokers the SSO provider (Google, Microsoft) and
+// redirects the browser back to a one-shot listener on 127.0.0.1.
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use crate::errors::FrameSenseError;
+
+pub const CLIENT_ID: &str = "framesense-desktop";
+pub const AUTHORIZE_PATH: &str = "/api/auth/oauth/authorize";
+pub const TOKEN_PATH: &str = "/api/auth/oauth/token";
+pub const CALLBACK_PATH: &str = "/callback";
+pub const CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+// Browsers open connections ahead of use; one that sends nothing in this long is dropped
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SIGNED_IN_PAGE: &str = "<!doctype html><html><body style=\"font-family: sans-serif; text-align: center; margin-top: 4em\">\
+<h2>Signed in to FrameSense</h2><p>You can close this tab and return to the app.</p></body></html>";
+const FAILED_PAGE: &str = "<!doctype html><html><body style=\"font-family: sans-serif; text-align: center; margin-top: 4em\">\
+<h2>FrameSense sign-in failed</h2><p>Return to the app and try again.</p></body></html>";
+
+fn url_safe(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn random_url_safe(len: usize) -> Result<String, String> {
+    let mut bytes = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Failed to generate random bytes".to_string())?;
+    Ok(url_safe(&bytes))
+}
+
+pub struct Pkce {
+    pub verifier: String,  // Sent only with the token request
+    pub challenge: String, // base64url(SHA-256(verifier)), sent with the authorization request
+}
+
+impl Pkce {
+    pub fn generate() -> Result<Self, String> {
+        Ok(Self::from_verifier(random_url_safe(32)?))
+    }
+
+    pub fn from_verifier(verifier: String) -> Self {
+        let challenge = url_safe(digest(&SHA256, verifier.as_bytes()).as_ref());
+        Self { verifier, challenge }
+    }
+}
+
+// Ties the redirect to the request this app started
+pub fn random_state() -> Result<String, String> {
+    random_url_safe(16)
+}
+
+// Where the browser goes: the backend's authorization endpoint. `provider` picks the SSO
+// provider up front; without it the backend shows its own choice.
+pub fn authorize_url(api_url: &str, redirect_uri: &str, pkce: &Pkce, state: &str, provider: Option<&str>) -> Result<String, String> {
+    let mut url = url::Url::parse(&format!("{}{}", api_url, AUTHORIZE_PATH))
+        .map_err(|e| format!("Invalid authorization URL: {}", e))?;
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", CLIENT_ID)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("code_challenge", &pkce.challenge)
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("state", state);
+        if let Some(provider) = provider {
+            query.append_pair("provider", provider);
+        }
+    }
+    Ok(url.into())
+}
+
+// The redirect target: a listener on an OS-assigned loopback port that takes the first
+// request to CALLBACK_PATH and closes
+pub struct LoopbackRedirect {
+    listener: TcpListener,
+    redirect_uri: String,
+}
+
+impl LoopbackRedirect {
+    pub async fn bind() -> Result<Self, String> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| format!("Failed to open the sign-in redirect listener: {}", e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to read the redirect listener address: {}", e))?
+            .port();
+        Ok(Self {
+            listener,
+            redirect_uri: format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH),
+        })
+    }
+
+    pub fn redirect_uri(&self) -> &str {
+        &self.redirect_uri
+    }
+
+    // The authorization code from the redirect. Other paths (the browser's favicon
+    // request) are answered with 404 and don't end the wait. Each connection is read in
+    // its own task, so a socket the browser opened early and left idle doesn't hold up
+    // the one that carries the redirect.
+    pub async fn wait_for_code(self, state: &str, timeout: Duration) -> Result<String, FrameSenseError> {
+        let (sender, mut callbacks) = mpsc::unbounded_channel();
+        let wait = async {
+            loop {
+                tokio::select! {
+                    accepted = self.listener.accept() => {
+                        let (socket, _) = accepted
+                            .map_err(|e| FrameSenseError::Other(format!("Sign-in redirect listener failed: {}", e)))?;
+                        tokio::spawn(handle_connection(socket, state.to_string(), sender.clone()));
+                    },
+                    Some(result) = callbacks.recv() => return result,
+                }
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.unwrap_or_else(|_| {
+            Err(FrameSenseError::Other(format!(
+                "No sign-in response from the browser within {} minutes",
+                timeout.as_secs() / 60
+            )))
+        })
+    }
+}
+
+// Answers one connection to the listener and passes on the outcome of a request to CALLBACK_PATH.
+// A callback carrying another request's state (or none) is refused without ending the wait,
+// so a stray or forged redirect can't cancel the sign-in the user is completing.
+async fn handle_connection(mut socket: TcpStream, state: String, callbacks: mpsc::UnboundedSender<Result<String, FrameSenseError>>) {
+    let Ok(Some(target)) = tokio::time::timeout(REQUEST_READ_TIMEOUT, read_request_target(&mut socket)).await else {
+        return;
+    };
+    let Ok(url) = url::Url::parse(&format!("http://127.0.0.1{}", target)) else {
+        respond(&mut socket, "400 Bad Request", FAILED_PAGE).await;
+        return;
+    };
+    if url.path() != CALLBACK_PATH {
+        respond(&mut socket, "404 Not Found", "").await;
+        return;
+    }
+
+    let Some(result) = callback_code(&url, &state) else {
+        println!("⚠️ Ignoring a sign-in redirect for another request");
+        respond(&mut socket, "400 Bad Request", FAILED_PAGE).await;
+        return;
+    };
+    match &result {
+        Ok(_) => respond(&mut socket, "200 OK", SIGNED_IN_PAGE).await,
+        Err(_) => respond(&mut socket, "400 Bad Request", FAILED_PAGE).await,
+    }
+    let _ = callbacks.send(result);
+}
+
+// `/callback?code=...&state=...`, or `?error=access_denied&error_description=...&state=...`.
+// None when the state isn't this request's; only a matching callback, code or error, is an answer.
+fn callback_code(url: &url::Url, expected_state: &str) -> Option<Result<String, FrameSenseError>> {
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+
+    if param("state").as_deref() != Some(expected_state) {
+        return None;
+    }
+    if let Some(error) = param("error") {
+        let description = param("error_description").unwrap_or_else(|| error.clone());
+        return Some(Err(FrameSenseError::Unauthorized(format!("Sign-in was not completed: {}", description))));
+    }
+    Some(param("code")
+        .filter(|code| !code.is_empty())
+        .ok_or_else(|| FrameSenseError::Unauthorized("Sign-in response had no authorization code".to_string())))
+}
+
+// "GET /callback?code=... HTTP/1.1" -> "/callback?code=..."
+async fn read_request_target(socket: &mut TcpStream) -> Option<String> {
+    let mut received = Vec::new();
+    let mut buffer = [0u8; 4096];
+    while !received.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = socket.read(&mut buffer).await.ok()?;
+        if read == 0 || received.len() > MAX_REQUEST_HEAD {
+            return None;
+        }
+        received.extend_from_slice(&buffer[..read]);
+    }
+    let head = String::from_utf8_lossy(&received);
+    let mut request_line = head.lines().next()?.split_whitespace();
+    match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(target)) if target.starts_with('/') => Some(target.to_string()),
+        _ => None,
+    }
+}
+
+async fn respond(socket: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+    let _ = socket.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge_is_the_s256_of_the_verifier() {
+        // RFC 7636, appendix B
+        let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string());
+        assert_eq!(pkce.challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+
+        let url = authorize_url("http://localhost:8080", "http://127.0.0.1:5000/callback", &pkce, "xyz", Some("google")).unwrap();
+        assert!(url.starts_with("http://localhost:8080/api/auth/oauth/authorize?response_type=code&client_id=framesense-desktop"));
+        assert!(url.contains("redirect_uri=http%3A%2F%2F127.0.0.1%3A5000%2Fcallback"));
+        assert!(url.contains("code_challenge_method=S256&state=xyz&provider=google"));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_redirect_for_another_request() {
+        let redirect = LoopbackRedirect::bind().await.unwrap();
+        let redirect_uri = redirect.redirect_uri().to_string();
+        let browser = tokio::spawn(async move {
+            let status = |url: String| async move { reqwest::get(url).await.unwrap().status().as_u16() };
+            // Someone else's state, no state, and an error without ours are all refused...
+            let refused = [
+                status(format!("{}?code=abc&state=someone-else", redirect_uri)).await,
+                status(format!("{}?code=abc", redirect_uri)).await,
+                status(format!("{}?error=access_denied&state=someone-else", redirect_uri)).await,
+            ];
+            // ...and the wait goes on until the real redirect
+            (refused, status(format!("{}?code=real&state=expected", redirect_uri)).await)
+        });
+
+        let code = redirect.wait_for_code("expected", Duration::from_secs(5)).await.unwrap();
+        assert_eq!(code, "real");
+        assert_eq!(browser.await.unwrap(), ([400, 400, 400], 200));
+    }
+
+    #[tokio::test]
+    async fn an_error_for_this_request_ends_the_wait() {
+        let redirect = LoopbackRedirect::bind().await.unwrap();
+        let callback = format!("{}?error=access_denied&error_description=User+cancelled&state=expected", redirect.redirect_uri());
+        let browser = tokio::spawn(async move { reqwest::get(callback).await.map(|response| response.status().as_u16()) });
+
+        let error = redirect.wait_for_code("expected", Duration::from_secs(5)).await.unwrap_err();
+        assert_eq!(error.code(), "unauthorized");
+        assert!(error.to_string().contains("User cancelled"));
+        assert_eq!(browser.await.unwrap().unwrap(), 400);
+    }
+
+    #[tokio::test]
+    async fn an_idle_connection_does_not_hold_up_the_redirect() {
+        let redirect = LoopbackRedirect::bind().await.unwrap();
+        let address = redirect.listener.local_addr().unwrap();
+        let _preconnect = TcpStream::connect(address).await.unwrap(); // Never sends a request
+        let callback = format!("{}?code=abc&state=expected", redirect.redirect_uri());
+        let browser = tokio::spawn(async move { reqwest::get(callback).await.map(|response| response.status().as_u16()) });
+
+        let code = redirect.wait_for_code("expected", Duration::from_secs(5)).await.unwrap();
+        assert_eq!(code, "abc");
+        assert_eq!(browser.await.unwrap().unwrap(), 200);
+    }
+}
@@ -0,0 +1,172 @@
+// Persistent OCR result cache keyed by decoded pixel content
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use super::{OCRResult, OCRSettings};
+use crate::lru_store::{Limits, LruStore};
+
+const LIMITS: Limits = Limits {
+    max_entries: 500,
+    max_size_bytes: 10 * 1024 * 1024, // 10MB of serialized results
+    ttl_secs: None,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedOcr {
+    result: OCRResult,
+}
+
+pub struct OcrCache {
+    store: LruStore<CachedOcr>,
+}
+
+// Hashes the decoded RGBA pixels rather than the PNG bytes, so the same screen
+// content re-encoded by a different capture path still hits. Engine, language
+// and preprocessing are part of the key because they change the output.
+pub fn cache_key(image: &DynamicImage, settings: &OCRSettings) -> String {
+    let rgba = image.to_rgba8();
+    let mut hasher = Sha256::new();
+    hasher.update(rgba.width().to_le_bytes());
+    hasher.update(rgba.height().to_le_bytes());
+    hasher.update(rgba.as_raw());
+    hasher.update([0u8]);
+    hasher.update(settings.engine.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(settings.language.as_bytes());
+    for step in &settings.preprocessing {
+        hasher.update([0u8]);
+        hasher.update(step.as_bytes());
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl OcrCache {
+    pub fn new() -> Self {
+        Self { store: LruStore::new("OCR cache", LIMITS) }
+    }
+
+    // Loads the cache file if present; a missing or unreadable file starts empty
+    pub fn load(storage_path: PathBuf) -> Self {
+        Self { store: LruStore::load("OCR cache", LIMITS, storage_path) }
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<OCRResult> {
+        self.store.get(key).map(|cached| cached.result.clone())
+    }
+
+    pub fn insert(&mut self, key: String, result: &OCRResult) {
+        let mut result = result.clone();
+        result.cache_hit = false;
+
+        let size_bytes = serde_json::to_string(&result).map(|json| json.len()).unwrap_or(0);
+        self.store.insert(key, CachedOcr { result }, size_bytes);
+    }
+
+    pub fn clear(&mut self) {
+        self.store.clear();
+    }
+
+    // Writes the cache file if anything changed since the last write
+    pub fn flush(&mut self) {
+        self.store.flush();
+    }
+
+    // (entries, total size in bytes, hits, misses) for this session
+    pub fn get_cache_stats(&self) -> (usize, usize, u64, u64) {
+        self.store.stats()
+    }
+}
+
+impl Default for OcrCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn settings(engine: &str, language: &str, preprocessing: &[&str]) -> OCRSettings {
+        OCRSettings {
+            engine: engine.to_string(),
+            language: language.to_string(),
+            preprocessing: preprocessing.iter().map(|step| step.to_string()).collect(),
+        }
+    }
+
+    fn capture(shade: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 4, Rgba([shade, shade, shade, 255])))
+    }
+
+    fn result(text: &str) -> OCRResult {
+        OCRResult {
+            text: text.to_string(),
+            confidence: 0.9,
+            has_text: !text.is_empty(),
+            words: Vec::new(),
+            entities: Vec::new(),
+            cache_hit: false,
+        }
+    }
+
+    #[test]
+    fn key_changes_with_pixels_engine_language_or_preprocessing() {
+        let base = settings("tesseract", "eng", &["grayscale", "auto_invert"]);
+        let key = cache_key(&capture(10), &base);
+        assert_eq!(cache_key(&capture(10), &base), key);
+
+        let changed = [
+            cache_key(&capture(11), &base),
+            cache_key(&capture(10), &settings("placeholder", "eng", &["grayscale", "auto_invert"])),
+            cache_key(&capture(10), &settings("tesseract", "eng+swe", &["grayscale", "auto_invert"])),
+            cache_key(&capture(10), &settings("tesseract", "eng", &["grayscale"])),
+            cache_key(&capture(10), &settings("tesseract", "eng", &["auto_invert", "grayscale"])),
+            cache_key(&capture(10), &settings("tesseract", "eng", &["grayscale", "auto_invert", "upscale2x"])),
+        ];
+        for other in &changed {
+            assert_ne!(other, &key);
+        }
+
+        let mut cache = OcrCache::new();
+        cache.insert(key.clone(), &result("Build failed"));
+        assert_eq!(cache.get(&key).unwrap().text, "Build failed");
+        assert!(changed.iter().all(|other| cache.get(other).is_none()));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_result_when_full() {
+        let mut cache = OcrCache::new();
+        for index in 0..LIMITS.max_entries {
+            cache.insert(index.to_string(), &result("line"));
+        }
+        assert!(cache.get("0").is_some()); // Keeps "0" over "1"
+
+        cache.insert("new".to_string(), &result("line"));
+        assert_eq!(cache.get_cache_stats().0, LIMITS.max_entries);
+        assert!(cache.get("1").is_none());
+        assert!(cache.get("0").is_some() && cache.get("new").is_some());
+    }
+
+    #[test]
+    fn evicts_by_size_and_refuses_oversized_results() {
+        let mut cache = OcrCache::new();
+        let large = "x".repeat(LIMITS.max_size_bytes * 3 / 5);
+        cache.insert("first".to_string(), &result(&large));
+        cache.insert("second".to_string(), &result(&large));
+        assert!(cache.get("first").is_none());
+        assert!(cache.get("second").is_some());
+
+        cache.insert("huge".to_string(), &result(&"x".repeat(LIMITS.max_size_bytes + 1)));
+        assert!(cache.get("huge").is_none());
+        assert_eq!(cache.get_cache_stats().0, 1);
+    }
+}
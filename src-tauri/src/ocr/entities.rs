@@ -0,0 +1,449 @@
+// Entity extraction over OCR text - turns recognized text into things the user can act on
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use super::{OCRWord, TextBox};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Url,
+    Email,
+    Phone,
+    Date,
+    Time,
+    IpAddress,
+    Hash,
+    TrackingNumber,
+    FilePath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityActionKind {
+    OpenUrl,      // Opened in the default browser through the shell plugin
+    ComposeEmail, // mailto: through the shell plugin
+    Call,         // tel: through the shell plugin
+    RevealPath,   // Opens the containing folder, only if the path exists locally
+    Copy,         // Performed by the frontend clipboard
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityAction {
+    pub kind: EntityActionKind,
+    pub label: String,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextEntity {
+    pub kind: EntityKind,
+    pub value: String,      // Text exactly as it appears in the OCR output
+    pub normalized: String, // Canonical form (lowercased email, digits-only phone, ...)
+    pub start: usize,       // Byte offsets into the OCR text
+    pub end: usize,
+    pub boxes: Vec<TextBox>, // Source word boxes, empty when the engine has no word boxes
+    pub actions: Vec<EntityAction>,
+}
+
+struct Pattern {
+    kind: EntityKind,
+    regex: Regex,
+}
+
+// Order matters: earlier patterns win when matches overlap (a URL containing
+// an IP stays a URL, a date is never re-read as a phone number)
+fn patterns() -> &'static [Pattern] {
+    static PATTERNS: OnceLock<Vec<Pattern>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let sources: [(EntityKind, &str); 12] = [
+            (EntityKind::Url, r#"(?i)\b(?:https?://|www\.)[^\s<>"'`]+"#),
+            (EntityKind::Email, r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b"),
+            (EntityKind::FilePath, r#"(?:^|[\s"'(=])((?:~|\.{1,2})?/[\w.@+-]+(?:/[\w.@+-]+)+/?)"#),
+            (EntityKind::FilePath, r#"\b[A-Za-z]:\\(?:[^\\\s:*?"<>|]+\\)*[^\\\s:*?"<>|]*"#),
+            (EntityKind::IpAddress, r"\b(?:(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\.){3}(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)(?::\d{1,5})?\b"),
+            (EntityKind::Date, r"\b\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2})?(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?)?\b"),
+            (EntityKind::Date, r"(?i)\b(?:\d{1,2}\s+(?:jan|feb|mar|apr|may|jun|jul|aug|sep|sept|oct|nov|dec)[a-z]*\.?,?\s+\d{4}|(?:jan|feb|mar|apr|may|jun|jul|aug|sep|sept|oct|nov|dec)[a-z]*\.?\s+\d{1,2}(?:st|nd|rd|th)?,?\s+\d{4})\b"),
+            (EntityKind::Date, r"\b\d{1,2}[/.]\d{1,2}[/.](?:\d{4}|\d{2})\b"),
+            (EntityKind::Time, r"(?i)\b(?:[01]?\d|2[0-3]):[0-5]\d(?::[0-5]\d)?(?:\s?[ap]m)?\b"),
+            (EntityKind::TrackingNumber, r"\b(?:1Z[0-9A-Z]{16}|9[2-5]\d{20}|[A-Z]{2}\d{9}[A-Z]{2})\b"),
+            (EntityKind::Hash, r"\b(?:[a-fA-F0-9]{64}|[a-fA-F0-9]{40}|[a-fA-F0-9]{32})\b"),
+            (EntityKind::Phone, r"(?:^|[^\w+])((?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d{2,4}(?:[ .-]\d{2,4}){1,4})\b"),
+        ];
+
+        sources
+            .iter()
+            .map(|(kind, source)| Pattern {
+                kind: *kind,
+                regex: Regex::new(source).expect("invalid entity pattern"),
+            })
+            .collect()
+    })
+}
+
+pub fn extract_entities(text: &str, words: &[OCRWord]) -> Vec<TextEntity> {
+    let word_spans = locate_words(text, words);
+    let mut taken: Vec<(usize, usize)> = Vec::new();
+    let mut entities = Vec::new();
+
+    for pattern in patterns() {
+        for captures in pattern.regex.captures_iter(text) {
+            // Paths and phone numbers use a capture group so the leading delimiter isn't part of the value
+            let matched = captures.get(1).or_else(|| captures.get(0)).unwrap();
+            let start = matched.start();
+            let value = trim_trailing_punctuation(matched.as_str());
+            let end = start + value.len();
+
+            if value.is_empty() || taken.iter().any(|&(s, e)| start < e && s < end) {
+                continue;
+            }
+
+            let normalized = match normalize(pattern.kind, value) {
+                Some(normalized) => normalized,
+                None => continue,
+            };
+
+            taken.push((start, end));
+            entities.push(TextEntity {
+                kind: pattern.kind,
+                value: value.to_string(),
+                actions: actions_for(pattern.kind, &normalized),
+                normalized,
+                start,
+                end,
+                boxes: boxes_for_span(&word_spans, start, end),
+            });
+        }
+    }
+
+    entities.sort_by_key(|entity| entity.start);
+    entities
+}
+
+// Returns None when a candidate match turns out not to be a real entity
+fn normalize(kind: EntityKind, value: &str) -> Option<String> {
+    match kind {
+        EntityKind::Url => {
+            if value.to_lowercase().starts_with("www.") {
+                Some(format!("https://{}", value))
+            } else {
+                Some(value.to_string())
+            }
+        },
+        EntityKind::Email => Some(value.to_lowercase()),
+        EntityKind::Phone => {
+            let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
+            // E.164 allows at most 15 digits; shorter runs are usually amounts or ids
+            if digits.len() < 7 || digits.len() > 15 {
+                return None;
+            }
+            if value.trim_start().starts_with('+') {
+                Some(format!("+{}", digits))
+            } else {
+                Some(digits)
+            }
+        },
+        EntityKind::Date => Some(normalize_date(value).unwrap_or_else(|| value.to_string())),
+        EntityKind::Hash => {
+            // All-digit runs of hash length are far more likely to be ids than hashes
+            if value.chars().all(|c| c.is_ascii_digit()) {
+                None
+            } else {
+                Some(value.to_lowercase())
+            }
+        },
+        EntityKind::Time | EntityKind::IpAddress | EntityKind::TrackingNumber | EntityKind::FilePath => {
+            Some(value.to_string())
+        },
+    }
+}
+
+fn normalize_date(value: &str) -> Option<String> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.to_rfc3339());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(datetime.format("%Y-%m-%dT%H:%M:%S").to_string());
+        }
+    }
+
+    let cleaned = value.replace(',', "").replace('.', " ");
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    for format in ["%Y-%m-%d", "%d %b %Y", "%d %B %Y", "%b %d %Y", "%B %d %Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(&cleaned, format) {
+            return Some(date.format("%Y-%m-%d").to_string());
+        }
+    }
+    // Day-first is ambiguous for dd/mm vs mm/dd, so numeric forms are only
+    // normalized when one of the two parts can't be a month
+    let parts: Vec<&str> = value.split(['/', '.']).collect();
+    if let [a, b, year] = parts.as_slice() {
+        let (a, b): (u32, u32) = (a.parse().ok()?, b.parse().ok()?);
+        let year: i32 = match year.len() {
+            2 => 2000 + year.parse::<i32>().ok()?,
+            _ => year.parse().ok()?,
+        };
+        let date = if a > 12 {
+            NaiveDate::from_ymd_opt(year, b, a)
+        } else if b > 12 {
+            NaiveDate::from_ymd_opt(year, a, b)
+        } else {
+            None
+        };
+        return date.map(|date| date.format("%Y-%m-%d").to_string());
+    }
+    None
+}
+
+fn actions_for(kind: EntityKind, normalized: &str) -> Vec<EntityAction> {
+    let mut actions = Vec::new();
+
+    match kind {
+        EntityKind::Url => actions.push(EntityAction {
+            kind: EntityActionKind::OpenUrl,
+            label: "Open in browser".to_string(),
+            target: normalized.to_string(),
+        }),
+        EntityKind::Email => actions.push(EntityAction {
+            kind: EntityActionKind::ComposeEmail,
+            label: "Send email".to_string(),
+            target: format!("mailto:{}", normalized),
+        }),
+        EntityKind::Phone => actions.push(EntityAction {
+            kind: EntityActionKind::Call,
+            label: "Call".to_string(),
+            target: format!("tel:{}", normalized),
+        }),
+        EntityKind::TrackingNumber => {
+            if let Some(url) = tracking_url(normalized) {
+                actions.push(EntityAction {
+                    kind: EntityActionKind::OpenUrl,
+                    label: "Track package".to_string(),
+                    target: url,
+                });
+            }
+        },
+        EntityKind::FilePath => actions.push(EntityAction {
+            kind: EntityActionKind::RevealPath,
+            label: "Show in folder".to_string(),
+            target: normalized.to_string(),
+        }),
+        EntityKind::Date | EntityKind::Time | EntityKind::IpAddress | EntityKind::Hash => {},
+    }
+
+    actions.push(EntityAction {
+        kind: EntityActionKind::Copy,
+        label: "Copy".to_string(),
+        target: normalized.to_string(),
+    });
+    actions
+}
+
+fn tracking_url(number: &str) -> Option<String> {
+    if number.starts_with("1Z") {
+        Some(format!("https://www.ups.com/track?tracknum={}", number))
+    } else if number.len() == 22 && number.starts_with('9') {
+        Some(format!("https://tools.usps.com/go/TrackConfirmAction?tLabels={}", number))
+    } else {
+        // UPU S10 numbers (e.g. RR123456789SE) depend on the destination postal service
+        None
+    }
+}
+
+fn trim_trailing_punctuation(value: &str) -> &str {
+    let mut trimmed = value.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+
+    // Keep a closing bracket only if the entity itself opened it, e.g. wiki URLs
+    while let Some(last) = trimmed.chars().last() {
+        let open = match last {
+            ')' => '(',
+            ']' => '[',
+            '}' => '{',
+            _ => break,
+        };
+        if trimmed.matches(open).count() >= trimmed.matches(last).count() {
+            break;
+        }
+        trimmed = &trimmed[..trimmed.len() - 1];
+        trimmed = trimmed.trim_end_matches(['.', ',', ';', ':', '!', '?']);
+    }
+    trimmed
+}
+
+// Maps each OCR word to its byte span in the text. Engines emit the text as
+// the words joined with whitespace, so a forward search keeps them aligned.
+fn locate_words(text: &str, words: &[OCRWord]) -> Vec<(usize, usize, TextBox)> {
+    let mut spans = Vec::with_capacity(words.len());
+    let mut cursor = 0;
+
+    for word in words {
+        let needle = word.text.trim();
+        if needle.is_empty() {
+            continue;
+        }
+        if let Some(offset) = text[cursor..].find(needle) {
+            let start = cursor + offset;
+            let end = start + needle.len();
+            spans.push((start, end, word.bbox));
+            cursor = end;
+        }
+    }
+    spans
+}
+
+fn boxes_for_span(word_spans: &[(usize, usize, TextBox)], start: usize, end: usize) -> Vec<TextBox> {
+    word_spans
+        .iter()
+        .filter(|(word_start, word_end, _)| *word_start < end && start < *word_end)
+        .map(|(_, _, bbox)| *bbox)
+        .collect()
+}
+
+impl EntityAction {
+    // Validates the target before it's handed to the OS opener so a crafted
+    // action from the webview can't launch arbitrary programs
+    pub fn open_target(&self) -> Result<String, String> {
+        match self.kind {
+            EntityActionKind::OpenUrl => {
+                let url = url::Url::parse(&self.target)
+                    .map_err(|e| format!("Invalid URL '{}': {}", self.target, e))?;
+                match url.scheme() {
+                    "http" | "https" => Ok(url.to_string()),
+                    scheme => Err(format!("Refusing to open URL with scheme '{}'", scheme)),
+                }
+            },
+            EntityActionKind::ComposeEmail if self.target.starts_with("mailto:") => Ok(self.target.clone()),
+            EntityActionKind::Call if self.target.starts_with("tel:") => Ok(self.target.clone()),
+            EntityActionKind::RevealPath => {
+                let expanded = if let Some(rest) = self.target.strip_prefix("~/") {
+                    dirs::home_dir()
+                        .ok_or("No home directory to expand '~'")?
+                        .join(rest)
+                } else {
+                    std::path::PathBuf::from(&self.target)
+                };
+                if !expanded.exists() {
+                    return Err(format!("Path does not exist: {}", expanded.display()));
+                }
+                let folder = if expanded.is_dir() {
+                    expanded
+                } else {
+                    expanded.parent().map(|p| p.to_path_buf()).unwrap_or(expanded)
+                };
+                Ok(folder.to_string_lossy().to_string())
+            },
+            EntityActionKind::Copy => Err("Copy actions are performed by the frontend clipboard".to_string()),
+            _ => Err(format!("Invalid target for {:?} action: {}", self.kind, self.target)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(kind: EntityActionKind, target: &str) -> EntityAction {
+        EntityAction { kind, label: String::new(), target: target.to_string() }
+    }
+
+    #[test]
+    fn extracts_every_entity_kind() {
+        let cases: [(&str, EntityKind, &str, &str); 16] = [
+            ("Docs at https://example.com/guide.", EntityKind::Url, "https://example.com/guide", "https://example.com/guide"),
+            ("see www.Example.org/path", EntityKind::Url, "www.Example.org/path", "https://www.Example.org/path"),
+            (
+                "(https://en.wikipedia.org/wiki/Rust_(programming_language)).",
+                EntityKind::Url,
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            ),
+            ("Mail Ada.Lovelace@Example.COM today", EntityKind::Email, "Ada.Lovelace@Example.COM", "ada.lovelace@example.com"),
+            ("Call +1 (415) 555-0100 now", EntityKind::Phone, "+1 (415) 555-0100", "+14155550100"),
+            ("Office 030 1234 5678", EntityKind::Phone, "030 1234 5678", "03012345678"),
+            ("Deployed 2024-05-01T14:30:00Z", EntityKind::Date, "2024-05-01T14:30:00Z", "2024-05-01T14:30:00+00:00"),
+            ("due 3 March 2025", EntityKind::Date, "3 March 2025", "2025-03-03"),
+            ("on 25/12/2024", EntityKind::Date, "25/12/2024", "2024-12-25"),
+            ("at 9:45 pm", EntityKind::Time, "9:45 pm", "9:45 pm"),
+            ("host 192.168.1.20:8080 is up", EntityKind::IpAddress, "192.168.1.20:8080", "192.168.1.20:8080"),
+            ("sha1 DA39A3EE5E6B4B0D3255BFEF95601890AFD80709", EntityKind::Hash, "DA39A3EE5E6B4B0D3255BFEF95601890AFD80709", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            ("UPS 1Z999AA10123456784", EntityKind::TrackingNumber, "1Z999AA10123456784", "1Z999AA10123456784"),
+            ("Tracking RR123456789SE", EntityKind::TrackingNumber, "RR123456789SE", "RR123456789SE"),
+            ("open ~/projects/app/src/main.rs", EntityKind::FilePath, "~/projects/app/src/main.rs", "~/projects/app/src/main.rs"),
+            (r"saved to C:\Users\ada\report.docx", EntityKind::FilePath, r"C:\Users\ada\report.docx", r"C:\Users\ada\report.docx"),
+        ];
+
+        for (text, kind, value, normalized) in cases {
+            let entities = extract_entities(text, &[]);
+            let found: Vec<(EntityKind, &str, &str)> = entities
+                .iter()
+                .map(|entity| (entity.kind, entity.value.as_str(), entity.normalized.as_str()))
+                .collect();
+            assert_eq!(found, [(kind, value, normalized)], "{}", text);
+            assert_eq!(&text[entities[0].start..entities[0].end], value);
+        }
+    }
+
+    #[test]
+    fn rejects_lookalikes() {
+        for text in ["Total 12 34", "order 12345678901234567890123456789012", "version 1.2"] {
+            assert!(extract_entities(text, &[]).is_empty(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn normalizes_phones_and_urls() {
+        let cases: [(EntityKind, &str, Option<&str>); 8] = [
+            (EntityKind::Phone, "+44 20 7946 0958", Some("+442079460958")),
+            (EntityKind::Phone, "(030) 1234-567", Some("0301234567")),
+            (EntityKind::Phone, "12-3456", None), // Too short for a phone number
+            (EntityKind::Phone, "+1 234 567 890 123 456 78", None), // Longer than E.164 allows
+            (EntityKind::Url, "www.example.com", Some("https://www.example.com")),
+            (EntityKind::Url, "WWW.example.com/a", Some("https://WWW.example.com/a")),
+            (EntityKind::Url, "http://example.com", Some("http://example.com")),
+            (EntityKind::Url, "https://example.com/?q=1", Some("https://example.com/?q=1")),
+        ];
+        for (kind, value, expected) in cases {
+            assert_eq!(normalize(kind, value).as_deref(), expected, "{}", value);
+        }
+
+        let phone = &extract_entities("Call +44 20 7946 0958", &[])[0];
+        assert_eq!((phone.actions[0].kind, phone.actions[0].target.as_str()), (EntityActionKind::Call, "tel:+442079460958"));
+        let email = &extract_entities("ADA@example.com", &[])[0];
+        assert_eq!((email.actions[0].kind, email.actions[0].target.as_str()), (EntityActionKind::ComposeEmail, "mailto:ada@example.com"));
+    }
+
+    #[test]
+    fn open_target_only_passes_safe_targets() {
+        let cases: [(EntityActionKind, &str, Option<&str>); 12] = [
+            (EntityActionKind::OpenUrl, "https://example.com/a?b=1", Some("https://example.com/a?b=1")),
+            (EntityActionKind::OpenUrl, "http://example.com", Some("http://example.com/")),
+            (EntityActionKind::OpenUrl, "javascript:alert(1)", None),
+            (EntityActionKind::OpenUrl, "file:///etc/passwd", None),
+            (EntityActionKind::OpenUrl, "not a url", None),
+            (EntityActionKind::ComposeEmail, "mailto:ada@example.com", Some("mailto:ada@example.com")),
+            (EntityActionKind::ComposeEmail, "javascript:alert(1)", None),
+            (EntityActionKind::Call, "tel:+442079460958", Some("tel:+442079460958")),
+            (EntityActionKind::Call, "file:///bin/sh", None),
+            (EntityActionKind::RevealPath, "file:///etc", None),
+            (EntityActionKind::RevealPath, "/no/such/framesense/path", None),
+            (EntityActionKind::Copy, "anything", None),
+        ];
+        for (kind, target, expected) in cases {
+            assert_eq!(action(kind, target).open_target().ok().as_deref(), expected, "{:?} {}", kind, target);
+        }
+
+        // A file reveals its folder, a folder itself
+        let dir = std::env::temp_dir().join(format!("framesense-entities-{}", std::process::id()));
+        let file = dir.join("notes.txt");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&file, "").unwrap();
+        let folder = dir.to_string_lossy().to_string();
+        assert_eq!(action(EntityActionKind::RevealPath, &file.to_string_lossy()).open_target(), Ok(folder.clone()));
+        assert_eq!(action(EntityActionKind::RevealPath, &folder).open_target(), Ok(folder));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
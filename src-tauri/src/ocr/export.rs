@@ -0,0 +1,525 @@
+// Exporters for structured OCR results: hOCR, ALTO XML and searchable PDF
+use flate2::{write::ZlibEncoder, Compression};
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+use super::{OCRResult, TextBox};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Hocr,
+    Alto,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Hocr => "hocr",
+            ExportFormat::Alto => "xml",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+struct LayoutWord {
+    text: String,
+    bbox: TextBox,
+    confidence: f32,
+}
+
+struct LayoutLine {
+    bbox: TextBox,
+    words: Vec<LayoutWord>,
+}
+
+pub fn export(result: &OCRResult, image: &DynamicImage, format: ExportFormat) -> Result<Vec<u8>, String> {
+    let (width, height) = image.dimensions();
+    let lines = layout_lines(result, width, height);
+
+    match format {
+        ExportFormat::Hocr => Ok(to_hocr(&lines, width, height).into_bytes()),
+        ExportFormat::Alto => Ok(to_alto(&lines, width, height).into_bytes()),
+        ExportFormat::Pdf => to_searchable_pdf(&lines, image),
+    }
+}
+
+// Groups words into lines using the engine's line numbers. Engines without
+// word boxes only give us text, so those lines are spread evenly down the page
+// with words sized by character count - enough for search and selection.
+fn layout_lines(result: &OCRResult, width: u32, height: u32) -> Vec<LayoutLine> {
+    if !result.words.is_empty() {
+        let mut lines: Vec<(u32, Vec<LayoutWord>)> = Vec::new();
+        for word in &result.words {
+            let layout_word = LayoutWord {
+                text: word.text.clone(),
+                bbox: word.bbox,
+                confidence: word.confidence,
+            };
+            match lines.last_mut() {
+                Some((line, words)) if *line == word.line => words.push(layout_word),
+                _ => lines.push((word.line, vec![layout_word])),
+            }
+        }
+        return lines
+            .into_iter()
+            .map(|(_, words)| LayoutLine {
+                bbox: union(words.iter().map(|word| word.bbox)),
+                words,
+            })
+            .collect();
+    }
+
+    let text_lines: Vec<&str> = result.text.lines().filter(|line| !line.trim().is_empty()).collect();
+    if !result.has_text || text_lines.is_empty() {
+        return Vec::new();
+    }
+
+    let line_height = (height / text_lines.len() as u32).max(1);
+    text_lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let y = index as u32 * line_height;
+            let char_count = line.chars().count().max(1) as u32;
+            let mut x = 0;
+            let words = line
+                .split_whitespace()
+                .map(|word| {
+                    let word_width = (width * (word.chars().count() as u32 + 1) / char_count).min(width - x);
+                    let bbox = TextBox { x, y, width: word_width, height: line_height };
+                    x += word_width;
+                    LayoutWord { text: word.to_string(), bbox, confidence: result.confidence }
+                })
+                .collect();
+            LayoutLine { bbox: TextBox { x: 0, y, width, height: line_height }, words }
+        })
+        .collect()
+}
+
+fn union(boxes: impl Iterator<Item = TextBox>) -> TextBox {
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    for bbox in boxes {
+        left = left.min(bbox.x);
+        top = top.min(bbox.y);
+        right = right.max(bbox.x + bbox.width);
+        bottom = bottom.max(bbox.y + bbox.height);
+    }
+    if left > right || top > bottom {
+        return TextBox { x: 0, y: 0, width: 0, height: 0 };
+    }
+    TextBox { x: left, y: top, width: right - left, height: bottom - top }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn hocr_bbox(bbox: &TextBox) -> String {
+    format!("bbox {} {} {} {}", bbox.x, bbox.y, bbox.x + bbox.width, bbox.y + bbox.height)
+}
+
+fn to_hocr(lines: &[LayoutLine], width: u32, height: u32) -> String {
+    let mut body = String::new();
+    let page = TextBox { x: 0, y: 0, width, height };
+    let content = union(lines.iter().map(|line| line.bbox));
+
+    body.push_str(&format!("  <div class='ocr_page' id='page_1' title='{}; ppageno 0'>\n", hocr_bbox(&page)));
+    if !lines.is_empty() {
+        body.push_str(&format!("   <div class='ocr_carea' id='block_1_1' title='{}'>\n", hocr_bbox(&content)));
+        body.push_str(&format!("    <p class='ocr_par' id='par_1_1' title='{}'>\n", hocr_bbox(&content)));
+        for (line_index, line) in lines.iter().enumerate() {
+            body.push_str(&format!(
+                "     <span class='ocr_line' id='line_1_{}' title='{}'>",
+                line_index + 1,
+                hocr_bbox(&line.bbox)
+            ));
+            for (word_index, word) in line.words.iter().enumerate() {
+                if word_index > 0 {
+                    body.push(' ');
+                }
+                body.push_str(&format!(
+                    "<span class='ocrx_word' id='word_1_{}_{}' title='{}; x_wconf {}'>{}</span>",
+                    line_index + 1,
+                    word_index + 1,
+                    hocr_bbox(&word.bbox),
+                    (word.confidence * 100.0).round() as i32,
+                    escape_xml(&word.text)
+                ));
+            }
+            body.push_str("</span>\n");
+        }
+        body.push_str("    </p>\n   </div>\n");
+    }
+    body.push_str("  </div>\n");
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xml:lang=\"en\" lang=\"en\">\n\
+         <head>\n\
+         \x20 <title>FrameSense capture</title>\n\
+         \x20 <meta http-equiv=\"Content-Type\" content=\"text/html;charset=utf-8\"/>\n\
+         \x20 <meta name='ocr-system' content='FrameSense {}'/>\n\
+         \x20 <meta name='ocr-capabilities' content='ocr_page ocr_carea ocr_par ocr_line ocrx_word'/>\n\
+         </head>\n\
+         <body>\n{}</body>\n</html>\n",
+        env!("CARGO_PKG_VERSION"),
+        body
+    )
+}
+
+fn alto_position(bbox: &TextBox) -> String {
+    format!("HPOS=\"{}\" VPOS=\"{}\" WIDTH=\"{}\" HEIGHT=\"{}\"", bbox.x, bbox.y, bbox.width, bbox.height)
+}
+
+fn to_alto(lines: &[LayoutLine], width: u32, height: u32) -> String {
+    let mut layout = String::new();
+    let page = TextBox { x: 0, y: 0, width, height };
+
+    layout.push_str(&format!("    <Page ID=\"page_1\" PHYSICAL_IMG_NR=\"1\" WIDTH=\"{}\" HEIGHT=\"{}\">\n", width, height));
+    layout.push_str(&format!("      <PrintSpace {}>\n", alto_position(&page)));
+    if !lines.is_empty() {
+        let content = union(lines.iter().map(|line| line.bbox));
+        layout.push_str(&format!("        <TextBlock ID=\"block_1\" {}>\n", alto_position(&content)));
+        for (line_index, line) in lines.iter().enumerate() {
+            layout.push_str(&format!(
+                "          <TextLine ID=\"line_{}\" {}>\n",
+                line_index + 1,
+                alto_position(&line.bbox)
+            ));
+            for (word_index, word) in line.words.iter().enumerate() {
+                if word_index > 0 {
+                    layout.push_str("            <SP/>\n");
+                }
+                layout.push_str(&format!(
+                    "            <String ID=\"string_{}_{}\" CONTENT=\"{}\" {} WC=\"{:.2}\"/>\n",
+                    line_index + 1,
+                    word_index + 1,
+                    escape_xml(&word.text),
+                    alto_position(&word.bbox),
+                    word.confidence.clamp(0.0, 1.0)
+                ));
+            }
+            layout.push_str("          </TextLine>\n");
+        }
+        layout.push_str("        </TextBlock>\n");
+    }
+    layout.push_str("      </PrintSpace>\n    </Page>\n");
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <alto xmlns=\"http://www.loc.gov/standards/alto/ns-v4#\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://www.loc.gov/standards/alto/ns-v4# http://www.loc.gov/alto/v4/alto-4-2.xsd\">\n\
+         \x20 <Description>\n\
+         \x20   <MeasurementUnit>pixel</MeasurementUnit>\n\
+         \x20   <OCRProcessing ID=\"ocr_1\">\n\
+         \x20     <ocrProcessingStep>\n\
+         \x20       <processingDateTime>{}</processingDateTime>\n\
+         \x20       <processingSoftware>\n\
+         \x20         <softwareName>FrameSense</softwareName>\n\
+         \x20         <softwareVersion>{}</softwareVersion>\n\
+         \x20       </processingSoftware>\n\
+         \x20     </ocrProcessingStep>\n\
+         \x20   </OCRProcessing>\n\
+         \x20 </Description>\n\
+         \x20 <Layout>\n{}  </Layout>\n</alto>\n",
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        env!("CARGO_PKG_VERSION"),
+        layout
+    )
+}
+
+// Captures are screen pixels; 96 DPI keeps the PDF page the size it was on screen
+const POINTS_PER_PIXEL: f32 = 72.0 / 96.0;
+
+// Encodes text as big-endian UTF-16 code units for the Identity-H font;
+// characters outside the BMP become U+FFFD since each CID is one code unit
+fn pdf_hex_string(text: &str) -> String {
+    let mut hex = String::with_capacity(text.len() * 4 + 2);
+    hex.push('<');
+    for c in text.chars() {
+        let unit = if (c as u32) <= 0xFFFF { c as u32 } else { 0xFFFD };
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex.push('>');
+    hex
+}
+
+// One page with the capture as the background image and each word placed as
+// invisible text (render mode 3) over its box, so viewers can search and
+// select it. The font is a non-embedded "glyphless" CID font whose ToUnicode
+// map is the identity - glyph shapes never render, only the text mapping matters.
+fn to_searchable_pdf(lines: &[LayoutLine], image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let (width, height) = image.dimensions();
+    let page_width = width as f32 * POINTS_PER_PIXEL;
+    let page_height = height as f32 * POINTS_PER_PIXEL;
+
+    let rgb = image.to_rgb8();
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(rgb.as_raw())
+        .map_err(|e| format!("Failed to compress PDF image: {}", e))?;
+    let image_stream = encoder
+        .finish()
+        .map_err(|e| format!("Failed to compress PDF image: {}", e))?;
+
+    let mut content = format!("q\n{:.2} 0 0 {:.2} 0 0 cm\n/Im1 Do\nQ\n", page_width, page_height);
+    for line in lines {
+        for word in &line.words {
+            let char_count = word.text.chars().count();
+            if char_count == 0 || word.bbox.width == 0 || word.bbox.height == 0 {
+                continue;
+            }
+            let font_size = word.bbox.height as f32 * POINTS_PER_PIXEL;
+            // Glyph advance is 500/1000 em, so stretch horizontally to the box width
+            let natural_width = char_count as f32 * font_size * 0.5;
+            let horizontal_scale = word.bbox.width as f32 * POINTS_PER_PIXEL / natural_width * 100.0;
+            let x = word.bbox.x as f32 * POINTS_PER_PIXEL;
+            let y = page_height - (word.bbox.y + word.bbox.height) as f32 * POINTS_PER_PIXEL;
+            content.push_str(&format!(
+                "BT\n3 Tr\n/F1 {:.2} Tf\n{:.2} Tz\n1 0 0 1 {:.2} {:.2} Tm\n{} Tj\nET\n",
+                font_size,
+                horizontal_scale,
+                x,
+                y,
+                pdf_hex_string(&word.text)
+            ));
+        }
+    }
+
+    let to_unicode = "/CIDInit /ProcSet findresource begin\n\
+                      12 dict begin\n\
+                      begincmap\n\
+                      /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+                      /CMapName /Adobe-Identity-UCS def\n\
+                      /CMapType 2 def\n\
+                      1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n\
+                      1 beginbfrange\n<0000> <FFFF> <0000>\nendbfrange\n\
+                      endcmap\n\
+                      CMapName currentdict /CMap defineresource pop\n\
+                      end\nend\n";
+
+    let mut pdf = PdfWriter::new();
+    pdf.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    pdf.object(b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    pdf.object(format!(
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+         /Resources << /XObject << /Im1 4 0 R >> /Font << /F1 5 0 R >> >> /Contents 8 0 R >>",
+        page_width, page_height
+    ).as_bytes());
+    pdf.stream(
+        &format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+            width, height, image_stream.len()
+        ),
+        &image_stream,
+    );
+    pdf.object(b"<< /Type /Font /Subtype /Type0 /BaseFont /GlyphLessFont /Encoding /Identity-H \
+                 /DescendantFonts [6 0 R] /ToUnicode 7 0 R >>");
+    pdf.object(b"<< /Type /Font /Subtype /CIDFontType2 /BaseFont /GlyphLessFont \
+                 /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+                 /FontDescriptor 9 0 R /DW 500 /CIDToGIDMap /Identity >>");
+    pdf.stream(&format!("<< /Length {} >>", to_unicode.len()), to_unicode.as_bytes());
+    pdf.stream(&format!("<< /Length {} >>", content.len()), content.as_bytes());
+    pdf.object(b"<< /Type /FontDescriptor /FontName /GlyphLessFont /Flags 5 /FontBBox [0 0 500 1000] \
+                 /ItalicAngle 0 /Ascent 1000 /Descent 0 /CapHeight 1000 /StemV 80 >>");
+
+    Ok(pdf.finish())
+}
+
+// Minimal PDF 1.4 writer: objects are numbered in the order they're added
+struct PdfWriter {
+    buffer: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        // The binary comment line marks the file as binary for transfer tools
+        let mut buffer = b"%PDF-1.4\n".to_vec();
+        buffer.extend_from_slice(b"%\xE2\xE3\xCF\xD3\n");
+        Self { buffer, offsets: Vec::new() }
+    }
+
+    fn begin_object(&mut self) {
+        self.offsets.push(self.buffer.len());
+        self.buffer
+            .extend_from_slice(format!("{} 0 obj\n", self.offsets.len()).as_bytes());
+    }
+
+    fn object(&mut self, dictionary: &[u8]) {
+        self.begin_object();
+        self.buffer.extend_from_slice(dictionary);
+        self.buffer.extend_from_slice(b"\nendobj\n");
+    }
+
+    fn stream(&mut self, dictionary: &str, data: &[u8]) {
+        self.begin_object();
+        self.buffer.extend_from_slice(dictionary.as_bytes());
+        self.buffer.extend_from_slice(b"\nstream\n");
+        self.buffer.extend_from_slice(data);
+        self.buffer.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref_offset = self.buffer.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            xref.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        xref.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            xref_offset
+        ));
+        self.buffer.extend_from_slice(xref.as_bytes());
+        self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::OCRWord;
+    use regex::Regex;
+
+    fn word(text: &str, (x, y, width, height): (u32, u32, u32, u32), confidence: f32, line: u32) -> OCRWord {
+        OCRWord { text: text.to_string(), confidence, bbox: TextBox { x, y, width, height }, line }
+    }
+
+    // Two lines of word boxes, with text that needs escaping and a non-Latin word
+    fn receipt() -> OCRResult {
+        OCRResult {
+            text: "Total: <$5 & up>\n\"it's\" Привет".to_string(),
+            confidence: 0.9,
+            has_text: true,
+            words: vec![
+                word("Total:", (10, 20, 60, 15), 0.93, 0),
+                word("<$5 & up>", (80, 20, 90, 15), 0.88, 0),
+                word("\"it's\"", (10, 50, 40, 15), 0.75, 1),
+                word("Привет", (60, 50, 50, 15), 0.8, 1),
+            ],
+            entities: Vec::new(),
+            cache_hit: false,
+        }
+    }
+
+    fn export_text(result: &OCRResult, format: ExportFormat) -> String {
+        String::from_utf8(export(result, &DynamicImage::new_rgb8(200, 100), format).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn word_text_is_xml_escaped() {
+        for format in [ExportFormat::Hocr, ExportFormat::Alto] {
+            let xml = export_text(&receipt(), format);
+            assert!(xml.contains("&lt;$5 &amp; up&gt;"), "{:?}", format);
+            assert!(xml.contains("&quot;it&apos;s&quot;"), "{:?}", format);
+            assert!(!xml.contains("<$5") && !xml.contains("\"it's\""), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn hocr_boxes_are_corner_coordinates() {
+        let hocr = export_text(&receipt(), ExportFormat::Hocr);
+        assert!(hocr.contains("<div class='ocr_page' id='page_1' title='bbox 0 0 200 100; ppageno 0'>"));
+        assert!(hocr.contains("<div class='ocr_carea' id='block_1_1' title='bbox 10 20 170 65'>"));
+        assert!(hocr.contains("<span class='ocr_line' id='line_1_1' title='bbox 10 20 170 35'>"));
+        assert!(hocr.contains("<span class='ocrx_word' id='word_1_1_1' title='bbox 10 20 70 35; x_wconf 93'>Total:</span>"));
+        assert!(hocr.contains("<span class='ocrx_word' id='word_1_2_2' title='bbox 60 50 110 65; x_wconf 80'>Привет</span>"));
+    }
+
+    #[test]
+    fn alto_positions_are_origin_and_size() {
+        let alto = export_text(&receipt(), ExportFormat::Alto);
+        assert!(alto.contains("<Page ID=\"page_1\" PHYSICAL_IMG_NR=\"1\" WIDTH=\"200\" HEIGHT=\"100\">"));
+        assert!(alto.contains("<TextBlock ID=\"block_1\" HPOS=\"10\" VPOS=\"20\" WIDTH=\"160\" HEIGHT=\"45\">"));
+        assert!(alto.contains("<TextLine ID=\"line_2\" HPOS=\"10\" VPOS=\"50\" WIDTH=\"100\" HEIGHT=\"15\">"));
+        assert!(alto.contains(
+            "<String ID=\"string_1_2\" CONTENT=\"&lt;$5 &amp; up&gt;\" HPOS=\"80\" VPOS=\"20\" WIDTH=\"90\" HEIGHT=\"15\" WC=\"0.88\"/>"
+        ));
+        assert_eq!(alto.matches("<SP/>").count(), 2);
+    }
+
+    #[test]
+    fn text_without_word_boxes_is_spread_down_the_page() {
+        let result = OCRResult {
+            text: "Hello world\n\nSecond line".to_string(),
+            confidence: 0.6,
+            has_text: true,
+            words: Vec::new(),
+            entities: Vec::new(),
+            cache_hit: false,
+        };
+        // Blank lines are dropped, the rest share the height; words are sized by character count
+        let alto = export_text(&result, ExportFormat::Alto);
+        assert!(alto.contains("<TextLine ID=\"line_1\" HPOS=\"0\" VPOS=\"0\" WIDTH=\"200\" HEIGHT=\"50\">"));
+        assert!(alto.contains("CONTENT=\"Hello\" HPOS=\"0\" VPOS=\"0\" WIDTH=\"109\" HEIGHT=\"50\" WC=\"0.60\""));
+        assert!(alto.contains("CONTENT=\"world\" HPOS=\"109\" VPOS=\"0\" WIDTH=\"91\" HEIGHT=\"50\""));
+        assert!(alto.contains("<TextLine ID=\"line_2\" HPOS=\"0\" VPOS=\"50\" WIDTH=\"200\" HEIGHT=\"50\">"));
+
+        // Nothing recognized: an empty page, no text block
+        let empty = OCRResult { text: String::new(), has_text: false, ..result };
+        for format in [ExportFormat::Hocr, ExportFormat::Alto] {
+            let xml = export_text(&empty, format);
+            assert!(!xml.contains("class='ocr_line'") && !xml.contains("<TextBlock"), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn pdf_xref_offsets_point_at_their_objects() {
+        let pdf = export(&receipt(), &DynamicImage::new_rgb8(200, 100), ExportFormat::Pdf).unwrap();
+        let tail = String::from_utf8_lossy(&pdf[pdf.len() - 64..]).into_owned();
+        let startxref: usize = tail.split("startxref\n").nth(1).unwrap().lines().next().unwrap().parse().unwrap();
+        let xref = String::from_utf8_lossy(&pdf[startxref..]).into_owned();
+        assert!(xref.starts_with("xref\n0 10\n0000000000 65535 f \n"));
+
+        let offsets: Vec<usize> = xref
+            .lines()
+            .skip(3)
+            .take(9)
+            .map(|entry| entry.strip_suffix(" 00000 n ").unwrap().parse().unwrap())
+            .collect();
+        for (index, offset) in offsets.iter().enumerate() {
+            let header = format!("{} 0 obj\n", index + 1);
+            assert!(pdf[*offset..].starts_with(header.as_bytes()), "object {} is not at {}", index + 1, offset);
+        }
+        assert!(xref.contains("trailer\n<< /Size 10 /Root 1 0 R >>"));
+    }
+
+    #[test]
+    fn pdf_word_text_maps_back_through_to_unicode() {
+        let pdf = export(&receipt(), &DynamicImage::new_rgb8(200, 100), ExportFormat::Pdf).unwrap();
+        let pdf = String::from_utf8_lossy(&pdf).into_owned();
+
+        // The identity range in the ToUnicode CMap: code c maps to U+(start + c - low)
+        let range = Regex::new(r"beginbfrange\n<([0-9A-F]{4})> <([0-9A-F]{4})> <([0-9A-F]{4})>\nendbfrange").unwrap();
+        let bounds = range.captures(&pdf).unwrap();
+        let hex = |index: usize| u32::from_str_radix(&bounds[index], 16).unwrap();
+        let (low, high, start) = (hex(1), hex(2), hex(3));
+
+        let shown = Regex::new(r"<([0-9A-F]*)> Tj").unwrap();
+        let words: Vec<String> = shown
+            .captures_iter(&pdf)
+            .map(|text| {
+                (0..text[1].len())
+                    .step_by(4)
+                    .map(|at| u32::from_str_radix(&text[1][at..at + 4], 16).unwrap())
+                    .map(|code| {
+                        assert!((low..=high).contains(&code));
+                        char::from_u32(start + code - low).unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(words, ["Total:", "<$5 & up>", "\"it's\"", "Привет"]);
+    }
+}
@@ -0,0 +1,243 @@
+// Frame tracking for live region OCR: skips unchanged frames and diffs text between updates
+use image::DynamicImage;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::OCRResult;
+use crate::errors::FrameSenseError;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "op", content = "text", rename_all = "snake_case")]
+pub enum DiffLine {
+    Equal(String),
+    Added(String),
+    Removed(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextDiff {
+    pub lines: Vec<DiffLine>,
+    pub added: usize,
+    pub removed: usize,
+    pub added_text: String, // Just the new lines, which is what a log tail or subtitle consumer wants
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveOcrUpdate {
+    pub text: String,
+    pub confidence: f32,
+    pub diff: TextDiff,
+    pub frames_captured: u64,
+    pub frames_skipped: u64, // Pixel-identical to the previous frame, never sent to OCR
+    pub frames_recognized: u64,
+}
+
+pub struct LiveOcrTracker {
+    last_frame_hash: Option<[u8; 32]>,
+    last_text: String,
+    frames_captured: u64,
+    frames_skipped: u64,
+    frames_recognized: u64,
+}
+
+fn frame_hash(image: &DynamicImage) -> [u8; 32] {
+    let rgba = image.to_rgba8();
+    let mut hasher = Sha256::new();
+    hasher.update(rgba.width().to_le_bytes());
+    hasher.update(rgba.height().to_le_bytes());
+    hasher.update(rgba.as_raw());
+    hasher.finalize().into()
+}
+
+impl LiveOcrTracker {
+    pub fn new() -> Self {
+        Self {
+            last_frame_hash: None,
+            last_text: String::new(),
+            frames_captured: 0,
+            frames_skipped: 0,
+            frames_recognized: 0,
+        }
+    }
+
+    // Returns an update only when the recognized text actually changed. A frame
+    // can change without its text changing (cursor blink, hover highlight), so
+    // those are recognized but not reported.
+    pub fn process_frame(
+        &mut self,
+        frame: &DynamicImage,
+        recognize: impl FnOnce(&DynamicImage) -> Result<OCRResult, FrameSenseError>,
+    ) -> Result<Option<LiveOcrUpdate>, FrameSenseError> {
+        self.frames_captured += 1;
+
+        let hash = frame_hash(frame);
+        if self.last_frame_hash == Some(hash) {
+            self.frames_skipped += 1;
+            return Ok(None);
+        }
+
+        let result = recognize(frame)?;
+        self.last_frame_hash = Some(hash);
+        self.frames_recognized += 1;
+
+        let text = result.text.trim().to_string();
+        if text == self.last_text {
+            return Ok(None);
+        }
+
+        let diff = diff_text(&self.last_text, &text);
+        self.last_text = text.clone();
+
+        Ok(Some(LiveOcrUpdate {
+            text,
+            confidence: result.confidence,
+            diff,
+            frames_captured: self.frames_captured,
+            frames_skipped: self.frames_skipped,
+            frames_recognized: self.frames_recognized,
+        }))
+    }
+}
+
+impl Default for LiveOcrTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Line-level diff via longest common subsequence; live regions are small
+// (a subtitle band, a log tail) so the quadratic table is cheap
+pub fn diff_text(previous: &str, current: &str) -> TextDiff {
+    let old: Vec<&str> = previous.lines().collect();
+    let new: Vec<&str> = current.lines().collect();
+
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Equal(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|line| DiffLine::Removed(line.to_string())));
+    lines.extend(new[j..].iter().map(|line| DiffLine::Added(line.to_string())));
+
+    let added_lines: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| match line {
+            DiffLine::Added(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    TextDiff {
+        added: added_lines.len(),
+        removed: lines.iter().filter(|line| matches!(line, DiffLine::Removed(_))).count(),
+        added_text: added_lines.join("\n"),
+        lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn frame(marker: u8) -> DynamicImage {
+        let mut image = RgbaImage::from_pixel(32, 16, Rgba([255, 255, 255, 255]));
+        image.put_pixel(0, 0, Rgba([marker, 0, 0, 255]));
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn recognized(text: &str) -> impl FnOnce(&DynamicImage) -> Result<OCRResult, FrameSenseError> + '_ {
+        move |_| {
+            Ok(OCRResult {
+                text: text.to_string(),
+                confidence: 0.9,
+                has_text: !text.is_empty(),
+                words: Vec::new(),
+                entities: Vec::new(),
+                cache_hit: false,
+            })
+        }
+    }
+
+    fn not_recognized(_: &DynamicImage) -> Result<OCRResult, FrameSenseError> {
+        panic!("an unchanged frame was sent to OCR")
+    }
+
+    #[test]
+    fn diff_is_the_longest_common_subsequence_of_lines() {
+        let scrolled = diff_text("a\nb\nc", "b\nc\nd");
+        assert_eq!(scrolled.lines, [
+            DiffLine::Removed("a".into()),
+            DiffLine::Equal("b".into()),
+            DiffLine::Equal("c".into()),
+            DiffLine::Added("d".into()),
+        ]);
+        assert_eq!((scrolled.added, scrolled.removed, scrolled.added_text.as_str()), (1, 1, "d"));
+
+        let inserted = diff_text("x\ny", "x\nz\ny");
+        assert_eq!(inserted.lines, [DiffLine::Equal("x".into()), DiffLine::Added("z".into()), DiffLine::Equal("y".into())]);
+
+        // A changed line is a removal followed by an addition
+        let changed = diff_text("one\ntwo", "one\n2");
+        assert_eq!(changed.lines, [DiffLine::Equal("one".into()), DiffLine::Removed("two".into()), DiffLine::Added("2".into())]);
+
+        let first = diff_text("", "hello\nworld");
+        assert_eq!((first.added, first.removed, first.added_text.as_str()), (2, 0, "hello\nworld"));
+        assert_eq!(
+            serde_json::to_value(&first.lines[0]).unwrap(),
+            serde_json::json!({ "op": "added", "text": "hello" })
+        );
+    }
+
+    #[test]
+    fn identical_frames_are_skipped_and_unchanged_text_is_suppressed() {
+        let mut tracker = LiveOcrTracker::new();
+
+        let update = tracker.process_frame(&frame(1), recognized("Build started")).unwrap().unwrap();
+        assert_eq!((update.text.as_str(), update.diff.added_text.as_str()), ("Build started", "Build started"));
+        assert_eq!((update.frames_captured, update.frames_skipped, update.frames_recognized), (1, 0, 1));
+
+        // Same pixels: never reaches OCR
+        assert!(tracker.process_frame(&frame(1), not_recognized).unwrap().is_none());
+
+        // New pixels, same text once trimmed (a blinking cursor): recognized but not reported
+        assert!(tracker.process_frame(&frame(2), recognized("  Build started\n")).unwrap().is_none());
+
+        let update = tracker.process_frame(&frame(3), recognized("Build started\nCompiling core")).unwrap().unwrap();
+        assert_eq!(update.diff.added_text, "Compiling core");
+        assert_eq!(update.diff.removed, 0);
+        assert_eq!((update.frames_captured, update.frames_skipped, update.frames_recognized), (4, 1, 3));
+    }
+
+    #[test]
+    fn a_failed_recognition_is_retried_on_the_same_frame() {
+        let mut tracker = LiveOcrTracker::new();
+        let failed = tracker.process_frame(&frame(1), |_| Err(FrameSenseError::OcrUnavailable("engine busy".to_string())));
+        assert!(failed.is_err());
+
+        let update = tracker.process_frame(&frame(1), recognized("Ready")).unwrap().unwrap();
+        assert_eq!(update.text, "Ready");
+        assert_eq!((update.frames_captured, update.frames_skipped, update.frames_recognized), (2, 0, 1));
+    }
+}
@@ -0,0 +1,295 @@
+// OCR regression harness - runs the fixture corpus through the configured engine
+// and scores it against ground truth with character and word error rates
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{tesseract, OCRService, OCRSettings};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Fixture {
+    pub name: String,
+    pub image: String,        // Relative to the fixtures directory
+    pub ground_truth: String, // Relative to the fixtures directory
+    pub language: String,
+    pub theme: String,
+    pub category: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    fixtures: Vec<Fixture>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Baseline {
+    pub max_cer: f64,
+    pub max_wer: f64,
+}
+
+// Engine name -> fixture name -> allowed error rates
+type Baselines = BTreeMap<String, BTreeMap<String, Baseline>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixtureStatus {
+    Passed,
+    Regressed,
+    MissingBaseline,
+    Skipped(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FixtureScore {
+    pub name: String,
+    pub cer: f64,
+    pub wer: f64,
+    pub baseline: Option<Baseline>,
+    pub status: FixtureStatus,
+    pub recognized: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegressionReport {
+    pub engine: String,
+    pub scores: Vec<FixtureScore>,
+}
+
+pub fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("ocr")
+}
+
+pub fn run_suite(dir: &Path, settings: &OCRSettings) -> Result<RegressionReport, String> {
+    let manifest_json = fs::read_to_string(dir.join("manifest.json"))
+        .map_err(|e| format!("Failed to read OCR fixture manifest in {:?}: {}", dir, e))?;
+    let manifest: Manifest = serde_json::from_str(&manifest_json)
+        .map_err(|e| format!("Failed to parse OCR fixture manifest: {}", e))?;
+    let baselines = load_baselines(dir)?;
+    let engine_baselines = baselines.get(&settings.engine);
+
+    let installed_languages = if settings.engine == "tesseract" {
+        Some(tesseract::available_languages()?)
+    } else {
+        None
+    };
+
+    let mut scores = Vec::new();
+    for fixture in &manifest.fixtures {
+        let skip_reason = installed_languages.as_ref().and_then(|languages| {
+            fixture
+                .language
+                .split('+')
+                .find(|language| !languages.iter().any(|installed| installed == language))
+                .map(|missing| format!("language '{}' not installed", missing))
+        });
+        if let Some(reason) = skip_reason {
+            scores.push(FixtureScore {
+                name: fixture.name.clone(),
+                cer: 0.0,
+                wer: 0.0,
+                baseline: None,
+                status: FixtureStatus::Skipped(reason),
+                recognized: String::new(),
+            });
+            continue;
+        }
+
+        let image = image::open(dir.join(&fixture.image))
+            .map_err(|e| format!("Failed to load fixture image {}: {}", fixture.image, e))?;
+        let expected = fs::read_to_string(dir.join(&fixture.ground_truth))
+            .map_err(|e| format!("Failed to read ground truth {}: {}", fixture.ground_truth, e))?;
+
+        // No cache here: every run has to exercise the engine
+        let mut fixture_settings = settings.clone();
+        fixture_settings.language = fixture.language.clone();
+        let mut service = OCRService::new()?.with_settings(fixture_settings);
+        let recognized = service.extract_from_image(&image)?.text;
+
+        let cer = character_error_rate(&expected, &recognized);
+        let wer = word_error_rate(&expected, &recognized);
+        let baseline = engine_baselines.and_then(|fixtures| fixtures.get(&fixture.name)).copied();
+        let status = match baseline {
+            Some(baseline) if cer <= baseline.max_cer && wer <= baseline.max_wer => FixtureStatus::Passed,
+            Some(_) => FixtureStatus::Regressed,
+            None => FixtureStatus::MissingBaseline,
+        };
+
+        scores.push(FixtureScore {
+            name: fixture.name.clone(),
+            cer,
+            wer,
+            baseline,
+            status,
+            recognized,
+        });
+    }
+
+    Ok(RegressionReport {
+        engine: settings.engine.clone(),
+        scores,
+    })
+}
+
+impl RegressionReport {
+    pub fn passed(&self) -> bool {
+        self.scores.iter().all(|score| {
+            matches!(score.status, FixtureStatus::Passed | FixtureStatus::Skipped(_))
+        })
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!("OCR regression report ({})", self.engine)];
+
+        for score in &self.scores {
+            let line = match (&score.status, score.baseline) {
+                (FixtureStatus::Skipped(reason), _) => format!("⏭️  {:<24} skipped: {}", score.name, reason),
+                (status, Some(baseline)) => format!(
+                    "{} {:<24} CER {:.3} (max {:.3})  WER {:.3} (max {:.3})",
+                    if *status == FixtureStatus::Passed { "✅" } else { "❌" },
+                    score.name, score.cer, baseline.max_cer, score.wer, baseline.max_wer
+                ),
+                (_, None) => format!(
+                    "❌ {:<24} CER {:.3}  WER {:.3}  no baseline recorded",
+                    score.name, score.cer, score.wer
+                ),
+            };
+            lines.push(line);
+        }
+
+        let passed = self.scores.iter().filter(|score| score.status == FixtureStatus::Passed).count();
+        let skipped = self.scores.iter().filter(|score| matches!(score.status, FixtureStatus::Skipped(_))).count();
+        lines.push(format!(
+            "{} passed, {} failed, {} skipped",
+            passed,
+            self.scores.len() - passed - skipped,
+            skipped
+        ));
+        lines.join("\n")
+    }
+}
+
+fn load_baselines(dir: &Path) -> Result<Baselines, String> {
+    let path = dir.join("baselines.json");
+    if !path.exists() {
+        return Ok(Baselines::new());
+    }
+    let json = fs::read_to_string(&path).map_err(|e| format!("Failed to read OCR baselines: {}", e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse OCR baselines: {}", e))
+}
+
+// Records the measured rates plus `margin` as the new baselines for this engine.
+// Run with FRAMESENSE_OCR_UPDATE_BASELINES=1 to record the first ones from a real run, and
+// again after an intentional engine change.
+pub fn update_baselines(dir: &Path, report: &RegressionReport, margin: f64) -> Result<(), String> {
+    let mut baselines = load_baselines(dir)?;
+    let engine_baselines = baselines.entry(report.engine.clone()).or_default();
+
+    for score in &report.scores {
+        if matches!(score.status, FixtureStatus::Skipped(_)) {
+            continue;
+        }
+        let round_up = |rate: f64| ((rate + margin).min(1.0) * 1000.0).ceil() / 1000.0;
+        engine_baselines.insert(score.name.clone(), Baseline {
+            max_cer: round_up(score.cer),
+            max_wer: round_up(score.wer),
+        });
+    }
+
+    let json = serde_json::to_string_pretty(&baselines)
+        .map_err(|e| format!("Failed to serialize OCR baselines: {}", e))?;
+    fs::write(dir.join("baselines.json"), json + "\n")
+        .map_err(|e| format!("Failed to write OCR baselines: {}", e))
+}
+
+// Trims each line, collapses runs of whitespace and drops blank lines, so
+// layout differences the user can't see don't count as errors
+fn normalize_text(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn edit_distance<T: PartialEq>(expected: &[T], actual: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=actual.len()).collect();
+    let mut current = vec![0; actual.len() + 1];
+
+    for (i, expected_item) in expected.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, actual_item) in actual.iter().enumerate() {
+            let substitution = previous[j] + usize::from(expected_item != actual_item);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[actual.len()]
+}
+
+// Edits needed to turn the recognized text into the ground truth, per ground truth character
+pub fn character_error_rate(expected: &str, actual: &str) -> f64 {
+    let expected: Vec<char> = normalize_text(expected).chars().collect();
+    let actual: Vec<char> = normalize_text(actual).chars().collect();
+    if expected.is_empty() {
+        return if actual.is_empty() { 0.0 } else { 1.0 };
+    }
+    edit_distance(&expected, &actual) as f64 / expected.len() as f64
+}
+
+pub fn word_error_rate(expected: &str, actual: &str) -> f64 {
+    let expected = normalize_text(expected);
+    let actual = normalize_text(actual);
+    let expected: Vec<&str> = expected.split_whitespace().collect();
+    let actual: Vec<&str> = actual.split_whitespace().collect();
+    if expected.is_empty() {
+        return if actual.is_empty() { 0.0 } else { 1.0 };
+    }
+    edit_distance(&expected, &actual) as f64 / expected.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_rates_count_edits_against_ground_truth() {
+        assert_eq!(character_error_rate("hello world", "hello world"), 0.0);
+        assert_eq!(character_error_rate("abcd", "abxd"), 0.25);
+        assert_eq!(word_error_rate("the quick brown fox", "the quick brown"), 0.25);
+        assert_eq!(word_error_rate("one two", "one three"), 0.5);
+        assert_eq!(character_error_rate("", ""), 0.0);
+        assert_eq!(character_error_rate("", "noise"), 1.0);
+    }
+
+    #[test]
+    fn layout_whitespace_is_not_an_error() {
+        let expected = "fn main() {\n    println!(\"hi\");\n}\n";
+        let actual = "fn main() {\n\nprintln!(\"hi\");  \n}";
+        assert_eq!(character_error_rate(expected, actual), 0.0);
+        assert_eq!(word_error_rate(expected, actual), 0.0);
+    }
+
+    // Runs wherever Tesseract is installed; machines without it skip the corpus
+    #[test]
+    fn ocr_fixtures_meet_baselines() {
+        if !tesseract::is_available() {
+            println!("⚠️ Tesseract not installed - skipping the OCR regression corpus");
+            return;
+        }
+        let settings = OCRSettings::from_env();
+
+        let dir = fixtures_dir();
+        let report = run_suite(&dir, &settings).expect("OCR regression suite failed to run");
+        println!("{}", report.summary());
+
+        if std::env::var("FRAMESENSE_OCR_UPDATE_BASELINES").is_ok() {
+            update_baselines(&dir, &report, 0.02).expect("failed to update OCR baselines");
+            return;
+        }
+        assert!(
+            report.passed(),
+            "OCR accuracy dropped below baselines (record missing ones with FRAMESENSE_OCR_UPDATE_BASELINES=1):\n{}",
+            report.summary()
+        );
+    }
+}
@@ -0,0 +1,190 @@
+// Tesseract engine driven through the `tesseract` CLI, so builds don't need
+// the system libraries the tesseract crate links against
+use image::DynamicImage;
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use super::{OCRResult, OCRWord, TextBox};
+use crate::errors::FrameSenseError;
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn tesseract_binary() -> String {
+    std::env::var("FRAMESENSE_TESSERACT_PATH").unwrap_or_else(|_| "tesseract".to_string())
+}
+
+// Version line of the installed binary, e.g. "tesseract 5.3.0"
+pub fn version() -> Option<String> {
+    static VERSION: OnceLock<Option<String>> = OnceLock::new();
+    VERSION
+        .get_or_init(|| {
+            let output = Command::new(tesseract_binary()).arg("--version").output().ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            // Older releases print the version to stderr
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            format!("{}{}", stdout, stderr).lines().next().map(|line| line.trim().to_string())
+        })
+        .clone()
+}
+
+pub fn is_available() -> bool {
+    version().is_some()
+}
+
+pub fn available_languages() -> Result<Vec<String>, String> {
+    let output = Command::new(tesseract_binary())
+        .arg("--list-langs")
+        .output()
+        .map_err(|e| format!("Failed to run tesseract: {}", e))?;
+
+    // First line is a "List of available languages ..." header
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+// `scale` is how much the image was upscaled by preprocessing; boxes are
+// divided by it so they stay in capture coordinates
+pub fn recognize(img: &DynamicImage, language: &str, scale: f32) -> Result<OCRResult, FrameSenseError> {
+    let temp_path = std::env::temp_dir().join(format!(
+        "framesense-ocr-{}-{}.png",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    img.save(&temp_path)
+        .map_err(|e| FrameSenseError::Storage(format!("Failed to write OCR input image: {}", e)))?;
+
+    let output = Command::new(tesseract_binary())
+        .arg(&temp_path)
+        .arg("stdout")
+        .args(["-l", language])
+        .args(["--psm", "3"])
+        .arg("tsv")
+        .output();
+    let _ = std::fs::remove_file(&temp_path);
+
+    let output = output.map_err(|e| FrameSenseError::OcrUnavailable(format!("Failed to run tesseract: {}", e)))?;
+    // A failing run is almost always a missing language pack or a broken install
+    if !output.status.success() {
+        return Err(FrameSenseError::OcrUnavailable(format!(
+            "Tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(parse_tsv(&String::from_utf8_lossy(&output.stdout), scale))
+}
+
+// TSV columns: level page block par line word left top width height conf text
+fn parse_tsv(tsv: &str, scale: f32) -> OCRResult {
+    let mut lines: BTreeMap<(u32, u32, u32, u32), Vec<OCRWord>> = BTreeMap::new();
+
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != "5" {
+            continue;
+        }
+        let text = columns[11].trim();
+        let confidence: f32 = columns[10].parse().unwrap_or(-1.0);
+        if text.is_empty() || confidence < 0.0 {
+            continue;
+        }
+
+        let number = |index: usize| columns[index].parse::<u32>().unwrap_or(0);
+        let scaled = |index: usize| (number(index) as f32 / scale).round() as u32;
+        let key = (number(1), number(2), number(3), number(4));
+
+        lines.entry(key).or_default().push(OCRWord {
+            text: text.to_string(),
+            confidence: confidence / 100.0,
+            bbox: TextBox {
+                x: scaled(6),
+                y: scaled(7),
+                width: scaled(8),
+                height: scaled(9),
+            },
+            line: 0, // Assigned once all lines are known
+        });
+    }
+
+    let text = lines
+        .values()
+        .map(|words| words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let words: Vec<OCRWord> = lines
+        .into_values()
+        .enumerate()
+        .flat_map(|(line_index, words)| {
+            words.into_iter().map(move |word| OCRWord { line: line_index as u32, ..word })
+        })
+        .collect();
+    let confidence = if words.is_empty() {
+        0.0
+    } else {
+        words.iter().map(|word| word.confidence).sum::<f32>() / words.len() as f32
+    };
+
+    OCRResult {
+        has_text: !text.trim().is_empty(),
+        text,
+        confidence,
+        words,
+        entities: Vec::new(),
+        cache_hit: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext";
+
+    fn tsv(rows: &[&str]) -> String {
+        std::iter::once(HEADER).chain(rows.iter().copied()).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn groups_words_into_lines_in_reading_order() {
+        let result = parse_tsv(&tsv(&[
+            "1\t1\t0\t0\t0\t0\t0\t0\t800\t600\t-1\t",
+            "5\t1\t2\t1\t1\t1\t40\t400\t120\t30\t91.0\tFooter",
+            "5\t1\t1\t1\t1\t1\t20\t10\t100\t30\t96.5\tBuild",
+            "5\t1\t1\t1\t1\t2\t130\t10\t90\t30\t93.5\tfailed",
+            "5\t1\t1\t1\t2\t1\t20\t50\t60\t30\t80.0\terror:",
+            "5\t1\t1\t1\t2\t2\t90\t50\t10\t30\t-1\t ",
+        ]), 1.0);
+
+        assert_eq!(result.text, "Build failed\nerror:\nFooter");
+        assert!(result.has_text);
+        let lines: Vec<(&str, u32)> = result.words.iter().map(|word| (word.text.as_str(), word.line)).collect();
+        assert_eq!(lines, [("Build", 0), ("failed", 0), ("error:", 1), ("Footer", 2)]);
+        assert!((result.confidence - 0.9025).abs() < 1e-4);
+    }
+
+    #[test]
+    fn boxes_are_scaled_back_to_the_capture() {
+        // The capture was upscaled 2x before recognition
+        let result = parse_tsv(&tsv(&["5\t1\t1\t1\t1\t1\t41\t20\t101\t30\t90\tsmall"]), 2.0);
+        let bbox = &result.words[0].bbox;
+        assert_eq!((bbox.x, bbox.y, bbox.width, bbox.height), (21, 10, 51, 15));
+    }
+
+    #[test]
+    fn empty_or_malformed_output_has_no_text() {
+        for output in ["", HEADER, &tsv(&["5\t1\t1\t1\t1\t1\t0\t0\t10\t10\t95"]), &tsv(&["5\t1\t1\t1\t1\t1\t0\t0\t10\t10\tx\tword"])] {
+            let result = parse_tsv(output, 1.0);
+            assert!(!result.has_text && result.words.is_empty(), "{:?}", output);
+            assert_eq!(result.confidence, 0.0);
+        }
+    }
+}
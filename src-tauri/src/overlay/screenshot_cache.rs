@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use crate::CaptureBounds;
+use crate::errors::FrameSenseError;
 use base64::Engine;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -51,7 +52,7 @@ impl ScreenshotCache {
         }
     }
     
-    pub fn capture_optimized(&mut self, bounds: CaptureBounds) -> Result<String, String> {
+    pub fn capture_optimized(&mut self, bounds: CaptureBounds) -> Result<String, FrameSenseError> {
         let bounds_key = BoundsKey::from(bounds.clone());
         
         // 1. Cache check
@@ -81,7 +82,7 @@ impl ScreenshotCache {
         Ok(image_data)
     }
     
-    fn capture_with_reused_buffer(&mut self, bounds: CaptureBounds) -> Result<String, String> {
+    fn capture_with_reused_buffer(&mut self, bounds: CaptureBounds) -> Result<String, FrameSenseError> {
         // Använd screenshots library men med optimerad encoding
         match screenshots::Screen::all() {
             Ok(screens) => {
@@ -97,7 +98,7 @@ impl ScreenshotCache {
                     
                     // Ensure minimum size
                     if safe_width < 10 || safe_height < 10 {
-                        return Err(format!("Capture area too small after adjustment: {}x{}", safe_width, safe_height));
+                        return Err(FrameSenseError::BoundsInvalid(format!("Capture area too small after adjustment: {}x{}", safe_width, safe_height)));
                     }
                     
                     match screen.capture_area(safe_x, safe_y, safe_width, safe_height) {
@@ -115,16 +116,16 @@ impl ScreenshotCache {
                                     println!("📸 Optimized capture: {}KB", png_data.len() / 1024);
                                     Ok(full_data)
                                 },
-                                Err(e) => Err(format!("PNG encoding failed: {}", e))
+                                Err(e) => Err(FrameSenseError::Other(format!("PNG encoding failed: {}", e)))
                             }
                         },
-                        Err(e) => Err(format!("Screen capture failed: {}", e))
+                        Err(e) => Err(FrameSenseError::CapturePermission(format!("Screen capture failed: {}", e)))
                     }
                 } else {
-                    Err("No screens available".to_string())
+                    Err(FrameSenseError::NoDisplay)
                 }
             },
-            Err(e) => Err(format!("Failed to access screens: {}", e))
+            Err(e) => Err(FrameSenseError::CapturePermission(format!("Failed to access screens: {}", e)))
         }
     }
     
@@ -173,7 +174,7 @@ impl ScreenshotCache {
                  self.cache.len(), freed_space / 1024);
     }
     
-    fn get_screen_info(&self) -> Result<ScreenInfo, String> {
+    fn get_screen_info(&self) -> Result<ScreenInfo, FrameSenseError> {
         match screenshots::Screen::all() {
             Ok(screens) => {
                 if let Some(screen) = screens.first() {
@@ -184,10 +185,10 @@ impl ScreenshotCache {
                         cached_at: Instant::now(),
                     })
                 } else {
-                    Err("No screens available".to_string())
+                    Err(FrameSenseError::NoDisplay)
                 }
             },
-            Err(e) => Err(format!("Failed to get screen info: {}", e))
+            Err(e) => Err(FrameSenseError::CapturePermission(format!("Failed to get screen info: {}", e)))
         }
     }
     
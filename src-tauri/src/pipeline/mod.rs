@@ -0,0 +1,562 @@
+// Capture processing pipelines - a capture goes through a list of stages defined in
+// config (capture → preprocess → OCR → redact → AI → ground → postprocess), each one timed and
+// leaving an artifact behind, so it's possible to tell what happened to a capture.
+// Screen selections, OCR requests, quick actions, live OCR and batch runs all go through here.
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::actions::{CaptureTarget, OutputFormat};
+use crate::analysis::grounding::Highlight;
+use crate::analysis::{AnalysisRequest, AnalysisResult};
+use crate::errors::FrameSenseError;
+use crate::ocr::OCRResult;
+
+pub mod stages;
+
+pub use stages::{AiStage, CaptureStage, GroundStage, OcrStage, PostprocessStage, PreprocessStage, RedactStage};
+
+const PIPELINES_FORMAT_VERSION: u32 = 1;
+// Debug runs kept on disk; older run directories are removed
+const KEPT_RUNS: usize = 20;
+
+pub const QUICK_ACTION_PIPELINE: &str = "quick_action";
+pub const PRIVATE_ACTION_PIPELINE: &str = "private_quick_action";
+pub const LIVE_OCR_PIPELINE: &str = "live_ocr";
+pub const BATCH_OCR_PIPELINE: &str = "batch_ocr";
+pub const OCR_PIPELINE: &str = "ocr";
+pub const SELECTION_PIPELINE: &str = "selection";
+
+// What stages need from the app. main.rs and live OCR provide real ones; stages
+// never reach for app state themselves.
+pub trait PipelineHost {
+    fn capture(&self, target: CaptureTarget) -> Result<DynamicImage, FrameSenseError>;
+    fn recognize(&self, image: &DynamicImage) -> Result<OCRResult, FrameSenseError>;
+    fn analyze(&self, request: &AnalysisRequest) -> Result<AnalysisResult, FrameSenseError>;
+}
+
+pub trait Stage {
+    fn name(&self) -> &'static str;
+
+    // Whether the stage changes the image, so debug runs save a copy after it
+    fn touches_image(&self) -> bool {
+        false
+    }
+
+    // Transforms the context; the returned value is kept as the stage's artifact
+    fn run(&self, context: &mut PipelineContext, host: &dyn PipelineHost) -> Result<Value, FrameSenseError>;
+}
+
+// One entry of a pipeline definition; `"stage"` picks the kind, the other keys are its settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum StageConfig {
+    Capture(CaptureStage),
+    Preprocess(PreprocessStage),
+    Ocr(OcrStage),
+    Redact(RedactStage),
+    Ai(AiStage),
+    Ground(GroundStage),
+    Postprocess(PostprocessStage),
+}
+
+impl StageConfig {
+    pub fn stage(&self) -> &dyn Stage {
+        match self {
+            StageConfig::Capture(stage) => stage,
+            StageConfig::Preprocess(stage) => stage,
+            StageConfig::Ocr(stage) => stage,
+            StageConfig::Redact(stage) => stage,
+            StageConfig::Ai(stage) => stage,
+            StageConfig::Ground(stage) => stage,
+            StageConfig::Postprocess(stage) => stage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineDefinition {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub stages: Vec<StageConfig>,
+    #[serde(default)]
+    pub keep_artifacts: bool, // Save each run's intermediate images and report for debugging
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+// What flows through the stages. Callers fill in what they already have (a frame
+// from live OCR, a batch image, the quick action's prompt) and read the rest back.
+#[derive(Default)]
+pub struct PipelineContext {
+    pub image: Option<DynamicImage>,
+    pub capture_target: CaptureTarget, // Used by capture stages that don't set their own target
+    pub ocr: Option<OCRResult>,
+    pub text: String, // OCR text, redacted once a redact stage ran
+    pub app_name: String,
+    pub prompt: Option<String>, // Template for AI stages that don't set their own prompt
+    pub model: Option<String>,
+    pub output_format: OutputFormat,
+    pub request: Option<AnalysisRequest>, // What the AI stage sent, so a failure can be queued
+    pub answer: Option<AnalysisResult>,
+    pub output: Option<String>, // Postprocessed answer (or text when there is no AI stage)
+    pub highlights: Vec<Highlight>, // Where the answer points in the capture, from a ground stage
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageReport {
+    pub stage: String,
+    pub duration_ms: u64,
+    pub success: bool,
+    pub error: Option<FrameSenseError>,
+    pub artifact: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineReport {
+    pub run_id: String,
+    pub pipeline: String,
+    pub success: bool,
+    pub error: Option<FrameSenseError>, // Error of the stage that stopped the run
+    pub total_ms: u64,
+    pub stages: Vec<StageReport>,
+    pub text: String,
+    pub answer: Option<AnalysisResult>,
+    pub output: Option<String>,
+    pub highlights: Vec<Highlight>,
+    pub artifacts_dir: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PipelineBundle {
+    version: u32,
+    pipelines: Vec<PipelineDefinition>,
+}
+
+// Built-in pipelines plus the user's own; a user pipeline with a built-in's id replaces it
+pub struct PipelineStore {
+    path: PathBuf,
+    pipelines: Vec<PipelineDefinition>,
+}
+
+fn millis() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+}
+
+fn builtin_pipelines() -> Vec<PipelineDefinition> {
+    let pipeline = |id: &str, name: &str, description: &str, stages: Vec<StageConfig>| PipelineDefinition {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        stages,
+        keep_artifacts: false,
+        builtin: true,
+    };
+    let ai = || StageConfig::Ai(AiStage::default());
+    vec![
+        pipeline(
+            QUICK_ACTION_PIPELINE,
+            "Quick action",
+            "Capture, read the text and ask the model",
+            vec![
+                StageConfig::Capture(CaptureStage::default()),
+                StageConfig::Ocr(OcrStage { optional: true }),
+                ai(),
+                StageConfig::Ground(GroundStage::default()),
+            ],
+        ),
+        pipeline(
+            PRIVATE_ACTION_PIPELINE,
+            "Quick action without personal data",
+            "Like a quick action, but emails, phone numbers and IP addresses are removed from the text and blacked out in the image first",
+            vec![
+                StageConfig::Capture(CaptureStage::default()),
+                StageConfig::Ocr(OcrStage { optional: false }),
+                StageConfig::Redact(RedactStage {
+                    kinds: stages::default_redact_kinds(),
+                    image: true,
+                }),
+                ai(),
+                StageConfig::Ground(GroundStage::default()),
+            ],
+        ),
+        pipeline(
+            LIVE_OCR_PIPELINE,
+            "Live OCR",
+            "Reads each changed frame of a live OCR region",
+            vec![StageConfig::Capture(CaptureStage::default()), StageConfig::Ocr(OcrStage { optional: false })],
+        ),
+        pipeline(
+            BATCH_OCR_PIPELINE,
+            "Batch OCR",
+            "Reads the text of every image in a batch",
+            vec![
+                StageConfig::Ocr(OcrStage { optional: false }),
+                StageConfig::Postprocess(PostprocessStage {
+                    steps: vec!["trim".to_string(), "collapse_blank_lines".to_string()],
+                }),
+            ],
+        ),
+        pipeline(
+            OCR_PIPELINE,
+            "OCR",
+            "Reads the text of a capture the window sends",
+            vec![StageConfig::Ocr(OcrStage { optional: false })],
+        ),
+        pipeline(
+            SELECTION_PIPELINE,
+            "Screen selection",
+            "Captures the area selected with the overlay for the main window",
+            vec![StageConfig::Capture(CaptureStage::default())],
+        ),
+    ]
+}
+
+impl PipelineDefinition {
+    // Rejects definitions that can't run, before a capture is spent on them
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty() || !self.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("Invalid pipeline id '{}': use letters, digits, '-' and '_'", self.id));
+        }
+        if self.stages.is_empty() {
+            return Err(format!("Pipeline '{}' has no stages", self.id));
+        }
+        for (index, config) in self.stages.iter().enumerate() {
+            let needs_ocr = matches!(config, StageConfig::Redact(_));
+            let has_ocr = self.stages[..index].iter().any(|earlier| matches!(earlier, StageConfig::Ocr(_)));
+            if needs_ocr && !has_ocr {
+                return Err(format!("Pipeline '{}': redact needs an OCR stage before it", self.id));
+            }
+            let has_ai = self.stages[..index].iter().any(|earlier| matches!(earlier, StageConfig::Ai(_)));
+            if matches!(config, StageConfig::Ground(_)) && !(has_ocr && has_ai) {
+                return Err(format!("Pipeline '{}': ground needs OCR and AI stages before it", self.id));
+            }
+            if let StageConfig::Postprocess(stage) = config {
+                stage.validate()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_ai_stage(&self) -> bool {
+        self.stages.iter().any(|config| matches!(config, StageConfig::Ai(_)))
+    }
+
+    // Runs the stages in order, stopping at the first one that fails. With
+    // `artifacts_root` (or `keep_artifacts`) the run's images and report are saved
+    // in a directory of their own under it.
+    pub fn run(&self, context: &mut PipelineContext, host: &dyn PipelineHost, artifacts_root: Option<&Path>) -> PipelineReport {
+        let run_id = format!("{}-{}", self.id, millis());
+        let started = Instant::now();
+        let artifacts_dir = artifacts_root.map(|root| root.join(&run_id));
+        if let Some(dir) = &artifacts_dir {
+            if let Err(e) = fs::create_dir_all(dir) {
+                println!("⚠️ Failed to create pipeline artifacts directory: {}", e);
+            }
+        }
+
+        let mut reports = Vec::new();
+        let mut error = None;
+        for (index, config) in self.stages.iter().enumerate() {
+            let stage = config.stage();
+            let stage_started = Instant::now();
+            let outcome = stage.run(context, host);
+            let duration_ms = stage_started.elapsed().as_millis() as u64;
+
+            if let (Some(dir), Some(image), true) = (&artifacts_dir, &context.image, stage.touches_image()) {
+                let path = dir.join(format!("{:02}-{}.png", index + 1, stage.name()));
+                if let Err(e) = image.save(&path) {
+                    println!("⚠️ Failed to save pipeline artifact {:?}: {}", path, e);
+                }
+            }
+
+            match outcome {
+                Ok(artifact) => reports.push(StageReport {
+                    stage: stage.name().to_string(),
+                    duration_ms,
+                    success: true,
+                    error: None,
+                    artifact,
+                }),
+                Err(stage_error) => {
+                    println!("❌ Pipeline {} stopped at {}: {}", self.id, stage.name(), stage_error);
+                    reports.push(StageReport {
+                        stage: stage.name().to_string(),
+                        duration_ms,
+                        success: false,
+                        error: Some(stage_error.clone()),
+                        artifact: Value::Null,
+                    });
+                    error = Some(stage_error);
+                    break;
+                },
+            }
+        }
+
+        let report = PipelineReport {
+            run_id,
+            pipeline: self.id.clone(),
+            success: error.is_none(),
+            error,
+            total_ms: started.elapsed().as_millis() as u64,
+            stages: reports,
+            text: context.text.clone(),
+            answer: context.answer.clone(),
+            output: context.output.clone(),
+            highlights: context.highlights.clone(),
+            artifacts_dir,
+        };
+        let timings: Vec<String> = report
+            .stages
+            .iter()
+            .map(|stage| format!("{} {}ms", stage.stage, stage.duration_ms))
+            .collect();
+        println!("🧪 Pipeline {} finished in {}ms ({})", self.id, report.total_ms, timings.join(", "));
+
+        if let Some(dir) = &report.artifacts_dir {
+            let written = serde_json::to_string_pretty(&report)
+                .map_err(|e| e.to_string())
+                .and_then(|json| fs::write(dir.join("report.json"), json).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                println!("⚠️ Failed to save pipeline report: {}", e);
+            }
+            if let Some(root) = dir.parent() {
+                prune_runs(root, KEPT_RUNS);
+            }
+        }
+        report
+    }
+}
+
+impl PipelineContext {
+    pub fn from_image(image: DynamicImage) -> Self {
+        Self {
+            image: Some(image),
+            ..Self::default()
+        }
+    }
+
+    // The OCR result with the pipeline's final text (redacted, postprocessed), for
+    // callers that only want text out of a pipeline
+    pub fn ocr_result(&self) -> Result<OCRResult, FrameSenseError> {
+        let mut result = self.ocr.clone().ok_or("The pipeline has no OCR stage")?;
+        result.text = self.output.clone().unwrap_or_else(|| self.text.clone());
+        Ok(result)
+    }
+}
+
+// Keeps the newest `keep` run directories
+fn prune_runs(root: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let mut runs: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    if runs.len() <= keep {
+        return;
+    }
+    runs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in runs.into_iter().skip(keep) {
+        if let Err(e) = fs::remove_dir_all(&path) {
+            println!("⚠️ Failed to remove old pipeline run {:?}: {}", path, e);
+        }
+    }
+}
+
+impl PipelineStore {
+    // A missing file means only the built-ins; an unreadable one is kept aside, not overwritten
+    pub fn load(path: PathBuf) -> Self {
+        let pipelines = match fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str::<PipelineBundle>(&json) {
+                Ok(bundle) => {
+                    println!("💾 Loaded {} pipelines from {:?}", bundle.pipelines.len(), path);
+                    bundle.pipelines
+                },
+                Err(e) => {
+                    println!("⚠️ Failed to parse pipelines, using the built-in ones: {}", e);
+                    let _ = fs::rename(&path, path.with_extension("json.invalid"));
+                    Vec::new()
+                },
+            },
+            Err(_) => Vec::new(),
+        };
+        Self { path, pipelines }
+    }
+
+    pub fn list(&self) -> Vec<PipelineDefinition> {
+        let mut pipelines: Vec<PipelineDefinition> = builtin_pipelines()
+            .into_iter()
+            .map(|builtin| self.pipelines.iter().find(|own| own.id == builtin.id).cloned().unwrap_or(builtin))
+            .collect();
+        pipelines.extend(
+            self.pipelines
+                .iter()
+                .filter(|own| !pipelines.iter().any(|listed| listed.id == own.id))
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+        pipelines
+    }
+
+    pub fn get(&self, id: &str) -> Result<PipelineDefinition, String> {
+        self.list()
+            .into_iter()
+            .find(|pipeline| pipeline.id == id)
+            .ok_or_else(|| format!("Pipeline not found: {}", id))
+    }
+
+    pub fn upsert(&mut self, mut pipeline: PipelineDefinition) -> Result<PipelineDefinition, String> {
+        pipeline.validate()?;
+        pipeline.builtin = false;
+        match self.pipelines.iter_mut().find(|own| own.id == pipeline.id) {
+            Some(existing) => *existing = pipeline.clone(),
+            None => self.pipelines.push(pipeline.clone()),
+        }
+        self.save()?;
+        println!("🧪 Saved pipeline '{}' ({} stages)", pipeline.id, pipeline.stages.len());
+        Ok(pipeline)
+    }
+
+    // Removing an edited built-in restores the original
+    pub fn remove(&mut self, id: &str) -> Result<(), String> {
+        let before = self.pipelines.len();
+        self.pipelines.retain(|own| own.id != id);
+        if self.pipelines.len() == before {
+            return Err(if builtin_pipelines().iter().any(|builtin| builtin.id == id) {
+                format!("'{}' is a built-in pipeline and can't be deleted", id)
+            } else {
+                format!("Pipeline not found: {}", id)
+            });
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+        let bundle = PipelineBundle {
+            version: PIPELINES_FORMAT_VERSION,
+            pipelines: self.pipelines.clone(),
+        };
+        let json = serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to serialize pipelines: {}", e))?;
+        fs::write(&self.path, json).map_err(|e| format!("Failed to save pipelines: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::{OCRWord, TextBox};
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    struct FakeHost;
+
+    impl PipelineHost for FakeHost {
+        fn capture(&self, _target: CaptureTarget) -> Result<DynamicImage, FrameSenseError> {
+            Ok(DynamicImage::ImageRgba8(RgbaImage::from_pixel(200, 40, Rgba([255, 255, 255, 255]))))
+        }
+
+        fn recognize(&self, _image: &DynamicImage) -> Result<OCRResult, FrameSenseError> {
+            let word = |text: &str, x| OCRWord {
+                text: text.to_string(),
+                confidence: 0.9,
+                bbox: TextBox { x, y: 10, width: 60, height: 20 },
+                line: 0,
+            };
+            Ok(OCRResult {
+                text: "Contact anna@example.com".to_string(),
+                confidence: 0.9,
+                has_text: true,
+                words: vec![word("Contact", 0), word("anna@example.com", 100)],
+                entities: Vec::new(),
+                cache_hit: false,
+            })
+        }
+
+        fn analyze(&self, request: &AnalysisRequest) -> Result<AnalysisResult, FrameSenseError> {
+            Ok(AnalysisResult {
+                answer: format!("```\nSaw: {}\n```", request.ocr_text.clone().unwrap_or_default()),
+                model: request.model.clone(),
+                tokens_used: None,
+                streamed: false,
+                cached: false,
+            })
+        }
+    }
+
+    fn definition(stages: &str) -> PipelineDefinition {
+        serde_json::from_str(&format!(r#"{{ "id": "test", "name": "Test", "stages": {} }}"#, stages)).unwrap()
+    }
+
+    #[test]
+    fn runs_configured_stages_in_order_with_artifacts() {
+        let pipeline = definition(
+            r#"[
+                { "stage": "capture" },
+                { "stage": "ocr" },
+                { "stage": "redact", "kinds": ["email"] },
+                { "stage": "ai", "prompt": "Summarize {ocr_text}", "model": "gpt-4o-mini" },
+                { "stage": "postprocess", "steps": ["strip_code_fences", "trim"] }
+            ]"#,
+        );
+        pipeline.validate().unwrap();
+
+        let mut context = PipelineContext::default();
+        let report = pipeline.run(&mut context, &FakeHost, None);
+        assert!(report.success, "{:?}", report.error);
+        let names: Vec<&str> = report.stages.iter().map(|stage| stage.stage.as_str()).collect();
+        assert_eq!(names, ["capture", "ocr", "redact", "ai", "postprocess"]);
+
+        assert_eq!(context.text, "Contact [email]");
+        assert_eq!(report.stages[2].artifact["redacted"]["email"], 1);
+        assert_eq!(context.image.as_ref().unwrap().get_pixel(120, 20), Rgba([0, 0, 0, 255]));
+        assert_eq!(context.image.as_ref().unwrap().get_pixel(20, 20), Rgba([255, 255, 255, 255]));
+        assert_eq!(report.stages[3].artifact["prompt"], "Summarize Contact [email]");
+        assert_eq!(report.output.as_deref(), Some("Saw: Contact [email]"));
+    }
+
+    #[test]
+    fn stops_at_the_first_failing_stage() {
+        let pipeline = definition(r#"[{ "stage": "capture" }, { "stage": "ai" }, { "stage": "postprocess", "steps": ["trim"] }]"#);
+        let report = pipeline.run(&mut PipelineContext::default(), &FakeHost, None);
+        assert!(!report.success);
+        assert_eq!(report.stages.len(), 2);
+        assert_eq!(report.error.map(|error| error.to_string()).as_deref(), Some("The AI stage has no prompt"));
+
+        assert!(definition(r#"[{ "stage": "redact" }]"#).validate().is_err());
+        assert!(definition(r#"[{ "stage": "postprocess", "steps": ["shout"] }]"#).validate().is_err());
+    }
+
+    #[test]
+    fn builtin_pipelines_are_valid() {
+        for pipeline in builtin_pipelines() {
+            pipeline.validate().unwrap_or_else(|e| panic!("{}: {}", pipeline.id, e));
+        }
+    }
+
+    #[test]
+    fn user_pipelines_override_builtins_until_removed() {
+        let path = std::env::temp_dir().join(format!("framesense-pipelines-{}.json", millis()));
+        let mut store = PipelineStore::load(path.clone());
+        assert!(store.get(LIVE_OCR_PIPELINE).unwrap().builtin);
+
+        let mut custom = definition(r#"[{ "stage": "capture" }, { "stage": "preprocess", "steps": ["grayscale"] }, { "stage": "ocr" }]"#);
+        custom.id = LIVE_OCR_PIPELINE.to_string();
+        store.upsert(custom).unwrap();
+        assert_eq!(PipelineStore::load(path.clone()).get(LIVE_OCR_PIPELINE).unwrap().stages.len(), 3);
+
+        store.remove(LIVE_OCR_PIPELINE).unwrap();
+        assert!(store.get(LIVE_OCR_PIPELINE).unwrap().builtin);
+        assert!(store.remove(LIVE_OCR_PIPELINE).is_err());
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,365 @@
+// The pipeline stages. Each one is its own config struct, deserialized from the
+// pipeline definition, and implements `Stage`.
+use base64::Engine;
+use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Cursor;
+
+use super::{PipelineContext, PipelineHost, Stage};
+use crate::actions::{render_template, CaptureTarget, OutputFormat, TemplateVars};
+use crate::analysis::grounding::ground_answer;
+use crate::analysis::AnalysisRequest;
+use crate::errors::FrameSenseError;
+use crate::ocr::entities::EntityKind;
+use crate::ocr::{extract_entities, preprocess};
+
+const POSTPROCESS_STEPS: [&str; 4] = ["trim", "strip_code_fences", "collapse_blank_lines", "single_line"];
+
+fn yes() -> bool {
+    true
+}
+
+pub fn default_redact_kinds() -> Vec<EntityKind> {
+    vec![EntityKind::Email, EntityKind::Phone, EntityKind::IpAddress]
+}
+
+fn kind_name(kind: EntityKind) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{:?}", kind))
+}
+
+// Captures the screen, unless the caller already supplied the image (a live OCR
+// frame, a batch image)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureStage {
+    #[serde(default)]
+    pub target: Option<CaptureTarget>, // None uses the caller's target
+}
+
+impl Stage for CaptureStage {
+    fn name(&self) -> &'static str {
+        "capture"
+    }
+
+    fn touches_image(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &mut PipelineContext, host: &dyn PipelineHost) -> Result<Value, FrameSenseError> {
+        let supplied = context.image.is_some();
+        if !supplied {
+            context.image = Some(host.capture(self.target.unwrap_or(context.capture_target))?);
+        }
+        let (width, height) = context.image.as_ref().map(|image| image.dimensions()).unwrap_or_default();
+        Ok(json!({ "width": width, "height": height, "supplied": supplied }))
+    }
+}
+
+// Image preprocessing with the OCR preprocessing steps (grayscale, invert, auto_invert, upscale2x)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreprocessStage {
+    pub steps: Vec<String>,
+}
+
+impl Stage for PreprocessStage {
+    fn name(&self) -> &'static str {
+        "preprocess"
+    }
+
+    fn touches_image(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &mut PipelineContext, _host: &dyn PipelineHost) -> Result<Value, FrameSenseError> {
+        let image = context.image.as_ref().ok_or("Nothing to preprocess, the pipeline has no image")?;
+        let (prepared, scale) = preprocess(image, &self.steps);
+        let (width, height) = prepared.dimensions();
+        context.image = Some(prepared);
+        Ok(json!({ "steps": self.steps, "width": width, "height": height, "scale": scale }))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OcrStage {
+    #[serde(default)]
+    pub optional: bool, // Carry on without text when OCR fails; the model still gets the image
+}
+
+impl Stage for OcrStage {
+    fn name(&self) -> &'static str {
+        "ocr"
+    }
+
+    fn run(&self, context: &mut PipelineContext, host: &dyn PipelineHost) -> Result<Value, FrameSenseError> {
+        let image = context.image.as_ref().ok_or("Nothing to read, the pipeline has no image")?;
+        match host.recognize(image) {
+            Ok(result) => {
+                context.text = result.text.clone();
+                let artifact = json!({
+                    "text": result.text,
+                    "confidence": result.confidence,
+                    "words": result.words.len(),
+                    "entities": result.entities.len(),
+                    "cache_hit": result.cache_hit
+                });
+                context.ocr = Some(result);
+                Ok(artifact)
+            },
+            Err(e) if self.optional => {
+                println!("⚠️ Optional OCR stage failed, continuing without text: {}", e);
+                Ok(json!({ "skipped": e.to_string() }))
+            },
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// Removes personal data from the OCR text before it leaves the machine, and with
+// `image` also blacks it out in the capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactStage {
+    #[serde(default = "default_redact_kinds")]
+    pub kinds: Vec<EntityKind>,
+    #[serde(default = "yes")]
+    pub image: bool,
+}
+
+impl Stage for RedactStage {
+    fn name(&self) -> &'static str {
+        "redact"
+    }
+
+    fn touches_image(&self) -> bool {
+        self.image
+    }
+
+    fn run(&self, context: &mut PipelineContext, _host: &dyn PipelineHost) -> Result<Value, FrameSenseError> {
+        let words = context.ocr.as_ref().map(|ocr| ocr.words.clone()).unwrap_or_default();
+        let mut entities: Vec<_> = extract_entities(&context.text, &words)
+            .into_iter()
+            .filter(|entity| self.kinds.contains(&entity.kind))
+            .collect();
+        // Replace from the end so earlier byte offsets stay valid
+        entities.sort_by_key(|entity| std::cmp::Reverse(entity.start));
+
+        let mut counts = serde_json::Map::new();
+        let mut boxes = 0;
+        for entity in &entities {
+            let kind = kind_name(entity.kind);
+            context.text.replace_range(entity.start..entity.end, &format!("[{}]", kind));
+            let count = counts.get(&kind).and_then(Value::as_u64).unwrap_or(0);
+            counts.insert(kind, json!(count + 1));
+
+            if let (true, Some(image)) = (self.image, context.image.as_mut()) {
+                for text_box in &entity.boxes {
+                    let (width, height) = image.dimensions();
+                    for y in text_box.y..(text_box.y + text_box.height).min(height) {
+                        for x in text_box.x..(text_box.x + text_box.width).min(width) {
+                            image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+                        }
+                    }
+                    boxes += 1;
+                }
+            }
+        }
+
+        if !entities.is_empty() {
+            println!("🕶️ Redacted {} entities ({} boxes blacked out)", entities.len(), boxes);
+        }
+        Ok(json!({ "redacted": counts, "boxes": boxes, "text": context.text }))
+    }
+}
+
+// Asks the model. Prompt, model and output format fall back to the caller's (a
+// quick action's) when the stage doesn't set them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiStage {
+    #[serde(default)]
+    pub prompt: Option<String>, // Template with {ocr_text}, {app_name} and {selection_size}
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub output_format: Option<OutputFormat>,
+    #[serde(default = "yes")]
+    pub include_image: bool,
+    #[serde(default)]
+    pub region_references: bool, // Ask for [[ref: ...]] markers for a later ground stage
+}
+
+impl Default for AiStage {
+    fn default() -> Self {
+        Self {
+            prompt: None,
+            model: None,
+            output_format: None,
+            include_image: true,
+            region_references: false,
+        }
+    }
+}
+
+pub fn encode_png(image: &DynamicImage) -> Result<String, String> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .map_err(|e| format!("PNG conversion failed: {}", e))?;
+    Ok(format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(&png)))
+}
+
+impl Stage for AiStage {
+    fn name(&self) -> &'static str {
+        "ai"
+    }
+
+    fn run(&self, context: &mut PipelineContext, host: &dyn PipelineHost) -> Result<Value, FrameSenseError> {
+        let template = self
+            .prompt
+            .clone()
+            .or_else(|| context.prompt.clone())
+            .ok_or("The AI stage has no prompt")?;
+        let (width, height) = context.image.as_ref().map(|image| image.dimensions()).unwrap_or_default();
+        let vars = TemplateVars {
+            ocr_text: context.text.clone(),
+            app_name: context.app_name.clone(),
+            selection_size: format!("{}x{}", width, height),
+        };
+        let question = self
+            .output_format
+            .unwrap_or(context.output_format)
+            .apply(render_template(&template, &vars));
+
+        let image_data = match (&context.image, self.include_image) {
+            (Some(image), true) => Some(encode_png(image)?),
+            _ => None,
+        };
+        let request = AnalysisRequest {
+            question,
+            image_data,
+            model: self.model.clone().or_else(|| context.model.clone()),
+            ocr_text: Some(context.text.clone()).filter(|text| !text.trim().is_empty()),
+            history: Vec::new(),
+            captures: Vec::new(),
+            force_refresh: false,
+            region_references: self.region_references,
+        };
+        context.request = Some(request.clone());
+
+        let answer = host.analyze(&request)?;
+        let artifact = json!({
+            "prompt": request.question,
+            "model": answer.model,
+            "answer": answer.answer,
+            "tokens_used": answer.tokens_used,
+            "cached": answer.cached
+        });
+        context.answer = Some(answer);
+        Ok(artifact)
+    }
+}
+
+// Finds the answer's quoted and [[ref: ...]] text among the OCR words, so the capture can
+// be outlined where the answer points. Markers are reduced to their text in the output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroundStage {
+    #[serde(default = "yes")]
+    pub quotes: bool, // Also ground quoted text, not only explicit markers
+}
+
+impl Default for GroundStage {
+    fn default() -> Self {
+        Self { quotes: true }
+    }
+}
+
+impl Stage for GroundStage {
+    fn name(&self) -> &'static str {
+        "ground"
+    }
+
+    fn run(&self, context: &mut PipelineContext, _host: &dyn PipelineHost) -> Result<Value, FrameSenseError> {
+        let answer = match (&context.output, &context.answer) {
+            (Some(output), _) => output.clone(),
+            (None, Some(answer)) => answer.answer.clone(),
+            (None, None) => return Err("Nothing to ground, the pipeline has no answer".into()),
+        };
+        // Without OCR words (an optional OCR stage that failed) every reference ends up unmatched
+        let words = context.ocr.as_ref().map(|ocr| ocr.words.as_slice()).unwrap_or_default();
+        let grounding = ground_answer(&answer, words, self.quotes);
+        context.output = Some(grounding.answer);
+        context.highlights = grounding.highlights;
+        Ok(json!({ "highlights": context.highlights, "unmatched": grounding.unmatched }))
+    }
+}
+
+// Text cleanup on the answer, or on the OCR text when there's no AI stage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PostprocessStage {
+    pub steps: Vec<String>,
+}
+
+fn strip_code_fences(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn collapse_blank_lines(text: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        let blank = line.trim().is_empty();
+        if blank && output.last().is_none_or(|previous| previous.trim().is_empty()) {
+            continue;
+        }
+        output.push(if blank { "" } else { line });
+    }
+    output.join("\n")
+}
+
+impl PostprocessStage {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.steps.iter().find(|step| !POSTPROCESS_STEPS.contains(&step.as_str())) {
+            Some(step) => Err(format!(
+                "Unknown postprocess step '{}' (known: {})",
+                step,
+                POSTPROCESS_STEPS.join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut output = text.to_string();
+        for step in &self.steps {
+            output = match step.as_str() {
+                "trim" => output.trim().to_string(),
+                "strip_code_fences" => strip_code_fences(&output),
+                "collapse_blank_lines" => collapse_blank_lines(&output),
+                "single_line" => output.split_whitespace().collect::<Vec<_>>().join(" "),
+                _ => output,
+            };
+        }
+        output
+    }
+}
+
+impl Stage for PostprocessStage {
+    fn name(&self) -> &'static str {
+        "postprocess"
+    }
+
+    fn run(&self, context: &mut PipelineContext, _host: &dyn PipelineHost) -> Result<Value, FrameSenseError> {
+        self.validate()?;
+        let input = match (&context.output, &context.answer) {
+            (Some(output), _) => output.clone(),
+            (None, Some(answer)) => answer.answer.clone(),
+            (None, None) => context.text.clone(),
+        };
+        let output = self.apply(&input);
+        context.output = Some(output.clone());
+        Ok(json!({ "steps": self.steps, "output": output }))
+    }
+}
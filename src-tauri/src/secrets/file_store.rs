@@ -0,0 +1,248 @@
+// Encrypted-file secret store: AES-256-GCM with a key derived (HKDF-SHA256) from this
+// machine's id, the OS user and a random salt kept in the file. Copied to another machine
+// or user account, the file can't be decrypted.
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hkdf::{Salt, HKDF_SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::process::Command;
+use std::sync::Mutex;
+
+use super::SecretStore;
+
+const FILE_FORMAT_VERSION: u32 = 1;
+const KEY_INFO: &[u8] = b"framesense secret store v1";
+const SALT_LEN: usize = 16;
+
+#[derive(Serialize, Deserialize)]
+struct SealedEntry {
+    nonce: String,      // Base64, fresh for every write
+    ciphertext: String, // Base64, with the GCM tag appended
+}
+
+#[derive(Serialize, Deserialize)]
+struct SecretFile {
+    version: u32,
+    salt: String,
+    entries: HashMap<String, SealedEntry>,
+}
+
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    machine_secret: Vec<u8>,
+    write_lock: Mutex<()>,
+}
+
+fn base64_engine() -> base64::engine::GeneralPurpose {
+    base64::engine::general_purpose::STANDARD
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn first_line(program: &str, args: &[&str], marker: &str) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains(marker))
+        .map(str::to_string)
+}
+
+#[cfg(target_os = "macos")]
+fn machine_id() -> Option<String> {
+    // "IOPlatformUUID" = "8D3E...-..."
+    let line = first_line("ioreg", &["-rd1", "-c", "IOPlatformExpertDevice"], "IOPlatformUUID")?;
+    line.split('"').nth(3).map(str::to_string)
+}
+
+#[cfg(target_os = "windows")]
+fn machine_id() -> Option<String> {
+    // MachineGuid    REG_SZ    1b2c...
+    let line = first_line(
+        "reg",
+        &["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"],
+        "MachineGuid",
+    )?;
+    line.split_whitespace().last().map(str::to_string)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+}
+
+// What the key is bound to. Without a machine id the host name stands in, which is weaker
+// but still keeps the file from opening under another account.
+fn machine_secret() -> Vec<u8> {
+    let machine = machine_id().unwrap_or_else(|| {
+        println!("⚠️ No machine id found, binding the secret store key to the host name");
+        std::env::var("HOSTNAME")
+            .or_else(|_| std::env::var("COMPUTERNAME"))
+            .unwrap_or_default()
+    });
+    let user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+    format!("{}\n{}", machine, user).into_bytes()
+}
+
+impl EncryptedFileStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self::with_machine_secret(path, machine_secret())
+    }
+
+    pub fn with_machine_secret(path: PathBuf, machine_secret: Vec<u8>) -> Self {
+        Self {
+            path,
+            machine_secret,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    fn key(&self, salt: &[u8]) -> LessSafeKey {
+        let prk = Salt::new(HKDF_SHA256, salt).extract(&self.machine_secret);
+        let okm = prk
+            .expand(&[KEY_INFO], &AES_256_GCM)
+            .expect("AES-256 key length is valid for HKDF-SHA256");
+        LessSafeKey::new(UnboundKey::from(okm))
+    }
+
+    fn read(&self) -> Result<Option<SecretFile>, String> {
+        let json = match fs::read_to_string(&self.path) {
+            Ok(json) => json,
+            Err(_) => return Ok(None),
+        };
+        let file: SecretFile = serde_json::from_str(&json).map_err(|e| format!("Secret store is corrupt: {}", e))?;
+        if file.version != FILE_FORMAT_VERSION {
+            return Err(format!("Unsupported secret store version {}", file.version));
+        }
+        Ok(Some(file))
+    }
+
+    fn write(&self, file: &SecretFile) -> Result<(), String> {
+        if file.entries.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove secret store: {}", e)),
+                _ => Ok(()),
+            };
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create secret store directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string(file).map_err(|e| format!("Failed to serialize secret store: {}", e))?;
+        // Owner-only, and write-then-rename so a crash can't leave half a file
+        let tmp_path = self.path.with_extension("enc.tmp");
+        fs::write(&tmp_path, json).map_err(|e| format!("Failed to write secret store: {}", e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("Failed to restrict secret store permissions: {}", e))?;
+        }
+        fs::rename(&tmp_path, &self.path).map_err(|e| format!("Failed to write secret store: {}", e))
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn backend(&self) -> &'static str {
+        "encrypted_file"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let Some(file) = self.read()? else {
+            return Ok(None);
+        };
+        let Some(entry) = file.entries.get(key) else {
+            return Ok(None);
+        };
+
+        let undecryptable = || "Stored secret can't be decrypted on this machine".to_string();
+        let salt = base64_engine().decode(&file.salt).map_err(|_| undecryptable())?;
+        let nonce = base64_engine().decode(&entry.nonce).map_err(|_| undecryptable())?;
+        let mut sealed = base64_engine().decode(&entry.ciphertext).map_err(|_| undecryptable())?;
+        let nonce = Nonce::try_assume_unique_for_key(&nonce).map_err(|_| undecryptable())?;
+
+        // The entry's name is the associated data, so entries can't be swapped around
+        let plaintext = self
+            .key(&salt)
+            .open_in_place(nonce, Aad::from(key.as_bytes()), &mut sealed)
+            .map_err(|_| undecryptable())?;
+        String::from_utf8(plaintext.to_vec()).map(Some).map_err(|_| undecryptable())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        let _guard = self.write_lock.lock().unwrap();
+        let random = SystemRandom::new();
+        let mut file = match self.read() {
+            Ok(Some(file)) => file,
+            // A corrupt or foreign file can't be read anyway; start over rather than fail every save
+            Ok(None) | Err(_) => {
+                let mut salt = [0u8; SALT_LEN];
+                random.fill(&mut salt).map_err(|_| "No system randomness available".to_string())?;
+                SecretFile {
+                    version: FILE_FORMAT_VERSION,
+                    salt: base64_engine().encode(salt),
+                    entries: HashMap::new(),
+                }
+            },
+        };
+
+        let salt = base64_engine().decode(&file.salt).map_err(|e| format!("Secret store salt is invalid: {}", e))?;
+        let mut nonce = [0u8; NONCE_LEN];
+        random.fill(&mut nonce).map_err(|_| "No system randomness available".to_string())?;
+        let mut sealed = value.as_bytes().to_vec();
+        self.key(&salt)
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(key.as_bytes()), &mut sealed)
+            .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+        file.entries.insert(key.to_string(), SealedEntry {
+            nonce: base64_engine().encode(nonce),
+            ciphertext: base64_engine().encode(&sealed),
+        });
+        self.write(&file)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        let _guard = self.write_lock.lock().unwrap();
+        let Some(mut file) = self.read().ok().flatten() else {
+            return Ok(());
+        };
+        if file.entries.remove(key).is_some() {
+            self.write(&file)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_only_with_the_same_machine_secret() {
+        let dir = std::env::temp_dir().join(format!("framesense-secrets-{}", std::process::id()));
+        let path = dir.join("secrets.enc");
+        let store = EncryptedFileStore::with_machine_secret(path.clone(), b"machine-a\nada".to_vec());
+
+        store.set("session", "{\"token\":\"eyJhbGciOi\"}").unwrap();
+        assert_eq!(store.get("session").unwrap().as_deref(), Some("{\"token\":\"eyJhbGciOi\"}"));
+        assert!(!fs::read_to_string(&path).unwrap().contains("eyJhbGciOi"));
+        assert_eq!(store.get("other").unwrap(), None);
+
+        let elsewhere = EncryptedFileStore::with_machine_secret(path.clone(), b"machine-b\nada".to_vec());
+        assert!(elsewhere.get("session").is_err());
+
+        store.delete("session").unwrap();
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
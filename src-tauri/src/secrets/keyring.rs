@@ -0,0 +1,147 @@
+// OS keyring backend on Linux: the freedesktop Secret Service (GNOME Keyring, KWallet) over
+// D-Bus. Secrets go into the default collection as items tagged with the application and
+// the key name, so they show up (and can be removed) in the desktop's password manager.
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+use dbus::blocking::SyncConnection;
+use dbus::Path;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::SecretStore;
+
+const SERVICE: &str = "org.freedesktop.secrets";
+const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+const COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+const ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
+const APPLICATION: &str = "framesense";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// (session, parameters, value, content type) as the Secret Service passes secrets around
+type Secret = (Path<'static>, Vec<u8>, Vec<u8>, String);
+
+pub struct SecretServiceStore {
+    connection: SyncConnection,
+    session: Path<'static>,
+    collection: Path<'static>,
+}
+
+fn dbus_error(action: &str, e: dbus::Error) -> String {
+    format!("Secret Service {} failed: {}", action, e.message().unwrap_or("unknown error"))
+}
+
+fn attributes(key: &str) -> HashMap<String, String> {
+    HashMap::from([
+        ("application".to_string(), APPLICATION.to_string()),
+        ("key".to_string(), key.to_string()),
+    ])
+}
+
+// A prompt means the keyring wants the user to confirm something; that can't be answered
+// from a background save, so it's treated as unavailable
+fn no_prompt(prompt: &Path) -> Result<(), String> {
+    if &**prompt == "/" {
+        Ok(())
+    } else {
+        Err("The keyring asked for confirmation, which FrameSense can't answer".to_string())
+    }
+}
+
+impl SecretServiceStore {
+    // Only succeeds with a running Secret Service whose default collection is unlocked
+    pub fn connect() -> Result<Self, String> {
+        let connection = SyncConnection::new_session().map_err(|e| dbus_error("connection", e))?;
+        let service = connection.with_proxy(SERVICE, SERVICE_PATH, TIMEOUT);
+
+        // The "plain" algorithm: secrets cross the session bus unencrypted, which only the
+        // same user can see anyway
+        let (_, session): (Variant<Box<dyn RefArg>>, Path<'static>) = service
+            .method_call(SERVICE_INTERFACE, "OpenSession", ("plain", Variant(String::new())))
+            .map_err(|e| dbus_error("session", e))?;
+        let (collection,): (Path<'static>,) = service
+            .method_call(SERVICE_INTERFACE, "ReadAlias", ("default",))
+            .map_err(|e| dbus_error("collection lookup", e))?;
+        if &*collection == "/" {
+            return Err("The keyring has no default collection".to_string());
+        }
+
+        let locked: bool = connection
+            .with_proxy(SERVICE, &collection, TIMEOUT)
+            .get(COLLECTION_INTERFACE, "Locked")
+            .map_err(|e| dbus_error("collection state", e))?;
+        if locked {
+            return Err("The default keyring is locked".to_string());
+        }
+
+        Ok(Self {
+            connection,
+            session,
+            collection,
+        })
+    }
+
+    fn find(&self, key: &str) -> Result<Vec<Path<'static>>, String> {
+        let (items,): (Vec<Path<'static>>,) = self
+            .connection
+            .with_proxy(SERVICE, &self.collection, TIMEOUT)
+            .method_call(COLLECTION_INTERFACE, "SearchItems", (attributes(key),))
+            .map_err(|e| dbus_error("search", e))?;
+        Ok(items)
+    }
+}
+
+impl SecretStore for SecretServiceStore {
+    fn backend(&self) -> &'static str {
+        "secret_service"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let Some(item) = self.find(key)?.into_iter().next() else {
+            return Ok(None);
+        };
+        let (secret,): (Secret,) = self
+            .connection
+            .with_proxy(SERVICE, &item, TIMEOUT)
+            .method_call(ITEM_INTERFACE, "GetSecret", (&self.session,))
+            .map_err(|e| dbus_error("read", e))?;
+        String::from_utf8(secret.2)
+            .map(Some)
+            .map_err(|_| "Keyring secret is not valid UTF-8".to_string())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        let mut properties = PropMap::new();
+        properties.insert(
+            format!("{}.Label", ITEM_INTERFACE),
+            Variant(Box::new(format!("FrameSense {}", key))),
+        );
+        properties.insert(format!("{}.Attributes", ITEM_INTERFACE), Variant(Box::new(attributes(key))));
+        let secret: Secret = (
+            self.session.clone(),
+            Vec::new(),
+            value.as_bytes().to_vec(),
+            "text/plain".to_string(),
+        );
+
+        // replace = true overwrites the item with the same attributes
+        let (_, prompt): (Path<'static>, Path<'static>) = self
+            .connection
+            .with_proxy(SERVICE, &self.collection, TIMEOUT)
+            .method_call(COLLECTION_INTERFACE, "CreateItem", (properties, secret, true))
+            .map_err(|e| dbus_error("write", e))?;
+        no_prompt(&prompt)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        for item in self.find(key)? {
+            let (prompt,): (Path<'static>,) = self
+                .connection
+                .with_proxy(SERVICE, &item, TIMEOUT)
+                .method_call(ITEM_INTERFACE, "Delete", ())
+                .map_err(|e| dbus_error("delete", e))?;
+            no_prompt(&prompt)?;
+        }
+        Ok(())
+    }
+}
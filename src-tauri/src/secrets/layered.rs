@@ -0,0 +1,151 @@
+// The keyring in front of the encrypted file. Which of them was reachable can differ from
+// one start to the next (a locked keyring, no Secret Service in a remote session), so
+// secrets written to the file while the keyring was away move into it on the next read,
+// and deletes go to both so a logged-out session doesn't come back from the other one.
+use std::sync::Arc;
+
+use super::SecretStore;
+
+pub struct LayeredStore {
+    primary: Arc<dyn SecretStore>,
+    fallback: Arc<dyn SecretStore>,
+}
+
+impl LayeredStore {
+    pub fn new(primary: Arc<dyn SecretStore>, fallback: Arc<dyn SecretStore>) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+impl SecretStore for LayeredStore {
+    fn backend(&self) -> &'static str {
+        self.primary.backend()
+    }
+
+    // A value in the fallback was written while the primary couldn't be used, so it's
+    // newer than anything the primary holds and replaces it there
+    fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let fallback_value = self.fallback.get(key).unwrap_or_else(|e| {
+            println!("⚠️ Couldn't read '{}' from the {} store: {}", key, self.fallback.backend(), e);
+            None
+        });
+        let Some(value) = fallback_value else {
+            return self.primary.get(key);
+        };
+
+        match self.primary.set(key, &value) {
+            Ok(()) => {
+                println!("🔐 Moved '{}' from the {} store into the {} store", key, self.fallback.backend(), self.primary.backend());
+                if let Err(e) = self.fallback.delete(key) {
+                    println!("⚠️ Couldn't remove the moved '{}' from the {} store: {}", key, self.fallback.backend(), e);
+                }
+            },
+            Err(e) => println!("⚠️ Keeping '{}' in the {} store: {}", key, self.fallback.backend(), e),
+        }
+        Ok(Some(value))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        match self.primary.set(key, value) {
+            Ok(()) => {
+                // An older copy left in the fallback would win the next read
+                if let Err(e) = self.fallback.delete(key) {
+                    println!("⚠️ Couldn't remove the old '{}' from the {} store: {}", key, self.fallback.backend(), e);
+                }
+                Ok(())
+            },
+            Err(e) => {
+                println!("⚠️ The {} store refused '{}', using the {} store: {}", self.primary.backend(), key, self.fallback.backend(), e);
+                self.fallback.set(key, value)
+            },
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        let primary = self.primary.delete(key);
+        let fallback = self.fallback.delete(key);
+        primary.and(fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    // In-memory store that can be switched off like a locked keyring
+    #[derive(Default)]
+    struct MemoryStore {
+        values: Mutex<HashMap<String, String>>,
+        offline: AtomicBool,
+    }
+
+    impl MemoryStore {
+        fn check(&self) -> Result<(), String> {
+            if self.offline.load(Ordering::SeqCst) {
+                Err("offline".to_string())
+            } else {
+                Ok(())
+            }
+        }
+
+        fn value(&self, key: &str) -> Option<String> {
+            self.values.lock().unwrap().get(key).cloned()
+        }
+    }
+
+    impl SecretStore for MemoryStore {
+        fn backend(&self) -> &'static str {
+            "memory"
+        }
+
+        fn get(&self, key: &str) -> Result<Option<String>, String> {
+            self.check()?;
+            Ok(self.value(key))
+        }
+
+        fn set(&self, key: &str, value: &str) -> Result<(), String> {
+            self.check()?;
+            self.values.lock().unwrap().insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, key: &str) -> Result<(), String> {
+            self.check()?;
+            self.values.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn secrets_written_while_the_keyring_was_away_move_into_it() {
+        let (keyring, file) = (Arc::new(MemoryStore::default()), Arc::new(MemoryStore::default()));
+        let store = LayeredStore::new(keyring.clone(), file.clone());
+        store.set("session", "old").unwrap();
+        assert_eq!((keyring.value("session").as_deref(), file.value("session")), (Some("old"), None));
+
+        // Logged in again while the keyring was locked
+        keyring.offline.store(true, Ordering::SeqCst);
+        store.set("session", "new").unwrap();
+        assert_eq!(file.value("session").as_deref(), Some("new"));
+        assert_eq!(store.get("session").unwrap().as_deref(), Some("new"));
+
+        keyring.offline.store(false, Ordering::SeqCst);
+        assert_eq!(store.get("session").unwrap().as_deref(), Some("new"));
+        assert_eq!((keyring.value("session").as_deref(), file.value("session")), (Some("new"), None));
+    }
+
+    #[test]
+    fn deletes_from_both_stores() {
+        let (keyring, file) = (Arc::new(MemoryStore::default()), Arc::new(MemoryStore::default()));
+        keyring.set("session", "in keyring").unwrap();
+        file.set("session", "in file").unwrap();
+
+        let store = LayeredStore::new(keyring.clone(), file.clone());
+        store.delete("session").unwrap();
+        assert_eq!((keyring.value("session"), file.value("session")), (None, None));
+        assert_eq!(store.get("session").unwrap(), None);
+    }
+}
@@ -0,0 +1,47 @@
+// Secret storage for the session and its bearer token: the OS keyring where there is one
+// (Secret Service on Linux), otherwise a file encrypted with a key bound to this machine
+use std::path::Path;
+use std::sync::Arc;
+
+mod file_store;
+#[cfg(target_os = "linux")]
+mod keyring;
+mod layered;
+
+pub use file_store::EncryptedFileStore;
+use layered::LayeredStore;
+
+pub trait SecretStore: Send + Sync {
+    fn backend(&self) -> &'static str; // Shown in diagnostics, e.g. "secret_service"
+    fn get(&self, key: &str) -> Result<Option<String>, String>;
+    fn set(&self, key: &str, value: &str) -> Result<(), String>;
+    fn delete(&self, key: &str) -> Result<(), String>;
+}
+
+#[cfg(target_os = "linux")]
+fn keyring_store() -> Option<Arc<dyn SecretStore>> {
+    match keyring::SecretServiceStore::connect() {
+        Ok(store) => Some(Arc::new(store)),
+        Err(e) => {
+            println!("ℹ️ Secret Service keyring unavailable, using the encrypted file store: {}", e);
+            None
+        },
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn keyring_store() -> Option<Arc<dyn SecretStore>> {
+    None
+}
+
+// The keyring when it's reachable and unlocked, backed by `secrets.enc` in `dir` for
+// whatever was saved while it wasn't; only the file when there is no keyring
+pub fn open(dir: &Path) -> Arc<dyn SecretStore> {
+    let file_store: Arc<dyn SecretStore> = Arc::new(EncryptedFileStore::new(dir.join("secrets.enc")));
+    let store: Arc<dyn SecretStore> = match keyring_store() {
+        Some(keyring) => Arc::new(LayeredStore::new(keyring, file_store)),
+        None => file_store,
+    };
+    println!("🔐 Secrets are kept in the {} store", store.backend());
+    store
+}
@@ -0,0 +1,42 @@
+// Name of the application in the foreground, for the {app_name} template variable.
+// Best effort: None when the platform tool is missing or the query fails.
+use std::process::Command;
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn frontmost_app_name() -> Option<String> {
+    run(
+        "osascript",
+        &["-e", "tell application \"System Events\" to get name of first application process whose frontmost is true"],
+    )
+}
+
+#[cfg(target_os = "windows")]
+pub fn frontmost_app_name() -> Option<String> {
+    run(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "Add-Type -Name W -Namespace U -MemberDefinition '[DllImport(\"user32.dll\")] public static extern IntPtr GetForegroundWindow(); [DllImport(\"user32.dll\")] public static extern int GetWindowThreadProcessId(IntPtr h, out int p);'; $p = 0; [void][U.W]::GetWindowThreadProcessId([U.W]::GetForegroundWindow(), [ref]$p); (Get-Process -Id $p).ProcessName",
+        ],
+    )
+}
+
+// X11 only; Wayland doesn't expose the focused window to other clients
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn frontmost_app_name() -> Option<String> {
+    run("xdotool", &["getactivewindow", "getwindowclassname"])
+}
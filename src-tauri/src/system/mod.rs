@@ -1,3 +1,5 @@
+pub mod active_app;
 pub mod permission_cache;
 
-pub use permission_cache::{Permission, PermissionCache}; 
\ No newline at end of file
+pub use active_app::frontmost_app_name;
+pub use permission_cache::{Permission, PermissionCache};
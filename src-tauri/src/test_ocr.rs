@@ -1,32 +1,28 @@
-// Standalone OCR test to verify Tesseract works
+// OCR verification - engine check plus the fixture regression suite
 use crate::ocr::OCRService;
 
-pub fn run_all_tests() {
+pub fn run_all_tests() -> Result<String, String> {
     println!("\n🚀 STARTING OCR VERIFICATION TESTS");
     println!("=====================================");
     
-    // Test 1: Basic initialization
-    println!("\n📋 Test 1: Basic OCR Test");
-    match OCRService::test_ocr() {
-        Ok(msg) => println!("✅ {}", msg),
-        Err(err) => {
-            println!("❌ {}", err);
-            return;
-        }
-    }
+    // Test 1: Engine availability
+    println!("\n📋 Test 1: OCR Engine");
+    let engine = OCRService::test_ocr().map_err(|err| {
+        println!("{}", err);
+        err
+    })?;
+    println!("{}", engine);
     
-    // Test 2: Integration test
-    println!("\n📋 Test 2: Integration Test");
-    match OCRService::run_integration_test() {
-        Ok(msg) => println!("✅ {}", msg),
-        Err(err) => {
-            println!("❌ {}", err);
-            return;
-        }
-    }
+    // Test 2: Accuracy against the fixture corpus
+    println!("\n📋 Test 2: Regression Suite");
+    let report = OCRService::run_integration_test().map_err(|report| {
+        println!("{}", report);
+        println!("\n❌ OCR accuracy dropped below the stored baselines");
+        report
+    })?;
+    println!("{}", report);
     
     println!("\n🎉 ALL OCR TESTS PASSED!");
-    println!("✅ Tesseract is working correctly with Rust");
-    println!("✅ Ready to proceed with Step 2 of AI.txt");
     println!("=====================================");
-} 
\ No newline at end of file
+    Ok(format!("{}\n\n{}", engine, report))
+}
@@ -0,0 +1,310 @@
+// Usage meter - counts analyses locally so the daily tier limit holds while offline,
+// and lines the counts up with the backend's whenever the session is verified.
+// The ledger belongs to one account on one backend; anyone else starts a fresh one.
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::analysis::ProviderKind;
+use crate::errors::FrameSenseError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub at: String, // RFC 3339, user's local time
+    pub model: Option<String>,
+    pub provider: ProviderKind,
+    pub cost: f64, // Estimated USD from the model registry, 0 for models outside it
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsageLedger {
+    #[serde(default)]
+    user_id: String, // Account the counts belong to, empty until someone signs in
+    #[serde(default)]
+    backend: String, // API URL of the backend that account lives on
+    day: String, // Local date ("2024-05-01") the daily count belongs to
+    daily: i32,  // Backend analyses today, the count the tier limit applies to
+    total: i32,
+    cost_today: f64,
+    records: Vec<UsageRecord>, // Today's analyses with every provider
+    reconciled_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageSnapshot {
+    pub day: String,
+    pub daily: i32,
+    pub total: i32,
+    pub limit: i32,             // -1 = unlimited
+    pub remaining: Option<i32>, // None when unlimited
+    pub cost_today: f64,
+    pub resets_at: String,
+    pub records: Vec<UsageRecord>,
+}
+
+pub struct UsageMeter {
+    path: PathBuf,
+    ledger: UsageLedger,
+}
+
+fn now() -> DateTime<FixedOffset> {
+    Local::now().fixed_offset()
+}
+
+fn day_key(now: &DateTime<FixedOffset>) -> String {
+    now.date_naive().format("%Y-%m-%d").to_string()
+}
+
+// Next local midnight, which is when the daily count starts over
+fn next_reset(now: &DateTime<FixedOffset>) -> String {
+    let tomorrow = now.date_naive().succ_opt().unwrap_or(NaiveDate::MAX);
+    let midnight = tomorrow.and_hms_opt(0, 0, 0).unwrap_or_default();
+    match now.offset().from_local_datetime(&midnight).single() {
+        Some(reset) => reset.to_rfc3339(),
+        None => (*now + Duration::days(1)).to_rfc3339(),
+    }
+}
+
+impl UsageMeter {
+    pub fn load(path: PathBuf) -> Self {
+        let ledger = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                println!("⚠️ Failed to parse usage ledger, starting over: {}", e);
+                UsageLedger::default()
+            }),
+            Err(_) => UsageLedger::default(),
+        };
+        Self { path, ledger }
+    }
+
+    // Starts a new day once the local date moves on
+    fn roll_over(&mut self, now: &DateTime<FixedOffset>) {
+        let today = day_key(now);
+        if self.ledger.day != today {
+            if !self.ledger.day.is_empty() {
+                println!("📅 Daily usage reset ({} analyses on {})", self.ledger.daily, self.ledger.day);
+            }
+            self.ledger.day = today;
+            self.ledger.daily = 0;
+            self.ledger.cost_today = 0.0;
+            self.ledger.records.clear();
+        }
+    }
+
+    // Hands the ledger to `user_id` on `backend`. Another account's counts (or the same
+    // account's on another backend) say nothing about this one, so those start over.
+    fn bind(&mut self, user_id: &str, backend: &str) {
+        if self.ledger.user_id == user_id && self.ledger.backend == backend {
+            return;
+        }
+        if !self.ledger.user_id.is_empty() {
+            println!("👤 Usage ledger belonged to another account, starting a fresh one");
+        }
+        self.ledger = UsageLedger {
+            user_id: user_id.to_string(),
+            backend: backend.to_string(),
+            ..UsageLedger::default()
+        };
+    }
+
+    // QuotaExceeded once the tier's daily limit is used up, so the frontend can offer an upgrade
+    pub fn check(&mut self, tier: &str, limit: i32) -> Result<(), FrameSenseError> {
+        let now = now();
+        self.roll_over(&now);
+        if limit >= 0 && self.ledger.daily >= limit {
+            return Err(FrameSenseError::QuotaExceeded {
+                tier: tier.to_string(),
+                used: self.ledger.daily,
+                limit,
+                resets_at: next_reset(&now),
+            });
+        }
+        Ok(())
+    }
+
+    // Records a completed analysis. Only backend analyses count towards the tier
+    // limit; BYOK calls are logged for the cost overview but run on the user's key.
+    pub fn record(&mut self, model: Option<String>, provider: ProviderKind, cost: f64) {
+        let now = now();
+        self.roll_over(&now);
+        if provider == ProviderKind::Backend {
+            self.ledger.daily += 1;
+            self.ledger.total += 1;
+        }
+        self.ledger.cost_today += cost;
+        self.ledger.records.push(UsageRecord {
+            at: now.to_rfc3339(),
+            model,
+            provider,
+            cost,
+        });
+        self.save();
+    }
+
+    // The backend sees analyses from every device, this meter sees ones the backend
+    // hasn't counted yet (offline, or still in flight), so each count keeps the larger.
+    // `counted_at` is when the backend last touched the user (RFC 3339); its daily count
+    // is only comparable when that falls on today's local date, otherwise it's another day's.
+    pub fn reconcile(
+        &mut self,
+        user_id: &str,
+        backend: &str,
+        backend_daily: Option<i32>,
+        backend_total: Option<i32>,
+        counted_at: Option<&str>,
+    ) {
+        let now = now();
+        self.bind(user_id, backend);
+        self.roll_over(&now);
+        let backend_day = counted_at
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| day_key(&at.with_timezone(&Local).fixed_offset()));
+        let (local_daily, local_total) = (self.ledger.daily, self.ledger.total);
+        if backend_day.as_deref() == Some(self.ledger.day.as_str()) {
+            self.ledger.daily = local_daily.max(backend_daily.unwrap_or(0));
+        }
+        self.ledger.total = local_total.max(backend_total.unwrap_or(0));
+        self.ledger.reconciled_at = Some(now.to_rfc3339());
+        self.save();
+
+        if (self.ledger.daily, self.ledger.total) != (local_daily, local_total) {
+            println!("🔄 Usage reconciled with backend: daily {} → {}, total {} → {}",
+                     local_daily, self.ledger.daily, local_total, self.ledger.total);
+        }
+    }
+
+    pub fn snapshot(&mut self, limit: i32) -> UsageSnapshot {
+        let now = now();
+        self.roll_over(&now);
+        UsageSnapshot {
+            day: self.ledger.day.clone(),
+            daily: self.ledger.daily,
+            total: self.ledger.total,
+            limit,
+            remaining: (limit >= 0).then(|| (limit - self.ledger.daily).max(0)),
+            cost_today: self.ledger.cost_today,
+            resets_at: next_reset(&now),
+            records: self.ledger.records.clone(),
+        }
+    }
+
+    // Losing a count isn't worth failing an analysis over, so errors are only logged
+    fn save(&self) {
+        let result = serde_json::to_string_pretty(&self.ledger)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::write(&self.path, json).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            println!("⚠️ Failed to save usage ledger: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKEND: &str = "https://api.finalyze.pro";
+
+    fn temp_meter(name: &str) -> UsageMeter {
+        let path = std::env::temp_dir().join(format!("framesense-usage-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut meter = UsageMeter::load(path);
+        meter.bind("ada", BACKEND);
+        meter
+    }
+
+    #[test]
+    fn refuses_backend_analyses_at_the_daily_limit() {
+        let mut meter = temp_meter("limit");
+        meter.record(Some("gpt-4o-mini".to_string()), ProviderKind::Backend, 0.001);
+        assert!(meter.check("free", 2).is_ok());
+        meter.record(Some("gpt-4o-mini".to_string()), ProviderKind::Backend, 0.001);
+
+        match meter.check("free", 2) {
+            Err(FrameSenseError::QuotaExceeded { tier, used, limit, .. }) => assert_eq!((tier.as_str(), used, limit), ("free", 2, 2)),
+            other => panic!("expected the daily limit, got {:?}", other),
+        }
+        assert!(meter.check("premium", -1).is_ok());
+
+        // BYOK calls are logged but run on the user's own key
+        let mut byok = temp_meter("byok");
+        byok.record(Some("llama3".to_string()), ProviderKind::OpenAiCompatible, 0.0);
+        assert!(byok.check("free", 1).is_ok());
+        assert_eq!(byok.snapshot(1).records.len(), 1);
+        let _ = fs::remove_file(&meter.path);
+        let _ = fs::remove_file(&byok.path);
+    }
+
+    #[test]
+    fn daily_count_starts_over_on_a_new_local_day() {
+        let mut meter = temp_meter("rollover");
+        meter.record(None, ProviderKind::Backend, 0.5);
+        meter.record(None, ProviderKind::Backend, 0.5);
+
+        // The ledger was last written yesterday
+        let yesterday = now() - Duration::days(1);
+        meter.ledger.day = day_key(&yesterday);
+        assert!(meter.check("free", 2).is_ok());
+
+        let snapshot = meter.snapshot(2);
+        assert_eq!(snapshot.day, day_key(&now()));
+        assert_eq!((snapshot.daily, snapshot.total, snapshot.remaining), (0, 2, Some(2)));
+        assert!(snapshot.records.is_empty() && snapshot.cost_today == 0.0);
+        let _ = fs::remove_file(&meter.path);
+    }
+
+    #[test]
+    fn reconcile_keeps_the_larger_count() {
+        let mut meter = temp_meter("reconcile");
+        let today = now().to_rfc3339();
+        meter.record(None, ProviderKind::Backend, 0.0);
+        meter.reconcile("ada", BACKEND, Some(0), Some(40), Some(&today));
+        assert_eq!((meter.ledger.daily, meter.ledger.total), (1, 40));
+        meter.reconcile("ada", BACKEND, Some(3), None, Some(&today));
+        assert_eq!((meter.ledger.daily, meter.ledger.total), (3, 40));
+        let _ = fs::remove_file(&meter.path);
+    }
+
+    #[test]
+    fn backend_daily_count_from_another_day_is_not_merged() {
+        let mut meter = temp_meter("stale");
+        meter.record(None, ProviderKind::Backend, 0.0);
+
+        // Yesterday's count, or one we can't date, leaves today's alone; the total still merges
+        let yesterday = (now() - Duration::days(1)).to_rfc3339();
+        meter.reconcile("ada", BACKEND, Some(9), Some(20), Some(&yesterday));
+        assert_eq!((meter.ledger.daily, meter.ledger.total), (1, 20));
+        meter.reconcile("ada", BACKEND, Some(9), None, None);
+        meter.reconcile("ada", BACKEND, Some(9), None, Some("yesterday"));
+        assert_eq!(meter.ledger.daily, 1);
+        let _ = fs::remove_file(&meter.path);
+    }
+
+    #[test]
+    fn another_account_or_backend_starts_a_fresh_ledger() {
+        let mut meter = temp_meter("owner");
+        let today = now().to_rfc3339();
+        meter.record(None, ProviderKind::Backend, 0.25);
+        meter.record(None, ProviderKind::Backend, 0.25);
+
+        // The owner is saved with the counts
+        let mut reloaded = UsageMeter::load(meter.path.clone());
+        reloaded.reconcile("ada", BACKEND, Some(0), Some(0), Some(&today));
+        assert_eq!((reloaded.ledger.daily, reloaded.ledger.total), (2, 2));
+
+        reloaded.reconcile("grace", BACKEND, Some(1), Some(5), Some(&today));
+        assert_eq!((reloaded.ledger.daily, reloaded.ledger.total), (1, 5));
+        assert_eq!(reloaded.snapshot(-1).cost_today, 0.0);
+
+        reloaded.reconcile("grace", "https://staging.finalyze.pro", Some(0), Some(0), Some(&today));
+        assert_eq!((reloaded.ledger.daily, reloaded.ledger.total), (0, 0));
+        assert_eq!(reloaded.ledger.backend, "https://staging.finalyze.pro");
+        let _ = fs::remove_file(&meter.path);
+    }
+}
@@ -23,6 +23,7 @@ import { createAIService } from './services/openai-service';
 import type { IAIService, AIRequest } from './types/ai-types';
 import UserService from './services/user-service';
 import DevHelpers from './utils/dev-helpers';
+import { errorMessage } from './types/errors';
 
 // STEG 4: AI Message interface for complete AI integration
 interface AIMessage {
@@ -394,7 +395,7 @@ function App() {
 				console.log('✅ Fallback overlay window created');
 			} catch (fallbackError) {
 				console.error('❌ Both overlay methods failed:', fallbackError);
-				alert(`Failed to create overlay: ${error}`);
+				alert(`Failed to create overlay: ${errorMessage(error)}`);
 			}
 		} finally {
 			// FAS 1: Faster reset (overlay pooling is quicker)
@@ -1,5 +1,6 @@
 import React, { useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage } from '../types/errors';
 
 interface DebugResult {
   success: boolean;
@@ -20,7 +21,7 @@ const DebugPanel: React.FC = () => {
       const result = await invoke('test_quick_command') as DebugResult;
       addResult(result);
     } catch (error) {
-      addResult({ success: false, message: `Error: ${error}` });
+      addResult({ success: false, message: `Error: ${errorMessage(error)}` });
     }
     setIsLoading(false);
   };
@@ -31,7 +32,7 @@ const DebugPanel: React.FC = () => {
       const result = await invoke('debug_capture_flow') as DebugResult;
       addResult(result);
     } catch (error) {
-      addResult({ success: false, message: `Error: ${error}` });
+      addResult({ success: false, message: `Error: ${errorMessage(error)}` });
     }
     setIsLoading(false);
   };
@@ -42,7 +43,7 @@ const DebugPanel: React.FC = () => {
       const result = await invoke('test_screen_capture') as DebugResult;
       addResult(result);
     } catch (error) {
-      addResult({ success: false, message: `Error: ${error}` });
+      addResult({ success: false, message: `Error: ${errorMessage(error)}` });
     }
     setIsLoading(false);
   };
@@ -56,7 +57,7 @@ const DebugPanel: React.FC = () => {
         message: result ? "✅ All permissions granted" : "❌ Permissions denied" 
       });
     } catch (error) {
-      addResult({ success: false, message: `Error: ${error}` });
+      addResult({ success: false, message: `Error: ${errorMessage(error)}` });
     }
     setIsLoading(false);
   };
@@ -67,7 +68,7 @@ const DebugPanel: React.FC = () => {
       const result = await invoke('test_alternative_capture') as DebugResult;
       addResult(result);
     } catch (error) {
-      addResult({ success: false, message: `Error: ${error}` });
+      addResult({ success: false, message: `Error: ${errorMessage(error)}` });
     }
     setIsLoading(false);
   };
@@ -1,5 +1,7 @@
 import React, { useState } from 'react';
 import { authService, type User } from '../services/auth-service-db';
+import { getBackendEnvironment } from '../services/backend-environment';
+import { errorMessage, isFrameSenseError } from '../types/errors';
 
 interface LoginDialogProps {
     isOpen: boolean;
@@ -12,6 +14,7 @@ export const LoginDialog: React.FC<LoginDialogProps> = ({ isOpen, onClose, onLog
     const [password, setPassword] = useState('');
     const [loading, setLoading] = useState(false);
     const [error, setError] = useState('');
+    const [browserLoading, setBrowserLoading] = useState(false); // Waiting for the browser redirect
 
     const handleLogin = async (e: React.FormEvent) => {
         e.preventDefault();
@@ -25,14 +28,41 @@ export const LoginDialog: React.FC<LoginDialogProps> = ({ isOpen, onClose, onLog
             setEmail('');
             setPassword('');
         } catch (error) {
-            setError(error as string);
+            setError(errorMessage(error));
         } finally {
             setLoading(false);
         }
     };
 
-    const openRegistrationPage = () => {
-        window.open('https://api.finalyze.pro', '_blank');
+    const handleBrowserLogin = async (provider: 'google' | 'microsoft') => {
+        setBrowserLoading(true);
+        setError('');
+
+        try {
+            const user = await authService.loginWithBrowser(provider);
+            await Promise.resolve(onLoginSuccess(user));
+            onClose();
+        } catch (error) {
+            // Cancelling from this dialog isn't an error worth showing
+            if (!(isFrameSenseError(error) && error.code === 'cancelled')) {
+                setError(errorMessage(error));
+            }
+        } finally {
+            setBrowserLoading(false);
+        }
+    };
+
+    const handleClose = () => {
+        if (browserLoading) {
+            authService.cancelBrowserLogin().catch(() => {});
+        }
+        onClose();
+    };
+
+    // The site of the backend this build talks to (--env, FRAMESENSE_ENV or environment.json)
+    const openRegistrationPage = async () => {
+        const { api_url } = await getBackendEnvironment();
+        window.open(api_url, '_blank');
     };
 
     if (!isOpen) return null;
@@ -81,7 +111,7 @@ export const LoginDialog: React.FC<LoginDialogProps> = ({ isOpen, onClose, onLog
                         </button>
                         <button
                             type="button"
-                            onClick={onClose}
+                            onClick={handleClose}
                             className="flex-1 bg-gray-600 hover:bg-gray-700 text-white py-2 px-4 rounded-lg font-medium transition-colors"
                         >
                             Cancel
@@ -89,6 +119,31 @@ export const LoginDialog: React.FC<LoginDialogProps> = ({ isOpen, onClose, onLog
                     </div>
                 </form>
                 
+                <div className="mt-4 space-y-2">
+                    {browserLoading ? (
+                        <p className="text-white/70 text-sm text-center">Finish signing in in your browser...</p>
+                    ) : (
+                        <>
+                            <button
+                                type="button"
+                                onClick={() => handleBrowserLogin('google')}
+                                disabled={loading}
+                                className="w-full bg-white/10 hover:bg-white/20 border border-white/20 text-white py-2 px-4 rounded-lg font-medium transition-colors"
+                            >
+                                Continue with Google
+                            </button>
+                            <button
+                                type="button"
+                                onClick={() => handleBrowserLogin('microsoft')}
+                                disabled={loading}
+                                className="w-full bg-white/10 hover:bg-white/20 border border-white/20 text-white py-2 px-4 rounded-lg font-medium transition-colors"
+                            >
+                                Continue with Microsoft
+                            </button>
+                        </>
+                    )}
+                </div>
+                
                 <div className="mt-4 pt-4 border-t border-white/10">
                     <p className="text-white/60 text-sm text-center">
                         Don't have an account?{' '}
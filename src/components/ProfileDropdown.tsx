@@ -1,6 +1,8 @@
 import React, { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { authService, type User } from '../services/auth-service-db';
+import { getBackendEnvironment } from '../services/backend-environment';
+import { errorMessage } from '../types/errors';
 
 interface ProfileDropdownProps {
     currentUser: User | null;
@@ -89,7 +91,7 @@ export const ProfileDropdown: React.FC<ProfileDropdownProps> = ({
             setPassword('');
             setIsOpen(false);
         } catch (error) {
-            setError(error as string);
+            setError(errorMessage(error));
         } finally {
             setLoading(false);
         }
@@ -117,8 +119,15 @@ export const ProfileDropdown: React.FC<ProfileDropdownProps> = ({
         }
     };
 
-    const openRegistrationPage = () => {
-        window.open('https://api.finalyze.pro', '_blank');
+    // The site of the backend this build talks to (--env, FRAMESENSE_ENV or environment.json)
+    const openRegistrationPage = async () => {
+        const { api_url } = await getBackendEnvironment();
+        window.open(api_url, '_blank');
+    };
+
+    const openAccountPage = async () => {
+        const { api_url } = await getBackendEnvironment();
+        window.open(`${api_url}/account`, '_blank');
     };
 
     const getTierColor = (tier: string) => {
@@ -180,7 +189,7 @@ export const ProfileDropdown: React.FC<ProfileDropdownProps> = ({
                                     </button>
 
                                     <button
-                                        onClick={() => window.open('https://api.finalyze.pro/account', '_blank')}
+                                        onClick={openAccountPage}
                                         className="w-full flex items-center space-x-2 p-2 text-white/80 hover:text-white hover:bg-white/10 rounded-lg transition-colors"
                                     >
                                         <span>🌐</span>
@@ -1,5 +1,7 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { DevHelpers } from '../utils/dev-helpers.js';
+import { errorMessage, isFrameSenseError } from '../types/errors';
 
 export interface User {
     id: string;
@@ -18,105 +20,80 @@ export interface User {
 class AuthService {
     private currentUser: User | null = null;
     private authListeners: Array<(user: User | null) => void> = [];
-    private apiUrl = 'https://api.finalyze.pro'; // Railway backend
     private sessionKey = 'framesense_user_session';
 
 
     async initialize() {
+        // The Rust side clears the session once the backend refuses to refresh it
+        await listen<{ reason: string }>('session-expired', (event) => {
+            console.log('🔒 Session expired:', event.payload.reason);
+            this.clearUserSessionLocal();
+            this.currentUser = null;
+            this.notifyAuthListeners(null);
+        });
+
         // Load current user from local storage
         await this.loadCurrentUser();
     }
 
+    // Email and password sign-in. Rust talks to the backend of the selected environment and
+    // saves the session, so custom and self-hosted backends work under the webview's CSP.
     async loginWithDatabase(email: string, password: string): Promise<User> {
         try {
             console.log('🔐 Logging in user with backend API:', email);
-            
-            // Use backend API instead of direct database call
-            const response = await fetch(`${this.apiUrl}/api/auth/login`, {
-                method: 'POST',
-                headers: {
-                    'Content-Type': 'application/json',
-                },
-                body: JSON.stringify({ email, password }),
-            });
+            const tauriUser: any = await invoke('login_user', { email, password });
+            const user = this.userFromTauri(tauriUser);
 
-            const data = await response.json();
-            
-            if (!response.ok) {
-                throw new Error(data.message || 'Login failed');
-            }
-
-            if (!data.success || !data.user) {
-                throw new Error('Invalid response from server');
-            }
-
-            const user = data.user;
-            // Add the token to the user object (backend returns it separately)
-            user.token = data.token;
-            
-            // Everyone gets premium tier when logged in (simple!)
-            user.tier = 'premium';
-            
             this.currentUser = user;
-            
-            console.log('🔍 DEBUG: About to save user session:', {
-                email: user.email,
-                tier: user.tier,
-                hasToken: !!user.token,
-                tokenLength: user.token ? user.token.length : 0
-            });
-            
-            // Save user session to BOTH Tauri storage AND localStorage
-            try {
-                // Convert user to Tauri-compatible format
-                const tauriUser = {
-                    id: user.id,
-                    email: user.email,
-                    name: user.name,
-                    tier: user.tier,
-                    token: user.token,
-                    usage: {
-                        daily: user.usage_daily || 0,
-                        total: user.usage_total || 0,
-                        last_reset: new Date().toISOString().split('T')[0] // YYYY-MM-DD format
-                    },
-                    created_at: user.created_at,
-                    subscription_status: user.subscription_status,
-                    stripe_customer_id: user.stripe_customer_id,
-                    usage_daily: user.usage_daily,
-                    usage_total: user.usage_total,
-                    updated_at: user.updated_at
-                };
-                
-                console.log('🔍 DEBUG: Converted user for Tauri:', {
-                    email: tauriUser.email,
-                    tier: tauriUser.tier,
-                    hasUsage: !!tauriUser.usage,
-                    usageDaily: tauriUser.usage.daily
-                });
-                
-                // @ts-ignore - invoke is available in Tauri context
-                await invoke('save_user_session', { user: tauriUser });
-                console.log('✅ DEBUG: User session saved to Tauri storage successfully');
-            } catch (error) {
-                console.error('❌ DEBUG: Failed to save to Tauri storage:', error);
-                console.log('ℹ️ DEBUG: Using localStorage only as fallback');
-            }
-            
             this.saveUserSessionLocal(user);
-            console.log('✅ DEBUG: User session saved to localStorage');
-            
-            // Notify listeners
             this.notifyAuthListeners(user);
-            
+
             console.log('✅ User logged in successfully:', user.email, user.tier);
             return user;
         } catch (error) {
             console.error('❌ Login failed:', error);
-            throw new Error(`Login failed: ${error}`);
+            throw new Error(`Login failed: ${errorMessage(error)}`);
         }
     }
 
+    // The session Rust returns, in the shape the rest of the frontend uses
+    private userFromTauri(tauriUser: any): User {
+        return {
+            id: tauriUser.id,
+            email: tauriUser.email,
+            name: tauriUser.name,
+            tier: 'premium', // Everyone gets premium tier when logged in (simple!)
+            token: tauriUser.token,
+            created_at: tauriUser.created_at,
+            subscription_status: tauriUser.subscription_status,
+            stripe_customer_id: tauriUser.stripe_customer_id,
+            usage_daily: tauriUser.usage?.daily || tauriUser.usage_daily || 0,
+            usage_total: tauriUser.usage?.total || tauriUser.usage_total || 0,
+            updated_at: tauriUser.updated_at
+        };
+    }
+
+    // SSO sign-in in the system browser (OAuth + PKCE). Rust opens the browser, waits for the
+    // redirect and saves the session; rejects with a FrameSenseError if it doesn't complete.
+    async loginWithBrowser(provider?: 'google' | 'microsoft'): Promise<User> {
+        console.log('🌐 Signing in through the browser:', provider ?? 'backend choice');
+        const tauriUser: any = await invoke('login_with_browser', { provider: provider ?? null });
+
+        const user = this.userFromTauri(tauriUser);
+
+        this.currentUser = user;
+        this.saveUserSessionLocal(user);
+        this.notifyAuthListeners(user);
+
+        console.log('✅ User signed in through the browser:', user.email, user.tier);
+        return user;
+    }
+
+    // Stops waiting for a browser sign-in the user abandoned
+    async cancelBrowserLogin(): Promise<void> {
+        await invoke('cancel_job', { jobId: 'browser-login' });
+    }
+
     async logout(): Promise<void> {
         console.log('🔍 DEBUG: logout() called');
         try {
@@ -141,7 +118,7 @@ class AuthService {
             console.log('🚪 DEBUG: User logged out successfully');
         } catch (error) {
             console.error('❌ DEBUG: Logout failed:', error);
-            throw new Error(`Logout failed: ${error}`);
+            throw new Error(`Logout failed: ${errorMessage(error)}`);
         }
     }
 
@@ -237,33 +214,28 @@ class AuthService {
                 return null;
             }
 
-            // Verify user status with backend API
-            const response = await fetch(`${this.apiUrl}/api/auth/verify`, {
-                method: 'GET',
-                headers: {
-                    'Authorization': `Bearer ${this.currentUser.token}`, // Use JWT token for auth
-                },
-            });
-
-            if (!response.ok) {
-                // If verification fails, clear local session
+            // Rust verifies the token with the backend, refreshing it first when it's near expiry
+            let tauriUser: any;
+            try {
+                tauriUser = await invoke('verify_payment_status');
+            } catch (error) {
+                // A refused session is cleared; a backend that can't be reached keeps it
+                if (isFrameSenseError(error) && error.code === 'unauthorized') {
+                    await this.logout();
+                    return null;
+                }
+                throw error;
+            }
+            if (!tauriUser) {
                 await this.logout();
                 return null;
             }
 
-            const data = await response.json();
-            if (data.success && data.user) {
-                const freshUser = data.user;
-                
-                // Force premium tier for all logged in users
-                freshUser.tier = 'premium';
-                
-                this.currentUser = freshUser;
-                this.notifyAuthListeners(freshUser);
-                return freshUser;
-            }
-            
-            return this.currentUser;
+            const freshUser = this.userFromTauri(tauriUser);
+            this.currentUser = freshUser;
+            this.saveUserSessionLocal(freshUser);
+            this.notifyAuthListeners(freshUser);
+            return freshUser;
         } catch (error) {
             console.error('❌ Failed to refresh user status:', error);
             return null;
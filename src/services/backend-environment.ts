@@ -0,0 +1,22 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export interface BackendEnvironment {
+  name: string; // production, staging, local, a profile from environment.json, or custom
+  api_url: string;
+  source: 'default' | 'config_file' | 'env_var' | 'cli_flag';
+}
+
+const PRODUCTION_API_URL = 'https://api.finalyze.pro';
+
+let environmentPromise: Promise<BackendEnvironment> | null = null;
+
+// The backend selected at startup (--env, FRAMESENSE_ENV or environment.json), read once from Rust
+export function getBackendEnvironment(): Promise<BackendEnvironment> {
+  if (!environmentPromise) {
+    environmentPromise = invoke<BackendEnvironment>('get_backend_environment').catch((error) => {
+      console.warn('⚠️ Could not read backend environment, using production:', error);
+      return { name: 'production', api_url: PRODUCTION_API_URL, source: 'default' };
+    });
+  }
+  return environmentPromise;
+}
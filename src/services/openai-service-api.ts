@@ -1,18 +1,10 @@
+import { invoke } from '@tauri-apps/api/core';
 import type { IAIService, AIRequest, AIResponse } from '../types/ai-types';
+import { errorMessage } from '../types/errors';
 
+// Rust sends the request to the backend of the selected environment (production unless
+// --env / FRAMESENSE_ENV say otherwise), so the webview never needs to reach it directly
 export class OpenAIServiceAPI implements IAIService {
-  private apiUrl: string;
-
-  constructor(config: { apiUrl?: string } = {}) {
-    this.apiUrl = config.apiUrl || this.getAPIUrl();
-    console.log('🔧 OpenAI API Service - Calling backend at:', this.apiUrl);
-  }
-
-  private getAPIUrl(): string {
-    // ✅ PRODUCTION: Always use Railway backend - no localhost fallbacks
-    return 'https://api.finalyze.pro/api/analyze';
-  }
-
   private getAuthToken(): string | null {
     try {
       // Try multiple possible storage keys for backward compatibility
@@ -54,64 +46,28 @@ export class OpenAIServiceAPI implements IAIService {
     console.log('🔄 Making backend API request...');
     
     try {
-      // 🔥 DEBUG: No auth needed for simple backend
-      console.log('🔥 DEBUG: Calling simple backend - NO AUTH REQUIRED');
-      
-      // Convert base64 image to blob for multipart upload
-      const formData = new FormData();
-      formData.append('question', request.message);
-      
-      if (request.imageData) {
-        // Convert base64 data URL to blob
-        const base64Data = request.imageData.replace(/^data:image\/[a-z]+;base64,/, '');
-        const byteString = atob(base64Data);
-        const arrayBuffer = new ArrayBuffer(byteString.length);
-        const uint8Array = new Uint8Array(arrayBuffer);
-        
-        for (let i = 0; i < byteString.length; i++) {
-          uint8Array[i] = byteString.charCodeAt(i);
-        }
-        
-        const blob = new Blob([arrayBuffer], { type: 'image/png' });
-        formData.append('image', blob, 'screenshot.png');
-      }
-
-             const response = await fetch(this.apiUrl, {
-         method: 'POST',
-         headers: {
-           // 🔥 DEBUG: No auth headers for simple backend
-         },
-         body: formData
-       });
-
-      if (!response.ok) {
-        const error = await response.json().catch(() => ({ message: 'Unknown error' }));
-        
-        // 🔥 DEBUG: Simple backend errors
-        console.error('🔥 DEBUG: Backend response error:', response.status, error);
-        
-        if (response.status === 401) {
-          throw new Error('🔥 DEBUG: 401 error from simple backend (should not happen)');
+      const result: any = await invoke('analyze_capture', {
+        request: {
+          question: request.message,
+          image_data: request.imageData ?? null,
+          model: null,
+          ocr_text: null
         }
-        
-        throw new Error(error.message || `HTTP ${response.status}`);
-      }
+      });
 
-      const result = await response.json();
-      
       console.log('✅ Backend API request successful');
       
       // Convert backend response to frontend format
       return {
-        content: result.answer || result.message || 'No response',
-        tokensUsed: result.tokensUsed,
-        model: 'gpt-4o-mini',
+        content: result.answer || 'No response',
+        tokensUsed: result.tokens_used ?? undefined,
+        model: result.model || 'gpt-4o-mini',
         timestamp: Date.now()
       };
 
     } catch (error: any) {
       console.error('❌ Backend API error:', error);
-      throw new Error(`Backend API error: ${error.message}`);
+      throw new Error(`Backend API error: ${errorMessage(error)}`);
     }
   }
 
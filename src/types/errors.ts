@@ -0,0 +1,50 @@
+// Errors from Tauri commands: { code, message, ...details }. Match on `code`; `message` is for display.
+export type FrameSenseErrorCode =
+  | 'network'
+  | 'timeout'
+  | 'unauthorized'
+  | 'tier_required'
+  | 'quota_exceeded'
+  | 'rate_limited'
+  | 'server'
+  | 'content_policy'
+  | 'rejected'
+  | 'queued'
+  | 'capture_permission'
+  | 'no_display'
+  | 'bounds_invalid'
+  | 'invalid_image'
+  | 'ocr_unavailable'
+  | 'storage'
+  | 'cancelled'
+  | 'other';
+
+export interface FrameSenseError {
+  code: FrameSenseErrorCode;
+  message: string;
+  // tier_required
+  model?: string;
+  required_tier?: string;
+  current_tier?: string;
+  // quota_exceeded
+  tier?: string;
+  used?: number;
+  limit?: number;
+  resets_at?: string;
+  // queued
+  job_id?: string;
+  // server, rejected: the HTTP status from the backend or provider, when there was one
+  status?: number;
+}
+
+export function isFrameSenseError(error: unknown): error is FrameSenseError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+}
+
+// Display text for anything a command or service threw
+export function errorMessage(error: unknown): string {
+  if (isFrameSenseError(error) || error instanceof Error) {
+    return error.message;
+  }
+  return String(error);
+}